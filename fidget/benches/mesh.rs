@@ -45,7 +45,9 @@ pub fn colonnade_mesh(c: &mut Criterion) {
         depth: 8,
         ..Default::default()
     };
-    let octree = &fidget::mesh::Octree::build(shape_vm, cfg);
+    let octree = &fidget::stats::without_stats(fidget::mesh::Octree::build(
+        shape_vm, cfg,
+    ));
 
     let mut group =
         c.benchmark_group("speed vs threads (colonnade, meshing) (depth 8)");
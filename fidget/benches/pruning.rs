@@ -0,0 +1,104 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+};
+use fidget::{
+    context::{Context, Node},
+    eval::{Function, MathFunction},
+    render::{ImageSize, RenderHints},
+    shape::{EzShape, Shape},
+    types::Interval,
+};
+
+const PROSPERO: &str = include_str!("../../models/prospero.vm");
+
+/// Splits `[-1, 1]²` into `n × n` tiles
+fn tiles(n: usize) -> Vec<(Interval, Interval)> {
+    let pos = |i: usize| i as f32 / n as f32 * 2.0 - 1.0;
+    (0..n)
+        .flat_map(|i| (0..n).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            (
+                Interval::new(pos(i), pos(i + 1)),
+                Interval::new(pos(j), pos(j + 1)),
+            )
+        })
+        .collect()
+}
+
+/// Evaluates every tile, returning the number of tiles which are proven to be
+/// empty or filled, and the mean tape length after simplification
+fn pruning<F: Function>(
+    shape: &Shape<F>,
+    tiles: &[(Interval, Interval)],
+) -> (usize, f64) {
+    let mut eval = Shape::<F>::new_interval_eval();
+    let tape = shape.ez_interval_tape();
+    let mut pruned = 0;
+    let mut len = 0;
+    for &(x, y) in tiles {
+        let (out, trace) = eval.eval(&tape, x, y, Interval::from(0.0)).unwrap();
+        pruned += !out.contains(0.0) as usize;
+        len += match trace {
+            Some(trace) => shape.ez_simplify(trace).unwrap().size(),
+            None => shape.size(),
+        };
+    }
+    (pruned, len as f64 / tiles.len() as f64)
+}
+
+fn run_bench<F: Function + MathFunction + RenderHints>(
+    c: &mut Criterion,
+    ctx: &Context,
+    root: Node,
+    name: &'static str,
+) {
+    let shape = &Shape::<F>::new(ctx, root).unwrap();
+
+    let mut group = c.benchmark_group("tile evaluation (prospero, 2d)");
+    for n in [4, 16, 64] {
+        let tiles = &tiles(n);
+        let (pruned, len) = pruning(shape, tiles);
+        println!(
+            "{name} ({n}×{n}): {pruned} / {} tiles pruned ({:.1}%), \
+             mean tape length {len:.1} / {}",
+            tiles.len(),
+            pruned as f64 / tiles.len() as f64 * 100.0,
+            shape.size(),
+        );
+        let tape = &shape.ez_interval_tape();
+        let mut eval = Shape::<F>::new_interval_eval();
+        group.bench_function(BenchmarkId::new(name, n), move |b| {
+            b.iter(|| {
+                for &(x, y) in tiles {
+                    black_box(eval.eval(tape, x, y, Interval::from(0.0)))
+                        .unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("render (prospero, 2d)");
+    for size in [256, 1024] {
+        let cfg = &fidget::render::ImageRenderConfig {
+            image_size: ImageSize::from(size),
+            tile_sizes: F::tile_sizes_2d(),
+            ..Default::default()
+        };
+        group.bench_function(BenchmarkId::new(name, size), move |b| {
+            b.iter(|| {
+                let tape = shape.clone();
+                black_box(cfg.run::<_, fidget::render::BitRenderMode>(tape))
+            })
+        });
+    }
+}
+
+pub fn prospero_pruning(c: &mut Criterion) {
+    let (ctx, root) = Context::from_text(PROSPERO.as_bytes()).unwrap();
+    run_bench::<fidget::vm::VmFunction>(c, &ctx, root, "interval");
+    run_bench::<fidget::vm::VmAffineFunction>(c, &ctx, root, "affine");
+}
+
+criterion_group!(benches, prospero_pruning);
+criterion_main!(benches);
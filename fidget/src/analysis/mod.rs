@@ -0,0 +1,299 @@
+//! Analysis of distance field quality
+//!
+//! Many shapes are not true signed distance fields: operations like scaling,
+//! blending, or smooth unions can make the field change faster (or slower)
+//! than distance.  Sphere tracing and offsetting rely on the field's Lipschitz
+//! constant (the largest value of `|∇f|`); if it's greater than 1, they will
+//! overshoot the surface.
+//!
+//! [`lipschitz`] estimates and conservatively bounds the Lipschitz constant of
+//! an expression over a region, using interval evaluation of the analytic
+//! gradient magnitude (built with [`Context::gradient`]).  The region is
+//! recursively subdivided into octants, simplifying the evaluation tape in each
+//! cell (in the same way as the renderers).
+//!
+//! ```
+//! use fidget::{
+//!     analysis::{lipschitz, LipschitzSettings},
+//!     context::Tree,
+//!     vm::VmFunction,
+//!     Context,
+//! };
+//!
+//! // A sphere which has been scaled by 3 along the X axis
+//! let (x, y, z) = Tree::axes();
+//! let tree = ((x * 3.0).square() + y.square() + z.square()).sqrt() - 1.0;
+//! let mut ctx = Context::new();
+//! let root = ctx.import(&tree);
+//!
+//! let report =
+//!     lipschitz::<VmFunction>(&ctx, root, &LipschitzSettings::default())?;
+//! assert!(report.bound >= 3.0);
+//! assert!(report.estimate > 2.5 && report.estimate <= 3.0);
+//! # Ok::<(), fidget::Error>(())
+//! ```
+use crate::{
+    context::{Context, Node},
+    eval::{Function, MathFunction},
+    shape::{Shape, ShapeTracingEval},
+    types::Interval,
+    var::Var,
+    Error,
+};
+use nalgebra::Vector3;
+
+/// Settings for Lipschitz analysis
+#[derive(Copy, Clone, Debug)]
+pub struct LipschitzSettings {
+    /// Region to analyze, as `[x, y, z]` intervals
+    pub region: [Interval; 3],
+
+    /// Maximum subdivision depth
+    ///
+    /// The smallest cells are `1 / 2^depth` the size of the region along each
+    /// axis.  Cells which cannot change the result are not subdivided.
+    pub depth: u8,
+}
+
+impl Default for LipschitzSettings {
+    fn default() -> Self {
+        Self {
+            region: [Interval::new(-1.0, 1.0); 3],
+            depth: 5,
+        }
+    }
+}
+
+/// Result of Lipschitz analysis
+#[derive(Copy, Clone, Debug)]
+pub struct LipschitzReport {
+    /// Conservative upper bound on `|∇f|` over the region
+    ///
+    /// If the function is continuous, this is an upper bound on its Lipschitz
+    /// constant.  It is infinite if the gradient is unbounded (or `NaN`)
+    /// somewhere in the region.
+    pub bound: f32,
+
+    /// Largest value of `|∇f|` found at sample points
+    ///
+    /// This is a lower bound on the Lipschitz constant.
+    pub estimate: f32,
+
+    /// Sample point at which `|∇f|` deviates most from 1
+    pub worst_point: Vector3<f32>,
+
+    /// Value of `|∇f|` at [`worst_point`](Self::worst_point)
+    pub worst_gradient: f32,
+}
+
+/// Estimates and bounds the Lipschitz constant of `root` over a region
+///
+/// Cells are evaluated with interval arithmetic, and sampled at their centers
+/// when they reach the maximum subdivision depth.  Samples where `|∇f|` is
+/// `NaN` are ignored when computing the estimate and worst point.
+///
+/// The expression may only use the `x`, `y`, and `z` variables.
+pub fn lipschitz<F: MathFunction>(
+    ctx: &Context,
+    root: Node,
+    settings: &LipschitzSettings,
+) -> Result<LipschitzReport, Error> {
+    // Build `|∇f|` in a private context, because it creates new nodes (and we
+    // only have a shared reference to `ctx`)
+    let mut ctx_grad = Context::new();
+    let root = ctx_grad.import(&ctx.export(root)?);
+    let grad = ctx_grad.gradient(root, &[Var::X, Var::Y, Var::Z])?;
+    let mut sum = ctx_grad.constant(0.0);
+    for g in grad {
+        let g2 = ctx_grad.square(g)?;
+        sum = ctx_grad.add(sum, g2)?;
+    }
+    let norm = ctx_grad.sqrt(sum)?;
+    let shape = Shape::<F>::new(&ctx_grad, norm)?;
+
+    let [x, y, z] = settings.region;
+    let mut worker = Worker::<F> {
+        eval_interval: Default::default(),
+        eval_point: Default::default(),
+        workspace: Default::default(),
+        max_depth: settings.depth,
+        report: LipschitzReport {
+            bound: 0.0,
+            estimate: 0.0,
+            worst_point: Vector3::new(x.midpoint(), y.midpoint(), z.midpoint()),
+            worst_gradient: f32::NAN,
+        },
+        worst_deviation: -1.0,
+    };
+    worker.recurse(&shape, settings.region, 0)?;
+    Ok(worker.report)
+}
+
+/// Per-analysis state
+struct Worker<F: Function> {
+    eval_interval: ShapeTracingEval<F::IntervalEval>,
+    eval_point: ShapeTracingEval<F::PointEval>,
+    workspace: F::Workspace,
+    max_depth: u8,
+
+    /// Results so far
+    report: LipschitzReport,
+
+    /// Value of `|worst_gradient - 1|`, or -1 if no samples have been taken
+    worst_deviation: f32,
+}
+
+impl<F: Function> Worker<F> {
+    fn recurse(
+        &mut self,
+        shape: &Shape<F>,
+        region: [Interval; 3],
+        depth: u8,
+    ) -> Result<(), Error> {
+        let [x, y, z] = region;
+        let tape = shape.interval_tape(Default::default());
+        let (i, trace) = self.eval_interval.eval(&tape, x, y, z)?;
+
+        if depth == self.max_depth {
+            self.report.bound = if i.has_nan() {
+                f32::INFINITY
+            } else {
+                self.report.bound.max(i.upper())
+            };
+            let p = Vector3::new(x.midpoint(), y.midpoint(), z.midpoint());
+            let tape = shape.point_tape(Default::default());
+            let (v, _) = self.eval_point.eval(&tape, p.x, p.y, p.z)?;
+            if !v.is_nan() {
+                self.report.estimate = self.report.estimate.max(v);
+                let d = (v - 1.0).abs();
+                if d > self.worst_deviation {
+                    self.worst_deviation = d;
+                    self.report.worst_point = p;
+                    self.report.worst_gradient = v;
+                }
+            }
+            return Ok(());
+        }
+
+        // Skip cells which can't raise the bound or contain a worse sample
+        if !i.has_nan()
+            && i.upper() <= self.report.bound
+            && (1.0 - i.lower()).max(i.upper() - 1.0) <= self.worst_deviation
+        {
+            return Ok(());
+        }
+
+        let trace = trace.cloned();
+        let sub_shape = match trace {
+            Some(trace) => shape.simplify(
+                &trace,
+                Default::default(),
+                &mut self.workspace,
+            )?,
+            None => shape.clone(),
+        };
+        let (x0, x1) = x.split();
+        let (y0, y1) = y.split();
+        let (z0, z1) = z.split();
+        for x in [x0, x1] {
+            for y in [y0, y1] {
+                for z in [z0, z1] {
+                    self.recurse(&sub_shape, [x, y, z], depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Tree, vm::VmFunction};
+
+    fn check(tree: Tree, settings: &LipschitzSettings) -> LipschitzReport {
+        let mut ctx = Context::new();
+        let root = ctx.import(&tree);
+        let report = lipschitz::<VmFunction>(&ctx, root, settings).unwrap();
+        assert!(report.estimate <= report.bound, "{report:?}");
+        report
+    }
+
+    #[test]
+    fn test_lipschitz_linear() {
+        let report = check(Tree::x() * 2.0, &LipschitzSettings::default());
+        assert_eq!(report.bound, 2.0);
+        assert_eq!(report.estimate, 2.0);
+        assert_eq!(report.worst_gradient, 2.0);
+
+        let (x, y, _z) = Tree::axes();
+        let report = check(x * 3.0 + y * 4.0, &LipschitzSettings::default());
+        assert_eq!(report.bound, 5.0);
+        assert_eq!(report.estimate, 5.0);
+
+        let report = check(Tree::constant(1.0), &LipschitzSettings::default());
+        assert_eq!(report.bound, 0.0);
+        assert_eq!(report.worst_gradient, 0.0);
+    }
+
+    #[test]
+    fn test_lipschitz_sphere() {
+        let (x, y, z) = Tree::axes();
+        let sphere = (x.square() + y.square() + z.square()).sqrt() - 1.0;
+
+        // The gradient is undefined at the origin, so the bound is infinite
+        // if the region touches it.
+        let report = check(sphere.clone(), &LipschitzSettings::default());
+        assert_eq!(report.bound, f32::INFINITY);
+        assert!((report.estimate - 1.0).abs() < 1e-6);
+        assert!((report.worst_gradient - 1.0).abs() < 1e-6);
+
+        let settings = LipschitzSettings {
+            region: [Interval::new(0.5, 1.5); 3],
+            depth: 5,
+        };
+        let report = check(sphere, &settings);
+        assert!(report.bound >= 1.0 && report.bound < 1.5, "{report:?}");
+    }
+
+    #[test]
+    fn test_lipschitz_worst_point() {
+        // |∇f| = 2r, which deviates most from 1 at the corners of the region
+        let (x, y, z) = Tree::axes();
+        let f = x.square() + y.square() + z.square() - 1.0;
+        let report = check(f, &LipschitzSettings::default());
+        let max = 2.0 * 3f32.sqrt();
+        assert!(report.bound >= max);
+        assert!(report.estimate > max * 0.95, "{report:?}");
+        for i in 0..3 {
+            assert!(report.worst_point[i].abs() > 0.9, "{report:?}");
+        }
+        assert_eq!(report.worst_gradient, report.estimate);
+    }
+
+    #[test]
+    fn test_lipschitz_subdivision() {
+        // Deeper subdivision tightens the bound
+        let (x, y, z) = Tree::axes();
+        let f = x.clone().min(y.clone() * 2.0).max(z.sin() * x);
+        let mut prev = f32::INFINITY;
+        for depth in 0..5 {
+            let settings = LipschitzSettings {
+                depth,
+                ..Default::default()
+            };
+            let report = check(f.clone(), &settings);
+            assert!(report.bound <= prev);
+            prev = report.bound;
+        }
+        assert!(prev < 2.5, "{prev}");
+
+        // Other variables are rejected
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let v = ctx.var(Var::new());
+        let root = ctx.mul(x, v).unwrap();
+        let settings = LipschitzSettings::default();
+        assert!(lipschitz::<VmFunction>(&ctx, root, &settings).is_err());
+    }
+}
@@ -0,0 +1,1257 @@
+//! Source code generation for math expressions
+//!
+//! This module translates a math expression into a standalone function in
+//! another language, e.g. for previewing a model on the GPU or embedding it in
+//! a controller.  The generated function takes a 3D point and returns the
+//! expression's value:
+//!
+//! - GLSL: `float f(vec3 p)`
+//! - WGSL: `fn f(p: vec3<f32>) -> f32`
+//! - C: `float f(float x, float y, float z)`
+//! - Rust: `pub fn f(x: f32, y: f32, z: f32) -> f32`
+//!
+//! When generating code from a [`Context`], an analytic gradient function
+//! (`f_grad`) can also be emitted; it is built with [`Context::gradient`].  In
+//! GLSL and WGSL, it returns a 3-element vector; in C, it writes to an
+//! `float out[3]` argument; and in Rust, it returns a `[f32; 3]`.
+//!
+//! The generated code matches Fidget's semantics for single-precision
+//! evaluation (see [`VmPointEval`](crate::vm::VmPointEval)), including
+//! `compare`, `and` / `or`, `not`, `mod` (as Euclidean remainder), and the
+//! handling of `NaN` in `min` and `max`.  Non-trivial operations are
+//! implemented by helper functions with a `fidget_` prefix, which are emitted
+//! alongside the main function.
+//!
+//! Generated Rust code produces bit-for-bit the same results as
+//! [`VmPointEval`](crate::vm::VmPointEval).  Generated C code uses the
+//! single-precision functions from `math.h`, and should be compiled without
+//! floating-point contraction or other unsafe math optimizations (e.g. with
+//! `-ffp-contract=off` in GCC).  GPU drivers may not preserve `NaN` and
+//! infinity values exactly.
+//!
+//! ```
+//! use fidget::{codegen::{self, Language}, context::Tree, Context};
+//!
+//! let tree = (Tree::x().square() + Tree::y().square()).sqrt() - 1.0;
+//! let mut ctx = Context::new();
+//! let root = ctx.import(&tree);
+//! let glsl = codegen::emit_context(&ctx, root, Language::Glsl, false)?;
+//! assert!(glsl.contains("float f(vec3 p) {"));
+//! # Ok::<(), fidget::Error>(())
+//! ```
+//!
+//! Only the `x`, `y`, and `z` variables are supported; using any other
+//! variable returns [`Error::UnsupportedVar`].
+use crate::{
+    compiler::{SsaOp, SsaTape},
+    context::{Context, Node},
+    var::{Var, VarMap},
+    vm::{GenericVmFunction, VmData},
+    Error,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Target language for code generation
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Language {
+    /// OpenGL Shading Language
+    Glsl,
+    /// WebGPU Shading Language
+    Wgsl,
+    /// C (C99 or later)
+    C,
+    /// Rust
+    Rust,
+}
+
+/// Generates code for the expression rooted at `root`
+///
+/// If `gradient` is true, then a second function (`f_grad`) is emitted, which
+/// returns the analytic partial derivatives with respect to `x`, `y`, and `z`.
+pub fn emit_context(
+    ctx: &Context,
+    root: Node,
+    language: Language,
+    gradient: bool,
+) -> Result<String, Error> {
+    let mut e = Emitter::new(language);
+    let (tape, vars) = SsaTape::new(ctx, &[root])?;
+    e.function("f", tape.tape.iter().rev().cloned(), &vars, Return::Scalar)?;
+
+    if gradient {
+        // Build the gradient in a private context, because it creates new
+        // nodes (and we only have a shared reference to `ctx`)
+        let mut ctx_grad = Context::new();
+        let root = ctx_grad.import(&ctx.export(root)?);
+        let grad = ctx_grad.gradient(root, &[Var::X, Var::Y, Var::Z])?;
+        let (tape, vars) = SsaTape::new(&ctx_grad, &grad)?;
+        let ops = tape.tape.iter().rev().cloned();
+        e.function("f_grad", ops, &vars, Return::Vec3)?;
+    }
+    Ok(e.finish())
+}
+
+/// Generates code for a VM tape, which must have a single output
+///
+/// This is useful for emitting a tape which has been simplified for a
+/// particular region of space; the generated function is only valid within
+/// that region.
+pub fn emit_vm_data<const N: usize>(
+    data: &VmData<N>,
+    language: Language,
+) -> Result<String, Error> {
+    if data.output_count() != 1 {
+        return Err(Error::MultipleOutputs(data.output_count()));
+    }
+    let mut e = Emitter::new(language);
+    e.function("f", data.iter_ssa(), &data.vars, Return::Scalar)?;
+    Ok(e.finish())
+}
+
+/// Generates code for a VM function, which must have a single output
+///
+/// This is a convenience wrapper around [`emit_vm_data`], e.g. for emitting a
+/// function after calling [`Function::simplify`](crate::eval::Function::simplify).
+pub fn emit_vm_function<const N: usize>(
+    f: &GenericVmFunction<N>,
+    language: Language,
+) -> Result<String, Error> {
+    emit_vm_data(f.data(), language)
+}
+
+/// Return type for a generated function
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Return {
+    /// A single value
+    Scalar,
+    /// A 3-element vector (used for gradients)
+    Vec3,
+}
+
+/// Helper functions, which are emitted before the main function if used
+///
+/// These are sorted so that helpers are declared before any of their users.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum Helper {
+    Nan,
+    Inf,
+    Min,
+    Max,
+    Compare,
+    Mod,
+    Pow,
+    Round,
+    Cbrt,
+    Sign,
+}
+
+impl Helper {
+    /// Returns helpers which are called by this helper
+    fn deps(&self, language: Language) -> &'static [Helper] {
+        match (language, self) {
+            (
+                Language::Glsl | Language::Wgsl,
+                Helper::Min | Helper::Max | Helper::Compare | Helper::Pow,
+            ) => &[Helper::Nan],
+            _ => &[],
+        }
+    }
+
+    /// Returns the helper's name in generated code
+    fn name(&self) -> &'static str {
+        match self {
+            Helper::Nan => "fidget_nan",
+            Helper::Inf => "fidget_inf",
+            Helper::Min => "fidget_min",
+            Helper::Max => "fidget_max",
+            Helper::Compare => "fidget_compare",
+            Helper::Mod => "fidget_mod",
+            Helper::Pow => "fidget_pow",
+            Helper::Round => "fidget_round",
+            Helper::Cbrt => "fidget_cbrt",
+            Helper::Sign => "fidget_sign",
+        }
+    }
+}
+
+/// Function called in generated code
+enum Call {
+    /// Built-in function (called as a method in Rust)
+    Builtin(&'static str),
+    /// Helper function, which is emitted alongside the generated code
+    Helper(Helper),
+}
+
+impl Language {
+    fn helper(&self, h: Helper) -> &'static str {
+        match self {
+            Language::Glsl => match h {
+                Helper::Nan => {
+                    "float fidget_nan() {
+    return uintBitsToFloat(0x7fc00000u);
+}"
+                }
+                Helper::Inf => {
+                    "float fidget_inf() {
+    return uintBitsToFloat(0x7f800000u);
+}"
+                }
+                Helper::Min => {
+                    "float fidget_min(float a, float b) {
+    if (a < b) return a;
+    if (b < a) return b;
+    return (isnan(a) || isnan(b)) ? fidget_nan() : b;
+}"
+                }
+                Helper::Max => {
+                    "float fidget_max(float a, float b) {
+    if (a > b) return a;
+    if (b > a) return b;
+    return (isnan(a) || isnan(b)) ? fidget_nan() : b;
+}"
+                }
+                Helper::Compare => {
+                    "float fidget_compare(float a, float b) {
+    if (a < b) return -1.0;
+    if (a > b) return 1.0;
+    if (a == b) return 0.0;
+    return fidget_nan();
+}"
+                }
+                Helper::Mod => {
+                    "float fidget_mod(float a, float b) {
+    float r = a - b * trunc(a / b);
+    return (r < 0.0) ? r + abs(b) : r;
+}"
+                }
+                Helper::Pow => {
+                    "float fidget_pow(float a, float b) {
+    if (b == 0.0 || a == 1.0) return 1.0;
+    if (a >= 0.0) return pow(a, b);
+    if (floor(b) != b) return fidget_nan();
+    float r = pow(-a, b);
+    return (mod(b, 2.0) == 1.0) ? -r : r;
+}"
+                }
+                Helper::Round => {
+                    "float fidget_round(float a) {
+    float t = trunc(a);
+    return (abs(a - t) >= 0.5) ? t + sign(a) : t;
+}"
+                }
+                Helper::Cbrt => {
+                    "float fidget_cbrt(float a) {
+    return sign(a) * pow(abs(a), 1.0 / 3.0);
+}"
+                }
+                Helper::Sign => {
+                    "float fidget_sign(float a) {
+    return (a > 0.0) ? 1.0 : ((a < 0.0) ? -1.0 : a);
+}"
+                }
+            },
+            Language::Wgsl => match h {
+                // Using a `var` prevents these from being evaluated as
+                // constant expressions, which may not produce NaN or infinity
+                Helper::Nan => {
+                    "fn fidget_nan() -> f32 {
+    var u = 0x7fc00000u;
+    return bitcast<f32>(u);
+}"
+                }
+                Helper::Inf => {
+                    "fn fidget_inf() -> f32 {
+    var u = 0x7f800000u;
+    return bitcast<f32>(u);
+}"
+                }
+                Helper::Min => {
+                    "fn fidget_min(a: f32, b: f32) -> f32 {
+    if (a < b) { return a; }
+    if (b < a) { return b; }
+    if (a != a || b != b) { return fidget_nan(); }
+    return b;
+}"
+                }
+                Helper::Max => {
+                    "fn fidget_max(a: f32, b: f32) -> f32 {
+    if (a > b) { return a; }
+    if (b > a) { return b; }
+    if (a != a || b != b) { return fidget_nan(); }
+    return b;
+}"
+                }
+                Helper::Compare => {
+                    "fn fidget_compare(a: f32, b: f32) -> f32 {
+    if (a < b) { return -1.0; }
+    if (a > b) { return 1.0; }
+    if (a == b) { return 0.0; }
+    return fidget_nan();
+}"
+                }
+                Helper::Mod => {
+                    "fn fidget_mod(a: f32, b: f32) -> f32 {
+    let r = a % b;
+    if (r < 0.0) { return r + abs(b); }
+    return r;
+}"
+                }
+                Helper::Pow => {
+                    "fn fidget_pow(a: f32, b: f32) -> f32 {
+    if (b == 0.0 || a == 1.0) { return 1.0; }
+    if (a >= 0.0) { return pow(a, b); }
+    if (floor(b) != b) { return fidget_nan(); }
+    let r = pow(-a, b);
+    if (abs(b % 2.0) == 1.0) { return -r; }
+    return r;
+}"
+                }
+                Helper::Round => {
+                    "fn fidget_round(a: f32) -> f32 {
+    let t = trunc(a);
+    if (abs(a - t) >= 0.5) { return t + sign(a); }
+    return t;
+}"
+                }
+                Helper::Cbrt => {
+                    "fn fidget_cbrt(a: f32) -> f32 {
+    return sign(a) * pow(abs(a), 1.0 / 3.0);
+}"
+                }
+                Helper::Sign => {
+                    "fn fidget_sign(a: f32) -> f32 {
+    return select(select(a, -1.0, a < 0.0), 1.0, a > 0.0);
+}"
+                }
+            },
+            Language::C => match h {
+                Helper::Min => {
+                    "static float fidget_min(float a, float b) {
+    if (a < b) return a;
+    if (b < a) return b;
+    return (isnan(a) || isnan(b)) ? NAN : b;
+}"
+                }
+                Helper::Max => {
+                    "static float fidget_max(float a, float b) {
+    if (a > b) return a;
+    if (b > a) return b;
+    return (isnan(a) || isnan(b)) ? NAN : b;
+}"
+                }
+                Helper::Compare => {
+                    "static float fidget_compare(float a, float b) {
+    if (a < b) return -1.0f;
+    if (a > b) return 1.0f;
+    if (a == b) return 0.0f;
+    return NAN;
+}"
+                }
+                Helper::Mod => {
+                    "static float fidget_mod(float a, float b) {
+    float r = fmodf(a, b);
+    return (r < 0.0f) ? r + fabsf(b) : r;
+}"
+                }
+                Helper::Sign => {
+                    "static float fidget_sign(float a) {
+    return (a > 0.0f) ? 1.0f : ((a < 0.0f) ? -1.0f : a);
+}"
+                }
+                _ => unreachable!("no {h:?} helper in C"),
+            },
+            Language::Rust => match h {
+                Helper::Min => {
+                    "fn fidget_min(a: f32, b: f32) -> f32 {
+    if a < b {
+        a
+    } else if b < a {
+        b
+    } else if a.is_nan() || b.is_nan() {
+        f32::NAN
+    } else {
+        b
+    }
+}"
+                }
+                Helper::Max => {
+                    "fn fidget_max(a: f32, b: f32) -> f32 {
+    if a > b {
+        a
+    } else if b > a {
+        b
+    } else if a.is_nan() || b.is_nan() {
+        f32::NAN
+    } else {
+        b
+    }
+}"
+                }
+                Helper::Compare => {
+                    "fn fidget_compare(a: f32, b: f32) -> f32 {
+    a.partial_cmp(&b).map(|c| c as i8 as f32).unwrap_or(f32::NAN)
+}"
+                }
+                Helper::Sign => {
+                    "fn fidget_sign(a: f32) -> f32 {
+    if a > 0.0 {
+        1.0
+    } else if a < 0.0 {
+        -1.0
+    } else {
+        a
+    }
+}"
+                }
+                _ => unreachable!("no {h:?} helper in Rust"),
+            },
+        }
+    }
+
+    /// Returns an expression which is `a` if `cond` is true and `b` otherwise
+    fn select(&self, cond: &str, a: &str, b: &str) -> String {
+        match self {
+            Language::Glsl | Language::C => format!("({cond}) ? {a} : {b}"),
+            Language::Wgsl => format!("select({b}, {a}, {cond})"),
+            Language::Rust => format!("if {cond} {{ {a} }} else {{ {b} }}"),
+        }
+    }
+}
+
+/// Code generator, which accumulates functions and the helpers they use
+struct Emitter {
+    language: Language,
+    helpers: BTreeSet<Helper>,
+    functions: Vec<String>,
+}
+
+impl Emitter {
+    fn new(language: Language) -> Self {
+        Self {
+            language,
+            helpers: BTreeSet::new(),
+            functions: vec![],
+        }
+    }
+
+    /// Marks a helper as used, returning its name
+    fn helper(&mut self, h: Helper) -> &'static str {
+        self.helpers.insert(h);
+        self.helpers.extend(h.deps(self.language));
+        h.name()
+    }
+
+    /// Formats a floating-point literal for use within an expression
+    ///
+    /// Negative values are wrapped in parentheses, except in Rust (which
+    /// warns about unnecessary parentheses); see [`Emitter::call`].
+    fn literal(&mut self, v: f32) -> String {
+        let out = self.bare_literal(v);
+        if v.is_sign_negative() && self.language != Language::Rust {
+            format!("({out})")
+        } else {
+            out
+        }
+    }
+
+    /// Formats a floating-point literal
+    fn bare_literal(&mut self, v: f32) -> String {
+        let s = if v.is_sign_negative() { "-" } else { "" };
+        match self.language {
+            Language::Glsl | Language::Wgsl => {
+                if v.is_nan() {
+                    format!("{}()", self.helper(Helper::Nan))
+                } else if v.is_infinite() {
+                    format!("{s}{}()", self.helper(Helper::Inf))
+                } else {
+                    format!("{v:?}")
+                }
+            }
+            Language::C => {
+                if v.is_nan() {
+                    "NAN".to_owned()
+                } else if v.is_infinite() {
+                    format!("{s}INFINITY")
+                } else {
+                    format!("{v:?}f")
+                }
+            }
+            Language::Rust => {
+                if v.is_nan() {
+                    "f32::NAN".to_owned()
+                } else if v.is_infinite() {
+                    format!("{s}f32::INFINITY")
+                } else {
+                    format!("{v:?}f32")
+                }
+            }
+        }
+    }
+
+    /// Returns an expression which calls the given function
+    fn call(&mut self, f: Call, args: &[&str]) -> String {
+        match f {
+            Call::Builtin(f) if self.language == Language::Rust => {
+                // Method calls bind more tightly than negation
+                let recv = if args[0].starts_with('-') {
+                    format!("({})", args[0])
+                } else {
+                    args[0].to_owned()
+                };
+                format!("{recv}.{f}({})", args[1..].join(", "))
+            }
+            Call::Builtin(f) => format!("{f}({})", args.join(", ")),
+            Call::Helper(h) => {
+                format!("{}({})", self.helper(h), args.join(", "))
+            }
+        }
+    }
+
+    /// Emits a function which evaluates the given SSA operations
+    fn function<I: Iterator<Item = SsaOp>>(
+        &mut self,
+        name: &str,
+        ops: I,
+        vars: &VarMap,
+        ret: Return,
+    ) -> Result<(), Error> {
+        let mut axes = [None; 3];
+        for (i, v) in [Var::X, Var::Y, Var::Z].iter().enumerate() {
+            if let Some(j) = vars.get(v) {
+                axes[i] = Some(j);
+            }
+        }
+        if axes.iter().flatten().count() != vars.len() {
+            return Err(Error::UnsupportedVar);
+        }
+
+        // Registers may be reassigned (e.g. when copying immediates for
+        // constant outputs), so each assignment gets a fresh local variable
+        let mut locals: HashMap<u32, String> = HashMap::new();
+        let mut count = 0;
+        let reg = |locals: &HashMap<u32, String>, r: u32| locals[&r].clone();
+
+        let mut body = String::new();
+        if self.language == Language::C {
+            // Avoid warnings about unused parameters
+            for (a, name) in axes.iter().zip(["x", "y", "z"]) {
+                if a.is_none() {
+                    writeln!(body, "    (void){name};").unwrap();
+                }
+            }
+        }
+        let mut outputs = vec![];
+        for op in ops {
+            let (out, expr) = match op {
+                SsaOp::Output(arg, i) => {
+                    let i = i as usize;
+                    if outputs.len() <= i {
+                        outputs.resize(i + 1, None);
+                    }
+                    outputs[i] = Some(reg(&locals, arg));
+                    continue;
+                }
+                SsaOp::Input(out, i) => {
+                    let axis = axes
+                        .iter()
+                        .position(|a| *a == Some(i as usize))
+                        .ok_or(Error::UnsupportedVar)?;
+                    let axis = ["x", "y", "z"][axis];
+                    match self.language {
+                        Language::Glsl | Language::Wgsl => {
+                            (out, format!("p.{axis}"))
+                        }
+                        Language::C | Language::Rust => (out, axis.to_owned()),
+                    }
+                }
+                SsaOp::CopyImm(out, imm) => (out, self.bare_literal(imm)),
+                SsaOp::NegReg(out, arg)
+                | SsaOp::AbsReg(out, arg)
+                | SsaOp::RecipReg(out, arg)
+                | SsaOp::SqrtReg(out, arg)
+                | SsaOp::SquareReg(out, arg)
+                | SsaOp::FloorReg(out, arg)
+                | SsaOp::CeilReg(out, arg)
+                | SsaOp::RoundReg(out, arg)
+                | SsaOp::SinReg(out, arg)
+                | SsaOp::CosReg(out, arg)
+                | SsaOp::TanReg(out, arg)
+                | SsaOp::AsinReg(out, arg)
+                | SsaOp::AcosReg(out, arg)
+                | SsaOp::AtanReg(out, arg)
+                | SsaOp::ExpReg(out, arg)
+                | SsaOp::LnReg(out, arg)
+                | SsaOp::NotReg(out, arg)
+                | SsaOp::SinhReg(out, arg)
+                | SsaOp::CoshReg(out, arg)
+                | SsaOp::TanhReg(out, arg)
+                | SsaOp::SignReg(out, arg)
+                | SsaOp::CbrtReg(out, arg)
+                | SsaOp::Log2Reg(out, arg)
+                | SsaOp::CopyReg(out, arg) => {
+                    let a = reg(&locals, arg);
+                    (out, self.unary(op, &a))
+                }
+                SsaOp::AddRegImm(out, arg, imm)
+                | SsaOp::MulRegImm(out, arg, imm)
+                | SsaOp::DivRegImm(out, arg, imm)
+                | SsaOp::SubRegImm(out, arg, imm)
+                | SsaOp::ModRegImm(out, arg, imm)
+                | SsaOp::AtanRegImm(out, arg, imm)
+                | SsaOp::CompareRegImm(out, arg, imm)
+                | SsaOp::PowRegImm(out, arg, imm)
+                | SsaOp::MinRegImm(out, arg, imm)
+                | SsaOp::MaxRegImm(out, arg, imm)
+                | SsaOp::AndRegImm(out, arg, imm)
+                | SsaOp::OrRegImm(out, arg, imm) => {
+                    let a = reg(&locals, arg);
+                    let b = self.literal(imm);
+                    (out, self.binary(op, &a, &b))
+                }
+                SsaOp::DivImmReg(out, arg, imm)
+                | SsaOp::SubImmReg(out, arg, imm)
+                | SsaOp::ModImmReg(out, arg, imm)
+                | SsaOp::AtanImmReg(out, arg, imm)
+                | SsaOp::CompareImmReg(out, arg, imm)
+                | SsaOp::PowImmReg(out, arg, imm) => {
+                    let a = self.literal(imm);
+                    let b = reg(&locals, arg);
+                    (out, self.binary(op, &a, &b))
+                }
+                SsaOp::AddRegReg(out, lhs, rhs)
+                | SsaOp::MulRegReg(out, lhs, rhs)
+                | SsaOp::DivRegReg(out, lhs, rhs)
+                | SsaOp::SubRegReg(out, lhs, rhs)
+                | SsaOp::CompareRegReg(out, lhs, rhs)
+                | SsaOp::AtanRegReg(out, lhs, rhs)
+                | SsaOp::PowRegReg(out, lhs, rhs)
+                | SsaOp::MinRegReg(out, lhs, rhs)
+                | SsaOp::MaxRegReg(out, lhs, rhs)
+                | SsaOp::AndRegReg(out, lhs, rhs)
+                | SsaOp::OrRegReg(out, lhs, rhs)
+                | SsaOp::ModRegReg(out, lhs, rhs) => {
+                    let a = reg(&locals, lhs);
+                    let b = reg(&locals, rhs);
+                    (out, self.binary(op, &a, &b))
+                }
+                SsaOp::SelectRegRegReg(out, cond, a, b) => {
+                    let zero = self.literal(0.0);
+                    let cond = format!("{} != {zero}", reg(&locals, cond));
+                    let (a, b) = (reg(&locals, a), reg(&locals, b));
+                    (out, self.language.select(&cond, &a, &b))
+                }
+            };
+            let name = format!("v{count}");
+            count += 1;
+            match self.language {
+                Language::Glsl | Language::C => {
+                    writeln!(body, "    float {name} = {expr};")
+                }
+                Language::Wgsl | Language::Rust => {
+                    writeln!(body, "    let {name}: f32 = {expr};")
+                }
+            }
+            .unwrap();
+            locals.insert(out, name);
+        }
+
+        let outputs = outputs
+            .into_iter()
+            .collect::<Option<Vec<String>>>()
+            .expect("missing output");
+        let mut out = String::new();
+        match (self.language, ret) {
+            (Language::Glsl, Return::Scalar) => {
+                writeln!(out, "float {name}(vec3 p) {{").unwrap();
+                out += &body;
+                writeln!(out, "    return {};", outputs[0]).unwrap();
+            }
+            (Language::Glsl, Return::Vec3) => {
+                writeln!(out, "vec3 {name}(vec3 p) {{").unwrap();
+                out += &body;
+                writeln!(out, "    return vec3({});", outputs.join(", "))
+                    .unwrap();
+            }
+            (Language::Wgsl, Return::Scalar) => {
+                writeln!(out, "fn {name}(p: vec3<f32>) -> f32 {{").unwrap();
+                out += &body;
+                writeln!(out, "    return {};", outputs[0]).unwrap();
+            }
+            (Language::Wgsl, Return::Vec3) => {
+                writeln!(out, "fn {name}(p: vec3<f32>) -> vec3<f32> {{")
+                    .unwrap();
+                out += &body;
+                writeln!(out, "    return vec3<f32>({});", outputs.join(", "))
+                    .unwrap();
+            }
+            (Language::C, Return::Scalar) => {
+                writeln!(out, "float {name}(float x, float y, float z) {{")
+                    .unwrap();
+                out += &body;
+                writeln!(out, "    return {};", outputs[0]).unwrap();
+            }
+            (Language::C, Return::Vec3) => {
+                writeln!(
+                    out,
+                    "void {name}(float x, float y, float z, float out[3]) {{"
+                )
+                .unwrap();
+                out += &body;
+                for (i, o) in outputs.iter().enumerate() {
+                    writeln!(out, "    out[{i}] = {o};").unwrap();
+                }
+            }
+            (Language::Rust, _) => {
+                // Unused axes are still present in the function signature
+                if axes.contains(&None) {
+                    writeln!(out, "#[allow(unused_variables)]").unwrap();
+                }
+                let t = match ret {
+                    Return::Scalar => "f32",
+                    Return::Vec3 => "[f32; 3]",
+                };
+                writeln!(
+                    out,
+                    "pub fn {name}(x: f32, y: f32, z: f32) -> {t} {{"
+                )
+                .unwrap();
+                out += &body;
+                match ret {
+                    Return::Scalar => writeln!(out, "    {}", outputs[0]),
+                    Return::Vec3 => {
+                        writeln!(out, "    [{}]", outputs.join(", "))
+                    }
+                }
+                .unwrap();
+            }
+        }
+        out += "}\n";
+        self.functions.push(out);
+        Ok(())
+    }
+
+    /// Returns an expression for a single-argument operation
+    fn unary(&mut self, op: SsaOp, a: &str) -> String {
+        use Language::*;
+        let lang = self.language;
+        let f = match op {
+            SsaOp::CopyReg(..) => return a.to_owned(),
+            SsaOp::NegReg(..) => return format!("-{a}"),
+            SsaOp::RecipReg(..) => {
+                return format!("{} / {a}", self.literal(1.0));
+            }
+            SsaOp::SquareReg(..) => return format!("{a} * {a}"),
+            SsaOp::NotReg(..) => {
+                let cond = format!("{a} == {}", self.literal(0.0));
+                let (t, f) = (self.literal(1.0), self.literal(0.0));
+                return self.language.select(&cond, &t, &f);
+            }
+            SsaOp::AbsReg(..) if lang == C => Call::Builtin("fabsf"),
+            SsaOp::AbsReg(..) => Call::Builtin("abs"),
+            SsaOp::SqrtReg(..) if lang == C => Call::Builtin("sqrtf"),
+            SsaOp::SqrtReg(..) => Call::Builtin("sqrt"),
+            SsaOp::FloorReg(..) if lang == C => Call::Builtin("floorf"),
+            SsaOp::FloorReg(..) => Call::Builtin("floor"),
+            SsaOp::CeilReg(..) if lang == C => Call::Builtin("ceilf"),
+            SsaOp::CeilReg(..) => Call::Builtin("ceil"),
+            SsaOp::RoundReg(..) => match lang {
+                Glsl | Wgsl => Call::Helper(Helper::Round),
+                C => Call::Builtin("roundf"),
+                Rust => Call::Builtin("round"),
+            },
+            SsaOp::SinReg(..) if lang == C => Call::Builtin("sinf"),
+            SsaOp::SinReg(..) => Call::Builtin("sin"),
+            SsaOp::CosReg(..) if lang == C => Call::Builtin("cosf"),
+            SsaOp::CosReg(..) => Call::Builtin("cos"),
+            SsaOp::TanReg(..) if lang == C => Call::Builtin("tanf"),
+            SsaOp::TanReg(..) => Call::Builtin("tan"),
+            SsaOp::AsinReg(..) if lang == C => Call::Builtin("asinf"),
+            SsaOp::AsinReg(..) => Call::Builtin("asin"),
+            SsaOp::AcosReg(..) if lang == C => Call::Builtin("acosf"),
+            SsaOp::AcosReg(..) => Call::Builtin("acos"),
+            SsaOp::AtanReg(..) if lang == C => Call::Builtin("atanf"),
+            SsaOp::AtanReg(..) => Call::Builtin("atan"),
+            SsaOp::ExpReg(..) if lang == C => Call::Builtin("expf"),
+            SsaOp::ExpReg(..) => Call::Builtin("exp"),
+            SsaOp::LnReg(..) => match lang {
+                Glsl | Wgsl => Call::Builtin("log"),
+                C => Call::Builtin("logf"),
+                Rust => Call::Builtin("ln"),
+            },
+            SsaOp::SinhReg(..) if lang == C => Call::Builtin("sinhf"),
+            SsaOp::SinhReg(..) => Call::Builtin("sinh"),
+            SsaOp::CoshReg(..) if lang == C => Call::Builtin("coshf"),
+            SsaOp::CoshReg(..) => Call::Builtin("cosh"),
+            SsaOp::TanhReg(..) if lang == C => Call::Builtin("tanhf"),
+            SsaOp::TanhReg(..) => Call::Builtin("tanh"),
+            SsaOp::SignReg(..) => Call::Helper(Helper::Sign),
+            SsaOp::CbrtReg(..) => match lang {
+                Glsl | Wgsl => Call::Helper(Helper::Cbrt),
+                C => Call::Builtin("cbrtf"),
+                Rust => Call::Builtin("cbrt"),
+            },
+            SsaOp::Log2Reg(..) if lang == C => Call::Builtin("log2f"),
+            SsaOp::Log2Reg(..) => Call::Builtin("log2"),
+            _ => unreachable!("not a unary operation: {op:?}"),
+        };
+        self.call(f, &[a])
+    }
+
+    /// Returns an expression for a two-argument operation
+    fn binary(&mut self, op: SsaOp, a: &str, b: &str) -> String {
+        use Language::*;
+        let f = match op {
+            SsaOp::AddRegImm(..) | SsaOp::AddRegReg(..) => {
+                return format!("{a} + {b}")
+            }
+            SsaOp::SubRegImm(..)
+            | SsaOp::SubImmReg(..)
+            | SsaOp::SubRegReg(..) => return format!("{a} - {b}"),
+            SsaOp::MulRegImm(..) | SsaOp::MulRegReg(..) => {
+                return format!("{a} * {b}")
+            }
+            SsaOp::DivRegImm(..)
+            | SsaOp::DivImmReg(..)
+            | SsaOp::DivRegReg(..) => return format!("{a} / {b}"),
+            SsaOp::AndRegImm(..) | SsaOp::AndRegReg(..) => {
+                let cond = format!("{a} == {}", self.literal(0.0));
+                return self.language.select(&cond, a, b);
+            }
+            SsaOp::OrRegImm(..) | SsaOp::OrRegReg(..) => {
+                let cond = format!("{a} != {}", self.literal(0.0));
+                return self.language.select(&cond, a, b);
+            }
+            SsaOp::AtanRegImm(..)
+            | SsaOp::AtanImmReg(..)
+            | SsaOp::AtanRegReg(..) => match self.language {
+                Glsl => Call::Builtin("atan"),
+                Wgsl | Rust => Call::Builtin("atan2"),
+                C => Call::Builtin("atan2f"),
+            },
+            SsaOp::MinRegImm(..) | SsaOp::MinRegReg(..) => {
+                Call::Helper(Helper::Min)
+            }
+            SsaOp::MaxRegImm(..) | SsaOp::MaxRegReg(..) => {
+                Call::Helper(Helper::Max)
+            }
+            SsaOp::CompareRegImm(..)
+            | SsaOp::CompareImmReg(..)
+            | SsaOp::CompareRegReg(..) => Call::Helper(Helper::Compare),
+            SsaOp::ModRegImm(..)
+            | SsaOp::ModImmReg(..)
+            | SsaOp::ModRegReg(..) => match self.language {
+                Glsl | Wgsl | C => Call::Helper(Helper::Mod),
+                Rust => Call::Builtin("rem_euclid"),
+            },
+            SsaOp::PowRegImm(..)
+            | SsaOp::PowImmReg(..)
+            | SsaOp::PowRegReg(..) => match self.language {
+                Glsl | Wgsl => Call::Helper(Helper::Pow),
+                C => Call::Builtin("powf"),
+                Rust => Call::Builtin("powf"),
+            },
+            _ => unreachable!("not a binary operation: {op:?}"),
+        };
+        self.call(f, &[a, b])
+    }
+
+    /// Returns the generated code, with helpers before functions
+    fn finish(self) -> String {
+        let mut out = String::new();
+        if self.language == Language::C {
+            out += "#include <math.h>\n\n";
+        }
+        for h in &self.helpers {
+            out += self.language.helper(*h);
+            out += "\n\n";
+        }
+        out += &self.functions.join("\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        eval::{Function, MathFunction, TracingEvaluator},
+        vm::{VmFunction, VmPointEval},
+    };
+
+    /// Builds an expression which uses every opcode
+    fn every_op() -> Tree {
+        let (x, y, z) = Tree::axes();
+        let t = x.clone().abs() + (1.0 / y.clone()) - z.clone().sqrt();
+        let t = t * x.square() / y.floor().min(z.ceil()).max(x.round());
+        let t = t + x.sin() + y.cos() + z.tan() + x.asin() + y.acos();
+        let t = t + z.atan() + x.exp() + y.ln() + z.not() + x.sinh();
+        let t = t + y.cosh() + z.tanh() + x.sign() + y.cbrt() + z.log2();
+        let t =
+            t + x.atan2(y.clone()) + y.compare(z.clone()) + z.modulo(x.clone());
+        let t = t + x.and(y.clone()) + y.or(z.clone()) + z.pow(x.clone());
+        let t = t + x.neg().pow(2.5) + Tree::constant(3.0).pow(y.clone());
+        let t = t + (1.0 - x.clone()) + Tree::constant(2.0) / y.clone();
+        let t = t + Tree::constant(3.0).modulo(z.clone());
+        let t = t + x.min(f64::NAN) + y.max(f64::INFINITY);
+        t.if_nonzero_else(t.clone() * 2.0, -1e-9)
+    }
+
+    fn validate_wgsl(src: &str) {
+        let module = naga::front::wgsl::parse_str(src)
+            .unwrap_or_else(|e| panic!("{}\n{src}", e.emit_to_string(src)));
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{e:?}\n{src}"));
+    }
+
+    fn validate_glsl(src: &str, gradient: bool) {
+        // Wrap the function in a fragment shader, so that it's a valid module
+        let src = format!(
+            "#version 450\n{src}\n\
+             layout(location = 0) out vec4 color;\n\
+             void main() {{\n\
+                 vec3 p = gl_FragCoord.xyz;\n\
+                 color = vec4({}, f(p));\n\
+             }}\n",
+            if gradient { "f_grad(p)" } else { "p" }
+        );
+        let mut frontend = naga::front::glsl::Frontend::default();
+        let options =
+            naga::front::glsl::Options::from(naga::ShaderStage::Fragment);
+        let module = frontend
+            .parse(&options, &src)
+            .unwrap_or_else(|e| panic!("{e:?}\n{src}"));
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{e:?}\n{src}"));
+    }
+
+    #[test]
+    fn test_emit_text() {
+        let tree = (Tree::x().square() + Tree::y().square()).sqrt() - 1.0;
+        let mut ctx = Context::new();
+        let root = ctx.import(&tree);
+
+        let glsl = emit_context(&ctx, root, Language::Glsl, false).unwrap();
+        assert_eq!(
+            glsl,
+            "float f(vec3 p) {
+    float v0 = p.x;
+    float v1 = v0 * v0;
+    float v2 = p.y;
+    float v3 = v2 * v2;
+    float v4 = v1 + v3;
+    float v5 = sqrt(v4);
+    float v6 = v5 - 1.0;
+    return v6;
+}
+"
+        );
+
+        let wgsl = emit_context(&ctx, root, Language::Wgsl, false).unwrap();
+        assert!(wgsl.starts_with("fn f(p: vec3<f32>) -> f32 {\n"));
+        assert!(wgsl.contains("    let v5: f32 = sqrt(v4);\n"));
+        assert!(!wgsl.contains("fidget_"));
+
+        // Helpers are only emitted once, before their users
+        let tree = Tree::x().min(Tree::y()).min(Tree::z().max(0.0));
+        let root = ctx.import(&tree);
+        let wgsl = emit_context(&ctx, root, Language::Wgsl, true).unwrap();
+        assert_eq!(wgsl.matches("fn fidget_min(").count(), 1);
+        assert!(wgsl.find("fn fidget_nan(") < wgsl.find("fn fidget_min("));
+        assert!(wgsl.find("fn fidget_min(") < wgsl.find("fn f("));
+        assert!(wgsl.contains("fn f_grad(p: vec3<f32>) -> vec3<f32> {\n"));
+
+        let c = emit_context(&ctx, root, Language::C, true).unwrap();
+        assert!(c.starts_with("#include <math.h>\n"));
+        assert!(c.contains("float f(float x, float y, float z) {\n"));
+        assert!(c.contains("    float v4 = fidget_max(v3, 0.0f);\n"));
+        assert!(c.contains(
+            "void f_grad(float x, float y, float z, float out[3]) {\n"
+        ));
+        assert!(!c.contains("fidget_nan"));
+
+        let root = ctx.import(&Tree::y().abs());
+        let c = emit_context(&ctx, root, Language::C, false).unwrap();
+        assert_eq!(
+            c,
+            "#include <math.h>
+
+float f(float x, float y, float z) {
+    (void)x;
+    (void)z;
+    float v0 = y;
+    float v1 = fabsf(v0);
+    return v1;
+}
+"
+        );
+
+        let tree = Tree::x().sin().pow(Tree::y()) / -2.0;
+        let root = ctx.import(&tree);
+        let rust = emit_context(&ctx, root, Language::Rust, false).unwrap();
+        assert_eq!(
+            rust,
+            "#[allow(unused_variables)]
+pub fn f(x: f32, y: f32, z: f32) -> f32 {
+    let v0: f32 = x;
+    let v1: f32 = v0.sin();
+    let v2: f32 = y;
+    let v3: f32 = v1.powf(v2);
+    let v4: f32 = v3 / -2.0f32;
+    v4
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_emit_validate() {
+        let mut ctx = Context::new();
+        let root = ctx.import(&every_op());
+        for gradient in [false, true] {
+            let src = emit_context(&ctx, root, Language::Wgsl, gradient);
+            validate_wgsl(&src.unwrap());
+            let src = emit_context(&ctx, root, Language::Glsl, gradient);
+            validate_glsl(&src.unwrap(), gradient);
+        }
+
+        // Constant expressions are valid as well
+        let c = ctx.constant(1.5);
+        validate_wgsl(&emit_context(&ctx, c, Language::Wgsl, true).unwrap());
+        let src = emit_context(&ctx, c, Language::Glsl, true).unwrap();
+        validate_glsl(&src, true);
+    }
+
+    #[test]
+    fn test_emit_vm_data() {
+        let mut ctx = Context::new();
+        let root = ctx.import(&every_op());
+        let data = VmData::<255>::new(&ctx, &[root]).unwrap();
+        validate_wgsl(&emit_vm_data(&data, Language::Wgsl).unwrap());
+        validate_glsl(&emit_vm_data(&data, Language::Glsl).unwrap(), false);
+
+        let data = VmData::<255>::new(&ctx, &[root, root]).unwrap();
+        assert!(matches!(
+            emit_vm_data(&data, Language::Glsl),
+            Err(Error::MultipleOutputs(2))
+        ));
+    }
+
+    /// Compiles generated Rust code with `rustc`, then checks that its results
+    /// match [`VmPointEval`] on a grid of points
+    ///
+    /// Each case is a function and its generated code, which must define
+    /// either `f` (for a single output) or `f_grad` (for three outputs).
+    fn check_rust(name: &str, cases: &[(VmFunction, String)]) {
+        let values = [-2.5, -1.0, -0.5, 0.0, 0.25, 1.0, 3.0, f32::NAN];
+        let mut points = vec![];
+        for x in values {
+            for y in values {
+                for z in values {
+                    points.push([x, y, z]);
+                }
+            }
+        }
+
+        let mut src = String::new();
+        for (i, (_, code)) in cases.iter().enumerate() {
+            writeln!(src, "mod e{i} {{\n{code}}}").unwrap();
+        }
+        src += "fn main() {\n    let points: &[[u32; 3]] = &[\n";
+        for p in &points {
+            let [x, y, z] = p.map(f32::to_bits);
+            writeln!(src, "        [{x}, {y}, {z}],").unwrap();
+        }
+        src += "    ];\n    for p in points {\n";
+        src += "        let [x, y, z] = p.map(|b| {\n";
+        src += "            std::hint::black_box(f32::from_bits(b))\n";
+        src += "        });\n";
+        for (i, (f, _)) in cases.iter().enumerate() {
+            let call = match f.data().output_count() {
+                1 => format!("[e{i}::f(x, y, z)]"),
+                3 => format!("e{i}::f_grad(x, y, z)"),
+                n => panic!("invalid output count {n}"),
+            };
+            writeln!(src, "        let out = {call};").unwrap();
+            src += "        let out = out.map(f32::to_bits);\n";
+            src += "        println!(\"{out:?}\");\n";
+        }
+        src += "    }\n}\n";
+
+        let dir = std::env::temp_dir()
+            .join(format!("fidget-codegen-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src_path = dir.join("main.rs");
+        let bin_path = dir.join("main");
+        std::fs::write(&src_path, &src).unwrap();
+        let rustc = std::env::var("RUSTC").unwrap_or("rustc".to_owned());
+        let out = std::process::Command::new(rustc)
+            .args(["--edition", "2021", "-o"])
+            .arg(&bin_path)
+            .arg(&src_path)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "rustc failed:\n{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        let out = std::process::Command::new(&bin_path).output().unwrap();
+        assert!(out.status.success());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let stdout = String::from_utf8(out.stdout).unwrap();
+        let mut lines = stdout.lines();
+        let mut eval = VmPointEval::new();
+        for [x, y, z] in &points {
+            for (f, code) in cases {
+                let line = lines.next().unwrap();
+                let actual = line
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .split(", ")
+                    .map(|v| f32::from_bits(v.parse().unwrap()))
+                    .collect::<Vec<f32>>();
+
+                let mut vars = vec![0.0; f.vars().len()];
+                for (v, value) in [(Var::X, x), (Var::Y, y), (Var::Z, z)] {
+                    if let Some(i) = f.vars().get(&v) {
+                        vars[i] = *value;
+                    }
+                }
+                let tape = f.point_tape(Default::default());
+                let (expected, _) = eval.eval(&tape, &vars).unwrap();
+                assert_eq!(actual.len(), expected.len());
+                for (a, e) in actual.iter().zip(expected) {
+                    assert!(
+                        a.to_bits() == e.to_bits()
+                            || (a.is_nan() && e.is_nan()),
+                        "mismatch at ({x}, {y}, {z}): {a} != {e}\n{code}"
+                    );
+                }
+            }
+        }
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_emit_rust() {
+        let (x, y, z) = Tree::axes();
+        let mut trees = vec![
+            x.neg(),
+            x.abs(),
+            x.sqrt(),
+            x.square(),
+            x.floor(),
+            x.ceil(),
+            x.round(),
+            x.sin(),
+            x.cos(),
+            x.tan(),
+            x.asin(),
+            x.acos(),
+            x.atan(),
+            x.exp(),
+            x.ln(),
+            x.not(),
+            x.sinh(),
+            x.cosh(),
+            x.tanh(),
+            x.sign(),
+            x.cbrt(),
+            x.log2(),
+            x.if_nonzero_else(y.clone(), z.clone()),
+            x.if_nonzero_else(1.5, -2.0),
+            Tree::constant(0.5),
+            Tree::constant(f64::NAN),
+            Tree::constant(f64::NEG_INFINITY),
+        ];
+        let binary: [fn(Tree, Tree) -> Tree; 10] = [
+            |a, b| a + b,
+            |a, b| a - b,
+            |a, b| a * b,
+            |a, b| a / b,
+            |a, b| a.atan2(b),
+            |a, b| a.min(b),
+            |a, b| a.max(b),
+            |a, b| a.compare(b),
+            |a, b| a.modulo(b),
+            |a, b| a.pow(b),
+        ];
+        for op in binary {
+            trees.push(op(x.clone(), y.clone()));
+            trees.push(op(x.clone(), Tree::constant(-1.5)));
+            trees.push(op(Tree::constant(2.0), x.clone()));
+            trees.push(op(x.clone(), Tree::constant(f64::NAN)));
+        }
+        for op in [Tree::and, Tree::or] {
+            trees.push(op(&x, y.clone()));
+            trees.push(op(&x, Tree::constant(-1.5)));
+        }
+        trees.push(every_op());
+
+        let mut ctx = Context::new();
+        let mut cases = vec![];
+        for t in &trees {
+            let root = ctx.import(t);
+            let f = VmFunction::new(&ctx, &[root]).unwrap();
+            let code = emit_context(&ctx, root, Language::Rust, false).unwrap();
+            cases.push((f, code));
+        }
+        let x = ctx.x();
+        let root = ctx.recip(x).unwrap();
+        let f = VmFunction::new(&ctx, &[root]).unwrap();
+        cases.push((
+            f,
+            emit_context(&ctx, root, Language::Rust, false).unwrap(),
+        ));
+
+        // Gradients are built in a separate context by `emit_context`
+        let root = ctx.import(&every_op());
+        let code = emit_context(&ctx, root, Language::Rust, true).unwrap();
+        let mut ctx_grad = Context::new();
+        let root_grad = ctx_grad.import(&every_op());
+        let grad = ctx_grad
+            .gradient(root_grad, &[Var::X, Var::Y, Var::Z])
+            .unwrap();
+        cases.push((VmFunction::new(&ctx_grad, &grad).unwrap(), code));
+
+        // Simplified tapes are emitted with their own semantics
+        let f = VmFunction::new(&ctx, &[root]).unwrap();
+        let mut eval = VmPointEval::new();
+        let tape = f.point_tape(Default::default());
+        let (_, trace) = eval.eval(&tape, &[0.5, 2.0, 0.25]).unwrap();
+        let f = f
+            .simplify(
+                trace.unwrap(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        assert!(f.size() < cases.last().unwrap().0.size());
+        let code = emit_vm_function(&f, Language::Rust).unwrap();
+        cases.push((f, code));
+
+        check_rust("rust", &cases);
+    }
+
+    #[test]
+    fn test_emit_unsupported_var() {
+        let mut ctx = Context::new();
+        let v = ctx.var(Var::new());
+        let x = ctx.x();
+        let root = ctx.add(x, v).unwrap();
+        assert!(matches!(
+            emit_context(&ctx, root, Language::Glsl, false),
+            Err(Error::UnsupportedVar)
+        ));
+    }
+}
@@ -257,6 +257,12 @@ impl<const N: usize> RegisterAllocator<N> {
             SsaOp::ExpReg(out, arg) => (out, arg, RegOp::ExpReg),
             SsaOp::LnReg(out, arg) => (out, arg, RegOp::LnReg),
             SsaOp::NotReg(out, arg) => (out, arg, RegOp::NotReg),
+            SsaOp::SinhReg(out, arg) => (out, arg, RegOp::SinhReg),
+            SsaOp::CoshReg(out, arg) => (out, arg, RegOp::CoshReg),
+            SsaOp::TanhReg(out, arg) => (out, arg, RegOp::TanhReg),
+            SsaOp::SignReg(out, arg) => (out, arg, RegOp::SignReg),
+            SsaOp::CbrtReg(out, arg) => (out, arg, RegOp::CbrtReg),
+            SsaOp::Log2Reg(out, arg) => (out, arg, RegOp::Log2Reg),
             SsaOp::CopyReg(out, arg) => (out, arg, RegOp::CopyReg),
             _ => panic!("Bad opcode: {op:?}"),
         };
@@ -288,7 +294,13 @@ impl<const N: usize> RegisterAllocator<N> {
             | SsaOp::AtanReg(..)
             | SsaOp::ExpReg(..)
             | SsaOp::LnReg(..)
-            | SsaOp::NotReg(..) => self.op_reg(op),
+            | SsaOp::NotReg(..)
+            | SsaOp::SinhReg(..)
+            | SsaOp::CoshReg(..)
+            | SsaOp::TanhReg(..)
+            | SsaOp::SignReg(..)
+            | SsaOp::CbrtReg(..)
+            | SsaOp::Log2Reg(..) => self.op_reg(op),
 
             SsaOp::AddRegImm(..)
             | SsaOp::SubRegImm(..)
@@ -305,7 +317,9 @@ impl<const N: usize> RegisterAllocator<N> {
             | SsaOp::ModRegImm(..)
             | SsaOp::ModImmReg(..)
             | SsaOp::AndRegImm(..)
-            | SsaOp::OrRegImm(..) => self.op_reg_imm(op),
+            | SsaOp::OrRegImm(..)
+            | SsaOp::PowRegImm(..)
+            | SsaOp::PowImmReg(..) => self.op_reg_imm(op),
 
             SsaOp::AddRegReg(..)
             | SsaOp::SubRegReg(..)
@@ -317,7 +331,12 @@ impl<const N: usize> RegisterAllocator<N> {
             | SsaOp::CompareRegReg(..)
             | SsaOp::ModRegReg(..)
             | SsaOp::AndRegReg(..)
-            | SsaOp::OrRegReg(..) => self.op_reg_reg(op),
+            | SsaOp::OrRegReg(..)
+            | SsaOp::PowRegReg(..) => self.op_reg_reg(op),
+
+            SsaOp::SelectRegRegReg(out, cond, a, b) => {
+                self.op_select(out, cond, a, b)
+            }
         }
     }
 
@@ -513,6 +532,9 @@ impl<const N: usize> RegisterAllocator<N> {
                 (out, lhs, rhs, RegOp::AndRegReg)
             }
             SsaOp::OrRegReg(out, lhs, rhs) => (out, lhs, rhs, RegOp::OrRegReg),
+            SsaOp::PowRegReg(out, lhs, rhs) => {
+                (out, lhs, rhs, RegOp::PowRegReg)
+            }
             _ => panic!("Bad opcode: {op:?}"),
         };
         let r_x = self.get_out_reg(out);
@@ -595,6 +617,88 @@ impl<const N: usize> RegisterAllocator<N> {
         }
     }
 
+    /// Lowers a three-register select operation into an [`RegOp`], pushing it
+    /// to the internal tape.
+    ///
+    /// Rather than enumerating every configuration (as in
+    /// [`op_reg_reg`](Self::op_reg_reg)), each unique input is handled in
+    /// turn:
+    ///
+    /// - An input in a register `r_y` is used directly
+    /// - The first input that is in memory or unassigned takes over the output
+    ///   register `r_x`, since the output value is not live before this
+    ///   operation.  If the input was in memory at `m_y`, then it is stored
+    ///   _before_ the operation (when read forward), i.e. `store r_x -> m_y`
+    ///   followed by `r_x = select ...`
+    /// - Any other input that is in memory or unassigned is given a new
+    ///   register `r_a` (which may push a `load r_a <- m_a` to restore the
+    ///   previous occupant of `r_a`).  If the input was in memory at `m_y`,
+    ///   then it's stored afterwards with `store r_a -> m_y`.
+    ///
+    /// This means that we never need more than four registers (or three, if
+    /// any of the inputs are repeated or already in registers).
+    #[inline(always)]
+    fn op_select(&mut self, out: u32, cond: u32, a: u32, b: u32) {
+        let r_x = self.get_out_reg(out);
+
+        let args = [cond, a, b];
+        let mut regs = [r_x; 3];
+        let mut pending: [Option<(Allocation, u8)>; 3] = [None; 3];
+        let mut out_reused = false;
+        for i in 0..args.len() {
+            if let Some(j) = (0..i).find(|&j| args[j] == args[i]) {
+                regs[i] = regs[j];
+                continue;
+            }
+            let alloc = self.get_allocation(args[i]);
+            if let Allocation::Register(r_y) = alloc {
+                assert!(r_y != r_x);
+                regs[i] = r_y;
+            } else {
+                let r = if out_reused {
+                    self.get_register()
+                } else {
+                    out_reused = true;
+                    r_x
+                };
+                regs[i] = r;
+                pending[i] = Some((alloc, r));
+            }
+        }
+
+        // Stores into new registers happen after the operation (read forward)
+        for p in pending.iter().flatten() {
+            if let (Allocation::Memory(m_y), r) = *p {
+                if r != r_x {
+                    self.push_store(r, m_y);
+                }
+            }
+        }
+        self.out
+            .push(RegOp::SelectRegRegReg(r_x, regs[0], regs[1], regs[2]));
+
+        // The store from the output register must happen before the operation
+        // (read forward), because the operation will overwrite it.
+        for p in pending.iter().flatten() {
+            if let (Allocation::Memory(m_y), r) = *p {
+                if r == r_x {
+                    self.push_store(r, m_y);
+                }
+            }
+        }
+
+        if !out_reused {
+            self.release_reg(r_x);
+        }
+        for (arg, p) in args.iter().zip(pending.iter()) {
+            match *p {
+                Some((_, r)) if r == r_x => self.rebind_register(*arg, r),
+                Some((_, r)) => self.bind_register(*arg, r),
+                None => (),
+            }
+        }
+    }
+
     /// Lowers a function taking one register and one immediate into an
     /// [`RegOp`], pushing it to the internal tape.
     #[inline(always)]
@@ -647,6 +751,12 @@ impl<const N: usize> RegisterAllocator<N> {
                 (out, arg, imm, RegOp::AndRegImm)
             }
             SsaOp::OrRegImm(out, arg, imm) => (out, arg, imm, RegOp::OrRegImm),
+            SsaOp::PowRegImm(out, arg, imm) => {
+                (out, arg, imm, RegOp::PowRegImm)
+            }
+            SsaOp::PowImmReg(out, arg, imm) => {
+                (out, arg, imm, RegOp::PowImmReg)
+            }
             _ => panic!("Bad opcode: {op:?}"),
         };
         self.op_reg_fn(out, arg, |out, arg| op(out, arg, imm));
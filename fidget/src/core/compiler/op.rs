@@ -68,6 +68,18 @@ macro_rules! opcodes {
             LnReg($t, $t),
             #[doc = "Computes the logical negation of the given register\n\nEquivalent to `if arg == 0 { 1 } else { 0 }`"]
             NotReg($t, $t),
+            #[doc = "Computes the hyperbolic sine of the given register"]
+            SinhReg($t, $t),
+            #[doc = "Computes the hyperbolic cosine of the given register"]
+            CoshReg($t, $t),
+            #[doc = "Computes the hyperbolic tangent of the given register"]
+            TanhReg($t, $t),
+            #[doc = "Computes the sign of the given register\n\nThe result is -1 for negative values, +1 for positive values, and the input for zero or `NaN`"]
+            SignReg($t, $t),
+            #[doc = "Computes the cube root of the given register"]
+            CbrtReg($t, $t),
+            #[doc = "Computes the base-2 log of the given register"]
+            Log2Reg($t, $t),
 
             // RegImm opcodes (without a choice)
             #[doc = "Add a register and an immediate"]
@@ -90,6 +102,8 @@ macro_rules! opcodes {
             AtanRegImm($t, $t, f32),
             #[doc = "Compares a register with an immediate"]
             CompareRegImm($t, $t, f32),
+            #[doc = "Raises a register to the power of an immediate"]
+            PowRegImm($t, $t, f32),
 
             // RegImm opcodes (with a choice)
             #[doc = "Compute the minimum of a register and an immediate"]
@@ -108,6 +122,8 @@ macro_rules! opcodes {
             AtanImmReg($t, $t, f32),
             #[doc = "Compares an immediate with a register"]
             CompareImmReg($t, $t, f32),
+            #[doc = "Raises an immediate to the power of a register"]
+            PowImmReg($t, $t, f32),
 
             // RegReg opcodes (without a choice)
             #[doc = "Add two registers"]
@@ -122,6 +138,8 @@ macro_rules! opcodes {
             CompareRegReg($t, $t, $t),
             #[doc = "atan2 of a position `(y, x)` specified as register, register"]
             AtanRegReg($t, $t, $t),
+            #[doc = "Raises one register to the power of another"]
+            PowRegReg($t, $t, $t),
 
             // RegReg opcodes (with a choice)
             #[doc = "Take the minimum of two registers"]
@@ -133,6 +151,10 @@ macro_rules! opcodes {
             #[doc = "Logical `OR` (short-circuiting)\n\nThis is equivalent to `if lhs != 0 { lhs } else { rhs }`"]
             OrRegReg($t, $t, $t),
 
+            // RegRegReg opcodes (with a choice)
+            #[doc = "Selects between two registers based on a condition register\n\nArguments are `(out, cond, a, b)`; this is equivalent to `if cond != 0 { a } else { b }`"]
+            SelectRegRegReg($t, $t, $t, $t),
+
             $(
                 $(#[$($a)*])*
                 $foo($($i),*)
@@ -149,6 +171,9 @@ opcodes!(
     /// - LHS register (or input slot for [`Input`](SsaOp::Input))
     /// - RHS register (or immediate for `*Imm`)
     ///
+    /// [`SelectRegRegReg`](SsaOp::SelectRegRegReg) is the exception, taking
+    /// an output register followed by three input registers.
+    ///
     /// Each "register" represents an SSA slot, which is never reused.
     #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
     pub enum SsaOp<u32> {
@@ -180,6 +205,12 @@ impl SsaOp {
             | SsaOp::ExpReg(out, ..)
             | SsaOp::LnReg(out, ..)
             | SsaOp::NotReg(out, ..)
+            | SsaOp::SinhReg(out, ..)
+            | SsaOp::CoshReg(out, ..)
+            | SsaOp::TanhReg(out, ..)
+            | SsaOp::SignReg(out, ..)
+            | SsaOp::CbrtReg(out, ..)
+            | SsaOp::Log2Reg(out, ..)
             | SsaOp::AddRegImm(out, ..)
             | SsaOp::MulRegImm(out, ..)
             | SsaOp::DivRegImm(out, ..)
@@ -203,10 +234,14 @@ impl SsaOp {
             | SsaOp::ModRegReg(out, ..)
             | SsaOp::ModRegImm(out, ..)
             | SsaOp::ModImmReg(out, ..)
+            | SsaOp::PowRegReg(out, ..)
+            | SsaOp::PowRegImm(out, ..)
+            | SsaOp::PowImmReg(out, ..)
             | SsaOp::AndRegImm(out, ..)
             | SsaOp::AndRegReg(out, ..)
             | SsaOp::OrRegImm(out, ..)
-            | SsaOp::OrRegReg(out, ..) => Some(*out),
+            | SsaOp::OrRegReg(out, ..)
+            | SsaOp::SelectRegRegReg(out, ..) => Some(*out),
             SsaOp::Output(..) => None,
         }
     }
@@ -234,6 +269,12 @@ impl SsaOp {
             | SsaOp::ExpReg(..)
             | SsaOp::LnReg(..)
             | SsaOp::NotReg(..)
+            | SsaOp::SinhReg(..)
+            | SsaOp::CoshReg(..)
+            | SsaOp::TanhReg(..)
+            | SsaOp::SignReg(..)
+            | SsaOp::CbrtReg(..)
+            | SsaOp::Log2Reg(..)
             | SsaOp::AddRegImm(..)
             | SsaOp::MulRegImm(..)
             | SsaOp::SubRegImm(..)
@@ -252,7 +293,10 @@ impl SsaOp {
             | SsaOp::CompareImmReg(..)
             | SsaOp::ModRegReg(..)
             | SsaOp::ModRegImm(..)
-            | SsaOp::ModImmReg(..) => false,
+            | SsaOp::ModImmReg(..)
+            | SsaOp::PowRegReg(..)
+            | SsaOp::PowRegImm(..)
+            | SsaOp::PowImmReg(..) => false,
             SsaOp::MinRegImm(..)
             | SsaOp::MaxRegImm(..)
             | SsaOp::MinRegReg(..)
@@ -260,7 +304,71 @@ impl SsaOp {
             | SsaOp::AndRegImm(..)
             | SsaOp::AndRegReg(..)
             | SsaOp::OrRegImm(..)
-            | SsaOp::OrRegReg(..) => true,
+            | SsaOp::OrRegReg(..)
+            | SsaOp::SelectRegRegReg(..) => true,
+        }
+    }
+    /// Returns the immediate value, if this opcode has one
+    pub fn imm(&self) -> Option<f32> {
+        match self {
+            SsaOp::CopyImm(.., imm)
+            | SsaOp::AddRegImm(.., imm)
+            | SsaOp::MulRegImm(.., imm)
+            | SsaOp::DivRegImm(.., imm)
+            | SsaOp::DivImmReg(.., imm)
+            | SsaOp::SubImmReg(.., imm)
+            | SsaOp::SubRegImm(.., imm)
+            | SsaOp::AtanRegImm(.., imm)
+            | SsaOp::AtanImmReg(.., imm)
+            | SsaOp::MinRegImm(.., imm)
+            | SsaOp::MaxRegImm(.., imm)
+            | SsaOp::CompareRegImm(.., imm)
+            | SsaOp::CompareImmReg(.., imm)
+            | SsaOp::ModRegImm(.., imm)
+            | SsaOp::ModImmReg(.., imm)
+            | SsaOp::PowRegImm(.., imm)
+            | SsaOp::PowImmReg(.., imm)
+            | SsaOp::AndRegImm(.., imm)
+            | SsaOp::OrRegImm(.., imm) => Some(*imm),
+            SsaOp::Input(..)
+            | SsaOp::Output(..)
+            | SsaOp::NegReg(..)
+            | SsaOp::AbsReg(..)
+            | SsaOp::RecipReg(..)
+            | SsaOp::SqrtReg(..)
+            | SsaOp::SquareReg(..)
+            | SsaOp::FloorReg(..)
+            | SsaOp::CeilReg(..)
+            | SsaOp::RoundReg(..)
+            | SsaOp::CopyReg(..)
+            | SsaOp::SinReg(..)
+            | SsaOp::CosReg(..)
+            | SsaOp::TanReg(..)
+            | SsaOp::AsinReg(..)
+            | SsaOp::AcosReg(..)
+            | SsaOp::AtanReg(..)
+            | SsaOp::ExpReg(..)
+            | SsaOp::LnReg(..)
+            | SsaOp::NotReg(..)
+            | SsaOp::SinhReg(..)
+            | SsaOp::CoshReg(..)
+            | SsaOp::TanhReg(..)
+            | SsaOp::SignReg(..)
+            | SsaOp::CbrtReg(..)
+            | SsaOp::Log2Reg(..)
+            | SsaOp::AddRegReg(..)
+            | SsaOp::MulRegReg(..)
+            | SsaOp::SubRegReg(..)
+            | SsaOp::DivRegReg(..)
+            | SsaOp::AtanRegReg(..)
+            | SsaOp::CompareRegReg(..)
+            | SsaOp::ModRegReg(..)
+            | SsaOp::PowRegReg(..)
+            | SsaOp::MinRegReg(..)
+            | SsaOp::MaxRegReg(..)
+            | SsaOp::AndRegReg(..)
+            | SsaOp::OrRegReg(..)
+            | SsaOp::SelectRegRegReg(..) => None,
         }
     }
 }
@@ -273,6 +381,9 @@ opcodes!(
     /// - LHS register (or input slot for [`Input`](RegOp::Input))
     /// - RHS register (or immediate for `*Imm`)
     ///
+    /// [`SelectRegRegReg`](RegOp::SelectRegRegReg) is the exception, taking
+    /// an output register followed by three input registers.
+    ///
     /// We have a maximum of 256 registers, though some tapes (e.g. ones
     /// targeting physical hardware) may choose to use fewer.
     #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
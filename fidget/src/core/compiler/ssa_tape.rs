@@ -16,14 +16,29 @@ use std::collections::{HashMap, HashSet};
 /// - 4-byte output register (required)
 /// - 4-byte LHS register
 /// - 4-byte RHS register (or immediate `f32`)
+/// - 4-byte second RHS register (only used by
+///   [`SelectRegRegReg`](SsaOp::SelectRegRegReg))
 ///
 /// All register addressing is absolute.
+///
+/// Immediates are stored in the tape as `f32`; their exact values are kept in
+/// a separate list for use by double-precision evaluators.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct SsaTape {
     /// The tape is stored in reverse order, such that the root of the tree is
     /// the first item in the tape.
     pub tape: Vec<SsaOp>,
 
+    /// Exact values of immediates in the tape, stored in the same order as
+    /// the operations which use them (i.e. also in reverse order)
+    ///
+    /// Each operation with an immediate (see [`SsaOp::imm`]) has exactly one
+    /// entry in this list.  Tapes serialized before this list was added
+    /// deserialize with it empty, in which case evaluators fall back to the
+    /// `f32` immediates.
+    #[serde(default)]
+    pub imms: Vec<f64>,
+
     /// Number of choice operations in the tape
     pub choice_count: usize,
 
@@ -45,7 +60,7 @@ impl SsaTape {
         #[derive(Copy, Clone, Debug)]
         enum Slot {
             Reg(u32),
-            Immediate(f32),
+            Immediate(f64),
         }
 
         // Accumulate parent counts and declare all nodes
@@ -58,9 +73,7 @@ impl SsaTape {
             }
             let op = ctx.get_op(node).ok_or(Error::BadNode)?;
             let prev = match op {
-                Op::Const(c) => {
-                    mapping.insert(node, Slot::Immediate(c.0 as f32))
-                }
+                Op::Const(c) => mapping.insert(node, Slot::Immediate(c.0)),
                 _ => {
                     if let Op::Input(v) = op {
                         vars.insert(*v);
@@ -82,14 +95,24 @@ impl SsaTape {
         let mut todo = roots.to_vec();
         let mut choice_count = 0;
 
+        // Immediates used by select operations, which are copied into
+        // registers before the select is evaluated
+        let mut imm_copies = vec![];
+
         let mut tape = vec![];
+        let mut imms = vec![];
         for (i, r) in roots.iter().enumerate() {
             let i = i as u32;
             match mapping[r] {
                 Slot::Reg(out_reg) => tape.push(SsaOp::Output(out_reg, i)),
                 Slot::Immediate(imm) => {
-                    tape.push(SsaOp::Output(0, i));
-                    tape.push(SsaOp::CopyImm(0, imm));
+                    // Each constant output gets its own register, so that it
+                    // doesn't clobber other outputs
+                    let out_reg = slot_count;
+                    slot_count += 1;
+                    tape.push(SsaOp::Output(out_reg, i));
+                    tape.push(SsaOp::CopyImm(out_reg, imm as f32));
+                    imms.push(imm);
                 }
             }
         }
@@ -110,6 +133,8 @@ impl SsaTape {
                 // Constants are skipped, because they become immediates
                 continue;
             };
+            // Exact value of this operation's immediate, if it has one
+            let mut imm_exact = None;
             let op = match op {
                 Op::Input(v) => {
                     let arg = vars[v];
@@ -184,6 +209,11 @@ impl SsaTape {
                             SsaOp::ModRegImm,
                             SsaOp::ModImmReg,
                         ),
+                        BinaryOpcode::Pow => (
+                            SsaOp::PowRegReg,
+                            SsaOp::PowRegImm,
+                            SsaOp::PowImmReg,
+                        ),
                     };
 
                     if matches!(
@@ -199,10 +229,12 @@ impl SsaTape {
                     match (lhs, rhs) {
                         (Slot::Reg(lhs), Slot::Reg(rhs)) => f.0(i, lhs, rhs),
                         (Slot::Reg(arg), Slot::Immediate(imm)) => {
-                            f.1(i, arg, imm)
+                            imm_exact = Some(imm);
+                            f.1(i, arg, imm as f32)
                         }
                         (Slot::Immediate(imm), Slot::Reg(arg)) => {
-                            f.2(i, arg, imm)
+                            imm_exact = Some(imm);
+                            f.2(i, arg, imm as f32)
                         }
                         (Slot::Immediate(..), Slot::Immediate(..)) => {
                             panic!("Cannot handle f(imm, imm)")
@@ -234,16 +266,45 @@ impl SsaTape {
                         UnaryOpcode::Exp => SsaOp::ExpReg,
                         UnaryOpcode::Ln => SsaOp::LnReg,
                         UnaryOpcode::Not => SsaOp::NotReg,
+                        UnaryOpcode::Sinh => SsaOp::SinhReg,
+                        UnaryOpcode::Cosh => SsaOp::CoshReg,
+                        UnaryOpcode::Tanh => SsaOp::TanhReg,
+                        UnaryOpcode::Sign => SsaOp::SignReg,
+                        UnaryOpcode::Cbrt => SsaOp::CbrtReg,
+                        UnaryOpcode::Log2 => SsaOp::Log2Reg,
                     };
                     op(i, lhs)
                 }
+                Op::Select(cond, a, b) => {
+                    choice_count += 1;
+                    let mut reg = |n: &Node| match mapping[n] {
+                        Slot::Reg(r) => r,
+                        Slot::Immediate(imm) => {
+                            let r = slot_count;
+                            slot_count += 1;
+                            imm_copies
+                                .push((SsaOp::CopyImm(r, imm as f32), imm));
+                            r
+                        }
+                    };
+                    SsaOp::SelectRegRegReg(i, reg(cond), reg(a), reg(b))
+                }
             };
             tape.push(op);
+            imms.extend(imm_exact);
+
+            // The tape is stored in reverse order, so these copies are
+            // evaluated before the operation that uses them.
+            for (op, imm) in imm_copies.drain(..) {
+                tape.push(op);
+                imms.push(imm);
+            }
         }
 
         Ok((
             SsaTape {
                 tape,
+                imms,
                 choice_count,
                 output_count: roots.len(),
             },
@@ -271,6 +332,7 @@ impl SsaTape {
     /// Resets to an empty tape, preserving allocations
     pub fn reset(&mut self) {
         self.tape.clear();
+        self.imms.clear();
         self.choice_count = 0;
     }
     /// Pretty-prints the given tape to `stdout`
@@ -300,7 +362,13 @@ impl SsaTape {
                 | SsaOp::AtanReg(out, arg)
                 | SsaOp::ExpReg(out, arg)
                 | SsaOp::LnReg(out, arg)
-                | SsaOp::NotReg(out, arg) => {
+                | SsaOp::NotReg(out, arg)
+                | SsaOp::SinhReg(out, arg)
+                | SsaOp::CoshReg(out, arg)
+                | SsaOp::TanhReg(out, arg)
+                | SsaOp::SignReg(out, arg)
+                | SsaOp::CbrtReg(out, arg)
+                | SsaOp::Log2Reg(out, arg) => {
                     let op = match op {
                         SsaOp::NegReg(..) => "NEG",
                         SsaOp::AbsReg(..) => "ABS",
@@ -319,6 +387,12 @@ impl SsaTape {
                         SsaOp::ExpReg(..) => "EXP",
                         SsaOp::LnReg(..) => "LN",
                         SsaOp::NotReg(..) => "NOT",
+                        SsaOp::SinhReg(..) => "SINH",
+                        SsaOp::CoshReg(..) => "COSH",
+                        SsaOp::TanhReg(..) => "TANH",
+                        SsaOp::SignReg(..) => "SIGN",
+                        SsaOp::CbrtReg(..) => "CBRT",
+                        SsaOp::Log2Reg(..) => "LOG2",
                         SsaOp::CopyReg(..) => "COPY",
                         _ => unreachable!(),
                     };
@@ -334,6 +408,7 @@ impl SsaTape {
                 | SsaOp::ModRegReg(out, lhs, rhs)
                 | SsaOp::AndRegReg(out, lhs, rhs)
                 | SsaOp::AtanRegReg(out, lhs, rhs)
+                | SsaOp::PowRegReg(out, lhs, rhs)
                 | SsaOp::OrRegReg(out, lhs, rhs) => {
                     let op = match op {
                         SsaOp::AddRegReg(..) => "ADD",
@@ -346,6 +421,7 @@ impl SsaTape {
                         SsaOp::ModRegReg(..) => "MAX",
                         SsaOp::AndRegReg(..) => "AND",
                         SsaOp::OrRegReg(..) => "OR",
+                        SsaOp::PowRegReg(..) => "POW",
                         _ => unreachable!(),
                     };
                     println!("${out} = {op} ${lhs} ${rhs}");
@@ -364,6 +440,8 @@ impl SsaTape {
                 | SsaOp::ModRegImm(out, arg, imm)
                 | SsaOp::ModImmReg(out, arg, imm)
                 | SsaOp::AndRegImm(out, arg, imm)
+                | SsaOp::PowRegImm(out, arg, imm)
+                | SsaOp::PowImmReg(out, arg, imm)
                 | SsaOp::OrRegImm(out, arg, imm) => {
                     let (op, swap) = match op {
                         SsaOp::AddRegImm(..) => ("ADD", false),
@@ -380,6 +458,8 @@ impl SsaTape {
                         SsaOp::ModImmReg(..) => ("MOD", true),
                         SsaOp::AndRegImm(..) => ("AND", false),
                         SsaOp::OrRegImm(..) => ("OR", false),
+                        SsaOp::PowRegImm(..) => ("POW", false),
+                        SsaOp::PowImmReg(..) => ("POW", true),
                         _ => unreachable!(),
                     };
                     if swap {
@@ -400,6 +480,9 @@ impl SsaTape {
                 SsaOp::CopyImm(out, imm) => {
                     println!("${out} = COPY {imm}");
                 }
+                SsaOp::SelectRegRegReg(out, cond, a, b) => {
+                    println!("${out} = SELECT ${cond} ${a} ${b}");
+                }
             }
         }
     }
@@ -428,6 +511,28 @@ mod test {
         assert_eq!(vs.len(), 2);
     }
 
+    #[test]
+    fn test_exact_imms() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let a = ctx.add(x, 0.1).unwrap();
+        let b = ctx.mul(y, 1e8 + 1.0).unwrap();
+        let c = ctx.min(a, b).unwrap();
+        let z = ctx.z();
+        let d = ctx.if_nonzero_else(z, c, 0.3).unwrap();
+
+        let (tape, _vs) = SsaTape::new(&ctx, &[d, x, c]).unwrap();
+        let imms = tape.iter().filter_map(|op| op.imm()).collect::<Vec<_>>();
+        assert_eq!(imms.len(), tape.imms.len());
+        for (imm, exact) in imms.iter().zip(&tape.imms) {
+            assert_eq!(*imm, *exact as f32);
+        }
+        let mut exact = tape.imms.clone();
+        exact.sort_by(f64::total_cmp);
+        assert_eq!(exact, [0.1, 0.3, 1e8 + 1.0]);
+    }
+
     #[test]
     fn test_dupe() {
         let mut ctx = Context::new();
@@ -0,0 +1,395 @@
+//! Balanced rebuilding of n-ary operation chains
+use super::{rewrite::Rewrite, BinaryOpcode, Context, Node, UnaryOpcode};
+use crate::{
+    eval::{Function, MathFunction, TracingEvaluator},
+    types::Interval,
+    var::Var,
+    vm::VmFunction,
+    Error,
+};
+
+/// Rules for [`Context::balance`] and [`Context::balance_spatial`]
+struct Balancer {
+    /// Region used to group `min` and `max` terms by spatial locality
+    region: Option<[Interval; 3]>,
+}
+
+impl Rewrite for Balancer {
+    const SIGNED: bool = false;
+
+    fn chain_op(&self, op: BinaryOpcode) -> Option<BinaryOpcode> {
+        match op {
+            BinaryOpcode::Add
+            | BinaryOpcode::Mul
+            | BinaryOpcode::Min
+            | BinaryOpcode::Max => Some(op),
+            _ => None,
+        }
+    }
+
+    fn unary(
+        &mut self,
+        ctx: &mut Context,
+        op: UnaryOpcode,
+        arg: Node,
+    ) -> Result<Node, Error> {
+        ctx.op_unary(arg, op)
+    }
+
+    fn chain(
+        &mut self,
+        ctx: &mut Context,
+        op: BinaryOpcode,
+        terms: Vec<(Node, f64)>,
+    ) -> Result<Node, Error> {
+        let nodes = terms.into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        ctx.balance_chain(op, &nodes, self.region)
+    }
+}
+
+/// Number of subdivisions (per axis) used to estimate spatial locality
+const LOCALITY_GRID: usize = 4;
+
+impl Context {
+    /// Rebuilds chains of `min`, `max`, `add`, and `mul` as balanced trees,
+    /// returning the new root.
+    ///
+    /// Scripts which combine many objects in a loop produce a linear chain
+    /// (e.g. `min(min(min(a, b), c), d)`); rebuilding it as a balanced tree
+    /// (`min(min(a, b), min(c, d))`) reduces its depth from `O(n)` to
+    /// `O(log n)`.  Chains are only flattened through nodes which are not
+    /// used elsewhere in the graph, so shared subexpressions remain shared.
+    ///
+    /// Apart from reassociation, no other simplification is performed; see
+    /// [`Context::optimize`] for an algebraic simplification pass.
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let mut out = x;
+    /// for i in 0..8 {
+    ///     let v = ctx.sub(x, i as f64).unwrap();
+    ///     out = ctx.min(out, v).unwrap();
+    /// }
+    /// let balanced = ctx.balance(out).unwrap();
+    /// assert_eq!(
+    ///     ctx.eval_xyz(balanced, 2.5, 0.0, 0.0).unwrap(),
+    ///     ctx.eval_xyz(out, 2.5, 0.0, 0.0).unwrap(),
+    /// );
+    /// ```
+    pub fn balance(&mut self, root: Node) -> Result<Node, Error> {
+        self.rewrite(root, &mut Balancer { region: None })
+    }
+
+    /// Rebuilds chains as balanced trees, grouping `min` and `max` children
+    /// by spatial locality within the given region.
+    ///
+    /// The region is subdivided into a coarse grid, and interval evaluation is
+    /// used to find the cells in which each child may be inside its shape
+    /// (i.e. less than or equal to zero).  Children are then grouped by the
+    /// centroids of those cells, splitting along the longest axis at each
+    /// level of the tree.  Nearby children end up in the same subtree, so
+    /// tracing evaluation during rendering can prune entire subtrees at once.
+    ///
+    /// Variables other than X, Y, and Z are treated as unbounded.
+    ///
+    /// `add` and `mul` chains are balanced without reordering, as in
+    /// [`Context::balance`].
+    pub fn balance_spatial(
+        &mut self,
+        root: Node,
+        region: [Interval; 3],
+    ) -> Result<Node, Error> {
+        self.rewrite(
+            root,
+            &mut Balancer {
+                region: Some(region),
+            },
+        )
+    }
+
+    /// Builds a tree from the terms of a chain, with depth `O(log n)`
+    ///
+    /// If a region is provided, then `min` and `max` terms are grouped by
+    /// spatial locality (see [`Context::balance_spatial`]); otherwise, their
+    /// order is preserved.
+    ///
+    /// # Panics
+    /// If `terms` is empty
+    pub(super) fn balance_chain(
+        &mut self,
+        op: BinaryOpcode,
+        terms: &[Node],
+        region: Option<[Interval; 3]>,
+    ) -> Result<Node, Error> {
+        match region {
+            Some(region)
+                if terms.len() > 2
+                    && matches!(op, BinaryOpcode::Min | BinaryOpcode::Max) =>
+            {
+                let mut items = self.centroids(terms, region)?;
+                self.build_spatial(op, &mut items)
+            }
+            _ => self.build_balanced(op, terms),
+        }
+    }
+
+    /// Builds a balanced tree of a commutative, associative operation
+    ///
+    /// # Panics
+    /// If `nodes` is empty
+    pub(super) fn build_balanced(
+        &mut self,
+        op: BinaryOpcode,
+        nodes: &[Node],
+    ) -> Result<Node, Error> {
+        match nodes {
+            [] => panic!("cannot build an empty tree"),
+            [n] => Ok(*n),
+            _ => {
+                let (lhs, rhs) = nodes.split_at(nodes.len() / 2);
+                let lhs = self.build_balanced(op, lhs)?;
+                let rhs = self.build_balanced(op, rhs)?;
+                self.rebuild_binary(op, lhs, rhs)
+            }
+        }
+    }
+
+    /// Estimates a representative position for each node within the region
+    ///
+    /// The position is the centroid of grid cells in which the node may be
+    /// less than or equal to zero; if there are no such cells (or every cell
+    /// qualifies), the center of the region is used instead.
+    fn centroids(
+        &self,
+        nodes: &[Node],
+        region: [Interval; 3],
+    ) -> Result<Vec<(Node, [f32; 3])>, Error> {
+        let f = VmFunction::new(self, nodes)?;
+        let tape = f.interval_tape(Default::default());
+        let mut eval = VmFunction::new_interval_eval();
+
+        let vars = f.vars();
+        let mut args =
+            vec![Interval::new(f32::NEG_INFINITY, f32::INFINITY); vars.len()];
+        let axes = [Var::X, Var::Y, Var::Z].map(|v| vars.get(&v));
+
+        let cell = |r: Interval, i: usize| {
+            let w = r.width() / LOCALITY_GRID as f32;
+            Interval::new(
+                r.lower() + w * i as f32,
+                r.lower() + w * (i + 1) as f32,
+            )
+        };
+
+        let mut sums = vec![([0.0f32; 3], 0usize); nodes.len()];
+        let total = LOCALITY_GRID.pow(3);
+        for i in 0..total {
+            let idx = [
+                i % LOCALITY_GRID,
+                (i / LOCALITY_GRID) % LOCALITY_GRID,
+                i / LOCALITY_GRID.pow(2),
+            ];
+            let cells = [0, 1, 2].map(|j| cell(region[j], idx[j]));
+            for (a, c) in axes.iter().zip(cells) {
+                if let Some(a) = a {
+                    args[*a] = c;
+                }
+            }
+            let (out, _trace) = eval.eval(&tape, &args)?;
+            for (s, v) in sums.iter_mut().zip(out) {
+                if v.lower() <= 0.0 {
+                    for (p, c) in s.0.iter_mut().zip(&cells) {
+                        *p += c.midpoint();
+                    }
+                    s.1 += 1;
+                }
+            }
+        }
+
+        let center = region.map(|r| r.midpoint());
+        Ok(nodes
+            .iter()
+            .zip(sums)
+            .map(|(n, (sum, count))| {
+                let pos = if count == 0 || count == total {
+                    center
+                } else {
+                    sum.map(|s| s / count as f32)
+                };
+                (*n, pos)
+            })
+            .collect())
+    }
+
+    /// Builds a tree from nodes and their positions, recursively splitting at
+    /// the median along the axis with the largest spread
+    fn build_spatial(
+        &mut self,
+        op: BinaryOpcode,
+        items: &mut [(Node, [f32; 3])],
+    ) -> Result<Node, Error> {
+        if let [(n, _)] = items {
+            return Ok(*n);
+        }
+        let mut lo = [f32::INFINITY; 3];
+        let mut hi = [f32::NEG_INFINITY; 3];
+        for (_, p) in items.iter() {
+            for ((lo, hi), p) in lo.iter_mut().zip(&mut hi).zip(p) {
+                *lo = lo.min(*p);
+                *hi = hi.max(*p);
+            }
+        }
+        let axis = (0..3)
+            .max_by(|a, b| (hi[*a] - lo[*a]).total_cmp(&(hi[*b] - lo[*b])))
+            .unwrap();
+        // Stable sort, so that ties preserve the original order
+        items.sort_by(|a, b| a.1[axis].total_cmp(&b.1[axis]));
+
+        let (lhs, rhs) = items.split_at_mut(items.len() / 2);
+        let lhs = self.build_spatial(op, lhs)?;
+        let rhs = self.build_spatial(op, rhs)?;
+        self.rebuild_binary(op, lhs, rhs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::Op;
+
+    /// Returns the depth of the graph rooted at `n`
+    fn depth(ctx: &Context, n: Node) -> usize {
+        1 + ctx
+            .get_op(n)
+            .unwrap()
+            .iter_children()
+            .map(|c| depth(ctx, c))
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_balance_chains() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        for op in [
+            BinaryOpcode::Add,
+            BinaryOpcode::Mul,
+            BinaryOpcode::Min,
+            BinaryOpcode::Max,
+        ] {
+            let mut out = ctx.sin(x).unwrap();
+            for i in 1..32 {
+                let v = ctx.mul(y, i as f64 / 32.0).unwrap();
+                let v = ctx.sin(v).unwrap();
+                out = ctx.rebuild_binary(op, out, v).unwrap();
+            }
+            assert_eq!(depth(&ctx, out), 34);
+
+            let b = ctx.balance(out).unwrap();
+            assert_eq!(depth(&ctx, b), 8);
+            for (x, y) in [(0.5, 1.5), (-2.0, 3.0)] {
+                let va = ctx.eval_xyz(out, x, y, 0.0).unwrap();
+                let vb = ctx.eval_xyz(b, x, y, 0.0).unwrap();
+                assert!((va - vb).abs() < 1e-9, "{op:?}: {va} != {vb}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_balance_shared() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+
+        // `inner` is used twice, so it must not be flattened
+        let inner = ctx.min(x, y).unwrap();
+        let a = ctx.min(inner, z).unwrap();
+        let b = ctx.sin(inner).unwrap();
+        let root = ctx.min(a, b).unwrap();
+        let out = ctx.balance(root).unwrap();
+
+        // The chain is rebuilt as `min(z, min(inner, sin(inner)))`
+        let Op::Binary(BinaryOpcode::Min, lhs, rhs) = *ctx.get_op(out).unwrap()
+        else {
+            panic!("expected a min node");
+        };
+        assert_eq!(lhs, z);
+        let Op::Binary(BinaryOpcode::Min, lhs, rhs) = *ctx.get_op(rhs).unwrap()
+        else {
+            panic!("expected a min node");
+        };
+        assert_eq!(lhs, inner);
+        assert_eq!(rhs, b);
+    }
+
+    #[test]
+    fn test_balance_spatial() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+
+        // A row of spheres along X, interleaved so that neighbors in the chain
+        // are far apart in space
+        let n = 16;
+        let mut spheres = vec![];
+        for i in 0..n {
+            let i = if i % 2 == 0 { i / 2 } else { n - 1 - i / 2 };
+            let cx = -0.9 + 1.8 * i as f64 / (n - 1) as f64;
+            let dx = ctx.sub(x, cx).unwrap();
+            let dx = ctx.square(dx).unwrap();
+            let dy = ctx.square(y).unwrap();
+            let dz = ctx.square(z).unwrap();
+            let r = ctx.add(dx, dy).unwrap();
+            let r = ctx.add(r, dz).unwrap();
+            let r = ctx.sqrt(r).unwrap();
+            let s = ctx.sub(r, 0.05).unwrap();
+            spheres.push((cx, s));
+        }
+        let mut out = spheres[0].1;
+        for (_, s) in &spheres[1..] {
+            out = ctx.min(out, *s).unwrap();
+        }
+
+        let region = [Interval::new(-1.0, 1.0); 3];
+        let bal = ctx.balance_spatial(out, region).unwrap();
+        assert_eq!(depth(&ctx, bal), depth(&ctx, spheres[0].1) + 4);
+
+        // The root splits space in half along the X axis
+        let Op::Binary(BinaryOpcode::Min, lhs, rhs) = *ctx.get_op(bal).unwrap()
+        else {
+            panic!("expected a min node");
+        };
+        let leaves = |n: Node| {
+            let mut out = vec![];
+            let mut todo = vec![n];
+            while let Some(m) = todo.pop() {
+                match *ctx.get_op(m).unwrap() {
+                    Op::Binary(BinaryOpcode::Min, a, b) => {
+                        todo.push(a);
+                        todo.push(b);
+                    }
+                    _ => out
+                        .push(spheres.iter().find(|(_, s)| *s == m).unwrap().0),
+                }
+            }
+            out
+        };
+        let (a, b) = (leaves(lhs), leaves(rhs));
+        assert_eq!(a.len(), n / 2);
+        assert_eq!(b.len(), n / 2);
+        let a_neg = a.iter().all(|c| *c < 0.0);
+        let b_neg = b.iter().all(|c| *c < 0.0);
+        let a_pos = a.iter().all(|c| *c > 0.0);
+        let b_pos = b.iter().all(|c| *c > 0.0);
+        assert!((a_neg && b_pos) || (a_pos && b_neg), "{a:?} {b:?}");
+
+        for (x, y, z) in [(0.5, 0.1, 0.0), (-0.7, 0.0, 0.2)] {
+            let va = ctx.eval_xyz(out, x, y, z).unwrap();
+            let vb = ctx.eval_xyz(bal, x, y, z).unwrap();
+            assert!((va - vb).abs() < 1e-9);
+        }
+    }
+}
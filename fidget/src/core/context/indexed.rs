@@ -160,8 +160,19 @@ macro_rules! define_index {
     ($name:ident, $doc:literal) => {
         #[doc = $doc]
         #[derive(
-            Copy, Clone, Default, Debug, Eq, PartialEq, Hash, Ord, PartialOrd,
+            Copy,
+            Clone,
+            Default,
+            Debug,
+            Eq,
+            PartialEq,
+            Hash,
+            Ord,
+            PartialOrd,
+            serde::Serialize,
+            serde::Deserialize,
         )]
+        #[serde(transparent)]
         pub struct $name(usize);
         impl crate::context::indexed::Index for $name {
             fn new(i: usize) -> Self {
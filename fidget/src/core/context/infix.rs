@@ -0,0 +1,810 @@
+//! Human-readable infix printing and parsing
+use super::{BinaryOpcode, Context, Node, Op, UnaryOpcode};
+use crate::{
+    var::{Var, VarIndex},
+    Error,
+};
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Precedence of `+` and `-`
+const PREC_ADD: u8 = 1;
+/// Precedence of `*` and `/`
+const PREC_MUL: u8 = 2;
+/// Precedence of unary `-`
+const PREC_NEG: u8 = 3;
+/// Precedence of `^`
+const PREC_POW: u8 = 4;
+/// Precedence of atoms (variables, constants, and function calls)
+const PREC_ATOM: u8 = 5;
+
+impl Context {
+    /// Prints the graph rooted at `root` as a human-readable infix expression
+    ///
+    /// Arithmetic uses operators (`+ - * / ^`), and other operations are
+    /// printed as function calls, e.g. `sqrt(x^2 + y^2) - 1`.  Non-trivial
+    /// subexpressions which are used more than once are hoisted into `let`
+    /// bindings (named `_0`, `_1`, etc), so the output stays proportional to
+    /// the size of the graph.
+    ///
+    /// Variables are printed as `x`, `y`, `z`, or `v_<index>` for other
+    /// [`Var`] values.  The output can be parsed back with
+    /// [`Context::from_infix`].
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let y2 = ctx.square(y).unwrap();
+    /// let sum = ctx.add(x2, y2).unwrap();
+    /// let r = ctx.sqrt(sum).unwrap();
+    /// let out = ctx.sub(r, 1.0).unwrap();
+    /// assert_eq!(ctx.to_infix(out).unwrap(), "sqrt(x^2 + y^2) - 1");
+    /// ```
+    pub fn to_infix(&self, root: Node) -> Result<String, Error> {
+        self.check_node(root)?;
+        let uses = self.use_counts(root);
+
+        // Children always have lower indices than their parents, so sorting
+        // by index gives us a topological ordering.
+        let mut nodes: Vec<Node> = uses.keys().cloned().collect();
+        nodes.sort();
+
+        let mut out = String::new();
+        let mut exprs: HashMap<Node, (String, u8)> = HashMap::new();
+        let mut bindings = 0;
+        for n in nodes {
+            // Single-use expressions are moved into their parent
+            let mut get = |c: Node| {
+                if uses[&c] == 1 {
+                    exprs.remove(&c).unwrap()
+                } else {
+                    exprs[&c].clone()
+                }
+            };
+            let op = *self.get_op(n).unwrap();
+            let (text, prec) = match op {
+                Op::Const(c) => format_const_prec(c.0),
+                Op::Input(v) => (format_var(v), PREC_ATOM),
+                Op::Unary(UnaryOpcode::Neg, arg) => {
+                    (format!("-{}", wrap(get(arg), PREC_NEG)), PREC_NEG)
+                }
+                Op::Unary(UnaryOpcode::Square, arg) => {
+                    (format!("{}^2", wrap(get(arg), PREC_ATOM)), PREC_POW)
+                }
+                Op::Unary(op, arg) => {
+                    (format!("{}({})", unary_name(op), get(arg).0), PREC_ATOM)
+                }
+                Op::Binary(op, lhs, rhs) => {
+                    let lhs = get(lhs);
+                    let rhs = get(rhs);
+                    let infix = match op {
+                        BinaryOpcode::Add => Some(("+", PREC_ADD)),
+                        BinaryOpcode::Sub => Some(("-", PREC_ADD)),
+                        BinaryOpcode::Mul => Some(("*", PREC_MUL)),
+                        BinaryOpcode::Div => Some(("/", PREC_MUL)),
+                        _ => None,
+                    };
+                    if let Some((sym, prec)) = infix {
+                        // Operators are left-associative, so a right-hand
+                        // operand at the same precedence needs parentheses
+                        let lhs = wrap(lhs, prec);
+                        let rhs = wrap(rhs, prec + 1);
+                        (format!("{lhs} {sym} {rhs}"), prec)
+                    } else if op == BinaryOpcode::Pow {
+                        // Right-associative, and the exponent may be negated
+                        let lhs = wrap(lhs, PREC_ATOM);
+                        let rhs = wrap(rhs, PREC_NEG);
+                        (format!("{lhs}^{rhs}"), PREC_POW)
+                    } else {
+                        let name = binary_name(op);
+                        (format!("{name}({}, {})", lhs.0, rhs.0), PREC_ATOM)
+                    }
+                }
+                Op::Select(cond, a, b) => {
+                    let cond = get(cond).0;
+                    let a = get(a).0;
+                    let b = get(b).0;
+                    (format!("if_nonzero_else({cond}, {a}, {b})"), PREC_ATOM)
+                }
+            };
+            let hoist = uses[&n] > 1
+                && matches!(
+                    op,
+                    Op::Unary(..) | Op::Binary(..) | Op::Select(..)
+                );
+            if hoist {
+                let name = format!("_{bindings}");
+                bindings += 1;
+                writeln!(&mut out, "let {name} = {text};").unwrap();
+                exprs.insert(n, (name, PREC_ATOM));
+            } else {
+                exprs.insert(n, (text, prec));
+            }
+        }
+        out += &exprs.remove(&root).unwrap().0;
+        Ok(out)
+    }
+
+    /// Parses an infix expression into a new context, returning the root
+    ///
+    /// This accepts the syntax printed by [`Context::to_infix`]: zero or more
+    /// `let <name> = <expr>;` bindings, followed by a single expression.
+    /// Comments begin with `#` and run until the end of the line.
+    ///
+    /// Operators have the usual precedence, from lowest to highest: `+` and
+    /// `-`; `*` and `/`; unary `-`; and `^` (which is right-associative).
+    /// Other operations are written as function calls, using lowercase opcode
+    /// names (e.g. `min(x, y)`, `atan2(y, x)`, `if_nonzero_else(c, a, b)`).
+    ///
+    /// Syntax errors are reported as [`Error::ParseError`], with the line and
+    /// column of the offending token.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let (ctx, root) = Context::from_infix("
+    ///     let r = sqrt(x^2 + y^2);
+    ///     max(r - 1, -r)
+    /// ").unwrap();
+    /// assert_eq!(ctx.eval_xyz(root, 3.0, 4.0, 0.0).unwrap(), 4.0);
+    /// assert!(Context::from_infix("x + ").is_err());
+    /// ```
+    pub fn from_infix(s: &str) -> Result<(Context, Node), Error> {
+        let tokens = lex(s)?;
+        let mut p = InfixParser {
+            tokens,
+            pos: 0,
+            ctx: Context::new(),
+            bindings: HashMap::new(),
+        };
+        let root = p.parse_program()?;
+        Ok((p.ctx, root))
+    }
+}
+
+/// Adds parentheses around an expression if its precedence is too low
+fn wrap((text, prec): (String, u8), min: u8) -> String {
+    if prec < min {
+        format!("({text})")
+    } else {
+        text
+    }
+}
+
+/// Formats a constant so that it parses back to the same value
+///
+/// Integers are printed without a decimal point; other values use the
+/// shortest representation which parses back to the same `f64`.
+pub(super) fn format_const(v: f64) -> String {
+    if v.fract() == 0.0 && v.abs() < 1e15 {
+        format!("{v}")
+    } else {
+        format!("{v:?}")
+    }
+}
+
+/// Formats a constant, returning its text and precedence
+fn format_const_prec(v: f64) -> (String, u8) {
+    if v.is_nan() {
+        return ("nan".to_owned(), PREC_ATOM);
+    }
+    let text = if v.is_infinite() {
+        "inf".to_owned()
+    } else {
+        format_const(v.abs())
+    };
+    if v.is_sign_negative() {
+        (format!("-{text}"), PREC_NEG)
+    } else {
+        (text, PREC_ATOM)
+    }
+}
+
+/// Formats a variable as an identifier
+pub(super) fn format_var(v: Var) -> String {
+    match v {
+        Var::X => "x".to_owned(),
+        Var::Y => "y".to_owned(),
+        Var::Z => "z".to_owned(),
+        Var::V(i) => format!("v_{}", i.get()),
+    }
+}
+
+/// Returns the name of a unary opcode, as used in text formats
+pub(super) fn unary_name(op: UnaryOpcode) -> &'static str {
+    match op {
+        UnaryOpcode::Neg => "neg",
+        UnaryOpcode::Abs => "abs",
+        UnaryOpcode::Recip => "recip",
+        UnaryOpcode::Sqrt => "sqrt",
+        UnaryOpcode::Square => "square",
+        UnaryOpcode::Floor => "floor",
+        UnaryOpcode::Ceil => "ceil",
+        UnaryOpcode::Round => "round",
+        UnaryOpcode::Sin => "sin",
+        UnaryOpcode::Cos => "cos",
+        UnaryOpcode::Tan => "tan",
+        UnaryOpcode::Asin => "asin",
+        UnaryOpcode::Acos => "acos",
+        UnaryOpcode::Atan => "atan",
+        UnaryOpcode::Exp => "exp",
+        UnaryOpcode::Ln => "ln",
+        UnaryOpcode::Not => "not",
+        UnaryOpcode::Sinh => "sinh",
+        UnaryOpcode::Cosh => "cosh",
+        UnaryOpcode::Tanh => "tanh",
+        UnaryOpcode::Sign => "sign",
+        UnaryOpcode::Cbrt => "cbrt",
+        UnaryOpcode::Log2 => "log2",
+    }
+}
+
+/// Returns the name of a binary opcode, as used in text formats
+pub(super) fn binary_name(op: BinaryOpcode) -> &'static str {
+    match op {
+        BinaryOpcode::Add => "add",
+        BinaryOpcode::Sub => "sub",
+        BinaryOpcode::Mul => "mul",
+        BinaryOpcode::Div => "div",
+        BinaryOpcode::Atan => "atan2",
+        BinaryOpcode::Min => "min",
+        BinaryOpcode::Max => "max",
+        BinaryOpcode::Compare => "compare",
+        BinaryOpcode::Mod => "mod",
+        BinaryOpcode::And => "and",
+        BinaryOpcode::Or => "or",
+        BinaryOpcode::Pow => "pow",
+    }
+}
+
+/// Looks up a unary opcode by name
+pub(super) fn unary_from_name(name: &str) -> Option<UnaryOpcode> {
+    let op = match name {
+        "neg" => UnaryOpcode::Neg,
+        "abs" => UnaryOpcode::Abs,
+        "recip" => UnaryOpcode::Recip,
+        "sqrt" => UnaryOpcode::Sqrt,
+        "square" => UnaryOpcode::Square,
+        "floor" => UnaryOpcode::Floor,
+        "ceil" => UnaryOpcode::Ceil,
+        "round" => UnaryOpcode::Round,
+        "sin" => UnaryOpcode::Sin,
+        "cos" => UnaryOpcode::Cos,
+        "tan" => UnaryOpcode::Tan,
+        "asin" => UnaryOpcode::Asin,
+        "acos" => UnaryOpcode::Acos,
+        "atan" => UnaryOpcode::Atan,
+        "exp" => UnaryOpcode::Exp,
+        "ln" => UnaryOpcode::Ln,
+        "not" => UnaryOpcode::Not,
+        "sinh" => UnaryOpcode::Sinh,
+        "cosh" => UnaryOpcode::Cosh,
+        "tanh" => UnaryOpcode::Tanh,
+        "sign" => UnaryOpcode::Sign,
+        "cbrt" => UnaryOpcode::Cbrt,
+        "log2" => UnaryOpcode::Log2,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Looks up a binary opcode by name
+pub(super) fn binary_from_name(name: &str) -> Option<BinaryOpcode> {
+    let op = match name {
+        "add" => BinaryOpcode::Add,
+        "sub" => BinaryOpcode::Sub,
+        "mul" => BinaryOpcode::Mul,
+        "div" => BinaryOpcode::Div,
+        "atan2" => BinaryOpcode::Atan,
+        "min" => BinaryOpcode::Min,
+        "max" => BinaryOpcode::Max,
+        "compare" => BinaryOpcode::Compare,
+        "mod" => BinaryOpcode::Mod,
+        "and" => BinaryOpcode::And,
+        "or" => BinaryOpcode::Or,
+        "pow" => BinaryOpcode::Pow,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Parses a built-in identifier (a variable or named constant)
+fn builtin_from_name(name: &str) -> Option<Builtin> {
+    let b = match name {
+        "x" => Builtin::Var(Var::X),
+        "y" => Builtin::Var(Var::Y),
+        "z" => Builtin::Var(Var::Z),
+        "inf" => Builtin::Const(f64::INFINITY),
+        "nan" => Builtin::Const(f64::NAN),
+        _ => {
+            let i = name.strip_prefix("v_")?.parse().ok()?;
+            Builtin::Var(Var::V(VarIndex::from_raw(i)))
+        }
+    };
+    Some(b)
+}
+
+enum Builtin {
+    Var(Var),
+    Const(f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Symbol(char),
+    End,
+}
+
+/// A token with its (1-indexed) position in the source
+struct Lexeme {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+fn lex(s: &str) -> Result<Vec<Lexeme>, Error> {
+    let mut out = vec![];
+    let mut iter = s.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+    while let Some(&c) = iter.peek() {
+        let (start_line, start_column) = (line, column);
+        let mut take = |iter: &mut std::iter::Peekable<std::str::Chars>| {
+            let c = iter.next().unwrap();
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            c
+        };
+        let token = if c.is_whitespace() {
+            take(&mut iter);
+            continue;
+        } else if c == '#' {
+            while iter.peek().is_some_and(|c| *c != '\n') {
+                take(&mut iter);
+            }
+            continue;
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut text = String::new();
+            while iter.peek().is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                text.push(take(&mut iter));
+            }
+            if iter.peek().is_some_and(|c| *c == 'e' || *c == 'E') {
+                text.push(take(&mut iter));
+                if iter.peek().is_some_and(|c| *c == '+' || *c == '-') {
+                    text.push(take(&mut iter));
+                }
+                while iter.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    text.push(take(&mut iter));
+                }
+            }
+            let v = text.parse().map_err(|_| Error::ParseError {
+                line: start_line,
+                column: start_column,
+                message: format!("invalid number `{text}`"),
+            })?;
+            Token::Number(v)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut text = String::new();
+            while iter
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+            {
+                text.push(take(&mut iter));
+            }
+            Token::Ident(text)
+        } else if "+-*/^(),=;".contains(c) {
+            Token::Symbol(take(&mut iter))
+        } else {
+            return Err(Error::ParseError {
+                line,
+                column,
+                message: format!("unexpected character `{c}`"),
+            });
+        };
+        out.push(Lexeme {
+            token,
+            line: start_line,
+            column: start_column,
+        });
+    }
+    out.push(Lexeme {
+        token: Token::End,
+        line,
+        column,
+    });
+    Ok(out)
+}
+
+/// Recursive-descent parser for infix expressions
+struct InfixParser {
+    tokens: Vec<Lexeme>,
+    pos: usize,
+    ctx: Context,
+    bindings: HashMap<String, Node>,
+}
+
+impl InfixParser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].token
+    }
+
+    fn next(&mut self) -> &Lexeme {
+        let t = &self.tokens[self.pos];
+        if t.token != Token::End {
+            self.pos += 1;
+        }
+        t
+    }
+
+    /// Builds an error at the given token
+    fn error_at(&self, pos: usize, message: String) -> Error {
+        let t = &self.tokens[pos];
+        Error::ParseError {
+            line: t.line,
+            column: t.column,
+            message,
+        }
+    }
+
+    /// Builds an error at the current token
+    fn unexpected(&self, expected: &str) -> Error {
+        let found = match self.peek() {
+            Token::Number(v) => format!("number `{v}`"),
+            Token::Ident(s) => format!("`{s}`"),
+            Token::Symbol(c) => format!("`{c}`"),
+            Token::End => "end of input".to_owned(),
+        };
+        self.error_at(self.pos, format!("expected {expected}, found {found}"))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if *self.peek() == Token::Symbol(c) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{c}`")))
+        }
+    }
+
+    fn parse_program(&mut self) -> Result<Node, Error> {
+        while *self.peek() == Token::Ident("let".to_owned()) {
+            self.next();
+            let pos = self.pos;
+            let Token::Ident(name) = self.peek().clone() else {
+                return Err(self.unexpected("identifier"));
+            };
+            if name == "let"
+                || self.bindings.contains_key(&name)
+                || builtin_from_name(&name).is_some()
+            {
+                return Err(
+                    self.error_at(pos, format!("cannot redefine `{name}`"))
+                );
+            }
+            self.next();
+            self.expect('=')?;
+            let value = self.parse_expr()?;
+            self.expect(';')?;
+            self.bindings.insert(name, value);
+        }
+        let out = self.parse_expr()?;
+        if *self.peek() != Token::End {
+            return Err(self.unexpected("end of input"));
+        }
+        Ok(out)
+    }
+
+    fn parse_expr(&mut self) -> Result<Node, Error> {
+        let mut lhs = self.parse_mul()?;
+        loop {
+            match self.peek() {
+                Token::Symbol('+') => {
+                    self.next();
+                    let rhs = self.parse_mul()?;
+                    lhs = self.ctx.add(lhs, rhs)?;
+                }
+                Token::Symbol('-') => {
+                    self.next();
+                    let rhs = self.parse_mul()?;
+                    lhs = self.ctx.sub(lhs, rhs)?;
+                }
+                _ => break Ok(lhs),
+            }
+        }
+    }
+
+    fn parse_mul(&mut self) -> Result<Node, Error> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Token::Symbol('*') => {
+                    self.next();
+                    let rhs = self.parse_unary()?;
+                    lhs = self.ctx.mul(lhs, rhs)?;
+                }
+                Token::Symbol('/') => {
+                    self.next();
+                    let rhs = self.parse_unary()?;
+                    lhs = self.ctx.div(lhs, rhs)?;
+                }
+                _ => break Ok(lhs),
+            }
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, Error> {
+        if *self.peek() == Token::Symbol('-') {
+            self.next();
+            let arg = self.parse_unary()?;
+            self.ctx.neg(arg)
+        } else {
+            self.parse_pow()
+        }
+    }
+
+    fn parse_pow(&mut self) -> Result<Node, Error> {
+        let lhs = self.parse_atom()?;
+        if *self.peek() == Token::Symbol('^') {
+            self.next();
+            let rhs = self.parse_unary()?;
+            self.ctx.pow(lhs, rhs)
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, Error> {
+        let pos = self.pos;
+        match self.peek().clone() {
+            Token::Number(v) => {
+                self.next();
+                Ok(self.ctx.constant(v))
+            }
+            Token::Symbol('(') => {
+                self.next();
+                let out = self.parse_expr()?;
+                self.expect(')')?;
+                Ok(out)
+            }
+            Token::Ident(name) => {
+                self.next();
+                if *self.peek() == Token::Symbol('(') {
+                    self.parse_call(pos, &name)
+                } else if let Some(n) = self.bindings.get(&name) {
+                    Ok(*n)
+                } else {
+                    match builtin_from_name(&name) {
+                        Some(Builtin::Var(v)) => Ok(self.ctx.var(v)),
+                        Some(Builtin::Const(f)) => Ok(self.ctx.constant(f)),
+                        None => Err(self.error_at(
+                            pos,
+                            format!("unknown variable `{name}`"),
+                        )),
+                    }
+                }
+            }
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+
+    /// Parses a function call, with the current token at the open parenthesis
+    fn parse_call(&mut self, pos: usize, name: &str) -> Result<Node, Error> {
+        self.expect('(')?;
+        let mut args = vec![];
+        if *self.peek() != Token::Symbol(')') {
+            loop {
+                args.push(self.parse_expr()?);
+                if *self.peek() == Token::Symbol(',') {
+                    self.next();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(')')?;
+
+        let arity = if let Some(op) = unary_from_name(name) {
+            if let [arg] = args[..] {
+                return self.ctx.op_unary(arg, op);
+            }
+            1
+        } else if let Some(op) = binary_from_name(name) {
+            if let [lhs, rhs] = args[..] {
+                return self.ctx.rebuild_binary(op, lhs, rhs);
+            }
+            2
+        } else if name == "if_nonzero_else" {
+            if let [cond, a, b] = args[..] {
+                return self.ctx.if_nonzero_else(cond, a, b);
+            }
+            3
+        } else {
+            return Err(
+                self.error_at(pos, format!("unknown function `{name}`"))
+            );
+        };
+        Err(self.error_at(
+            pos,
+            format!(
+                "`{name}` expects {arity} argument{}, found {}",
+                if arity == 1 { "" } else { "s" },
+                args.len()
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::Tree;
+
+    /// Checks that an expression survives a print-parse-print round trip
+    fn round_trip(ctx: &Context, root: Node) -> (Context, Node) {
+        let text = ctx.to_infix(root).unwrap();
+        let (new_ctx, new_root) = Context::from_infix(&text).unwrap();
+        assert_eq!(new_ctx.to_infix(new_root).unwrap(), text);
+        (new_ctx, new_root)
+    }
+
+    #[test]
+    fn test_to_infix_precedence() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+        let xy = ctx.sub(x, y).unwrap();
+        let yz = ctx.sub(y, z).unwrap();
+        let y_times_z = ctx.mul(y, z).unwrap();
+        let neg_x = ctx.neg(x).unwrap();
+        let neg_y = ctx.neg(y).unwrap();
+        let x2 = ctx.square(x).unwrap();
+        let pow_xy = ctx.pow(x, y).unwrap();
+        let pow_yz = ctx.pow(y, z).unwrap();
+        let x_eps = ctx.add(x, 1e-7).unwrap();
+        let cases = [
+            (ctx.sub(xy, z).unwrap(), "x - y - z"),
+            (ctx.sub(x, yz).unwrap(), "x - (y - z)"),
+            (ctx.mul(xy, z).unwrap(), "z * (x - y)"),
+            (ctx.div(x, y_times_z).unwrap(), "x / (y * z)"),
+            (ctx.neg(xy).unwrap(), "-(x - y)"),
+            (ctx.square(neg_x).unwrap(), "(-x)^2"),
+            (ctx.neg(x2).unwrap(), "-x^2"),
+            (ctx.pow(x, pow_yz).unwrap(), "x^y^z"),
+            (ctx.pow(pow_xy, z).unwrap(), "(x^y)^z"),
+            (ctx.pow(x, -0.5).unwrap(), "x^-0.5"),
+            (ctx.min(x, neg_y).unwrap(), "min(x, -y)"),
+            (ctx.atan2(y, x).unwrap(), "atan2(y, x)"),
+            (
+                ctx.if_nonzero_else(x, y, z).unwrap(),
+                "if_nonzero_else(x, y, z)",
+            ),
+            (ctx.recip(x_eps).unwrap(), "recip(x + 1e-7)"),
+            (ctx.mul(x, f64::INFINITY).unwrap(), "x * inf"),
+            (ctx.add(x, f64::NEG_INFINITY).unwrap(), "x + -inf"),
+        ];
+        for (node, expected) in cases {
+            assert_eq!(ctx.to_infix(node).unwrap(), expected);
+            let (new_ctx, new_node) = round_trip(&ctx, node);
+            for (px, py, pz) in [(0.5, 1.5, 2.0), (-1.5, 0.25, 3.0)] {
+                let a = ctx.eval_xyz(node, px, py, pz).unwrap();
+                let b = new_ctx.eval_xyz(new_node, px, py, pz).unwrap();
+                assert!(a == b || (a.is_nan() && b.is_nan()), "{expected}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_infix_hoisting() {
+        let mut ctx = Context::new();
+        let [x, y, _z] = ctx.axes();
+        let a = ctx.add(x, y).unwrap();
+        let b = ctx.sin(a).unwrap();
+        let c = ctx.cos(a).unwrap();
+        let d = ctx.mul(b, c).unwrap();
+        let e = ctx.max(d, b).unwrap();
+        assert_eq!(
+            ctx.to_infix(e).unwrap(),
+            "let _0 = x + y;\nlet _1 = sin(_0);\nmax(_1, _1 * cos(_0))"
+        );
+        let (new_ctx, new_e) = round_trip(&ctx, e);
+        assert_eq!(new_ctx.len(), ctx.len() - 1); // no `z` in the new context
+        assert_eq!(
+            new_ctx.eval_xyz(new_e, 0.5, 0.25, 0.0).unwrap(),
+            ctx.eval_xyz(e, 0.5, 0.25, 0.0).unwrap(),
+        );
+
+        // Inputs and constants are never hoisted
+        let f = ctx.mul(x, x).unwrap();
+        let g = ctx.div(f, x).unwrap();
+        assert_eq!(ctx.to_infix(g).unwrap(), "x^2 / x");
+    }
+
+    #[test]
+    fn test_to_infix_vars() {
+        let mut ctx = Context::new();
+        let v = Var::new();
+        let v_node = ctx.var(v);
+        let x = ctx.x();
+        let sum = ctx.add(x, v_node).unwrap();
+        let text = ctx.to_infix(sum).unwrap();
+        let Var::V(i) = v else { unreachable!() };
+        assert!(text.contains(&format!("v_{}", i.get())));
+
+        let (new_ctx, new_sum) = round_trip(&ctx, sum);
+        let Op::Binary(_, a, b) = *new_ctx.get_op(new_sum).unwrap() else {
+            panic!("expected a binary operation");
+        };
+        let vars = [new_ctx.get_var(a).unwrap(), new_ctx.get_var(b).unwrap()];
+        assert!(vars.contains(&v));
+        assert!(vars.contains(&Var::X));
+    }
+
+    #[test]
+    fn test_from_infix() {
+        let (ctx, root) = Context::from_infix(
+            "# distance to a unit circle
+            let r = sqrt(x^2 + y^2);
+            r - 1",
+        )
+        .unwrap();
+        assert_eq!(ctx.eval_xyz(root, 3.0, 4.0, 0.0).unwrap(), 4.0);
+        assert_eq!(ctx.to_infix(root).unwrap(), "sqrt(x^2 + y^2) - 1");
+
+        let (ctx, root) = Context::from_infix("2 * -3 ^ 2 - 1e1").unwrap();
+        assert_eq!(ctx.get_const(root).unwrap(), -28.0);
+
+        let (ctx, root) = Context::from_infix("square(neg(.5))").unwrap();
+        assert_eq!(ctx.get_const(root).unwrap(), 0.25);
+    }
+
+    #[test]
+    fn test_from_infix_errors() {
+        let check = |s: &str, line: usize, column: usize, msg: &str| {
+            match Context::from_infix(s) {
+                Err(Error::ParseError {
+                    line: l,
+                    column: c,
+                    message,
+                }) => {
+                    assert_eq!((l, c), (line, column), "{s}: {message}");
+                    assert!(message.contains(msg), "{s}: {message}");
+                }
+                Err(e) => panic!("{s}: unexpected error {e:?}"),
+                Ok(..) => panic!("{s}: expected an error"),
+            }
+        };
+        check("x +", 1, 4, "expected expression");
+        check("x + w", 1, 5, "unknown variable `w`");
+        check("let a = x;\nfoo(a)", 2, 1, "unknown function `foo`");
+        check("let a = x;\n  min(a)", 2, 3, "expects 2 arguments, found 1");
+        check("sin(x, y)", 1, 1, "expects 1 argument, found 2");
+        check("(x + y", 1, 7, "expected `)`");
+        check("x y", 1, 3, "expected end of input");
+        check("let x = 1; x", 1, 5, "cannot redefine `x`");
+        check("let a = 1 a", 1, 11, "expected `;`");
+        check("x $ y", 1, 3, "unexpected character");
+        check("1.2.3", 1, 1, "invalid number");
+        check("", 1, 1, "expected expression");
+    }
+
+    #[test]
+    fn test_tree_display() {
+        let t = (Tree::x().square() + Tree::y().square()).sqrt() - 1.0;
+        assert_eq!(t.to_string(), "sqrt(x^2 + y^2) - 1");
+
+        // Shared subtrees are deduplicated when imported, then hoisted
+        let s = Tree::x().sin();
+        let t = s.clone() * s + Tree::y();
+        assert_eq!(t.to_string(), "let _0 = sin(x);\n_0 * _0 + y");
+        let a = Tree::x() + Tree::y();
+        let t = a.clone().sin() + a.cos();
+        assert_eq!(t.to_string(), "let _0 = x + y;\nsin(_0) + cos(_0)");
+    }
+}
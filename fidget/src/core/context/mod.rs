@@ -15,19 +15,27 @@
 //!   [`Function`](crate::eval::Function) objects for evaluation.
 //!
 //! In other words, the typical workflow is `Tree → (Context, Node) → Function`.
+mod balance;
 mod indexed;
+mod infix;
 mod op;
+mod optimize;
+mod rewrite;
+mod serialize;
+mod structural;
+mod substitute;
+mod text;
 mod tree;
 
 use indexed::{define_index, Index, IndexMap, IndexVec};
 pub use op::{BinaryOpcode, Op, UnaryOpcode};
+pub use text::TextFile;
 pub use tree::{Tree, TreeOp};
 
 use crate::{var::Var, Error};
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::HashMap;
 use std::fmt::Write;
-use std::io::{BufRead, BufReader, Read};
 use std::sync::Arc;
 
 use ordered_float::OrderedFloat;
@@ -44,6 +52,21 @@ define_index!(Node, "An index in the `Context::ops` map");
 /// Items in the context are accessed with [`Node`] keys, which are simple
 /// handles into an internal map.  Inside the context, operations are
 /// represented with the [`Op`] type.
+///
+/// A `Context` can be serialized with [`serde`]; node indices are preserved, so
+/// root nodes can be serialized alongside the context.  Call
+/// [`Context::gc`] beforehand to drop nodes which aren't used by the roots.
+/// ```
+/// # use fidget::context::{Context, Node};
+/// let mut ctx = Context::new();
+/// let x = ctx.x();
+/// let root = ctx.sin(x).unwrap();
+/// let data = bincode::serialize(&(&ctx, [root])).unwrap();
+///
+/// let (ctx, [root]): (Context, [Node; 1]) =
+///     bincode::deserialize(&data).unwrap();
+/// assert_eq!(ctx.eval_xyz(root, 1.0, 0.0, 0.0).unwrap(), 1f64.sin());
+/// ```
 #[derive(Debug, Default)]
 pub struct Context {
     ops: IndexMap<Op, Node>,
@@ -70,6 +93,59 @@ impl Context {
         self.ops.clear();
     }
 
+    /// Removes every node which is not reachable from the given roots, then
+    /// compacts the remaining nodes.
+    ///
+    /// Returns a map from old [`Node`] handles to new ones; it contains an
+    /// entry for every node that was kept (including the roots).  All other
+    /// handles from this context are invalidated.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let _unused = ctx.mul(x, y).unwrap();
+    /// let sum = ctx.add(x, 1.0).unwrap();
+    /// assert_eq!(ctx.len(), 5);
+    ///
+    /// let remap = ctx.gc(&[sum]).unwrap();
+    /// assert_eq!(ctx.len(), 3); // x, 1.0, sum
+    /// let sum = remap[&sum];
+    /// assert_eq!(ctx.eval_xyz(sum, 2.0, 0.0, 0.0).unwrap(), 3.0);
+    /// ```
+    pub fn gc(&mut self, roots: &[Node]) -> Result<HashMap<Node, Node>, Error> {
+        for r in roots {
+            self.check_node(*r)?;
+        }
+
+        let mut live = vec![false; self.ops.len()];
+        let mut todo = roots.to_vec();
+        while let Some(n) = todo.pop() {
+            if std::mem::replace(&mut live[n.get()], true) {
+                continue;
+            }
+            todo.extend(self.get_op(n).unwrap().iter_children());
+        }
+
+        // Children are always inserted before their parents, so walking in
+        // index order guarantees that every child has already been remapped.
+        let mut remap = HashMap::new();
+        let mut ops = IndexMap::default();
+        for n in self.ops.keys().filter(|n| live[n.get()]) {
+            let m = |c: &Node| remap[c];
+            let op = match *self.get_op(n).unwrap() {
+                op @ (Op::Const(..) | Op::Input(..)) => op,
+                Op::Unary(op, arg) => Op::Unary(op, m(&arg)),
+                Op::Binary(op, lhs, rhs) => Op::Binary(op, m(&lhs), m(&rhs)),
+                Op::Select(cond, a, b) => Op::Select(m(&cond), m(&a), m(&b)),
+            };
+            remap.insert(n, ops.insert(op));
+        }
+        self.ops = ops;
+        Ok(remap)
+    }
+
     /// Returns the number of [`Op`] nodes in the context
     ///
     /// ```
@@ -552,6 +628,70 @@ impl Context {
         self.op_unary(a, UnaryOpcode::Ln)
     }
 
+    /// Builds a node which calculates the base-2 log of its input
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.log2(x).unwrap();
+    /// let v = ctx.eval_xyz(op, 8.0, 0.0, 0.0).unwrap();
+    /// assert_eq!(v, 3.0);
+    /// ```
+    pub fn log2<A: IntoNode>(&mut self, a: A) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        self.op_unary(a, UnaryOpcode::Log2)
+    }
+
+    /// Builds a node which calculates the hyperbolic sine of its input
+    pub fn sinh<A: IntoNode>(&mut self, a: A) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        self.op_unary(a, UnaryOpcode::Sinh)
+    }
+
+    /// Builds a node which calculates the hyperbolic cosine of its input
+    pub fn cosh<A: IntoNode>(&mut self, a: A) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        self.op_unary(a, UnaryOpcode::Cosh)
+    }
+
+    /// Builds a node which calculates the hyperbolic tangent of its input
+    pub fn tanh<A: IntoNode>(&mut self, a: A) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        self.op_unary(a, UnaryOpcode::Tanh)
+    }
+
+    /// Builds a node which calculates the sign of its input
+    ///
+    /// The result is -1 if the input is negative, +1 if it is positive, and
+    /// the input itself if it is zero or `NaN`.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.sign(x).unwrap();
+    /// let v = ctx.eval_xyz(op, -3.0, 0.0, 0.0).unwrap();
+    /// assert_eq!(v, -1.0);
+    /// let v = ctx.eval_xyz(op, 0.0, 0.0, 0.0).unwrap();
+    /// assert_eq!(v, 0.0);
+    /// ```
+    pub fn sign<A: IntoNode>(&mut self, a: A) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        self.op_unary(a, UnaryOpcode::Sign)
+    }
+
+    /// Builds a node which calculates the cube root of its input
+    ///
+    /// Unlike `pow(x, 1/3)`, this is defined for negative inputs.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.cbrt(x).unwrap();
+    /// let v = ctx.eval_xyz(op, -8.0, 0.0, 0.0).unwrap();
+    /// assert_eq!(v, -2.0);
+    /// ```
+    pub fn cbrt<A: IntoNode>(&mut self, a: A) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        self.op_unary(a, UnaryOpcode::Cbrt)
+    }
+
     ////////////////////////////////////////////////////////////////////////////
     // Derived functions
     /// Builds a node which squares its input
@@ -678,6 +818,32 @@ impl Context {
         self.op_binary(y, x, BinaryOpcode::Atan)
     }
 
+    /// Builds a node which raises `a` to the power of `b`
+    ///
+    /// Negative bases are valid if the exponent is an integer; otherwise, they
+    /// produce `NaN`.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.pow(x, 3.0).unwrap();
+    /// let v = ctx.eval_xyz(op, -2.0, 0.0, 0.0).unwrap();
+    /// assert_eq!(v, -8.0);
+    /// ```
+    pub fn pow<A: IntoNode, B: IntoNode>(
+        &mut self,
+        a: A,
+        b: B,
+    ) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        let b = b.into_node(self)?;
+
+        match self.get_const(b) {
+            Ok(1.0) => Ok(a),
+            Ok(2.0) => self.square(a),
+            _ => self.op_binary(a, b, BinaryOpcode::Pow),
+        }
+    }
+
     /// Builds a node that compares two values
     ///
     /// The result is -1 if `a < b`, +1 if `a > b`, 0 if `a == b`, and `NaN` if
@@ -768,7 +934,10 @@ impl Context {
     /// Builds a node that returns the first node if the condition is not
     /// equal to zero, else returns the other node
     ///
-    /// The result is `a` if `condition != 0`, else the result is `b`.
+    /// The result is `a` if `condition != 0`, else the result is `b`.  This
+    /// is represented as a single [`Op::Select`] node, which records a choice
+    /// during tracing evaluation; if the condition is a constant, the
+    /// appropriate branch is returned directly.
     /// ```
     /// # let mut ctx = fidget::context::Context::new();
     /// let x = ctx.x();
@@ -792,10 +961,13 @@ impl Context {
         let a = a.into_node(self)?;
         let b = b.into_node(self)?;
 
-        let lhs = self.and(condition, a)?;
-        let n_condition = self.not(condition)?;
-        let rhs = self.and(n_condition, b)?;
-        self.or(lhs, rhs)
+        if let Op::Const(c) = self.get_op(condition).ok_or(Error::BadNode)? {
+            Ok(if c.0 != 0.0 { a } else { b })
+        } else if a == b {
+            Ok(a)
+        } else {
+            Ok(self.ops.insert(Op::Select(condition, a, b)))
+        }
     }
 
     ////////////////////////////////////////////////////////////////////////////
@@ -887,6 +1059,7 @@ impl Context {
                             b
                         }
                     }
+                    BinaryOpcode::Pow => a.powf(b),
                 }
             }
 
@@ -911,6 +1084,31 @@ impl Context {
                     UnaryOpcode::Exp => a.exp(),
                     UnaryOpcode::Ln => a.ln(),
                     UnaryOpcode::Not => (a == 0.0).into(),
+                    UnaryOpcode::Sinh => a.sinh(),
+                    UnaryOpcode::Cosh => a.cosh(),
+                    UnaryOpcode::Tanh => a.tanh(),
+                    UnaryOpcode::Sign => {
+                        if a > 0.0 {
+                            1.0
+                        } else if a < 0.0 {
+                            -1.0
+                        } else {
+                            a
+                        }
+                    }
+                    UnaryOpcode::Cbrt => a.cbrt(),
+                    UnaryOpcode::Log2 => a.log2(),
+                }
+            }
+
+            Op::Select(c, a, b) => {
+                let c = get(*c)?;
+                let a = get(*a)?;
+                let b = get(*b)?;
+                if c != 0.0 {
+                    a
+                } else {
+                    b
                 }
             }
         };
@@ -919,89 +1117,6 @@ impl Context {
         Ok(v)
     }
 
-    /// Parses a flat text representation of a math tree. For example, the
-    /// circle `(- (+ (square x) (square y)) 1)` can be parsed from
-    /// ```
-    /// # use fidget::context::Context;
-    /// let txt = "
-    /// ## This is a comment!
-    /// 0x600000b90000 var-x
-    /// 0x600000b900a0 square 0x600000b90000
-    /// 0x600000b90050 var-y
-    /// 0x600000b900f0 square 0x600000b90050
-    /// 0x600000b90140 add 0x600000b900a0 0x600000b900f0
-    /// 0x600000b90190 sqrt 0x600000b90140
-    /// 0x600000b901e0 const 1
-    /// ";
-    /// let (ctx, _node) = Context::from_text(&mut txt.as_bytes()).unwrap();
-    /// assert_eq!(ctx.len(), 7);
-    /// ```
-    ///
-    /// This representation is loosely defined and only intended for use in
-    /// quick experiments.
-    pub fn from_text<R: Read>(r: R) -> Result<(Self, Node), Error> {
-        let reader = BufReader::new(r);
-        let mut ctx = Self::new();
-        let mut seen = BTreeMap::new();
-        let mut last = None;
-
-        for line in reader.lines().map(|line| line.unwrap()) {
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
-            let mut iter = line.split_whitespace();
-            let i: String = iter.next().unwrap().to_owned();
-            let opcode = iter.next().unwrap();
-
-            let mut pop = || {
-                let txt = iter.next().unwrap();
-                seen.get(txt)
-                    .cloned()
-                    .ok_or_else(|| Error::UnknownVariable(txt.to_string()))
-            };
-            let node = match opcode {
-                "const" => ctx.constant(iter.next().unwrap().parse().unwrap()),
-                "var-x" => ctx.x(),
-                "var-y" => ctx.y(),
-                "var-z" => ctx.z(),
-                "abs" => ctx.abs(pop()?)?,
-                "neg" => ctx.neg(pop()?)?,
-                "sqrt" => ctx.sqrt(pop()?)?,
-                "square" => ctx.square(pop()?)?,
-                "floor" => ctx.floor(pop()?)?,
-                "ceil" => ctx.ceil(pop()?)?,
-                "round" => ctx.round(pop()?)?,
-                "sin" => ctx.sin(pop()?)?,
-                "cos" => ctx.cos(pop()?)?,
-                "tan" => ctx.tan(pop()?)?,
-                "asin" => ctx.asin(pop()?)?,
-                "acos" => ctx.acos(pop()?)?,
-                "atan" => ctx.atan(pop()?)?,
-                "ln" => ctx.ln(pop()?)?,
-                "not" => ctx.not(pop()?)?,
-                "exp" => ctx.exp(pop()?)?,
-                "add" => ctx.add(pop()?, pop()?)?,
-                "mul" => ctx.mul(pop()?, pop()?)?,
-                "min" => ctx.min(pop()?, pop()?)?,
-                "max" => ctx.max(pop()?, pop()?)?,
-                "div" => ctx.div(pop()?, pop()?)?,
-                "atan2" => ctx.atan2(pop()?, pop()?)?,
-                "sub" => ctx.sub(pop()?, pop()?)?,
-                "compare" => ctx.compare(pop()?, pop()?)?,
-                "mod" => ctx.modulo(pop()?, pop()?)?,
-                "and" => ctx.and(pop()?, pop()?)?,
-                "or" => ctx.or(pop()?, pop()?)?,
-                op => return Err(Error::UnknownOpcode(op.to_owned())),
-            };
-            seen.insert(i, node);
-            last = Some(node);
-        }
-        match last {
-            Some(node) => Ok((ctx, node)),
-            None => Err(Error::EmptyFile),
-        }
-    }
-
     /// Converts the entire context into a GraphViz drawing
     pub fn dot(&self) -> String {
         let mut out = "digraph mygraph{\n".to_owned();
@@ -1038,6 +1153,7 @@ impl Context {
                 BinaryOpcode::Mod => out += "mod",
                 BinaryOpcode::And => out += "and",
                 BinaryOpcode::Or => out += "or",
+                BinaryOpcode::Pow => out += "pow",
             },
             Op::Unary(op, ..) => match op {
                 UnaryOpcode::Neg => out += "neg",
@@ -1057,7 +1173,14 @@ impl Context {
                 UnaryOpcode::Exp => out += "exp",
                 UnaryOpcode::Ln => out += "ln",
                 UnaryOpcode::Not => out += "not",
+                UnaryOpcode::Sinh => out += "sinh",
+                UnaryOpcode::Cosh => out += "cosh",
+                UnaryOpcode::Tanh => out += "tanh",
+                UnaryOpcode::Sign => out += "sign",
+                UnaryOpcode::Cbrt => out += "cbrt",
+                UnaryOpcode::Log2 => out += "log2",
             },
+            Op::Select(..) => out += "select",
         };
         write!(
             out,
@@ -1107,7 +1230,9 @@ impl Context {
                     // we can return the previous Node.
                     if matches!(
                         t.as_ref(),
-                        TreeOp::Unary(..) | TreeOp::Binary(..)
+                        TreeOp::Unary(..)
+                            | TreeOp::Binary(..)
+                            | TreeOp::Select(..)
                     ) {
                         if let Some(p) =
                             seen.get(&(*axes.last().unwrap(), Arc::as_ptr(t)))
@@ -1138,6 +1263,12 @@ impl Context {
                             todo.push(Action::Down(lhs));
                             todo.push(Action::Down(rhs));
                         }
+                        TreeOp::Select(cond, a, b) => {
+                            todo.push(Action::Up(t));
+                            todo.push(Action::Down(cond));
+                            todo.push(Action::Down(a));
+                            todo.push(Action::Down(b));
+                        }
                         TreeOp::RemapAxes { target: _, x, y, z } => {
                             // Action::Up(t) does the remapping and target eval
                             todo.push(Action::Up(t));
@@ -1167,6 +1298,13 @@ impl Context {
                             }
                             stack.push(out);
                         }
+                        TreeOp::Select(..) => {
+                            let cond = stack.pop().unwrap();
+                            let a = stack.pop().unwrap();
+                            let b = stack.pop().unwrap();
+                            let out = self.if_nonzero_else(cond, a, b).unwrap();
+                            stack.push(out);
+                        }
                         TreeOp::RemapAxes { target, .. } => {
                             let x = stack.pop().unwrap();
                             let y = stack.pop().unwrap();
@@ -1183,7 +1321,9 @@ impl Context {
                     // isn't perfect, but it doesn't need to be for correctness.
                     if matches!(
                         t.as_ref(),
-                        TreeOp::Unary(..) | TreeOp::Binary(..)
+                        TreeOp::Unary(..)
+                            | TreeOp::Binary(..)
+                            | TreeOp::Select(..)
                     ) && Arc::strong_count(t) > 1
                     {
                         seen.insert(
@@ -1250,6 +1390,12 @@ impl Context {
                             todo.push(Action::Down(*lhs));
                             todo.push(Action::Down(*rhs));
                         }
+                        Op::Select(cond, a, b) => {
+                            todo.push(Action::Up(n, *op));
+                            todo.push(Action::Down(*cond));
+                            todo.push(Action::Down(*a));
+                            todo.push(Action::Down(*b));
+                        }
                     }
                 }
                 Action::Up(n, op) => match op {
@@ -1272,6 +1418,18 @@ impl Context {
                         seen.insert(n, out.clone());
                         stack.push(out);
                     }
+                    Op::Select(..) => {
+                        let cond = stack.pop().unwrap();
+                        let a = stack.pop().unwrap();
+                        let b = stack.pop().unwrap();
+                        let out = Tree::from(TreeOp::Select(
+                            cond.arc().clone(),
+                            a.arc().clone(),
+                            b.arc().clone(),
+                        ));
+                        seen.insert(n, out.clone());
+                        stack.push(out);
+                    }
                 },
             }
         }
@@ -1281,217 +1439,372 @@ impl Context {
 
     /// Takes the symbolic derivative of a node with respect to a variable
     pub fn deriv(&mut self, n: Node, v: Var) -> Result<Node, Error> {
-        if self.get_op(n).is_none() {
-            return Err(Error::BadNode);
+        Ok(self.gradient(n, &[v])?[0])
+    }
+
+    /// Takes the symbolic gradient of a node with respect to a set of
+    /// variables
+    ///
+    /// The returned `Vec` has one partial derivative per variable, in the same
+    /// order as `vars`.  All partial derivatives are computed in a single pass
+    /// over the graph, so intermediate values (e.g. `cos(x)` when
+    /// differentiating `sin(x)`) are shared between them.
+    /// ```
+    /// # use fidget::{context::Context, var::Var};
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let xy = ctx.mul(x, y).unwrap();
+    /// let g = ctx.gradient(xy, &[Var::X, Var::Y]).unwrap();
+    /// assert_eq!(ctx.eval_xyz(g[0], 2.0, 3.0, 0.0).unwrap(), 3.0);
+    /// assert_eq!(ctx.eval_xyz(g[1], 2.0, 3.0, 0.0).unwrap(), 2.0);
+    /// ```
+    pub fn gradient(
+        &mut self,
+        n: Node,
+        vars: &[Var],
+    ) -> Result<Vec<Node>, Error> {
+        Ok(self.jacobian(&[n], vars)?.pop().unwrap())
+    }
+
+    /// Builds the symbolic Hessian matrix (second partial derivatives) of a
+    /// node with respect to a set of variables
+    ///
+    /// The result is indexed as `h[i][j] = d²n / (dv_i dv_j)`.  As with
+    /// [`Context::gradient`], subexpressions are shared between every entry in
+    /// the matrix.
+    /// ```
+    /// # use fidget::{context::Context, var::Var};
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let f = ctx.mul(x2, y).unwrap(); // x² y
+    /// let h = ctx.hessian(f, &[Var::X, Var::Y]).unwrap();
+    /// assert_eq!(ctx.eval_xyz(h[0][0], 2.0, 3.0, 0.0).unwrap(), 6.0);
+    /// assert_eq!(ctx.eval_xyz(h[0][1], 2.0, 3.0, 0.0).unwrap(), 4.0);
+    /// assert_eq!(ctx.eval_xyz(h[1][0], 2.0, 3.0, 0.0).unwrap(), 4.0);
+    /// assert_eq!(ctx.eval_xyz(h[1][1], 2.0, 3.0, 0.0).unwrap(), 0.0);
+    /// ```
+    pub fn hessian(
+        &mut self,
+        n: Node,
+        vars: &[Var],
+    ) -> Result<Vec<Vec<Node>>, Error> {
+        let g = self.gradient(n, vars)?;
+        self.jacobian(&g, vars)
+    }
+
+    /// Takes the symbolic partial derivatives of a set of nodes with respect
+    /// to a set of variables, sharing work between all of them
+    ///
+    /// Returns one row per node, with one partial derivative per variable.
+    fn jacobian(
+        &mut self,
+        roots: &[Node],
+        vars: &[Var],
+    ) -> Result<Vec<Vec<Node>>, Error> {
+        for n in roots {
+            self.check_node(*n)?;
         }
 
         // Do recursion on the heap to avoid stack overflows for deep trees
         enum Action {
             /// Pushes `Up(n)` followed by `Down(n)` for each child
             Down(Node),
-            /// Consumes trees from the stack and pushes a new tree
+            /// Consumes partial derivatives from the stack and pushes new ones
             Up(Node, Op),
         }
-        let mut todo = vec![Action::Down(n)];
-        let mut stack = vec![];
         let zero = self.constant(0.0);
-
-        // Cache of Node -> Node mapping, for deduplication
-        let mut seen: HashMap<Node, Node> = HashMap::new();
-
-        while let Some(t) = todo.pop() {
-            match t {
-                Action::Down(n) => {
-                    // If we've already seen this TreeOp with these axes, then
-                    // we can return the previous Node.
-                    if let Some(p) = seen.get(&n) {
-                        stack.push(*p);
-                        continue;
-                    }
-                    let op = *self.get_op(n).unwrap();
-                    match op {
-                        Op::Const(_c) => {
-                            seen.insert(n, zero);
-                            stack.push(zero);
-                        }
-                        Op::Input(u) => {
-                            let z =
-                                if v == u { self.constant(1.0) } else { zero };
-                            seen.insert(n, z);
-                            stack.push(z);
-                        }
-                        Op::Unary(_op, arg) => {
-                            todo.push(Action::Up(n, op));
-                            todo.push(Action::Down(arg));
-                        }
-                        Op::Binary(_op, lhs, rhs) => {
-                            todo.push(Action::Up(n, op));
-                            todo.push(Action::Down(lhs));
-                            todo.push(Action::Down(rhs));
+        let one = self.constant(1.0);
+
+        // Cache of Node -> partial derivatives, shared between every root
+        let mut seen: HashMap<Node, Vec<Node>> = HashMap::new();
+
+        let mut out = vec![];
+        for &root in roots {
+            let mut todo = vec![Action::Down(root)];
+            let mut stack: Vec<Vec<Node>> = vec![];
+            while let Some(t) = todo.pop() {
+                match t {
+                    Action::Down(n) => {
+                        // If we've already differentiated this node, then we
+                        // can reuse its partial derivatives.
+                        if let Some(p) = seen.get(&n) {
+                            stack.push(p.clone());
+                            continue;
                         }
-                    }
-                }
-                Action::Up(n, op) => match op {
-                    Op::Const(..) | Op::Input(..) => unreachable!(),
-                    Op::Unary(op, v_arg) => {
-                        let d_arg = stack.pop().unwrap();
-                        let out = match op {
-                            UnaryOpcode::Neg => self.neg(d_arg),
-                            UnaryOpcode::Abs => {
-                                let cond = self.less_than(v_arg, zero).unwrap();
-                                let pos = d_arg;
-                                let neg = self.neg(d_arg).unwrap();
-                                self.if_nonzero_else(cond, neg, pos)
-                            }
-                            UnaryOpcode::Recip => {
-                                let a = self.square(v_arg).unwrap();
-                                let b = self.neg(d_arg).unwrap();
-                                self.div(b, a)
-                            }
-                            UnaryOpcode::Sqrt => {
-                                let v = self.mul(n, 2.0).unwrap();
-                                self.div(d_arg, v)
-                            }
-                            UnaryOpcode::Square => {
-                                let v = self.mul(d_arg, v_arg).unwrap();
-                                self.mul(2.0, v)
-                            }
-                            // Discontinuous constants don't have Dirac deltas
-                            UnaryOpcode::Floor
-                            | UnaryOpcode::Ceil
-                            | UnaryOpcode::Round => Ok(zero),
-
-                            UnaryOpcode::Sin => {
-                                let c = self.cos(v_arg).unwrap();
-                                self.mul(c, d_arg)
-                            }
-
-                            UnaryOpcode::Cos => {
-                                let s = self.sin(v_arg).unwrap();
-                                let s = self.neg(s).unwrap();
-                                self.mul(s, d_arg)
+                        let op = *self.get_op(n).unwrap();
+                        match op {
+                            Op::Const(_c) => {
+                                let d = vec![zero; vars.len()];
+                                seen.insert(n, d.clone());
+                                stack.push(d);
                             }
-
-                            UnaryOpcode::Tan => {
-                                let c = self.cos(v_arg).unwrap();
-                                let c = self.square(c).unwrap();
-                                self.div(d_arg, c)
+                            Op::Input(u) => {
+                                let d: Vec<_> = vars
+                                    .iter()
+                                    .map(|v| if *v == u { one } else { zero })
+                                    .collect();
+                                seen.insert(n, d.clone());
+                                stack.push(d);
                             }
-
-                            UnaryOpcode::Asin => {
-                                let v = self.square(v_arg).unwrap();
-                                let v = self.sub(1.0, v).unwrap();
-                                let v = self.sqrt(v).unwrap();
-                                self.div(d_arg, v)
+                            Op::Unary(_op, arg) => {
+                                todo.push(Action::Up(n, op));
+                                todo.push(Action::Down(arg));
                             }
-                            UnaryOpcode::Acos => {
-                                let v = self.square(v_arg).unwrap();
-                                let v = self.sub(1.0, v).unwrap();
-                                let v = self.sqrt(v).unwrap();
-                                let v = self.neg(v).unwrap();
-                                self.div(d_arg, v)
+                            Op::Binary(_op, lhs, rhs) => {
+                                todo.push(Action::Up(n, op));
+                                todo.push(Action::Down(lhs));
+                                todo.push(Action::Down(rhs));
                             }
-                            UnaryOpcode::Atan => {
-                                let v = self.square(v_arg).unwrap();
-                                let v = self.add(1.0, v).unwrap();
-                                self.div(d_arg, v)
+                            Op::Select(_cond, a, b) => {
+                                // The condition is piecewise constant, so it
+                                // doesn't contribute to the derivative
+                                todo.push(Action::Up(n, op));
+                                todo.push(Action::Down(a));
+                                todo.push(Action::Down(b));
                             }
-                            UnaryOpcode::Exp => self.mul(n, d_arg),
-                            UnaryOpcode::Ln => self.div(d_arg, v_arg),
-                            UnaryOpcode::Not => Ok(zero),
                         }
-                        .unwrap();
-                        seen.insert(n, out);
-                        stack.push(out);
                     }
-                    Op::Binary(op, v_lhs, v_rhs) => {
-                        let d_lhs = stack.pop().unwrap();
-                        let d_rhs = stack.pop().unwrap();
-                        let out = match op {
-                            BinaryOpcode::Add => self.add(d_lhs, d_rhs),
-                            BinaryOpcode::Sub => self.sub(d_lhs, d_rhs),
-                            BinaryOpcode::Mul => {
-                                let a = self.mul(d_lhs, v_rhs).unwrap();
-                                let b = self.mul(v_lhs, d_rhs).unwrap();
-                                self.add(a, b)
-                            }
-                            BinaryOpcode::Div => {
-                                let v = self.square(v_rhs).unwrap();
-                                let a = self.mul(v_rhs, d_lhs).unwrap();
-                                let b = self.mul(v_lhs, d_rhs).unwrap();
-                                let c = self.sub(a, b).unwrap();
-                                self.div(c, v)
-                            }
-                            BinaryOpcode::Atan => {
-                                let a = self.square(v_lhs).unwrap();
-                                let b = self.square(v_rhs).unwrap();
-                                let d = self.add(a, b).unwrap();
-
-                                let a = self.mul(v_rhs, d_lhs).unwrap();
-                                let b = self.mul(v_lhs, d_rhs).unwrap();
-                                let v = self.sub(a, b).unwrap();
-                                self.div(v, d)
-                            }
-                            BinaryOpcode::Min => {
-                                let cond =
-                                    self.less_than(v_lhs, v_rhs).unwrap();
-                                self.if_nonzero_else(cond, d_lhs, d_rhs)
+                    Action::Up(n, op) => {
+                        let d = match op {
+                            Op::Const(..) | Op::Input(..) => unreachable!(),
+                            Op::Unary(op, v_arg) => stack
+                                .pop()
+                                .unwrap()
+                                .into_iter()
+                                .map(|d_arg| {
+                                    self.deriv_unary(n, op, v_arg, d_arg)
+                                })
+                                .collect::<Result<Vec<_>, _>>()?,
+                            Op::Binary(op, v_lhs, v_rhs) => {
+                                let d_lhs = stack.pop().unwrap();
+                                let d_rhs = stack.pop().unwrap();
+                                d_lhs
+                                    .into_iter()
+                                    .zip(d_rhs)
+                                    .map(|(d_lhs, d_rhs)| {
+                                        self.deriv_binary(
+                                            n, op, v_lhs, v_rhs, d_lhs, d_rhs,
+                                        )
+                                    })
+                                    .collect::<Result<Vec<_>, _>>()?
                             }
-                            BinaryOpcode::Max => {
-                                let cond =
-                                    self.less_than(v_rhs, v_lhs).unwrap();
-                                self.if_nonzero_else(cond, d_lhs, d_rhs)
+                            Op::Select(cond, ..) => {
+                                let d_a = stack.pop().unwrap();
+                                let d_b = stack.pop().unwrap();
+                                d_a.into_iter()
+                                    .zip(d_b)
+                                    .map(|(d_a, d_b)| {
+                                        self.if_nonzero_else(cond, d_a, d_b)
+                                    })
+                                    .collect::<Result<Vec<_>, _>>()?
                             }
-                            BinaryOpcode::Compare => Ok(zero),
-                            BinaryOpcode::Mod => {
-                                let e = self.div(v_lhs, v_rhs).unwrap();
-                                let q = self.floor(e).unwrap();
-
-                                // XXX
-                                // (we don't actually have %, so hack it from
-                                // `modulo`, which is actually `rem_euclid`)
-                                // ???
-                                let m = self.modulo(q, v_rhs).unwrap();
-                                let cond = self.less_than(q, zero).unwrap();
-                                let offset = self
-                                    .if_nonzero_else(cond, v_rhs, zero)
-                                    .unwrap();
-                                let m = self.sub(m, offset).unwrap();
-
-                                // Torn from the div_euclid implementation
-                                let outer = self.less_than(m, zero).unwrap();
-                                let inner =
-                                    self.less_than(zero, v_rhs).unwrap();
-                                let qa = self.sub(q, 1.0).unwrap();
-                                let qb = self.add(q, 1.0).unwrap();
-                                let inner = self
-                                    .if_nonzero_else(inner, qa, qb)
-                                    .unwrap();
-                                let e = self
-                                    .if_nonzero_else(outer, inner, q)
-                                    .unwrap();
-
-                                let v = self.mul(d_rhs, e).unwrap();
-                                self.sub(d_lhs, v)
-                            }
-                            BinaryOpcode::And => {
-                                let cond = self.compare(v_lhs, zero).unwrap();
-                                self.if_nonzero_else(cond, d_rhs, d_lhs)
-                            }
-                            BinaryOpcode::Or => {
-                                let cond = self.compare(v_lhs, zero).unwrap();
-                                self.if_nonzero_else(cond, d_lhs, d_rhs)
-                            }
-                        }
-                        .unwrap();
-                        seen.insert(n, out);
-                        stack.push(out);
+                        };
+                        seen.insert(n, d.clone());
+                        stack.push(d);
                     }
-                },
+                }
+            }
+            assert_eq!(stack.len(), 1);
+            out.push(stack.pop().unwrap());
+        }
+        Ok(out)
+    }
+
+    /// Returns the partial derivative of the unary operation `n = op(v_arg)`,
+    /// given the partial derivative of its argument
+    fn deriv_unary(
+        &mut self,
+        n: Node,
+        op: UnaryOpcode,
+        v_arg: Node,
+        d_arg: Node,
+    ) -> Result<Node, Error> {
+        let zero = self.constant(0.0);
+        match op {
+            UnaryOpcode::Neg => self.neg(d_arg),
+            UnaryOpcode::Abs => {
+                let cond = self.less_than(v_arg, zero).unwrap();
+                let pos = d_arg;
+                let neg = self.neg(d_arg).unwrap();
+                self.if_nonzero_else(cond, neg, pos)
+            }
+            UnaryOpcode::Recip => {
+                let a = self.square(v_arg).unwrap();
+                let b = self.neg(d_arg).unwrap();
+                self.div(b, a)
+            }
+            UnaryOpcode::Sqrt => {
+                let v = self.mul(n, 2.0).unwrap();
+                self.div(d_arg, v)
+            }
+            UnaryOpcode::Square => {
+                let v = self.mul(d_arg, v_arg).unwrap();
+                self.mul(2.0, v)
+            }
+            // Discontinuous constants don't have Dirac deltas
+            UnaryOpcode::Floor | UnaryOpcode::Ceil | UnaryOpcode::Round => {
+                Ok(zero)
+            }
+
+            UnaryOpcode::Sin => {
+                let c = self.cos(v_arg).unwrap();
+                self.mul(c, d_arg)
+            }
+
+            UnaryOpcode::Cos => {
+                let s = self.sin(v_arg).unwrap();
+                let s = self.neg(s).unwrap();
+                self.mul(s, d_arg)
+            }
+
+            UnaryOpcode::Tan => {
+                let c = self.cos(v_arg).unwrap();
+                let c = self.square(c).unwrap();
+                self.div(d_arg, c)
+            }
+
+            UnaryOpcode::Asin => {
+                let v = self.square(v_arg).unwrap();
+                let v = self.sub(1.0, v).unwrap();
+                let v = self.sqrt(v).unwrap();
+                self.div(d_arg, v)
+            }
+            UnaryOpcode::Acos => {
+                let v = self.square(v_arg).unwrap();
+                let v = self.sub(1.0, v).unwrap();
+                let v = self.sqrt(v).unwrap();
+                let v = self.neg(v).unwrap();
+                self.div(d_arg, v)
+            }
+            UnaryOpcode::Atan => {
+                let v = self.square(v_arg).unwrap();
+                let v = self.add(1.0, v).unwrap();
+                self.div(d_arg, v)
+            }
+            UnaryOpcode::Exp => self.mul(n, d_arg),
+            UnaryOpcode::Ln => self.div(d_arg, v_arg),
+            UnaryOpcode::Not => Ok(zero),
+            UnaryOpcode::Sinh => {
+                let c = self.cosh(v_arg).unwrap();
+                self.mul(c, d_arg)
+            }
+            UnaryOpcode::Cosh => {
+                let s = self.sinh(v_arg).unwrap();
+                self.mul(s, d_arg)
+            }
+            UnaryOpcode::Tanh => {
+                let c = self.cosh(v_arg).unwrap();
+                let c = self.square(c).unwrap();
+                self.div(d_arg, c)
+            }
+            // Like floor, the jump at zero has no Dirac delta
+            UnaryOpcode::Sign => Ok(zero),
+            UnaryOpcode::Cbrt => {
+                let v = self.square(n).unwrap();
+                let v = self.mul(v, 3.0).unwrap();
+                self.div(d_arg, v)
+            }
+            UnaryOpcode::Log2 => {
+                let v = self.mul(v_arg, std::f64::consts::LN_2).unwrap();
+                self.div(d_arg, v)
+            }
+        }
+    }
+
+    /// Returns the partial derivative of the binary operation
+    /// `n = op(v_lhs, v_rhs)`, given the partial derivatives of its arguments
+    fn deriv_binary(
+        &mut self,
+        n: Node,
+        op: BinaryOpcode,
+        v_lhs: Node,
+        v_rhs: Node,
+        d_lhs: Node,
+        d_rhs: Node,
+    ) -> Result<Node, Error> {
+        let zero = self.constant(0.0);
+        match op {
+            BinaryOpcode::Add => self.add(d_lhs, d_rhs),
+            BinaryOpcode::Sub => self.sub(d_lhs, d_rhs),
+            BinaryOpcode::Mul => {
+                let a = self.mul(d_lhs, v_rhs).unwrap();
+                let b = self.mul(v_lhs, d_rhs).unwrap();
+                self.add(a, b)
+            }
+            BinaryOpcode::Div => {
+                let v = self.square(v_rhs).unwrap();
+                let a = self.mul(v_rhs, d_lhs).unwrap();
+                let b = self.mul(v_lhs, d_rhs).unwrap();
+                let c = self.sub(a, b).unwrap();
+                self.div(c, v)
+            }
+            BinaryOpcode::Atan => {
+                let a = self.square(v_lhs).unwrap();
+                let b = self.square(v_rhs).unwrap();
+                let d = self.add(a, b).unwrap();
+
+                let a = self.mul(v_rhs, d_lhs).unwrap();
+                let b = self.mul(v_lhs, d_rhs).unwrap();
+                let v = self.sub(a, b).unwrap();
+                self.div(v, d)
+            }
+            BinaryOpcode::Min => {
+                let cond = self.less_than(v_lhs, v_rhs).unwrap();
+                self.if_nonzero_else(cond, d_lhs, d_rhs)
+            }
+            BinaryOpcode::Max => {
+                let cond = self.less_than(v_rhs, v_lhs).unwrap();
+                self.if_nonzero_else(cond, d_lhs, d_rhs)
+            }
+            BinaryOpcode::Compare => Ok(zero),
+            BinaryOpcode::Mod => {
+                let e = self.div(v_lhs, v_rhs).unwrap();
+                let q = self.floor(e).unwrap();
+
+                // XXX
+                // (we don't actually have %, so hack it from
+                // `modulo`, which is actually `rem_euclid`)
+                // ???
+                let m = self.modulo(q, v_rhs).unwrap();
+                let cond = self.less_than(q, zero).unwrap();
+                let offset = self.if_nonzero_else(cond, v_rhs, zero).unwrap();
+                let m = self.sub(m, offset).unwrap();
+
+                // Torn from the div_euclid implementation
+                let outer = self.less_than(m, zero).unwrap();
+                let inner = self.less_than(zero, v_rhs).unwrap();
+                let qa = self.sub(q, 1.0).unwrap();
+                let qb = self.add(q, 1.0).unwrap();
+                let inner = self.if_nonzero_else(inner, qa, qb).unwrap();
+                let e = self.if_nonzero_else(outer, inner, q).unwrap();
+
+                let v = self.mul(d_rhs, e).unwrap();
+                self.sub(d_lhs, v)
+            }
+            BinaryOpcode::And => {
+                let cond = self.compare(v_lhs, zero).unwrap();
+                self.if_nonzero_else(cond, d_rhs, d_lhs)
+            }
+            BinaryOpcode::Or => {
+                let cond = self.compare(v_lhs, zero).unwrap();
+                self.if_nonzero_else(cond, d_lhs, d_rhs)
+            }
+            BinaryOpcode::Pow => {
+                // d(a^b) = b * a^(b-1) * da + a^b * ln(a) * db
+                let e = self.sub(v_rhs, 1.0).unwrap();
+                let p = self.pow(v_lhs, e).unwrap();
+                let p = self.mul(v_rhs, p).unwrap();
+                let a = self.mul(p, d_lhs).unwrap();
+
+                let ln = self.ln(v_lhs).unwrap();
+                let q = self.mul(n, ln).unwrap();
+                let b = self.mul(q, d_rhs).unwrap();
+                self.add(a, b)
             }
         }
-        assert_eq!(stack.len(), 1);
-        Ok(stack.pop().unwrap())
     }
 }
 
@@ -1582,6 +1895,37 @@ mod test {
         assert_eq!(tape.vars.len(), 1);
     }
 
+    #[test]
+    fn test_gc() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+        let a = ctx.sin(x).unwrap();
+        let b = ctx.mul(a, y).unwrap();
+        let _dead = ctx.cos(b).unwrap();
+        let c = ctx.if_nonzero_else(z, b, a).unwrap();
+        let _dead = ctx.sub(c, 3.0).unwrap();
+        let d = ctx.square(y).unwrap();
+
+        let before = [c, d].map(|n| ctx.eval_xyz(n, 0.5, 2.0, 1.0).unwrap());
+        let remap = ctx.gc(&[c, d]).unwrap();
+        assert_eq!(ctx.len(), 7); // x, y, z, a, b, c, d
+        assert_eq!(remap.len(), 7);
+        let after =
+            [c, d].map(|n| ctx.eval_xyz(remap[&n], 0.5, 2.0, 1.0).unwrap());
+        assert_eq!(before, after);
+
+        // Deduplication still works after compaction
+        let x2 = ctx.x();
+        assert_eq!(x2, remap[&x]);
+        let b2 = ctx.mul(remap[&a], remap[&y]).unwrap();
+        assert_eq!(b2, remap[&b]);
+        assert_eq!(ctx.len(), 7);
+
+        assert!(ctx.gc(&[Node::new(100)]).is_err());
+        assert!(ctx.gc(&[]).unwrap().is_empty());
+        assert!(ctx.is_empty());
+    }
+
     #[test]
     fn test_export() {
         let mut ctx = Context::new();
@@ -3,10 +3,22 @@ use crate::{
     var::Var,
 };
 use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
 /// A one-argument math operation
 #[allow(missing_docs)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Hash,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+)]
 pub enum UnaryOpcode {
     Neg,
     Abs,
@@ -25,11 +37,28 @@ pub enum UnaryOpcode {
     Exp,
     Ln,
     Not,
+    Sinh,
+    Cosh,
+    Tanh,
+    Sign,
+    Cbrt,
+    Log2,
 }
 
 /// A two-argument math operation
 #[allow(missing_docs)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Hash,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+)]
 pub enum BinaryOpcode {
     Add,
     Sub,
@@ -42,6 +71,7 @@ pub enum BinaryOpcode {
     Mod,
     And,
     Or,
+    Pow,
 }
 
 /// An operation in a math expression
@@ -60,6 +90,9 @@ pub enum Op {
     Const(OrderedFloat<f64>),
     Binary(BinaryOpcode, Node, Node),
     Unary(UnaryOpcode, Node),
+    /// Ternary selection, returning the second node if the first is non-zero
+    /// and the third node otherwise
+    Select(Node, Node, Node),
 }
 
 fn dot_color_to_rgb(s: &str) -> &'static str {
@@ -78,9 +111,8 @@ impl Op {
         match self {
             Op::Const(..) => "green",
             Op::Input(..) => "red",
-            Op::Binary(BinaryOpcode::Min | BinaryOpcode::Max, ..) => {
-                "dodgerblue"
-            }
+            Op::Binary(BinaryOpcode::Min | BinaryOpcode::Max, ..)
+            | Op::Select(..) => "dodgerblue",
             Op::Binary(..) | Op::Unary(..) => "goldenrod",
         }
     }
@@ -90,16 +122,17 @@ impl Op {
         match self {
             Op::Const(..) => "oval",
             Op::Input(..) => "circle",
-            Op::Binary(..) | Op::Unary(..) => "box",
+            Op::Binary(..) | Op::Unary(..) | Op::Select(..) => "box",
         }
     }
 
-    /// Iterates over children, producing 0, 1, or 2 values
+    /// Iterates over children, producing 0, 1, 2, or 3 values
     pub fn iter_children(&self) -> impl Iterator<Item = Node> {
         let out = match self {
-            Op::Binary(_, a, b) => [Some(*a), Some(*b)],
-            Op::Unary(_, a) => [Some(*a), None],
-            Op::Input(..) | Op::Const(..) => [None, None],
+            Op::Select(c, a, b) => [Some(*c), Some(*a), Some(*b)],
+            Op::Binary(_, a, b) => [Some(*a), Some(*b), None],
+            Op::Unary(_, a) => [Some(*a), None, None],
+            Op::Input(..) | Op::Const(..) => [None, None, None],
         };
         out.into_iter().flatten()
     }
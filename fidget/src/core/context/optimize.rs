@@ -0,0 +1,538 @@
+//! Whole-graph algebraic simplification
+use super::{rewrite::Rewrite, BinaryOpcode, Context, Node, Op, UnaryOpcode};
+use crate::Error;
+
+use std::collections::{HashMap, HashSet};
+
+/// Rules for [`Context::optimize`]
+struct Optimizer;
+
+impl Rewrite for Optimizer {
+    const SIGNED: bool = true;
+
+    fn chain_op(&self, op: BinaryOpcode) -> Option<BinaryOpcode> {
+        match op {
+            BinaryOpcode::Sub => Some(BinaryOpcode::Add),
+            BinaryOpcode::Add
+            | BinaryOpcode::Mul
+            | BinaryOpcode::Min
+            | BinaryOpcode::Max => Some(op),
+            _ => None,
+        }
+    }
+
+    fn unary(
+        &mut self,
+        ctx: &mut Context,
+        op: UnaryOpcode,
+        arg: Node,
+    ) -> Result<Node, Error> {
+        ctx.optimize_unary(op, arg)
+    }
+
+    fn chain(
+        &mut self,
+        ctx: &mut Context,
+        op: BinaryOpcode,
+        terms: Vec<(Node, f64)>,
+    ) -> Result<Node, Error> {
+        match op {
+            BinaryOpcode::Add => ctx.optimize_sum(terms),
+            BinaryOpcode::Mul => ctx.optimize_product(terms),
+            BinaryOpcode::Min | BinaryOpcode::Max => {
+                let nodes = terms.into_iter().map(|(n, _)| n);
+                ctx.optimize_min_max(op, nodes)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Context {
+    /// Applies algebraic simplifications to the graph rooted at `root`,
+    /// returning the new root.
+    ///
+    /// The builder functions (e.g. [`Context::add`]) only perform local
+    /// constant folding when a node is created.  This pass works on the whole
+    /// graph, and performs the following rewrites:
+    ///
+    /// - Identity and annihilator elimination (`x * 1`, `x + 0`, `x * 0`)
+    /// - Cancellation of like terms in sums (`x - x`, `x + y - x`)
+    /// - Collection of constants in sums and products (`2 * (x * 3)`), and
+    ///   distribution of a constant factor over a sum with a constant term
+    ///   (`2 * (x + 1)` becomes `2 * x + 2`)
+    /// - Cancellation of double negation through `neg` and `sub`
+    /// - Flattening of nested `min` and `max` operations, which are then
+    ///   rebuilt as balanced trees (as in [`Context::balance`])
+    /// - Rewriting `sqrt(square(x))` into `abs(x)`
+    ///
+    /// Chains are only flattened through nodes which are not used elsewhere in
+    /// the graph, so shared subexpressions remain shared.
+    ///
+    /// Like the builder functions, these rewrites assume that values are
+    /// finite: for example, `x - x` is simplified to zero, even though it would
+    /// produce `NaN` for an infinite `x`.
+    ///
+    /// Existing nodes are not modified, so all `Node` handles remain valid.
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let a = ctx.add(x, y).unwrap();
+    /// let b = ctx.sub(a, x).unwrap(); // (x + y) - x
+    /// let out = ctx.optimize(b).unwrap();
+    /// assert_eq!(out, y);
+    /// ```
+    pub fn optimize(&mut self, root: Node) -> Result<Node, Error> {
+        self.rewrite(root, &mut Optimizer)
+    }
+
+    /// Rebuilds a sum from a set of (optimized) terms and coefficients
+    fn optimize_sum(&mut self, terms: Vec<(Node, f64)>) -> Result<Node, Error> {
+        // Accumulate coefficients for each unique term, preserving order
+        let mut constant = 0.0;
+        let mut order = vec![];
+        let mut coeffs: HashMap<Node, f64> = HashMap::new();
+        for (mut n, mut c) in terms {
+            loop {
+                match *self.get_op(n).unwrap() {
+                    Op::Unary(UnaryOpcode::Neg, a) => {
+                        n = a;
+                        c = -c;
+                    }
+                    // Pull constant terms out of sums and differences, e.g.
+                    // from a constant factor that was distributed
+                    Op::Binary(BinaryOpcode::Add, a, b)
+                        if self.get_const(b).is_ok() =>
+                    {
+                        constant += c * self.get_const(b).unwrap();
+                        n = a;
+                    }
+                    Op::Binary(BinaryOpcode::Add, a, b)
+                        if self.get_const(a).is_ok() =>
+                    {
+                        constant += c * self.get_const(a).unwrap();
+                        n = b;
+                    }
+                    Op::Binary(BinaryOpcode::Sub, a, b)
+                        if self.get_const(b).is_ok() =>
+                    {
+                        constant -= c * self.get_const(b).unwrap();
+                        n = a;
+                    }
+                    Op::Binary(BinaryOpcode::Sub, a, b)
+                        if self.get_const(a).is_ok() =>
+                    {
+                        constant += c * self.get_const(a).unwrap();
+                        n = b;
+                        c = -c;
+                    }
+                    Op::Binary(BinaryOpcode::Mul, a, b)
+                        if self.get_const(b).is_ok() =>
+                    {
+                        c *= self.get_const(b).unwrap();
+                        n = a;
+                    }
+                    Op::Binary(BinaryOpcode::Mul, a, b)
+                        if self.get_const(a).is_ok() =>
+                    {
+                        c *= self.get_const(a).unwrap();
+                        n = b;
+                    }
+                    _ => break,
+                }
+            }
+            if let Ok(v) = self.get_const(n) {
+                constant += c * v;
+            } else {
+                *coeffs.entry(n).or_insert_with(|| {
+                    order.push(n);
+                    0.0
+                }) += c;
+            }
+        }
+
+        let mut pos = vec![];
+        let mut neg = vec![];
+        for n in order {
+            let c = coeffs[&n];
+            if c == 0.0 {
+                continue;
+            }
+            let t = if c.abs() == 1.0 {
+                n
+            } else {
+                self.mul(n, c.abs())?
+            };
+            if c > 0.0 {
+                pos.push(t);
+            } else {
+                neg.push(t);
+            }
+        }
+
+        match (pos.is_empty(), neg.is_empty()) {
+            (true, true) => Ok(self.constant(constant)),
+            (true, false) => {
+                let n = self.build_balanced(BinaryOpcode::Add, &neg)?;
+                if constant == 0.0 {
+                    self.neg(n)
+                } else {
+                    self.sub(constant, n)
+                }
+            }
+            (false, _) => {
+                let mut out = self.build_balanced(BinaryOpcode::Add, &pos)?;
+                if !neg.is_empty() {
+                    let n = self.build_balanced(BinaryOpcode::Add, &neg)?;
+                    out = self.sub(out, n)?;
+                }
+                self.add(out, constant)
+            }
+        }
+    }
+
+    /// Rebuilds a product from a set of (optimized) terms and signs
+    fn optimize_product(
+        &mut self,
+        terms: Vec<(Node, f64)>,
+    ) -> Result<Node, Error> {
+        let mut constant = 1.0;
+        let mut nodes = vec![];
+        for (mut n, c) in terms {
+            constant *= c;
+            if let Op::Unary(UnaryOpcode::Neg, a) = *self.get_op(n).unwrap() {
+                n = a;
+                constant = -constant;
+            }
+            match self.get_const(n) {
+                Ok(v) => constant *= v,
+                Err(_) => nodes.push(n),
+            }
+        }
+        if nodes.is_empty() || constant == 0.0 {
+            return Ok(self.constant(constant));
+        }
+        let out = self.build_balanced(BinaryOpcode::Mul, &nodes)?;
+        if constant == 1.0 {
+            return Ok(out);
+        } else if constant == -1.0 {
+            return self.neg(out);
+        }
+
+        // Distribute the constant over a sum or difference with a constant
+        // term, so that it can be folded into that term
+        if let Op::Binary(op @ (BinaryOpcode::Add | BinaryOpcode::Sub), a, b) =
+            *self.get_op(out).unwrap()
+        {
+            match (self.get_const(a), self.get_const(b)) {
+                (Ok(va), Err(_)) => {
+                    let vb = self.mul(b, constant)?;
+                    let va = self.constant(va * constant);
+                    return self.rebuild_binary(op, va, vb);
+                }
+                (Err(_), Ok(vb)) => {
+                    let va = self.mul(a, constant)?;
+                    let vb = self.constant(vb * constant);
+                    return self.rebuild_binary(op, va, vb);
+                }
+                _ => (),
+            }
+        }
+        self.mul(out, constant)
+    }
+
+    /// Rebuilds a `min` or `max` chain from a set of (optimized) terms
+    fn optimize_min_max<I: Iterator<Item = Node>>(
+        &mut self,
+        op: BinaryOpcode,
+        terms: I,
+    ) -> Result<Node, Error> {
+        let mut nodes = vec![];
+        let mut unique = HashSet::new();
+        let mut constant = None;
+        for n in terms {
+            if self.get_const(n).is_ok() {
+                // Use the builder for constant folding, to match the semantics
+                // of the evaluators (e.g. for NaN propagation)
+                constant = Some(match constant {
+                    Some(c) => self.rebuild_binary(op, c, n)?,
+                    None => n,
+                });
+            } else if unique.insert(n) {
+                nodes.push(n);
+            }
+        }
+        nodes.extend(constant);
+        self.balance_chain(op, &nodes, None)
+    }
+
+    /// Applies simplifications to a unary operation with an optimized argument
+    fn optimize_unary(
+        &mut self,
+        op: UnaryOpcode,
+        arg: Node,
+    ) -> Result<Node, Error> {
+        let arg_op = *self.get_op(arg).unwrap();
+        match (op, arg_op) {
+            (UnaryOpcode::Neg, Op::Unary(UnaryOpcode::Neg, a)) => Ok(a),
+            (UnaryOpcode::Neg, Op::Binary(BinaryOpcode::Sub, a, b)) => {
+                self.sub(b, a)
+            }
+            (UnaryOpcode::Sqrt, Op::Unary(UnaryOpcode::Square, a)) => {
+                self.abs(a)
+            }
+            (
+                UnaryOpcode::Abs | UnaryOpcode::Square,
+                Op::Unary(UnaryOpcode::Neg | UnaryOpcode::Abs, a),
+            ) => self.op_unary(a, op),
+            (UnaryOpcode::Abs, Op::Unary(UnaryOpcode::Square, _)) => Ok(arg),
+            _ => self.op_unary(arg, op),
+        }
+    }
+
+    /// Builds a binary operation using its builder function, which applies
+    /// local simplifications and constant folding
+    pub(super) fn rebuild_binary(
+        &mut self,
+        op: BinaryOpcode,
+        a: Node,
+        b: Node,
+    ) -> Result<Node, Error> {
+        match op {
+            BinaryOpcode::Add => self.add(a, b),
+            BinaryOpcode::Sub => self.sub(a, b),
+            BinaryOpcode::Mul => self.mul(a, b),
+            BinaryOpcode::Div => self.div(a, b),
+            BinaryOpcode::Atan => self.atan2(a, b),
+            BinaryOpcode::Min => self.min(a, b),
+            BinaryOpcode::Max => self.max(a, b),
+            BinaryOpcode::Compare => self.compare(a, b),
+            BinaryOpcode::Mod => self.modulo(a, b),
+            BinaryOpcode::And => self.and(a, b),
+            BinaryOpcode::Or => self.or(a, b),
+            BinaryOpcode::Pow => self.pow(a, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns the depth of the graph rooted at `n`
+    fn depth(ctx: &Context, n: Node) -> usize {
+        1 + ctx
+            .get_op(n)
+            .unwrap()
+            .iter_children()
+            .map(|c| depth(ctx, c))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Checks that two nodes evaluate to the same value at a few points
+    fn check_equal(ctx: &Context, a: Node, b: Node) {
+        for (x, y, z) in [(0.3, -1.2, 2.5), (-2.1, 0.7, -0.4), (1.9, 3.3, 0.1)]
+        {
+            let va = ctx.eval_xyz(a, x, y, z).unwrap();
+            let vb = ctx.eval_xyz(b, x, y, z).unwrap();
+            assert!((va - vb).abs() < 1e-9, "{va} != {vb} at ({x}, {y}, {z})");
+        }
+    }
+
+    #[test]
+    fn test_identities() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+
+        // (x + y) - x
+        let a = ctx.add(x, y).unwrap();
+        let b = ctx.sub(a, x).unwrap();
+        assert_eq!(ctx.optimize(b).unwrap(), y);
+
+        // x - x
+        let c = ctx.sub(x, x).unwrap();
+        let out = ctx.optimize(c).unwrap();
+        assert_eq!(ctx.get_const(out).unwrap(), 0.0);
+
+        // (x + 1) - 1
+        let d = ctx.add(x, 1.0).unwrap();
+        let d = ctx.sub(d, 1.0).unwrap();
+        assert_eq!(ctx.optimize(d).unwrap(), x);
+
+        // (x * 2) * 0.5
+        let e = ctx.mul(x, 2.0).unwrap();
+        let e = ctx.mul(e, 0.5).unwrap();
+        assert_eq!(ctx.optimize(e).unwrap(), x);
+
+        // (x * y) * 0 (with the zero hidden behind a subtraction)
+        let z = ctx.sub(y, y).unwrap();
+        let f = ctx.mul(x, y).unwrap();
+        let f = ctx.mul(f, z).unwrap();
+        let out = ctx.optimize(f).unwrap();
+        assert_eq!(ctx.get_const(out).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_negation() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+
+        let a = ctx.neg(x).unwrap();
+        let a = ctx.neg(a).unwrap();
+        assert_eq!(ctx.optimize(a).unwrap(), x);
+
+        // -(x - y) => y - x
+        let b = ctx.sub(x, y).unwrap();
+        let b = ctx.neg(b).unwrap();
+        let out = ctx.optimize(b).unwrap();
+        assert_eq!(ctx.get_op(out), Some(&Op::Binary(BinaryOpcode::Sub, y, x)));
+
+        // x - (-y) => x + y
+        let c = ctx.neg(y).unwrap();
+        let c = ctx.sub(x, c).unwrap();
+        let out = ctx.optimize(c).unwrap();
+        assert_eq!(ctx.get_op(out), Some(&Op::Binary(BinaryOpcode::Add, x, y)));
+
+        // (-x) * (-y) => x * y
+        let nx = ctx.neg(x).unwrap();
+        let ny = ctx.neg(y).unwrap();
+        let d = ctx.mul(nx, ny).unwrap();
+        let out = ctx.optimize(d).unwrap();
+        assert_eq!(ctx.get_op(out), Some(&Op::Binary(BinaryOpcode::Mul, x, y)));
+    }
+
+    #[test]
+    fn test_negated_product() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+
+        // x * -(y * z)
+        let yz = ctx.mul(y, z).unwrap();
+        let n = ctx.neg(yz).unwrap();
+        let a = ctx.mul(x, n).unwrap();
+        let out = ctx.optimize(a).unwrap();
+        assert_eq!(ctx.eval_xyz(a, 1.0, 2.0, 3.0).unwrap(), -6.0);
+        assert_eq!(ctx.eval_xyz(out, 1.0, 2.0, 3.0).unwrap(), -6.0);
+        check_equal(&ctx, a, out);
+
+        // x * -(-(y * z))
+        let yz = ctx.mul(y, z).unwrap();
+        let n = ctx.neg(yz).unwrap();
+        let n = ctx.neg(n).unwrap();
+        let b = ctx.mul(x, n).unwrap();
+        let out = ctx.optimize(b).unwrap();
+        assert_eq!(ctx.eval_xyz(out, 1.0, 2.0, 3.0).unwrap(), 6.0);
+        check_equal(&ctx, b, out);
+
+        // (-x * y) * -(z * -(x * -y))
+        let nx = ctx.neg(x).unwrap();
+        let lhs = ctx.mul(nx, y).unwrap();
+        let ny = ctx.neg(y).unwrap();
+        let xy = ctx.mul(x, ny).unwrap();
+        let n = ctx.neg(xy).unwrap();
+        let rhs = ctx.mul(z, n).unwrap();
+        let rhs = ctx.neg(rhs).unwrap();
+        let c = ctx.mul(lhs, rhs).unwrap();
+        let out = ctx.optimize(c).unwrap();
+        assert_eq!(
+            ctx.eval_xyz(c, 1.0, 2.0, 3.0).unwrap(),
+            ctx.eval_xyz(out, 1.0, 2.0, 3.0).unwrap()
+        );
+        check_equal(&ctx, c, out);
+    }
+
+    #[test]
+    fn test_sqrt_square() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let s = ctx.square(x).unwrap();
+        let s = ctx.sqrt(s).unwrap();
+        let out = ctx.optimize(s).unwrap();
+        assert_eq!(ctx.get_op(out), Some(&Op::Unary(UnaryOpcode::Abs, x)));
+        check_equal(&ctx, s, out);
+    }
+
+    #[test]
+    fn test_distribute() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+
+        // 2 * (x + 1) + 3 => 2 * x + 5
+        let a = ctx.add(x, 1.0).unwrap();
+        let a = ctx.mul(a, 2.0).unwrap();
+        let a = ctx.add(a, 3.0).unwrap();
+        let out = ctx.optimize(a).unwrap();
+        check_equal(&ctx, a, out);
+        let expected = ctx.mul(x, 2.0).unwrap();
+        let expected = ctx.add(expected, 5.0).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_min_max_balanced() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let mut out = x;
+        for i in 0..64 {
+            let v = ctx.sub(x, i as f64).unwrap();
+            let v = ctx.square(v).unwrap();
+            out = ctx.min(out, v).unwrap();
+        }
+        assert_eq!(depth(&ctx, out), 66);
+        let opt = ctx.optimize(out).unwrap();
+        assert_eq!(depth(&ctx, opt), 10);
+        check_equal(&ctx, out, opt);
+
+        // Constants are folded together, and duplicates are removed
+        let y = ctx.y();
+        let a = ctx.max(x, 1.0).unwrap();
+        let b = ctx.max(y, 3.0).unwrap();
+        let c = ctx.max(a, b).unwrap();
+        let c = ctx.max(c, x).unwrap();
+        let opt = ctx.optimize(c).unwrap();
+        check_equal(&ctx, c, opt);
+        let expected = ctx.max(y, 3.0).unwrap();
+        let expected = ctx.max(x, expected).unwrap();
+        assert_eq!(opt, expected);
+    }
+
+    #[test]
+    fn test_shared() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+
+        // The inner min is shared, so it shouldn't be flattened
+        let inner = ctx.min(x, y).unwrap();
+        let a = ctx.min(inner, z).unwrap();
+        let b = ctx.add(inner, 1.0).unwrap();
+        let root = ctx.mul(a, b).unwrap();
+        let opt = ctx.optimize(root).unwrap();
+        assert_eq!(opt, root);
+    }
+
+    #[test]
+    fn test_optimize_consistent() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+        let mut out = ctx.constant(0.0);
+        for (i, v) in [x, y, z, x, y].into_iter().enumerate() {
+            let a = ctx.mul(v, i as f64 + 1.0).unwrap();
+            let a = ctx.sub(a, 0.5).unwrap();
+            let n = ctx.neg(a).unwrap();
+            let b = ctx.sin(n).unwrap();
+            let b = ctx.mul(b, -2.0).unwrap();
+            let b = ctx.sub(b, n).unwrap();
+            out = ctx.add(out, b).unwrap();
+            out = ctx.max(out, a).unwrap();
+        }
+        let opt = ctx.optimize(out).unwrap();
+        check_equal(&ctx, out, opt);
+    }
+}
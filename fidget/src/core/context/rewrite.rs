@@ -0,0 +1,213 @@
+//! Bottom-up graph rewriting, shared by the optimizer and balancer
+use super::{BinaryOpcode, Context, Node, Op, UnaryOpcode};
+use crate::Error;
+
+use std::collections::HashMap;
+
+/// Rules used by [`Context::rewrite`] to rebuild a graph
+pub(super) trait Rewrite {
+    /// If true, chains of `add` expand through `sub` and `neg` nodes, and
+    /// chains of `mul` expand through `neg` nodes, with signed coefficients
+    const SIGNED: bool;
+
+    /// Returns the chain opcode for a binary operation, or `None` if the
+    /// operation should be rebuilt on its own
+    fn chain_op(&self, op: BinaryOpcode) -> Option<BinaryOpcode>;
+
+    /// Rebuilds a unary operation with a rewritten argument
+    fn unary(
+        &mut self,
+        ctx: &mut Context,
+        op: UnaryOpcode,
+        arg: Node,
+    ) -> Result<Node, Error>;
+
+    /// Rebuilds a chain from its rewritten terms and their coefficients
+    ///
+    /// Coefficients are always 1 unless [`Rewrite::SIGNED`] is true.
+    fn chain(
+        &mut self,
+        ctx: &mut Context,
+        op: BinaryOpcode,
+        terms: Vec<(Node, f64)>,
+    ) -> Result<Node, Error>;
+}
+
+impl Context {
+    /// Rebuilds the graph rooted at `root` from the bottom up, flattening
+    /// chains of associative operations and handing them to `rules`
+    ///
+    /// Binary operations which aren't chains and `select` nodes are rebuilt
+    /// with their builder functions; existing nodes are not modified.
+    pub(super) fn rewrite<R: Rewrite>(
+        &mut self,
+        root: Node,
+        rules: &mut R,
+    ) -> Result<Node, Error> {
+        self.check_node(root)?;
+        let uses = self.use_counts(root);
+
+        // Do recursion on the heap to avoid stack overflows for deep trees
+        enum Action {
+            /// Pushes `Up(n)` followed by `Down(c)` for each child
+            Down(Node),
+            /// Consumes rewritten children from the stack and pushes a new node
+            Up(Node, Op),
+            /// Consumes rewritten chain terms from the stack and rebuilds the
+            /// chain, using the given coefficients
+            Chain(Node, BinaryOpcode, Vec<f64>),
+        }
+        let mut todo = vec![Action::Down(root)];
+        let mut stack = vec![];
+
+        // Cache of original -> rewritten nodes
+        let mut seen: HashMap<Node, Node> = HashMap::new();
+
+        while let Some(t) = todo.pop() {
+            match t {
+                Action::Down(n) => {
+                    if let Some(p) = seen.get(&n) {
+                        stack.push(*p);
+                        continue;
+                    }
+                    let op = *self.get_op(n).unwrap();
+                    match op {
+                        Op::Const(..) | Op::Input(..) => {
+                            seen.insert(n, n);
+                            stack.push(n);
+                        }
+                        Op::Binary(op, lhs, rhs) => {
+                            if let Some(op) = rules.chain_op(op) {
+                                let terms =
+                                    self.chain_terms(n, op, &uses, R::SIGNED);
+                                let (nodes, coeffs): (Vec<_>, Vec<_>) =
+                                    terms.into_iter().unzip();
+                                todo.push(Action::Chain(n, op, coeffs));
+                                todo.extend(
+                                    nodes.into_iter().map(Action::Down),
+                                );
+                            } else {
+                                todo.push(Action::Up(
+                                    n,
+                                    Op::Binary(op, lhs, rhs),
+                                ));
+                                todo.push(Action::Down(lhs));
+                                todo.push(Action::Down(rhs));
+                            }
+                        }
+                        Op::Unary(_op, arg) => {
+                            todo.push(Action::Up(n, op));
+                            todo.push(Action::Down(arg));
+                        }
+                        Op::Select(cond, a, b) => {
+                            todo.push(Action::Up(n, op));
+                            todo.push(Action::Down(cond));
+                            todo.push(Action::Down(a));
+                            todo.push(Action::Down(b));
+                        }
+                    }
+                }
+                Action::Up(n, op) => {
+                    let out = match op {
+                        Op::Const(..) | Op::Input(..) => unreachable!(),
+                        Op::Unary(op, ..) => {
+                            let arg = stack.pop().unwrap();
+                            rules.unary(self, op, arg)?
+                        }
+                        Op::Binary(op, ..) => {
+                            let lhs = stack.pop().unwrap();
+                            let rhs = stack.pop().unwrap();
+                            self.rebuild_binary(op, lhs, rhs)?
+                        }
+                        Op::Select(..) => {
+                            let cond = stack.pop().unwrap();
+                            let a = stack.pop().unwrap();
+                            let b = stack.pop().unwrap();
+                            self.if_nonzero_else(cond, a, b)?
+                        }
+                    };
+                    seen.insert(n, out);
+                    stack.push(out);
+                }
+                Action::Chain(n, op, coeffs) => {
+                    let terms = coeffs
+                        .into_iter()
+                        .map(|c| (stack.pop().unwrap(), c))
+                        .collect::<Vec<_>>();
+                    let out = rules.chain(self, op, terms)?;
+                    seen.insert(n, out);
+                    stack.push(out);
+                }
+            }
+        }
+        assert_eq!(stack.len(), 1);
+        Ok(stack.pop().unwrap())
+    }
+
+    /// Counts the number of parents of each node reachable from `root`
+    pub(super) fn use_counts(&self, root: Node) -> HashMap<Node, usize> {
+        let mut uses = HashMap::new();
+        uses.insert(root, 0);
+        let mut todo = vec![root];
+        while let Some(n) = todo.pop() {
+            for c in self.get_op(n).unwrap().iter_children() {
+                let u = uses.entry(c).or_insert(0);
+                if *u == 0 {
+                    todo.push(c);
+                }
+                *u += 1;
+            }
+        }
+        uses
+    }
+
+    /// Collects the terms of an n-ary chain rooted at `n`
+    ///
+    /// The chain is expanded through nodes of the same type with a single
+    /// parent.  If `signed` is true, then sums also expand through `sub` and
+    /// `neg` nodes (which negate their terms), and products expand through
+    /// `neg` nodes.
+    ///
+    /// Returns a list of `(node, coefficient)` terms.
+    fn chain_terms(
+        &self,
+        n: Node,
+        op: BinaryOpcode,
+        uses: &HashMap<Node, usize>,
+        signed: bool,
+    ) -> Vec<(Node, f64)> {
+        let mut out = vec![];
+        let mut todo = vec![(n, 1.0)];
+        while let Some((m, c)) = todo.pop() {
+            let expand = m == n || uses[&m] == 1;
+            match *self.get_op(m).unwrap() {
+                Op::Binary(o, a, b) if expand && o == op => {
+                    // The sign of a product applies to the product as a
+                    // whole, so only one of its factors carries it
+                    let cb = if op == BinaryOpcode::Mul { 1.0 } else { c };
+                    todo.push((b, cb));
+                    todo.push((a, c));
+                }
+                Op::Binary(BinaryOpcode::Sub, a, b)
+                    if signed && expand && op == BinaryOpcode::Add =>
+                {
+                    todo.push((b, -c));
+                    todo.push((a, c));
+                }
+                Op::Unary(UnaryOpcode::Neg, a)
+                    if signed
+                        && expand
+                        && m != n
+                        && matches!(
+                            op,
+                            BinaryOpcode::Add | BinaryOpcode::Mul
+                        ) =>
+                {
+                    todo.push((a, -c));
+                }
+                _ => out.push((m, c)),
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,131 @@
+//! Serialization and deserialization of contexts
+use super::{indexed::Index, BinaryOpcode, Context, Node, Op, UnaryOpcode};
+use crate::var::Var;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serialized form of an [`Op`]
+///
+/// This is separate from `Op` so that the serialized format uses plain `f64`
+/// values for constants and is decoupled from the in-memory representation.
+#[derive(Serialize, Deserialize)]
+enum SerializedOp {
+    Input(Var),
+    Const(f64),
+    Binary(BinaryOpcode, Node, Node),
+    Unary(UnaryOpcode, Node),
+    Select(Node, Node, Node),
+}
+
+impl From<&Op> for SerializedOp {
+    fn from(op: &Op) -> Self {
+        match *op {
+            Op::Input(v) => SerializedOp::Input(v),
+            Op::Const(c) => SerializedOp::Const(c.0),
+            Op::Binary(op, a, b) => SerializedOp::Binary(op, a, b),
+            Op::Unary(op, a) => SerializedOp::Unary(op, a),
+            Op::Select(c, a, b) => SerializedOp::Select(c, a, b),
+        }
+    }
+}
+
+impl From<SerializedOp> for Op {
+    fn from(op: SerializedOp) -> Self {
+        match op {
+            SerializedOp::Input(v) => Op::Input(v),
+            SerializedOp::Const(c) => Op::Const(c.into()),
+            SerializedOp::Binary(op, a, b) => Op::Binary(op, a, b),
+            SerializedOp::Unary(op, a) => Op::Unary(op, a),
+            SerializedOp::Select(c, a, b) => Op::Select(c, a, b),
+        }
+    }
+}
+
+impl Serialize for Context {
+    /// Serializes every node in the context, in index order
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(
+            self.ops
+                .keys()
+                .map(|n| SerializedOp::from(self.ops.get_by_index(n).unwrap())),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for Context {
+    /// Deserializes a context, preserving node indices
+    ///
+    /// The result is checked for validity: every node must only refer to
+    /// nodes with lower indices, and nodes must not be duplicated.
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let ops = Vec::<SerializedOp>::deserialize(d)?;
+        let mut ctx = Context::new();
+        for (i, op) in ops.into_iter().enumerate() {
+            let op = Op::from(op);
+            if let Some(c) = op.iter_children().find(|c| c.get() >= i) {
+                return Err(D::Error::custom(format!(
+                    "node {i} refers to invalid child {}",
+                    c.get()
+                )));
+            }
+            if ctx.ops.insert(op).get() != i {
+                return Err(D::Error::custom(format!(
+                    "node {i} is a duplicate"
+                )));
+            }
+        }
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_context_round_trip() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+        let v = ctx.var(Var::new());
+        let a = ctx.add(x, 0.5).unwrap();
+        let b = ctx.atan2(a, y).unwrap();
+        let c = ctx.if_nonzero_else(z, b, v).unwrap();
+        let d = ctx.sqrt(c).unwrap();
+        let nan = ctx.constant(f64::NAN);
+        let roots = vec![d, b, nan];
+
+        let data = bincode::serialize(&(&ctx, &roots)).unwrap();
+        let (out, out_roots): (Context, Vec<Node>) =
+            bincode::deserialize(&data).unwrap();
+        assert_eq!(out.len(), ctx.len());
+        assert_eq!(out_roots, roots);
+        for n in ctx.ops.keys() {
+            assert_eq!(out.get_op(n), ctx.get_op(n));
+        }
+
+        // Nodes are still deduplicated in the new context
+        let mut out = out;
+        assert_eq!(out.atan2(a, y).unwrap(), b);
+        assert!(out.get_const(nan).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_context_invalid() {
+        let bad_child = vec![
+            SerializedOp::Input(Var::X),
+            SerializedOp::Unary(UnaryOpcode::Neg, Node::new(1)),
+        ];
+        let data = bincode::serialize(&bad_child).unwrap();
+        let err = bincode::deserialize::<Context>(&data).unwrap_err();
+        assert!(err.to_string().contains("invalid child"), "{err}");
+
+        let duplicate = vec![
+            SerializedOp::Input(Var::X),
+            SerializedOp::Const(1.0),
+            SerializedOp::Input(Var::X),
+        ];
+        let data = bincode::serialize(&duplicate).unwrap();
+        let err = bincode::deserialize::<Context>(&data).unwrap_err();
+        assert!(err.to_string().contains("duplicate"), "{err}");
+    }
+}
@@ -0,0 +1,317 @@
+//! Structural hashing and equality of trees
+use super::{BinaryOpcode, Tree, TreeOp, UnaryOpcode};
+use crate::var::Var;
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+/// Canonical operation, with children stored as indices into an [`Interner`]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+enum CanonOp {
+    Input(Var),
+    /// Constant, stored as bits (with a single canonical `NaN`)
+    Const(u64),
+    Unary(UnaryOpcode, usize),
+    Binary(BinaryOpcode, usize, usize),
+    Select(usize, usize, usize),
+}
+
+/// Hash-consing arena of canonical operations
+///
+/// Trees are interned with their `RemapAxes` operations applied, so two trees
+/// with the same meaning (but different `Arc` identities or remapping) are
+/// interned to the same index.
+#[derive(Default)]
+struct Interner {
+    ops: HashMap<CanonOp, usize>,
+
+    /// Structural hash of each interned operation, by index
+    hashes: Vec<u64>,
+}
+
+impl Interner {
+    fn insert(&mut self, op: CanonOp) -> usize {
+        if let Some(i) = self.ops.get(&op) {
+            return *i;
+        }
+        let mut h = DefaultHasher::new();
+        std::mem::discriminant(&op).hash(&mut h);
+        match op {
+            CanonOp::Input(v) => v.hash(&mut h),
+            CanonOp::Const(c) => c.hash(&mut h),
+            CanonOp::Unary(op, a) => (op, self.hashes[a]).hash(&mut h),
+            CanonOp::Binary(op, a, b) => {
+                (op, self.hashes[a], self.hashes[b]).hash(&mut h)
+            }
+            CanonOp::Select(c, a, b) => {
+                (self.hashes[c], self.hashes[a], self.hashes[b]).hash(&mut h)
+            }
+        }
+        let i = self.hashes.len();
+        self.hashes.push(h.finish());
+        self.ops.insert(op, i);
+        i
+    }
+
+    /// Interns a tree, applying `RemapAxes` operations lazily
+    ///
+    /// Each `(TreeOp, axes)` pair is only visited once, so this is linear in
+    /// the number of distinct remappings (rather than exponential in the depth
+    /// of nested remappings).
+    fn intern(&mut self, tree: &Tree) -> usize {
+        // Do recursion on the heap to avoid stack overflows for deep trees
+        enum Action<'a> {
+            /// Pushes `Up(op)` followed by `Down(c)` for each child
+            Down(&'a Arc<TreeOp>),
+            /// Consumes interned children from the stack and pushes a new index
+            Up(&'a Arc<TreeOp>),
+            /// Pops the latest axis frame, caching the remapped result
+            Pop(&'a Arc<TreeOp>),
+        }
+        let x = self.insert(CanonOp::Input(Var::X));
+        let y = self.insert(CanonOp::Input(Var::Y));
+        let z = self.insert(CanonOp::Input(Var::Z));
+        let mut axes = vec![[x, y, z]];
+        let mut todo = vec![Action::Down(tree.arc())];
+        let mut stack = vec![];
+
+        // Cache of (axes, TreeOp) -> index
+        let mut seen: HashMap<([usize; 3], *const TreeOp), usize> =
+            HashMap::new();
+
+        while let Some(t) = todo.pop() {
+            match t {
+                Action::Down(t) => {
+                    let frame = *axes.last().unwrap();
+                    if let Some(i) = seen.get(&(frame, Arc::as_ptr(t))) {
+                        stack.push(*i);
+                        continue;
+                    }
+                    match t.as_ref() {
+                        TreeOp::Const(c) => {
+                            let c = if c.is_nan() { f64::NAN } else { *c };
+                            stack
+                                .push(self.insert(CanonOp::Const(c.to_bits())));
+                        }
+                        TreeOp::Input(v) => stack.push(match *v {
+                            Var::X => frame[0],
+                            Var::Y => frame[1],
+                            Var::Z => frame[2],
+                            v @ Var::V(..) => self.insert(CanonOp::Input(v)),
+                        }),
+                        TreeOp::Unary(_op, arg) => {
+                            todo.push(Action::Up(t));
+                            todo.push(Action::Down(arg));
+                        }
+                        TreeOp::Binary(_op, lhs, rhs) => {
+                            todo.push(Action::Up(t));
+                            todo.push(Action::Down(lhs));
+                            todo.push(Action::Down(rhs));
+                        }
+                        TreeOp::Select(cond, a, b) => {
+                            todo.push(Action::Up(t));
+                            todo.push(Action::Down(cond));
+                            todo.push(Action::Down(a));
+                            todo.push(Action::Down(b));
+                        }
+                        TreeOp::RemapAxes { target: _, x, y, z } => {
+                            // Action::Up(t) does the remapping and target eval
+                            todo.push(Action::Up(t));
+                            todo.push(Action::Down(x));
+                            todo.push(Action::Down(y));
+                            todo.push(Action::Down(z));
+                        }
+                    }
+                }
+                Action::Up(t) => {
+                    let op = match t.as_ref() {
+                        TreeOp::Const(..) | TreeOp::Input(..) => unreachable!(),
+                        TreeOp::Unary(op, ..) => {
+                            let arg = stack.pop().unwrap();
+                            CanonOp::Unary(*op, arg)
+                        }
+                        TreeOp::Binary(op, ..) => {
+                            let lhs = stack.pop().unwrap();
+                            let rhs = stack.pop().unwrap();
+                            CanonOp::Binary(*op, lhs, rhs)
+                        }
+                        TreeOp::Select(..) => {
+                            let cond = stack.pop().unwrap();
+                            let a = stack.pop().unwrap();
+                            let b = stack.pop().unwrap();
+                            CanonOp::Select(cond, a, b)
+                        }
+                        TreeOp::RemapAxes { target, .. } => {
+                            let x = stack.pop().unwrap();
+                            let y = stack.pop().unwrap();
+                            let z = stack.pop().unwrap();
+                            axes.push([x, y, z]);
+                            todo.push(Action::Pop(t));
+                            todo.push(Action::Down(target));
+                            continue;
+                        }
+                    };
+                    let i = self.insert(op);
+                    seen.insert((*axes.last().unwrap(), Arc::as_ptr(t)), i);
+                    stack.push(i);
+                }
+                Action::Pop(t) => {
+                    axes.pop().unwrap();
+                    let frame = *axes.last().unwrap();
+                    seen.insert(
+                        (frame, Arc::as_ptr(t)),
+                        *stack.last().unwrap(),
+                    );
+                }
+            }
+        }
+        assert_eq!(stack.len(), 1);
+        stack.pop().unwrap()
+    }
+}
+
+impl Tree {
+    /// Computes a hash of the tree's structure
+    ///
+    /// Unlike [`PartialEq`] (which compares pointers), this depends only on the
+    /// tree's contents, with [`remap_xyz`](Tree::remap_xyz) operations applied.
+    /// Trees that are [structurally equal](Tree::structurally_eq) have the same
+    /// hash, even if they were built separately.
+    ///
+    /// Shared subtrees are only visited once per remapping, so this is
+    /// efficient even for heavily-shared or deeply-remapped trees.  The hash is
+    /// deterministic, but may change between versions of Fidget (or Rust), so
+    /// it should not be persisted.
+    ///
+    /// ```
+    /// # use fidget::context::Tree;
+    /// let a = Tree::x() + Tree::y().sin();
+    /// let b = Tree::y() + Tree::x().sin();
+    /// let c = b.remap_xyz(Tree::y(), Tree::x(), Tree::z());
+    /// assert_ne!(a, c); // different pointers
+    /// assert_eq!(a.structural_hash(), c.structural_hash());
+    /// assert_ne!(a.structural_hash(), b.structural_hash());
+    /// ```
+    pub fn structural_hash(&self) -> u64 {
+        let mut interner = Interner::default();
+        let i = interner.intern(self);
+        interner.hashes[i]
+    }
+
+    /// Checks whether two trees have the same structure
+    ///
+    /// Trees are compared with [`remap_xyz`](Tree::remap_xyz) operations
+    /// applied, ignoring `Arc` identity.  No algebraic simplification is
+    /// performed, so (for example) `x + y` and `y + x` are not structurally
+    /// equal.  All `NaN` constants are considered equal.
+    ///
+    /// ```
+    /// # use fidget::context::Tree;
+    /// let a = Tree::x().square() * 2.0;
+    /// let b = Tree::y().square().remap_xyz(Tree::z(), Tree::x(), Tree::y()) * 2.0;
+    /// assert!(a.structurally_eq(&b));
+    /// assert!(!a.structurally_eq(&(Tree::x().square() * 3.0)));
+    /// ```
+    pub fn structurally_eq(&self, other: &Tree) -> bool {
+        let mut interner = Interner::default();
+        interner.intern(self) == interner.intern(other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_structural_eq() {
+        let (x, y, z) = Tree::axes();
+        let a = (x.clone() + y.clone()).max(z.clone().sin());
+        let b = (Tree::x() + Tree::y()).max(Tree::z().sin());
+        assert_ne!(a, b);
+        assert!(a.structurally_eq(&b));
+        assert_eq!(a.structural_hash(), b.structural_hash());
+
+        // Operand order and constants matter
+        let c = (y.clone() + x.clone()).max(z.clone().sin());
+        assert!(!a.structurally_eq(&c));
+        assert_ne!(a.structural_hash(), c.structural_hash());
+        let d = x.clone() * 2.0;
+        assert!(!d.structurally_eq(&(x.clone() * 2.5)));
+        assert!(d.structurally_eq(&(x.clone() * 2)));
+        assert!(d.structurally_eq(&(x.clone() * 2.0f32)));
+        assert!(
+            (x.clone() + f64::NAN).structurally_eq(&(x.clone() + -f64::NAN))
+        );
+
+        // Variables are compared by identity
+        let v = Var::new();
+        let e = Tree::from(v) + 1.0;
+        assert!(e.structurally_eq(&(Tree::from(v) + 1.0)));
+        assert!(!e.structurally_eq(&(Tree::from(Var::new()) + 1.0)));
+
+        // Opcodes are distinguished
+        assert!(!x.sin().structurally_eq(&x.cos()));
+        assert!(!x.min(y.clone()).structurally_eq(&x.max(y.clone())));
+        let s1 = x.if_nonzero_else(y.clone(), z.clone());
+        let s2 = x.if_nonzero_else(z.clone(), y.clone());
+        assert!(!s1.structurally_eq(&s2));
+        assert!(s1.structurally_eq(&x.if_nonzero_else(y.clone(), z.clone())));
+    }
+
+    #[test]
+    fn test_structural_remap() {
+        let (x, y, z) = Tree::axes();
+        let t = x.clone() * 2.0 + y.clone().sin();
+
+        // Remapping is applied before comparison
+        let r = t.remap_xyz(z.clone(), x.clone() + 1.0, y.clone());
+        let expected = z.clone() * 2.0 + (x.clone() + 1.0).sin();
+        assert!(r.structurally_eq(&expected));
+        assert_eq!(r.structural_hash(), expected.structural_hash());
+        assert!(!r.structurally_eq(&t));
+
+        // Identity remapping is a no-op
+        let r = t.remap_xyz(x.clone(), y.clone(), z.clone());
+        assert!(r.structurally_eq(&t));
+
+        // Nested remapping
+        let r = t.remap_xyz(y.clone(), x.clone(), z.clone()).remap_xyz(
+            z.clone(),
+            x.clone(),
+            y.clone(),
+        );
+        let expected = x.clone() * 2.0 + z.clone().sin();
+        assert!(r.structurally_eq(&expected));
+    }
+
+    #[test]
+    fn test_structural_no_blowup() {
+        // Each level doubles the size of the flattened tree, so this would
+        // take 2^64 steps without caching
+        let mut t = Tree::x();
+        let mut flat = Tree::x();
+        for i in 0..64 {
+            let s = t.clone() + t.clone();
+            t = s.remap_xyz(Tree::x() + i as f64, Tree::y(), Tree::z());
+            let s = flat.clone() + flat.clone();
+            flat = s.remap_xyz(Tree::x() + i as f64, Tree::y(), Tree::z());
+        }
+        assert!(t.structurally_eq(&flat));
+        assert_eq!(t.structural_hash(), flat.structural_hash());
+
+        // Deep trees don't overflow the stack
+        let mut t = Tree::x();
+        for _ in 0..100_000 {
+            t = t.sin();
+        }
+        let mut u = Tree::x();
+        for _ in 0..100_000 {
+            u = u.sin();
+        }
+        assert!(t.structurally_eq(&u));
+        assert!(!t.structurally_eq(&u.sin()));
+    }
+}
@@ -0,0 +1,241 @@
+//! Variable substitution and partial evaluation
+use super::{Context, Node, Op};
+use crate::{var::Var, Error};
+
+use std::collections::HashMap;
+
+impl Context {
+    /// Replaces variables in the graph rooted at `root` with other nodes,
+    /// returning the new root.
+    ///
+    /// Every `Op::Input(v)` with an entry in `vars` is replaced by the
+    /// corresponding node; other variables are left unchanged.  Substitution
+    /// is not recursive, i.e. variables within the replacement nodes are not
+    /// themselves substituted.
+    ///
+    /// The graph is rebuilt with the usual builder functions, so constant
+    /// folding is applied to the result.  Unlike
+    /// [`Tree::remap_xyz`](super::Tree::remap_xyz), this works for any
+    /// [`Var`] and is applied immediately.
+    ///
+    /// ```
+    /// # use fidget::{context::Context, var::Var};
+    /// # use std::collections::HashMap;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let sum = ctx.add(x, 1.0).unwrap();
+    ///
+    /// let y2 = ctx.mul(y, 2.0).unwrap();
+    /// let out = ctx.substitute(sum, &HashMap::from([(Var::X, y2)])).unwrap();
+    /// assert_eq!(ctx.eval_xyz(out, 0.0, 3.0, 0.0).unwrap(), 7.0);
+    /// ```
+    pub fn substitute(
+        &mut self,
+        root: Node,
+        vars: &HashMap<Var, Node>,
+    ) -> Result<Node, Error> {
+        self.check_node(root)?;
+        for n in vars.values() {
+            self.check_node(*n)?;
+        }
+
+        // Do recursion on the heap to avoid stack overflows for deep trees
+        enum Action {
+            /// Pushes `Up(n)` followed by `Down(c)` for each child
+            Down(Node),
+            /// Consumes substituted children from the stack and pushes a node
+            Up(Node, Op),
+        }
+        let mut todo = vec![Action::Down(root)];
+        let mut stack = vec![];
+
+        // Cache of original -> substituted nodes
+        let mut seen: HashMap<Node, Node> = HashMap::new();
+
+        while let Some(t) = todo.pop() {
+            match t {
+                Action::Down(n) => {
+                    if let Some(p) = seen.get(&n) {
+                        stack.push(*p);
+                        continue;
+                    }
+                    let op = *self.get_op(n).unwrap();
+                    match op {
+                        Op::Const(..) => {
+                            seen.insert(n, n);
+                            stack.push(n);
+                        }
+                        Op::Input(v) => {
+                            let out = vars.get(&v).cloned().unwrap_or(n);
+                            seen.insert(n, out);
+                            stack.push(out);
+                        }
+                        Op::Unary(_op, arg) => {
+                            todo.push(Action::Up(n, op));
+                            todo.push(Action::Down(arg));
+                        }
+                        Op::Binary(_op, lhs, rhs) => {
+                            todo.push(Action::Up(n, op));
+                            todo.push(Action::Down(lhs));
+                            todo.push(Action::Down(rhs));
+                        }
+                        Op::Select(cond, a, b) => {
+                            todo.push(Action::Up(n, op));
+                            todo.push(Action::Down(cond));
+                            todo.push(Action::Down(a));
+                            todo.push(Action::Down(b));
+                        }
+                    }
+                }
+                Action::Up(n, op) => {
+                    // Unchanged subgraphs are reused as-is
+                    let out = match op {
+                        Op::Const(..) | Op::Input(..) => unreachable!(),
+                        Op::Unary(op, arg) => {
+                            let new_arg = stack.pop().unwrap();
+                            if new_arg == arg {
+                                n
+                            } else {
+                                self.op_unary(new_arg, op)?
+                            }
+                        }
+                        Op::Binary(op, lhs, rhs) => {
+                            let new_lhs = stack.pop().unwrap();
+                            let new_rhs = stack.pop().unwrap();
+                            if (new_lhs, new_rhs) == (lhs, rhs) {
+                                n
+                            } else {
+                                self.rebuild_binary(op, new_lhs, new_rhs)?
+                            }
+                        }
+                        Op::Select(cond, a, b) => {
+                            let new_cond = stack.pop().unwrap();
+                            let new_a = stack.pop().unwrap();
+                            let new_b = stack.pop().unwrap();
+                            if (new_cond, new_a, new_b) == (cond, a, b) {
+                                n
+                            } else {
+                                self.if_nonzero_else(new_cond, new_a, new_b)?
+                            }
+                        }
+                    };
+                    seen.insert(n, out);
+                    stack.push(out);
+                }
+            }
+        }
+        assert_eq!(stack.len(), 1);
+        Ok(stack.pop().unwrap())
+    }
+
+    /// Binds variables in the graph rooted at `root` to constant values,
+    /// returning the new (partially evaluated) root.
+    ///
+    /// Constants are propagated through the graph using the builder functions'
+    /// constant folding, so any subexpression which only depends on bound
+    /// variables is collapsed into a single constant.  This is useful for
+    /// freezing design parameters before evaluation.
+    ///
+    /// ```
+    /// # use fidget::{context::Context, var::Var};
+    /// # use std::collections::HashMap;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let r = Var::new();
+    /// let r_node = ctx.var(r);
+    /// let r2 = ctx.square(r_node).unwrap();
+    /// let out = ctx.sub(x, r2).unwrap();
+    /// assert!(ctx.eval_xyz(out, 5.0, 0.0, 0.0).is_err()); // `r` is unbound
+    ///
+    /// let bound = ctx.bind_constants(out, &HashMap::from([(r, 2.0)])).unwrap();
+    /// assert_eq!(ctx.eval_xyz(bound, 5.0, 0.0, 0.0).unwrap(), 1.0);
+    /// ```
+    pub fn bind_constants(
+        &mut self,
+        root: Node,
+        vars: &HashMap<Var, f64>,
+    ) -> Result<Node, Error> {
+        let vars = vars.iter().map(|(v, f)| (*v, self.constant(*f))).collect();
+        self.substitute(root, &vars)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::{indexed::Index, BinaryOpcode};
+
+    #[test]
+    fn test_substitute() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+        let a = ctx.sin(x).unwrap();
+        let b = ctx.mul(a, z).unwrap();
+        let c = ctx.if_nonzero_else(y, b, a).unwrap();
+
+        // Swapping X and Z
+        let vars = HashMap::from([(Var::X, z), (Var::Z, x)]);
+        let out = ctx.substitute(c, &vars).unwrap();
+        for (x, y, z) in [(0.5, 1.0, 2.0), (1.5, 0.0, -1.0)] {
+            assert_eq!(
+                ctx.eval_xyz(out, x, y, z).unwrap(),
+                ctx.eval_xyz(c, z, y, x).unwrap()
+            );
+        }
+
+        // Substituting an unused variable returns the original node
+        let v = Var::new();
+        let v_node = ctx.var(v);
+        let vars = HashMap::from([(v, v_node)]);
+        assert_eq!(ctx.substitute(c, &vars).unwrap(), c);
+        let vars = HashMap::from([(Var::Y, x)]);
+        assert_eq!(ctx.substitute(b, &vars).unwrap(), b);
+
+        // Substitution is not recursive
+        let vars = HashMap::from([(Var::X, a)]);
+        let out = ctx.substitute(a, &vars).unwrap();
+        let Op::Unary(_, inner) = *ctx.get_op(out).unwrap() else {
+            panic!("expected a unary node");
+        };
+        assert_eq!(inner, a);
+
+        // Invalid nodes are detected
+        let vars = HashMap::from([(Var::X, Node::new(1000))]);
+        assert!(ctx.substitute(c, &vars).is_err());
+    }
+
+    #[test]
+    fn test_bind_constants() {
+        let mut ctx = Context::new();
+        let [x, y, _z] = ctx.axes();
+        let a = ctx.sin(x).unwrap();
+        let b = ctx.add(a, y).unwrap();
+        let c = ctx.mul(b, y).unwrap();
+
+        // Binding everything produces a single constant
+        let vars = HashMap::from([(Var::X, 1.0), (Var::Y, 2.0)]);
+        let out = ctx.bind_constants(c, &vars).unwrap();
+        assert_eq!(ctx.get_const(out).unwrap(), (1f64.sin() + 2.0) * 2.0);
+
+        // Binding X folds the sine, leaving (k + y) * y
+        let vars = HashMap::from([(Var::X, 1.0)]);
+        let out = ctx.bind_constants(c, &vars).unwrap();
+        let Op::Binary(BinaryOpcode::Mul, lhs, rhs) = *ctx.get_op(out).unwrap()
+        else {
+            panic!("expected a multiplication");
+        };
+        let sum = if lhs == y { rhs } else { lhs };
+        let Op::Binary(BinaryOpcode::Add, lhs, rhs) = *ctx.get_op(sum).unwrap()
+        else {
+            panic!("expected an addition");
+        };
+        let k = ctx.get_const(lhs).or(ctx.get_const(rhs)).unwrap();
+        assert_eq!(k, 1f64.sin());
+
+        // Binding to zero triggers the builders' simplification rules
+        let vars = HashMap::from([(Var::Y, 0.0)]);
+        let out = ctx.bind_constants(c, &vars).unwrap();
+        assert_eq!(ctx.get_const(out).unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,603 @@
+//! Flat text format for math graphs
+use super::{
+    infix::{
+        binary_from_name, binary_name, format_const, format_var,
+        unary_from_name, unary_name,
+    },
+    Context, Node, Op,
+};
+use crate::{
+    var::{Var, VarIndex},
+    Error,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Contents of a text file, returned by [`Context::parse_text`]
+#[derive(Debug)]
+pub struct TextFile {
+    /// Context containing every node in the file
+    pub ctx: Context,
+    /// Output nodes, in the order in which they were declared
+    pub outputs: Vec<Node>,
+    /// Named variables (other than `x`, `y`, and `z`) used in the file
+    pub vars: HashMap<String, Var>,
+}
+
+impl Context {
+    /// Parses a flat text representation of a math tree. For example, the
+    /// circle `(- (+ (square x) (square y)) 1)` can be parsed from
+    /// ```
+    /// # use fidget::context::Context;
+    /// let txt = "
+    /// ## This is a comment!
+    /// 0x600000b90000 var-x
+    /// 0x600000b900a0 square 0x600000b90000
+    /// 0x600000b90050 var-y
+    /// 0x600000b900f0 square 0x600000b90050
+    /// 0x600000b90140 add 0x600000b900a0 0x600000b900f0
+    /// 0x600000b90190 sqrt 0x600000b90140
+    /// 0x600000b901e0 const 1
+    /// ";
+    /// let (ctx, _node) = Context::from_text(&mut txt.as_bytes()).unwrap();
+    /// assert_eq!(ctx.len(), 7);
+    /// ```
+    ///
+    /// See [`Context::parse_text`] for details on the format.  This function
+    /// expects a single output, returning [`Error::MultipleOutputs`] if the
+    /// file declares more than one.
+    pub fn from_text<R: Read>(r: R) -> Result<(Self, Node), Error> {
+        let f = Self::parse_text(r)?;
+        match f.outputs.as_slice() {
+            [n] => Ok((f.ctx, *n)),
+            n => Err(Error::MultipleOutputs(n.len())),
+        }
+    }
+
+    /// Parses a flat text representation of a math graph with any number of
+    /// outputs
+    ///
+    /// Each line has the form `<name> <opcode> [<arg>...]`, where the name
+    /// is an arbitrary token without whitespace, and arguments refer to names
+    /// from previous lines.  Valid opcodes are
+    ///
+    /// - `const <value>`, for a constant value
+    /// - `var-x`, `var-y`, and `var-z`, for the three axes
+    /// - `var-<name>` for any other variable.  A name of the form `v_<n>`
+    ///   refers to a specific [`Var`] (matching [`Context::to_infix`]); any
+    ///   other name is assigned a new variable, which is returned in
+    ///   [`TextFile::vars`].
+    /// - The lowercase names of unary and binary opcodes (e.g. `neg`,
+    ///   `square`, `add`, `atan2`), with one or two arguments respectively
+    /// - `select <cond> <a> <b>`, for
+    ///   [`if_nonzero_else`](Context::if_nonzero_else)
+    /// - `output <arg>`, which declares its argument as an output
+    ///
+    /// If there are no `output` lines, then the last node in the file is the
+    /// only output.  Blank lines are ignored, and `#` begins a comment which
+    /// runs until the end of the line.
+    ///
+    /// ```
+    /// # use fidget::{context::Context, var::Var};
+    /// # use std::collections::HashMap;
+    /// let txt = "
+    /// _0 var-x
+    /// _1 var-radius
+    /// _2 sub _0 _1
+    /// _3 neg _2
+    /// out0 output _2 # the first output
+    /// out1 output _3
+    /// ";
+    /// let f = Context::parse_text(txt.as_bytes()).unwrap();
+    /// assert_eq!(f.outputs.len(), 2);
+    ///
+    /// let vars = HashMap::from([(Var::X, 3.0), (f.vars["radius"], 1.0)]);
+    /// assert_eq!(f.ctx.eval(f.outputs[0], &vars).unwrap(), 2.0);
+    /// assert_eq!(f.ctx.eval(f.outputs[1], &vars).unwrap(), -2.0);
+    /// ```
+    ///
+    /// Malformed files are reported as [`Error::ParseError`], with the line
+    /// and column of the offending token.
+    pub fn parse_text<R: Read>(r: R) -> Result<TextFile, Error> {
+        let reader = BufReader::new(r);
+        let mut ctx = Self::new();
+        let mut seen: HashMap<String, Node> = HashMap::new();
+        let mut vars: HashMap<String, Var> = HashMap::new();
+        let mut outputs = vec![];
+        let mut last = None;
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let err = |column, message| Error::ParseError {
+                line: i + 1,
+                column,
+                message,
+            };
+
+            let mut tokens = split_tokens(&line);
+            if let Some(c) = tokens.iter().position(|t| t.1.starts_with('#')) {
+                tokens.truncate(c);
+            }
+            let Some(&(name_col, name)) = tokens.first() else {
+                continue;
+            };
+            let Some(&(op_col, opcode)) = tokens.get(1) else {
+                return Err(err(
+                    line.chars().count() + 1,
+                    format!("expected opcode after `{name}`"),
+                ));
+            };
+            if seen.contains_key(name) {
+                return Err(err(name_col, format!("duplicate name `{name}`")));
+            }
+
+            let unary = unary_from_name(opcode);
+            let binary = binary_from_name(opcode);
+            let arity = match opcode {
+                "const" | "output" => 1,
+                "select" => 3,
+                _ if opcode.starts_with("var-") => 0,
+                _ if unary.is_some() => 1,
+                _ if binary.is_some() => 2,
+                _ => {
+                    return Err(err(
+                        op_col,
+                        format!("unknown opcode `{opcode}`"),
+                    ))
+                }
+            };
+            let args = &tokens[2..];
+            if args.len() != arity {
+                return Err(err(
+                    op_col,
+                    format!(
+                        "`{opcode}` expects {arity} argument{}, found {}",
+                        if arity == 1 { "" } else { "s" },
+                        args.len()
+                    ),
+                ));
+            }
+
+            let node = if opcode == "const" {
+                let (col, v) = args[0];
+                let v = v
+                    .parse()
+                    .map_err(|_| err(col, format!("invalid constant `{v}`")))?;
+                ctx.constant(v)
+            } else if let Some(var_name) = opcode.strip_prefix("var-") {
+                let v = match var_name {
+                    "" => {
+                        return Err(err(op_col, "empty variable name".into()))
+                    }
+                    "x" => Var::X,
+                    "y" => Var::Y,
+                    "z" => Var::Z,
+                    _ => {
+                        *vars.entry(var_name.to_owned()).or_insert_with(|| {
+                            var_name
+                                .strip_prefix("v_")
+                                .and_then(|i| i.parse().ok())
+                                .map(|i| Var::V(VarIndex::from_raw(i)))
+                                .unwrap_or_else(Var::new)
+                        })
+                    }
+                };
+                ctx.var(v)
+            } else {
+                let mut nodes = Vec::with_capacity(args.len());
+                for &(col, a) in args {
+                    let n = seen.get(a).ok_or_else(|| {
+                        err(col, format!("unknown node `{a}`"))
+                    })?;
+                    nodes.push(*n);
+                }
+                match (nodes.as_slice(), unary, binary) {
+                    ([a], Some(op), _) => ctx.op_unary(*a, op)?,
+                    ([a, b], _, Some(op)) => ctx.rebuild_binary(op, *a, *b)?,
+                    ([c, a, b], ..) => ctx.if_nonzero_else(*c, *a, *b)?,
+                    ([a], None, None) => {
+                        outputs.push(*a);
+                        *a
+                    }
+                    _ => unreachable!(),
+                }
+            };
+            seen.insert(name.to_owned(), node);
+            last = Some(node);
+        }
+        if outputs.is_empty() {
+            outputs.push(last.ok_or(Error::EmptyFile)?);
+        }
+        Ok(TextFile { ctx, outputs, vars })
+    }
+
+    /// Writes the graphs rooted at `roots` in the flat text format
+    ///
+    /// Nodes are written in topological order and renamed to `_0`, `_1`,
+    /// etc, so the output only depends on the graph (and not on other nodes in
+    /// the context).  Variables other than the three axes are written as
+    /// `var-v_<n>`, so they are parsed back to the same [`Var`]; use
+    /// [`Context::to_text_with_vars`] to write them with names instead.
+    ///
+    /// If there is more than one root, they are listed at the end of the file
+    /// with `output` declarations; otherwise, the single root is the last line
+    /// of the file.  Returns [`Error::NoOutputs`] if `roots` is empty, because
+    /// the resulting file couldn't be parsed.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let sum = ctx.add(x, y).unwrap();
+    /// let out = ctx.sqrt(sum).unwrap();
+    ///
+    /// let mut txt = vec![];
+    /// ctx.to_text(&mut txt, &[out]).unwrap();
+    /// assert_eq!(
+    ///     std::str::from_utf8(&txt).unwrap(),
+    ///     "_0 var-x\n_1 var-y\n_2 add _0 _1\n_3 sqrt _2\n"
+    /// );
+    ///
+    /// let (new_ctx, new_out) = Context::from_text(txt.as_slice()).unwrap();
+    /// assert_eq!(new_ctx.eval_xyz(new_out, 3.0, 6.0, 0.0).unwrap(), 3.0);
+    /// ```
+    pub fn to_text(
+        &self,
+        w: &mut impl Write,
+        roots: &[Node],
+    ) -> Result<(), Error> {
+        self.to_text_with_vars(w, roots, &HashMap::new())
+    }
+
+    /// Writes the graphs rooted at `roots` in the flat text format, using the
+    /// given names for variables
+    ///
+    /// `vars` has the same form as [`TextFile::vars`], so a parsed file can be
+    /// written back with its original names.  Variables which aren't in `vars`
+    /// are written as `var-v_<n>`, and the three axes are always written as
+    /// `var-x`, `var-y`, and `var-z`.  If a variable has more than one name,
+    /// the first name (in sorted order) is used.
+    ///
+    /// Returns [`Error::BadVarName`] if a name would not be parsed back to the
+    /// same variable: for example, if it's empty, contains whitespace or `#`,
+    /// or is one of the axis names.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let f = Context::parse_text("_0 var-radius\n_1 neg _0".as_bytes())?;
+    /// let mut txt = vec![];
+    /// f.ctx.to_text_with_vars(&mut txt, &f.outputs, &f.vars)?;
+    /// assert_eq!(
+    ///     std::str::from_utf8(&txt).unwrap(),
+    ///     "_0 var-radius\n_1 neg _0\n"
+    /// );
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn to_text_with_vars(
+        &self,
+        w: &mut impl Write,
+        roots: &[Node],
+        vars: &HashMap<String, Var>,
+    ) -> Result<(), Error> {
+        if roots.is_empty() {
+            return Err(Error::NoOutputs);
+        }
+        for r in roots {
+            self.check_node(*r)?;
+        }
+
+        let mut var_names: HashMap<Var, &str> = HashMap::new();
+        for (name, v) in vars {
+            let valid = !name.is_empty()
+                && !name.contains(|c: char| c.is_whitespace() || c == '#')
+                && !matches!(name.as_str(), "x" | "y" | "z")
+                && name
+                    .strip_prefix("v_")
+                    .and_then(|i| i.parse().ok())
+                    .map(|i| *v == Var::V(VarIndex::from_raw(i)))
+                    .unwrap_or(true);
+            if !valid {
+                return Err(Error::BadVarName(name.clone()));
+            }
+            if !matches!(v, Var::V(..)) {
+                continue;
+            }
+            let e = var_names.entry(*v).or_insert(name);
+            if name.as_str() < *e {
+                *e = name;
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut todo = roots.to_vec();
+        while let Some(n) = todo.pop() {
+            if seen.insert(n) {
+                todo.extend(self.get_op(n).unwrap().iter_children());
+            }
+        }
+
+        // Children always have lower indices than their parents, so sorting
+        // by index gives us a topological ordering.
+        let mut nodes: Vec<Node> = seen.into_iter().collect();
+        nodes.sort();
+
+        let mut names = HashMap::new();
+        for (i, n) in nodes.into_iter().enumerate() {
+            write!(w, "_{i} ")?;
+            match *self.get_op(n).unwrap() {
+                Op::Const(c) => writeln!(w, "const {}", format_const(c.0))?,
+                Op::Input(v) => match var_names.get(&v) {
+                    Some(name) => writeln!(w, "var-{name}")?,
+                    None => writeln!(w, "var-{}", format_var(v))?,
+                },
+                Op::Unary(op, a) => {
+                    writeln!(w, "{} _{}", unary_name(op), names[&a])?
+                }
+                Op::Binary(op, a, b) => writeln!(
+                    w,
+                    "{} _{} _{}",
+                    binary_name(op),
+                    names[&a],
+                    names[&b]
+                )?,
+                Op::Select(c, a, b) => writeln!(
+                    w,
+                    "select _{} _{} _{}",
+                    names[&c], names[&a], names[&b]
+                )?,
+            }
+            names.insert(n, i);
+        }
+        if roots.len() != 1 {
+            for (i, r) in roots.iter().enumerate() {
+                writeln!(w, "out{i} output _{}", names[r])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a line on whitespace, returning tokens and their 1-indexed columns
+fn split_tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut out = vec![];
+    let mut start = None;
+    for (col, (i, c)) in line.char_indices().enumerate() {
+        if c.is_whitespace() {
+            if let Some((j, k)) = start.take() {
+                out.push((k + 1, &line[j..i]));
+            }
+        } else if start.is_none() {
+            start = Some((i, col));
+        }
+    }
+    if let Some((j, k)) = start {
+        out.push((k + 1, &line[j..]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_text_models() {
+        for (name, txt) in [
+            ("bear", include_str!("../../../../models/bear.vm")),
+            ("colonnade", include_str!("../../../../models/colonnade.vm")),
+            ("hi", include_str!("../../../../models/hi.vm")),
+            ("prospero", include_str!("../../../../models/prospero.vm")),
+            ("quarter", include_str!("../../../../models/quarter.vm")),
+            (
+                "tanglecube",
+                include_str!("../../../../models/tanglecube.vm"),
+            ),
+        ] {
+            let f = Context::parse_text(txt.as_bytes())
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(f.outputs.len(), 1);
+            assert!(f.vars.is_empty());
+            assert!(Context::from_text(txt.as_bytes()).is_ok());
+        }
+    }
+
+    /// Writes the given roots, then checks that parsing the text evaluates
+    /// identically and writes the same text back
+    fn round_trip(ctx: &Context, roots: &[Node], vars: &[Var]) -> String {
+        let mut txt = vec![];
+        ctx.to_text(&mut txt, roots).unwrap();
+        let f = Context::parse_text(txt.as_slice()).unwrap();
+        assert_eq!(f.outputs.len(), roots.len());
+
+        let mut again = vec![];
+        f.ctx.to_text(&mut again, &f.outputs).unwrap();
+        assert_eq!(txt, again);
+
+        for i in 0..16 {
+            let v = |j: usize| ((i * 7 + j * 3) % 16) as f64 / 4.0 - 2.0;
+            let values: HashMap<Var, f64> = [Var::X, Var::Y, Var::Z]
+                .iter()
+                .chain(vars)
+                .enumerate()
+                .map(|(j, var)| (*var, v(j)))
+                .collect();
+            for (a, b) in roots.iter().zip(&f.outputs) {
+                let a = ctx.eval(*a, &values).unwrap();
+                let b = f.ctx.eval(*b, &values).unwrap();
+                assert!(a == b || (a.is_nan() && b.is_nan()), "{a} != {b}");
+            }
+        }
+        String::from_utf8(txt).unwrap()
+    }
+
+    #[test]
+    fn test_to_text_models() {
+        for txt in [
+            include_str!("../../../../models/hi.vm"),
+            include_str!("../../../../models/quarter.vm"),
+            include_str!("../../../../models/tanglecube.vm"),
+            include_str!("../../../../models/colonnade.vm"),
+        ] {
+            let (ctx, root) = Context::from_text(txt.as_bytes()).unwrap();
+            round_trip(&ctx, &[root], &[]);
+        }
+    }
+
+    #[test]
+    fn test_to_text_multi() {
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+        let v = Var::new();
+        let v_node = ctx.var(v);
+        let _unused = ctx.sin(z).unwrap();
+        let a = ctx.mul(x, v_node).unwrap();
+        let b = ctx.add(a, 1e-9).unwrap();
+        let c = ctx.if_nonzero_else(y, b, f64::INFINITY).unwrap();
+        let d = ctx.atan2(c, -0.5).unwrap();
+
+        let txt = round_trip(&ctx, &[d, a, c, a], &[v]);
+        // Unrelated nodes in the context are not written
+        assert!(!txt.contains("sin"));
+        assert!(!txt.contains("var-z"));
+        assert!(txt.contains("const 1e-9\n"));
+        assert!(txt.contains("const inf\n"));
+        assert!(txt.ends_with(
+            "out0 output _9\nout1 output _3\nout2 output _7\nout3 output _3\n"
+        ));
+
+        // Named variables are preserved
+        let f = Context::parse_text(txt.as_bytes()).unwrap();
+        let Var::V(i) = v else { unreachable!() };
+        assert_eq!(f.vars[&format!("v_{}", i.get())], v);
+    }
+
+    #[test]
+    fn test_to_text_named_vars() {
+        let txt = "_0 var-x\n_1 var-radius\n_2 sub _0 _1\n";
+        let f = Context::parse_text(txt.as_bytes()).unwrap();
+        let mut out = vec![];
+        f.ctx
+            .to_text_with_vars(&mut out, &f.outputs, &f.vars)
+            .unwrap();
+        assert_eq!(std::str::from_utf8(&out).unwrap(), txt);
+
+        // Parsing the output again assigns the name to a new variable, which
+        // is written back with the same name
+        let g = Context::parse_text(out.as_slice()).unwrap();
+        let mut again = vec![];
+        g.ctx
+            .to_text_with_vars(&mut again, &g.outputs, &g.vars)
+            .unwrap();
+        assert_eq!(out, again);
+        let vars = HashMap::from([(Var::X, 3.0), (g.vars["radius"], 1.0)]);
+        assert_eq!(g.ctx.eval(g.outputs[0], &vars).unwrap(), 2.0);
+
+        // Without names, the variable is written by index
+        let mut anon = vec![];
+        f.ctx.to_text(&mut anon, &f.outputs).unwrap();
+        assert!(!std::str::from_utf8(&anon).unwrap().contains("radius"));
+
+        for bad in ["", "x", "two words", "a#b", "v_0"] {
+            let vars = HashMap::from([(bad.to_owned(), f.vars["radius"])]);
+            assert!(
+                matches!(
+                    f.ctx.to_text_with_vars(&mut vec![], &f.outputs, &vars),
+                    Err(Error::BadVarName(s)) if s == bad
+                ),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_text_empty() {
+        let ctx = Context::new();
+        assert!(matches!(
+            ctx.to_text(&mut vec![], &[]),
+            Err(Error::NoOutputs)
+        ));
+    }
+
+    #[test]
+    fn test_parse_text_vars() {
+        let txt = "
+            _0 var-a
+            _1 var-b
+            _2 var-a
+            _3 var-v_17
+            _4 add _0 _1
+            _5 mul _4 _2
+            _6 sub _5 _3
+        ";
+        let f = Context::parse_text(txt.as_bytes()).unwrap();
+        assert_eq!(f.vars.len(), 3);
+        assert_ne!(f.vars["a"], f.vars["b"]);
+        assert_eq!(f.vars["v_17"], Var::V(VarIndex::from_raw(17)));
+        let vars = HashMap::from([
+            (f.vars["a"], 2.0),
+            (f.vars["b"], 3.0),
+            (f.vars["v_17"], 1.0),
+        ]);
+        assert_eq!(f.ctx.eval(f.outputs[0], &vars).unwrap(), 9.0);
+    }
+
+    #[test]
+    fn test_parse_text_outputs() {
+        let txt = "
+            _0 var-x
+            _1 var-y
+            a output _1
+            _2 select _0 a _1 # trailing comment
+            _3 recip _2
+            b output _3
+            c output _0
+        ";
+        let f = Context::parse_text(txt.as_bytes()).unwrap();
+        assert_eq!(f.outputs.len(), 3);
+        assert_eq!(f.ctx.get_var(f.outputs[0]).unwrap(), Var::Y);
+        assert_eq!(f.ctx.eval_xyz(f.outputs[1], 1.0, 4.0, 0.0).unwrap(), 0.25);
+        assert_eq!(f.ctx.get_var(f.outputs[2]).unwrap(), Var::X);
+        assert!(matches!(
+            Context::from_text(txt.as_bytes()),
+            Err(Error::MultipleOutputs(3))
+        ));
+    }
+
+    #[test]
+    fn test_parse_text_errors() {
+        let check = |s: &str, line: usize, column: usize, msg: &str| {
+            match Context::parse_text(s.as_bytes()) {
+                Err(Error::ParseError {
+                    line: l,
+                    column: c,
+                    message,
+                }) => {
+                    assert_eq!((l, c), (line, column), "{s}: {message}");
+                    assert!(message.contains(msg), "{s}: {message}");
+                }
+                Err(e) => panic!("{s}: unexpected error {e:?}"),
+                Ok(..) => panic!("{s}: expected an error"),
+            }
+        };
+        check("_0 var-x\n_1", 2, 3, "expected opcode after `_1`");
+        check("_0 var-x\n  _1 foo _0", 2, 6, "unknown opcode `foo`");
+        check("_0 var-x\n_1 add _0", 2, 4, "expects 2 arguments, found 1");
+        check("_0 var-x _0", 1, 4, "expects 0 arguments, found 1");
+        check("_0 var-x\n_1 neg _2", 2, 8, "unknown node `_2`");
+        check("_0 const 1.0.0", 1, 10, "invalid constant `1.0.0`");
+        check("_0 var-", 1, 4, "empty variable name");
+        check("_0 var-x\n\n_0 var-y", 3, 1, "duplicate name `_0`");
+        check("_0 var-x\nout output", 2, 5, "expects 1 argument, found 0");
+
+        assert!(matches!(
+            Context::parse_text("# nothing here\n\n".as_bytes()),
+            Err(Error::EmptyFile)
+        ));
+        assert!(matches!(
+            Context::parse_text(&b"_0 var-x\n_1 \xff"[..]),
+            Err(Error::IoError(..))
+        ));
+    }
+}
@@ -1,7 +1,8 @@
 //! Context-free math trees
 use super::op::{BinaryOpcode, UnaryOpcode};
-use crate::{var::Var, Error};
-use std::sync::Arc;
+use crate::{types::Interval, var::Var, Error};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashMap, sync::Arc};
 
 /// Opcode type for trees
 ///
@@ -15,6 +16,9 @@ pub enum TreeOp {
     Const(f64),
     Binary(BinaryOpcode, Arc<TreeOp>, Arc<TreeOp>),
     Unary(UnaryOpcode, Arc<TreeOp>),
+    /// Ternary selection, returning the second tree if the first is non-zero
+    /// and the third tree otherwise
+    Select(Arc<TreeOp>, Arc<TreeOp>, Arc<TreeOp>),
     /// Lazy remapping of trees
     ///
     /// When imported into a `Context`, all `x/y/z` clauses within `target` will
@@ -60,6 +64,11 @@ impl TreeOp {
                 matches!(**lhs, TreeOp::Const(..))
                     && matches!(**rhs, TreeOp::Const(..))
             }
+            TreeOp::Select(cond, a, b) => {
+                matches!(**cond, TreeOp::Const(..))
+                    && matches!(**a, TreeOp::Const(..))
+                    && matches!(**b, TreeOp::Const(..))
+            }
             TreeOp::RemapAxes { target, x, y, z } => {
                 matches!(**target, TreeOp::Const(..))
                     && matches!(**x, TreeOp::Const(..))
@@ -74,6 +83,7 @@ impl TreeOp {
             TreeOp::Const(..) | TreeOp::Input(..) => [None, None, None, None],
             TreeOp::Unary(_op, arg) => [Some(arg), None, None, None],
             TreeOp::Binary(_op, lhs, rhs) => [Some(lhs), Some(rhs), None, None],
+            TreeOp::Select(cond, a, b) => [Some(cond), Some(a), Some(b), None],
             TreeOp::RemapAxes { target, x, y, z } => {
                 [Some(target), Some(x), Some(y), Some(z)]
             }
@@ -135,6 +145,19 @@ impl PartialEq for Tree {
 }
 impl Eq for Tree {}
 
+impl std::fmt::Display for Tree {
+    /// Prints the tree as an infix expression
+    ///
+    /// See [`Context::to_infix`](crate::context::Context::to_infix) for
+    /// details on the syntax.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut ctx = super::Context::new();
+        let node = ctx.import(self);
+        let s = ctx.to_infix(node).map_err(|_| std::fmt::Error)?;
+        write!(f, "{s}")
+    }
+}
+
 impl Tree {
     /// Returns an `(x, y, z)` tuple
     pub fn axes() -> (Self, Self, Self) {
@@ -177,10 +200,32 @@ impl Tree {
 
     /// Performs symbolic differentiation with respect to the given variable
     pub fn deriv(&self, v: Var) -> Tree {
-        let mut ctx = crate::Context::new();
+        let mut ctx = super::Context::new();
         let node = ctx.import(self);
         ctx.deriv(node, v).and_then(|d| ctx.export(d)).unwrap()
     }
+
+    /// Rebuilds chains of `min`, `max`, `add`, and `mul` as balanced trees
+    ///
+    /// See [`Context::balance`](crate::Context::balance) for details.
+    pub fn balance(&self) -> Tree {
+        let mut ctx = super::Context::new();
+        let node = ctx.import(self);
+        ctx.balance(node).and_then(|b| ctx.export(b)).unwrap()
+    }
+
+    /// Rebuilds chains as balanced trees, grouping `min` and `max` children
+    /// by spatial locality within the given region
+    ///
+    /// See [`Context::balance_spatial`](crate::Context::balance_spatial) for
+    /// details.
+    pub fn balance_spatial(&self, region: [Interval; 3]) -> Tree {
+        let mut ctx = super::Context::new();
+        let node = ctx.import(self);
+        ctx.balance_spatial(node, region)
+            .and_then(|b| ctx.export(b))
+            .unwrap()
+    }
 }
 
 impl TryFrom<Tree> for Var {
@@ -247,6 +292,20 @@ impl Tree {
     pub fn atan2<T: Into<Tree>>(&self, other: T) -> Self {
         Self::op_binary(self.clone(), other.into(), BinaryOpcode::Atan)
     }
+    pub fn if_nonzero_else<A: Into<Tree>, B: Into<Tree>>(
+        &self,
+        a: A,
+        b: B,
+    ) -> Self {
+        Tree(Arc::new(TreeOp::Select(
+            self.0.clone(),
+            a.into().0,
+            b.into().0,
+        )))
+    }
+    pub fn pow<T: Into<Tree>>(&self, other: T) -> Self {
+        Self::op_binary(self.clone(), other.into(), BinaryOpcode::Pow)
+    }
     pub fn neg(&self) -> Self {
         Self::op_unary(self.clone(), UnaryOpcode::Neg)
     }
@@ -280,6 +339,24 @@ impl Tree {
     pub fn abs(&self) -> Self {
         Self::op_unary(self.clone(), UnaryOpcode::Abs)
     }
+    pub fn sinh(&self) -> Self {
+        Self::op_unary(self.clone(), UnaryOpcode::Sinh)
+    }
+    pub fn cosh(&self) -> Self {
+        Self::op_unary(self.clone(), UnaryOpcode::Cosh)
+    }
+    pub fn tanh(&self) -> Self {
+        Self::op_unary(self.clone(), UnaryOpcode::Tanh)
+    }
+    pub fn sign(&self) -> Self {
+        Self::op_unary(self.clone(), UnaryOpcode::Sign)
+    }
+    pub fn cbrt(&self) -> Self {
+        Self::op_unary(self.clone(), UnaryOpcode::Cbrt)
+    }
+    pub fn log2(&self) -> Self {
+        Self::op_unary(self.clone(), UnaryOpcode::Log2)
+    }
 }
 
 macro_rules! impl_binary {
@@ -318,11 +395,166 @@ impl_binary!(Sub, SubAssign, sub, sub_assign);
 impl_binary!(Mul, MulAssign, mul, mul_assign);
 impl_binary!(Div, DivAssign, div, div_assign);
 
+/// Serialized form of a [`TreeOp`], which refers to children by index
+#[derive(Serialize, Deserialize)]
+enum SerializedTreeOp {
+    Input(Var),
+    Const(f64),
+    Binary(BinaryOpcode, usize, usize),
+    Unary(UnaryOpcode, usize),
+    Select(usize, usize, usize),
+    RemapAxes {
+        target: usize,
+        x: usize,
+        y: usize,
+        z: usize,
+    },
+}
+
+impl Serialize for Tree {
+    /// Serializes the tree as a flat list of operations
+    ///
+    /// Shared subtrees (i.e. multiple references to the same `Arc<TreeOp>`)
+    /// are only stored once, so the serialized size is proportional to the
+    /// number of unique subtrees.
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut out = vec![];
+        let mut seen: HashMap<*const TreeOp, usize> = HashMap::new();
+
+        // Do recursion on the heap to avoid stack overflows for deep trees.
+        // Each item is pushed twice: first to push its children, then (once
+        // the children have been serialized) to serialize the item itself.
+        let mut todo = vec![(self.arc(), false)];
+        while let Some((t, ready)) = todo.pop() {
+            let ptr = Arc::as_ptr(t);
+            if seen.contains_key(&ptr) {
+                continue;
+            }
+            let children: &[&Arc<TreeOp>] = match &**t {
+                TreeOp::Const(..) | TreeOp::Input(..) => &[],
+                TreeOp::Unary(_op, arg) => &[arg],
+                TreeOp::Binary(_op, lhs, rhs) => &[lhs, rhs],
+                TreeOp::Select(cond, a, b) => &[cond, a, b],
+                TreeOp::RemapAxes { target, x, y, z } => &[target, x, y, z],
+            };
+            if !ready {
+                todo.push((t, true));
+                todo.extend(children.iter().rev().map(|c| (*c, false)));
+                continue;
+            }
+            let i = |c: &Arc<TreeOp>| seen[&Arc::as_ptr(c)];
+            out.push(match &**t {
+                TreeOp::Input(v) => SerializedTreeOp::Input(*v),
+                TreeOp::Const(c) => SerializedTreeOp::Const(*c),
+                TreeOp::Unary(op, arg) => SerializedTreeOp::Unary(*op, i(arg)),
+                TreeOp::Binary(op, lhs, rhs) => {
+                    SerializedTreeOp::Binary(*op, i(lhs), i(rhs))
+                }
+                TreeOp::Select(cond, a, b) => {
+                    SerializedTreeOp::Select(i(cond), i(a), i(b))
+                }
+                TreeOp::RemapAxes { target, x, y, z } => {
+                    SerializedTreeOp::RemapAxes {
+                        target: i(target),
+                        x: i(x),
+                        y: i(y),
+                        z: i(z),
+                    }
+                }
+            });
+            seen.insert(ptr, out.len() - 1);
+        }
+        out.serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tree {
+    /// Deserializes a tree, restoring shared subtrees
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let ops = Vec::<SerializedTreeOp>::deserialize(d)?;
+        let mut built: Vec<Arc<TreeOp>> = Vec::with_capacity(ops.len());
+        for (i, op) in ops.into_iter().enumerate() {
+            let get = |j: usize| {
+                built.get(j).cloned().ok_or_else(|| {
+                    D::Error::custom(format!(
+                        "item {i} refers to invalid child {j}"
+                    ))
+                })
+            };
+            let t = match op {
+                SerializedTreeOp::Input(v) => TreeOp::Input(v),
+                SerializedTreeOp::Const(c) => TreeOp::Const(c),
+                SerializedTreeOp::Unary(op, arg) => {
+                    TreeOp::Unary(op, get(arg)?)
+                }
+                SerializedTreeOp::Binary(op, lhs, rhs) => {
+                    TreeOp::Binary(op, get(lhs)?, get(rhs)?)
+                }
+                SerializedTreeOp::Select(cond, a, b) => {
+                    TreeOp::Select(get(cond)?, get(a)?, get(b)?)
+                }
+                SerializedTreeOp::RemapAxes { target, x, y, z } => {
+                    TreeOp::RemapAxes {
+                        target: get(target)?,
+                        x: get(x)?,
+                        y: get(y)?,
+                        z: get(z)?,
+                    }
+                }
+            };
+            built.push(Arc::new(t));
+        }
+        let root = built.pop().ok_or_else(|| D::Error::custom("empty tree"))?;
+        Ok(Tree(root))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::Context;
 
+    #[test]
+    fn test_tree_serialize() {
+        // Without sharing, this tree would expand to 2^64 nodes
+        let mut t = Tree::x() + Tree::y().sin();
+        for _ in 0..64 {
+            t = t.clone() * t.clone() - 0.25;
+        }
+        let t = t.remap_xyz(Tree::y(), Tree::x() * 2.0, Tree::z());
+        let data = bincode::serialize(&t).unwrap();
+        assert!(data.len() < 10_000);
+
+        let out: Tree = bincode::deserialize(&data).unwrap();
+        assert_eq!(bincode::serialize(&out).unwrap(), data);
+
+        let mut ctx = Context::new();
+        let a = ctx.import(&t);
+        let b = ctx.import(&out);
+        assert_eq!(a, b);
+
+        // Deep trees don't overflow the stack
+        let mut t = Tree::x();
+        for _ in 0..100_000 {
+            t += 1.0;
+        }
+        let data = bincode::serialize(&t).unwrap();
+        let out: Tree = bincode::deserialize(&data).unwrap();
+        let a = ctx.import(&t);
+        let b = ctx.import(&out);
+        assert_eq!(a, b);
+
+        // Invalid data is rejected
+        let data = bincode::serialize(&vec![
+            SerializedTreeOp::Input(Var::X),
+            SerializedTreeOp::Unary(UnaryOpcode::Neg, 1),
+        ])
+        .unwrap();
+        assert!(bincode::deserialize::<Tree>(&data).is_err());
+        let data = bincode::serialize(&Vec::<SerializedTreeOp>::new()).unwrap();
+        assert!(bincode::deserialize::<Tree>(&data).is_err());
+    }
+
     #[test]
     fn tree_x() {
         let x1 = Tree::x();
@@ -399,6 +631,57 @@ mod test {
         assert_eq!(ctx.eval_xyz(v_, 0.0, 1.0, 0.0).unwrap(), 4.0);
     }
 
+    #[test]
+    fn test_balance() {
+        let mut t = Tree::x().sin();
+        for i in 1..1000 {
+            t = t.min((Tree::y() * i as f64).cos());
+        }
+        let b = t.balance();
+        let mut ctx = Context::new();
+        let t = ctx.import(&t);
+        let b = ctx.import(&b);
+        assert_ne!(t, b);
+        for (x, y) in [(0.2, 0.7), (-1.5, 2.3)] {
+            assert_eq!(
+                ctx.eval_xyz(t, x, y, 0.0).unwrap(),
+                ctx.eval_xyz(b, x, y, 0.0).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_balance_spatial() {
+        // Spheres along the X axis, interleaved so that neighbors in the chain
+        // are far apart in space
+        let n = 16;
+        let sphere = |i: usize| {
+            let cx = -0.9 + 1.8 * i as f64 / (n - 1) as f64;
+            let r = ((Tree::x() - cx).square() + Tree::y().square()).sqrt();
+            r - 0.05
+        };
+        let mut t = sphere(0);
+        for i in (0..n / 2).flat_map(|i| [i, n - 1 - i]).skip(1) {
+            t = t.min(sphere(i));
+        }
+        let region = [Interval::new(-1.0, 1.0); 3];
+        let b = t.balance_spatial(region);
+
+        // Spatial grouping gives a different tree than plain balancing
+        let mut ctx = Context::new();
+        let t = ctx.import(&t);
+        let b = ctx.import(&b);
+        let plain = ctx.balance(t).unwrap();
+        assert_ne!(t, b);
+        assert_ne!(plain, b);
+        for (x, y) in [(0.2, 0.7), (-0.9, 0.0), (0.5, -0.01)] {
+            assert_eq!(
+                ctx.eval_xyz(t, x, y, 0.0).unwrap(),
+                ctx.eval_xyz(b, x, y, 0.0).unwrap()
+            );
+        }
+    }
+
     #[test]
     fn deep_recursion_drop() {
         let mut x = Tree::x();
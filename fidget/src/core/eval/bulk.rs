@@ -52,6 +52,142 @@ pub trait BulkEvaluator: Default {
     }
 }
 
+/// Bulk evaluator which simultaneously captures an execution trace
+///
+/// This is a hybrid of [`BulkEvaluator`] and
+/// [`TracingEvaluator`](crate::eval::TracingEvaluator): it evaluates many
+/// points in a single call, and records a single trace which is aggregated
+/// across every point.  A branch is only marked as taken on one side if
+/// _every_ point took that side.
+///
+/// Unlike a trace from interval evaluation, the resulting trace is only valid
+/// at the evaluated points; a function simplified with it produces identical
+/// results at those points, but may be wrong elsewhere.
+pub trait BulkTracingEvaluator: Default {
+    /// Data type used during evaluation
+    type Data: From<f32> + Copy + Clone;
+
+    /// Instruction tape used during evaluation
+    type Tape: Tape<Storage = Self::TapeStorage>;
+
+    /// Associated type for tape storage
+    ///
+    /// This is a workaround for plumbing purposes
+    type TapeStorage;
+
+    /// Associated type for the trace captured during evaluation
+    type Trace;
+
+    /// Evaluates many points using the given instruction tape
+    ///
+    /// `vars` follows the same layout as in [`BulkEvaluator::eval`].
+    ///
+    /// The returned output and trace are borrowed from the evaluator.  The
+    /// trace is `None` if no simplification is possible (or if `vars` are
+    /// empty).
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<BulkTracingResult<'_, Self::Data, Self::Trace>, Error>;
+
+    /// Build a new empty evaluator
+    fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Tuple of bulk tracing evaluation result
+pub type BulkTracingResult<'a, Data, Trace> =
+    (BulkOutput<'a, Data>, Option<&'a Trace>);
+
+/// Bulk evaluator which captures a separate trace for every sample
+///
+/// Unlike [`BulkTracingEvaluator`], traces are not aggregated: each sample gets
+/// the same trace that a [`TracingEvaluator`](crate::eval::TracingEvaluator)
+/// would produce for it alone.  This is typically used for interval
+/// evaluation, where each sample is a region of space; evaluating (for example)
+/// every child of a tile in one call amortizes the cost of instruction
+/// dispatch, while still allowing each child to be simplified individually.
+pub trait MultiTracingEvaluator: Default {
+    /// Data type used during evaluation
+    type Data: From<f32> + Copy + Clone;
+
+    /// Instruction tape used during evaluation
+    type Tape: Tape<Storage = Self::TapeStorage>;
+
+    /// Associated type for tape storage
+    ///
+    /// This is a workaround for plumbing purposes
+    type TapeStorage;
+
+    /// Associated type for the trace captured during evaluation
+    type Trace;
+
+    /// Evaluates many samples using the given instruction tape
+    ///
+    /// `vars` follows the same layout as in [`BulkEvaluator::eval`].
+    ///
+    /// The returned output and traces are borrowed from the evaluator.
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<MultiTracingResult<'_, Self::Data, Self::Trace>, Error>;
+
+    /// Build a new empty evaluator
+    fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Tuple of multi-trace evaluation result
+pub type MultiTracingResult<'a, Data, Trace> =
+    (BulkOutput<'a, Data>, BulkTraces<'a, Trace>);
+
+/// Container for per-sample traces
+///
+/// This is indexed by sample; each trace is `None` if no simplification is
+/// possible for that sample.
+pub struct BulkTraces<'a, T> {
+    traces: &'a [T],
+    simplify: &'a [bool],
+}
+
+impl<'a, T> BulkTraces<'a, T> {
+    pub(crate) fn new(traces: &'a [T], simplify: &'a [bool]) -> Self {
+        assert_eq!(traces.len(), simplify.len());
+        Self { traces, simplify }
+    }
+
+    /// Returns the number of samples
+    pub fn len(&self) -> usize {
+        self.traces.len()
+    }
+
+    /// Checks whether there are zero samples
+    pub fn is_empty(&self) -> bool {
+        self.traces.is_empty()
+    }
+
+    /// Returns the trace for the given sample
+    ///
+    /// # Panics
+    /// If `i` is out of bounds
+    pub fn get(&self, i: usize) -> Option<&'a T> {
+        if self.simplify[i] {
+            Some(&self.traces[i])
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over per-sample traces
+    pub fn iter(&self) -> impl Iterator<Item = Option<&'a T>> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+}
+
 /// Container for bulk output results
 ///
 /// This container represents an array-of-arrays.  It is indexed first by
@@ -0,0 +1,638 @@
+//! Memoization of [`Function::simplify`]
+use crate::{
+    compiler::RegOp,
+    context::{Context, Node},
+    eval::{
+        BulkEvaluator, BulkTracingEvaluator, Function, MathFunction,
+        MultiTracingEvaluator, TracingEvaluator,
+    },
+    render::{RenderHints, TileSizes},
+    var::VarMap,
+    Error,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Hit and miss statistics for a [`SimplifyCache`]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Number of calls to `simplify` which returned a cached function
+    pub hits: u64,
+    /// Number of calls to `simplify` which ran the inner simplification
+    pub misses: u64,
+    /// Number of entries which have been evicted to stay within capacity
+    pub evictions: u64,
+    /// Number of entries currently in the cache
+    pub len: usize,
+    /// Total [`Function::size`] of the entries currently in the cache
+    pub size: usize,
+}
+
+/// A single cached simplification
+struct CacheEntry<F> {
+    /// Simplified function
+    f: F,
+    /// Identity of the simplified function, used as the parent in its own keys
+    id: u64,
+    /// Last time that this entry was used, as a key into `CacheData::lru`
+    tick: u64,
+    /// Size of the simplified function, from [`Function::size`]
+    size: usize,
+}
+
+/// Mutable state within a [`SimplifyCache`], protected by a mutex
+struct CacheData<F: Function> {
+    /// Map from parent identity to traces and their simplified functions
+    ///
+    /// This is nested (rather than keyed by a tuple) so that lookups can
+    /// borrow the trace.
+    map: HashMap<u64, HashMap<F::Trace, CacheEntry<F>>>,
+    /// Map from last-use tick to key, so the first item is least recently used
+    lru: BTreeMap<u64, (u64, F::Trace)>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl<F: Function> CacheData<F>
+where
+    F::Trace: Hash,
+{
+    /// Evicts the least recently used entry, returning `false` if empty
+    fn evict(&mut self) -> bool {
+        let Some((_, (id, trace))) = self.lru.pop_first() else {
+            return false;
+        };
+        let inner = self.map.get_mut(&id).unwrap();
+        let e = inner.remove(&trace).unwrap();
+        if inner.is_empty() {
+            self.map.remove(&id);
+        }
+        self.stats.len -= 1;
+        self.stats.size -= e.size;
+        self.stats.evictions += 1;
+        true
+    }
+}
+
+/// Thread-safe least-recently-used cache of simplified functions
+///
+/// Entries are keyed by the identity of the function being simplified (which
+/// is assigned by the cache) and the trace passed to [`Function::simplify`].
+///
+/// Memory use is bounded in two ways: by the number of cached functions (the
+/// capacity), and by their total [`Function::size`] (e.g. the total length of
+/// their tapes).  The latter matters for the JIT, where each function owns a
+/// block of machine code that is roughly proportional to its tape length.
+/// When either limit is reached, the least recently used entries are dropped;
+/// a function which is larger than the size limit on its own is never cached.
+///
+/// The cache is usually created implicitly by [`CachedFunction::new`], but may
+/// be shared between several root functions with
+/// [`CachedFunction::with_cache`].
+pub struct SimplifyCache<F: Function> {
+    data: Mutex<CacheData<F>>,
+    next_id: AtomicU64,
+    capacity: usize,
+    max_size: usize,
+}
+
+impl<F: Function> SimplifyCache<F>
+where
+    F::Trace: Hash,
+{
+    /// Default number of cached functions
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    /// Default limit on the total [`Function::size`] of cached functions
+    pub const DEFAULT_MAX_SIZE: usize = 1 << 20;
+
+    /// Builds a new cache which holds up to `capacity` simplified functions,
+    /// with the default size limit
+    ///
+    /// A capacity of 0 disables caching, but statistics are still recorded.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_max_size(capacity, Self::DEFAULT_MAX_SIZE)
+    }
+
+    /// Builds a new cache which holds up to `capacity` simplified functions,
+    /// with a total [`Function::size`] of at most `max_size`
+    pub fn with_max_size(capacity: usize, max_size: usize) -> Self {
+        Self {
+            data: Mutex::new(CacheData {
+                map: HashMap::new(),
+                lru: BTreeMap::new(),
+                tick: 0,
+                stats: CacheStats::default(),
+            }),
+            next_id: AtomicU64::new(0),
+            capacity,
+            max_size,
+        }
+    }
+
+    /// Returns the maximum number of cached functions
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the maximum total [`Function::size`] of cached functions
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns a snapshot of hit and miss statistics
+    pub fn stats(&self) -> CacheStats {
+        self.data.lock().unwrap().stats
+    }
+
+    /// Drops every cached function
+    ///
+    /// Statistics (other than [`CacheStats::len`] and [`CacheStats::size`])
+    /// are preserved.
+    pub fn clear(&self) {
+        let mut data = self.data.lock().unwrap();
+        data.map.clear();
+        data.lru.clear();
+        data.stats.len = 0;
+        data.stats.size = 0;
+    }
+
+    /// Returns a new identity for a function
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Looks up a simplified function, marking it as recently used
+    fn get(&self, id: u64, trace: &F::Trace) -> Option<(F, u64)> {
+        let mut data = self.data.lock().unwrap();
+        let data = &mut *data;
+        let tick = data.tick;
+        let out =
+            data.map
+                .get_mut(&id)
+                .and_then(|m| m.get_mut(trace))
+                .map(|e| {
+                    let prev = std::mem::replace(&mut e.tick, tick);
+                    (e.f.clone(), e.id, prev)
+                });
+        match out {
+            Some((f, id, prev)) => {
+                data.tick += 1;
+                let key = data.lru.remove(&prev).unwrap();
+                data.lru.insert(tick, key);
+                data.stats.hits += 1;
+                Some((f, id))
+            }
+            None => {
+                data.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Inserts a simplified function, evicting old entries if necessary
+    ///
+    /// If another thread has already inserted a function with the same key,
+    /// then that function is returned instead, so that every caller sees the
+    /// same identity.
+    fn insert(&self, id: u64, trace: &F::Trace, f: F) -> (F, u64) {
+        let child = self.next_id();
+        let size = f.size();
+        if self.capacity == 0 || size > self.max_size {
+            return (f, child);
+        }
+        let mut data = self.data.lock().unwrap();
+        let data = &mut *data;
+        if let Some(e) = data.map.get(&id).and_then(|m| m.get(trace)) {
+            return (e.f.clone(), e.id);
+        }
+        while data.stats.len >= self.capacity
+            || data.stats.size + size > self.max_size
+        {
+            if !data.evict() {
+                break;
+            }
+        }
+        let tick = data.tick;
+        data.tick += 1;
+        data.lru.insert(tick, (id, trace.clone()));
+        data.map.entry(id).or_default().insert(
+            trace.clone(),
+            CacheEntry {
+                f: f.clone(),
+                id: child,
+                tick,
+                size,
+            },
+        );
+        data.stats.len += 1;
+        data.stats.size += size;
+        (f, child)
+    }
+}
+
+/// A [`Function`] wrapper which memoizes [`Function::simplify`]
+///
+/// Simplifying with a trace that matches a previous call (on the same parent
+/// function) returns the previously simplified function, rather than running
+/// simplification again.  This is especially helpful for the JIT, where each
+/// simplification also assembles and maps new machine code, and for renderers
+/// and meshing, where neighboring cells often produce identical traces.
+///
+/// Every evaluator and tape is delegated to the inner function, so results are
+/// identical to the unwrapped function.
+///
+/// ```
+/// use fidget::{
+///     context::Context,
+///     eval::{CachedFunction, MathFunction, TracingEvaluator},
+///     shape::{EzShape, Shape},
+///     vm::VmFunction,
+/// };
+///
+/// let mut ctx = Context::new();
+/// let x = ctx.x();
+/// let y = ctx.y();
+/// let m = ctx.min(x, y)?;
+/// let shape = Shape::<CachedFunction<VmFunction>>::new(&ctx, m)?;
+///
+/// let mut eval = Shape::<CachedFunction<VmFunction>>::new_point_eval();
+/// let tape = shape.ez_point_tape();
+/// let (_, trace) = eval.eval(&tape, 1.0, 2.0, 0.0)?;
+/// let trace = trace.unwrap().clone();
+///
+/// let a = shape.ez_simplify(&trace)?;
+/// let b = shape.ez_simplify(&trace)?;
+/// assert_eq!(a.size(), b.size());
+///
+/// let stats = shape.inner().stats();
+/// assert_eq!((stats.hits, stats.misses), (1, 1));
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub struct CachedFunction<F: Function> {
+    f: F,
+    id: u64,
+    cache: Arc<SimplifyCache<F>>,
+}
+
+impl<F: Function> Clone for CachedFunction<F> {
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f.clone(),
+            id: self.id,
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<F: Function> CachedFunction<F>
+where
+    F::Trace: Hash,
+{
+    /// Wraps a function with a new cache of the default capacity
+    pub fn new(f: F) -> Self {
+        Self::with_capacity(f, SimplifyCache::<F>::DEFAULT_CAPACITY)
+    }
+
+    /// Wraps a function with a new cache of the given capacity
+    pub fn with_capacity(f: F, capacity: usize) -> Self {
+        Self::with_cache(f, Arc::new(SimplifyCache::new(capacity)))
+    }
+
+    /// Wraps a function using an existing (possibly shared) cache
+    pub fn with_cache(f: F, cache: Arc<SimplifyCache<F>>) -> Self {
+        let id = cache.next_id();
+        Self { f, id, cache }
+    }
+
+    /// Returns the cache used by this function
+    pub fn cache(&self) -> &Arc<SimplifyCache<F>> {
+        &self.cache
+    }
+
+    /// Returns a snapshot of the cache's hit and miss statistics
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    /// Returns a reference to the inner function
+    pub fn inner(&self) -> &F {
+        &self.f
+    }
+
+    /// Unwraps the inner function
+    pub fn into_inner(self) -> F {
+        self.f
+    }
+}
+
+impl<F: Function> Function for CachedFunction<F>
+where
+    F::Trace: Hash,
+{
+    type Trace = F::Trace;
+    type Storage = F::Storage;
+    type Workspace = F::Workspace;
+    type TapeStorage = F::TapeStorage;
+
+    type PointEval = F::PointEval;
+    type IntervalEval = F::IntervalEval;
+    type IntervalSliceEval = F::IntervalSliceEval;
+    type FloatSliceEval = F::FloatSliceEval;
+    type GradSliceEval = F::GradSliceEval;
+    type DualSliceEval<const P: usize> = F::DualSliceEval<P>;
+    type FloatSliceTracingEval = F::FloatSliceTracingEval;
+
+    fn point_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::PointEval as TracingEvaluator>::Tape {
+        self.f.point_tape(storage)
+    }
+
+    fn interval_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::IntervalEval as TracingEvaluator>::Tape {
+        self.f.interval_tape(storage)
+    }
+
+    fn interval_slice_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::IntervalSliceEval as MultiTracingEvaluator>::Tape {
+        self.f.interval_slice_tape(storage)
+    }
+
+    fn float_slice_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::FloatSliceEval as BulkEvaluator>::Tape {
+        self.f.float_slice_tape(storage)
+    }
+
+    fn grad_slice_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::GradSliceEval as BulkEvaluator>::Tape {
+        self.f.grad_slice_tape(storage)
+    }
+
+    fn float_slice_tracing_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::FloatSliceTracingEval as BulkTracingEvaluator>::Tape {
+        self.f.float_slice_tracing_tape(storage)
+    }
+
+    fn dual_slice_tape<const P: usize>(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::DualSliceEval<P> as BulkEvaluator>::Tape {
+        self.f.dual_slice_tape(storage)
+    }
+
+    /// Returns a cached simplification, or simplifies and caches the result
+    ///
+    /// On a cache hit, `storage` is dropped rather than reused.
+    fn simplify(
+        &self,
+        trace: &Self::Trace,
+        storage: Self::Storage,
+        workspace: &mut Self::Workspace,
+    ) -> Result<Self, Error> {
+        let (f, id) = match self.cache.get(self.id, trace) {
+            Some(out) => out,
+            None => {
+                // Simplify without holding the lock, since this may be slow
+                let f = self.f.simplify(trace, storage, workspace)?;
+                self.cache.insert(self.id, trace, f)
+            }
+        };
+        Ok(Self {
+            f,
+            id,
+            cache: self.cache.clone(),
+        })
+    }
+
+    fn recycle(self) -> Option<Self::Storage> {
+        self.f.recycle()
+    }
+
+    fn size(&self) -> usize {
+        self.f.size()
+    }
+
+    fn vars(&self) -> &VarMap {
+        self.f.vars()
+    }
+
+    fn visit_ops(&self, f: &mut dyn FnMut(RegOp)) {
+        self.f.visit_ops(f)
+    }
+}
+
+impl<F: MathFunction> MathFunction for CachedFunction<F>
+where
+    F::Trace: Hash,
+{
+    fn new(ctx: &Context, nodes: &[Node]) -> Result<Self, Error> {
+        F::new(ctx, nodes).map(CachedFunction::new)
+    }
+}
+
+impl<F: Function + RenderHints> RenderHints for CachedFunction<F> {
+    fn tile_sizes_3d() -> TileSizes {
+        F::tile_sizes_3d()
+    }
+
+    fn tile_sizes_2d() -> TileSizes {
+        F::tile_sizes_2d()
+    }
+
+    fn simplify_tree_during_meshing(d: usize) -> bool {
+        F::simplify_tree_during_meshing(d)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        render::{BitRenderMode, ImageRenderConfig},
+        shape::{EzShape, Shape},
+        stats::without_stats,
+        vm::{Choice, VmFunction, VmTrace},
+    };
+
+    fn min_shape() -> Shape<CachedFunction<VmFunction>> {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let m = ctx.min(x, y).unwrap();
+        let m = ctx.max(m, z).unwrap();
+        Shape::new(&ctx, m).unwrap()
+    }
+
+    #[test]
+    fn test_cache_hit() {
+        let shape = min_shape();
+        let both = VmTrace::from(vec![Choice::Both, Choice::Both]);
+        let left = VmTrace::from(vec![Choice::Left, Choice::Left]);
+
+        let a = shape.ez_simplify(&both).unwrap();
+        let b = shape.ez_simplify(&both).unwrap();
+        assert_eq!(a.size(), b.size());
+        let stats = shape.inner().stats();
+        assert_eq!((stats.hits, stats.misses, stats.len), (1, 1, 1));
+
+        shape.ez_simplify(&left).unwrap();
+        let stats = shape.inner().stats();
+        assert_eq!((stats.hits, stats.misses, stats.len), (1, 2, 2));
+
+        // Simplifying the child uses a different key than the root
+        let c = a.ez_simplify(&left).unwrap();
+        assert!(c.size() < a.size());
+        let d = b.ez_simplify(&left).unwrap();
+        assert_eq!(c.size(), d.size());
+        let stats = shape.inner().stats();
+        assert_eq!((stats.hits, stats.misses, stats.len), (2, 3, 3));
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let mut ctx = Context::new();
+        let (x, y) = (ctx.x(), ctx.y());
+        let m = ctx.min(x, y).unwrap();
+        let f = CachedFunction::with_capacity(
+            VmFunction::new(&ctx, &[m]).unwrap(),
+            1,
+        );
+        let mut ws = Default::default();
+        let left = VmTrace::from(vec![Choice::Left]);
+        let right = VmTrace::from(vec![Choice::Right]);
+        for t in [&left, &right, &left, &left] {
+            f.simplify(t, Default::default(), &mut ws).unwrap();
+        }
+        let stats = f.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 3);
+        assert_eq!(stats.evictions, 2);
+        assert_eq!(stats.len, 1);
+
+        f.cache().clear();
+        assert_eq!(f.stats().len, 0);
+        f.simplify(&left, Default::default(), &mut ws).unwrap();
+        assert_eq!(f.stats().misses, 4);
+    }
+
+    #[test]
+    fn test_cache_max_size() {
+        let mut ctx = Context::new();
+        let (x, y, z) = (ctx.x(), ctx.y(), ctx.z());
+        let m = ctx.min(x, y).unwrap();
+        let m = ctx.max(m, z).unwrap();
+        let f = VmFunction::new(&ctx, &[m]).unwrap();
+        let mut ws = Default::default();
+
+        // Each simplified tape has two inputs, one operation, and an output
+        let left = VmTrace::from(vec![Choice::Left, Choice::Both]);
+        let right = VmTrace::from(vec![Choice::Right, Choice::Both]);
+        let size = f
+            .simplify(&left, Default::default(), &mut ws)
+            .unwrap()
+            .size();
+
+        // Room for one entry by size, though there's room for many by count
+        let cache = Arc::new(SimplifyCache::with_max_size(16, size));
+        let f = CachedFunction::with_cache(f, cache);
+        for t in [&left, &right, &left] {
+            f.simplify(t, Default::default(), &mut ws).unwrap();
+        }
+        let stats = f.stats();
+        assert_eq!((stats.hits, stats.misses), (0, 3));
+        assert_eq!((stats.len, stats.size, stats.evictions), (1, size, 2));
+
+        // Functions which are too large on their own are never cached
+        let cache = Arc::new(SimplifyCache::with_max_size(16, size - 1));
+        let f = CachedFunction::with_cache(f.into_inner(), cache);
+        for _ in 0..2 {
+            f.simplify(&left, Default::default(), &mut ws).unwrap();
+        }
+        let stats = f.stats();
+        assert_eq!((stats.hits, stats.misses), (0, 2));
+        assert_eq!((stats.len, stats.size, stats.evictions), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_cache_disabled() {
+        let mut ctx = Context::new();
+        let (x, y) = (ctx.x(), ctx.y());
+        let m = ctx.min(x, y).unwrap();
+        let f = CachedFunction::with_capacity(
+            VmFunction::new(&ctx, &[m]).unwrap(),
+            0,
+        );
+        let mut ws = Default::default();
+        let left = VmTrace::from(vec![Choice::Left]);
+        for _ in 0..3 {
+            f.simplify(&left, Default::default(), &mut ws).unwrap();
+        }
+        let stats = f.stats();
+        assert_eq!((stats.hits, stats.misses, stats.len), (0, 3, 0));
+    }
+
+    #[test]
+    fn test_cached_render() {
+        // Union of many circles, so that neighboring tiles share traces
+        let mut ctx = Context::new();
+        let [x, y, _z] = ctx.axes();
+        let mut out = None;
+        for i in 0..4 {
+            for j in 0..4 {
+                let dx = ctx.sub(x, i as f32 * 0.5 - 0.75).unwrap();
+                let dy = ctx.sub(y, j as f32 * 0.5 - 0.75).unwrap();
+                let dx2 = ctx.square(dx).unwrap();
+                let dy2 = ctx.square(dy).unwrap();
+                let r = ctx.add(dx2, dy2).unwrap();
+                let r = ctx.sqrt(r).unwrap();
+                let c = ctx.sub(r, 0.2).unwrap();
+                out = Some(match out {
+                    Some(prev) => ctx.min(prev, c).unwrap(),
+                    None => c,
+                });
+            }
+        }
+        let node = out.unwrap();
+
+        let cfg = ImageRenderConfig {
+            image_size: 256.into(),
+            ..Default::default()
+        };
+        let expected = cfg
+            .run::<_, BitRenderMode>(
+                Shape::<VmFunction>::new(&ctx, node).unwrap(),
+            )
+            .map(without_stats)
+            .unwrap();
+        let shape =
+            Shape::<CachedFunction<VmFunction>>::new(&ctx, node).unwrap();
+        let actual = cfg
+            .run::<_, BitRenderMode>(shape.clone())
+            .map(without_stats)
+            .unwrap();
+        assert!(expected.iter().eq(actual.iter()));
+
+        let stats = shape.inner().stats();
+        assert!(stats.hits > 0, "{stats:?}");
+        assert!(stats.misses > 0, "{stats:?}");
+    }
+}
@@ -1,8 +1,10 @@
 //! Traits and data structures for function evaluation
 use crate::{
+    compiler::RegOp,
     context::{Context, Node},
-    types::{Grad, Interval},
+    types::{Dual, Grad, GradF64, Hess, Interval, IntervalF64},
     var::VarMap,
+    vm::Choice,
     Error,
 };
 
@@ -11,11 +13,17 @@ use crate::{
 pub mod test;
 
 mod bulk;
+mod cache;
 mod tracing;
 
 // Reexport a few types
-pub use bulk::{BulkEvaluator, BulkOutput};
+pub use bulk::{
+    BulkEvaluator, BulkOutput, BulkTraces, BulkTracingEvaluator,
+    BulkTracingResult, MultiTracingEvaluator, MultiTracingResult,
+};
+pub use cache::{CacheStats, CachedFunction, SimplifyCache};
 pub use tracing::TracingEvaluator;
+pub(crate) use tracing::TracingResult;
 
 /// A tape represents something that can be evaluated by an evaluator
 ///
@@ -57,6 +65,14 @@ pub trait Tape: Send + Sync + Clone {
 pub trait Trace {
     /// Copies the contents of `other` into `self`
     fn copy_from(&mut self, other: &Self);
+
+    /// Returns the choices recorded in this trace
+    ///
+    /// This is only used for profiling (see [`crate::stats`]); the default
+    /// implementation returns an empty slice.
+    fn choices(&self) -> &[Choice] {
+        &[]
+    }
 }
 
 impl<T: Copy + Clone + Default> Trace for Vec<T> {
@@ -123,6 +139,22 @@ pub trait Function: Send + Sync + Clone {
         Self::IntervalEval::new()
     }
 
+    /// Associated type for evaluating many intervals in one call
+    ///
+    /// Each interval gets its own trace, identical to the trace from
+    /// [`Function::IntervalEval`]; see [`MultiTracingEvaluator`] for details.
+    type IntervalSliceEval: MultiTracingEvaluator<
+            Data = Interval,
+            Trace = Self::Trace,
+            TapeStorage = Self::TapeStorage,
+        > + Send
+        + Sync;
+
+    /// Builds a new interval slice evaluator
+    fn new_interval_slice_eval() -> Self::IntervalSliceEval {
+        Self::IntervalSliceEval::new()
+    }
+
     /// Associated type for evaluating many points in one call
     type FloatSliceEval: BulkEvaluator<Data = f32, TapeStorage = Self::TapeStorage>
         + Send
@@ -143,6 +175,35 @@ pub trait Function: Send + Sync + Clone {
         Self::GradSliceEval::new()
     }
 
+    /// Associated type for evaluating many dual numbers in one call
+    ///
+    /// Each value carries `P` partial derivatives, which may be taken with
+    /// respect to any set of inputs (rather than just `x`, `y`, and `z`).
+    type DualSliceEval<const P: usize>: BulkEvaluator<Data = Dual<P>, TapeStorage = Self::TapeStorage>
+        + Send
+        + Sync;
+
+    /// Builds a new dual number slice evaluator
+    fn new_dual_slice_eval<const P: usize>() -> Self::DualSliceEval<P> {
+        Self::DualSliceEval::<P>::new()
+    }
+
+    /// Associated type for evaluating many points in one call, with tracing
+    ///
+    /// Choices are aggregated across every point; see
+    /// [`BulkTracingEvaluator`] for details.
+    type FloatSliceTracingEval: BulkTracingEvaluator<
+            Data = f32,
+            Trace = Self::Trace,
+            TapeStorage = Self::TapeStorage,
+        > + Send
+        + Sync;
+
+    /// Builds a new tracing float slice evaluator
+    fn new_float_slice_tracing_eval() -> Self::FloatSliceTracingEval {
+        Self::FloatSliceTracingEval::new()
+    }
+
     /// Returns an evaluation tape for a point evaluator
     fn point_tape(
         &self,
@@ -155,6 +216,12 @@ pub trait Function: Send + Sync + Clone {
         storage: Self::TapeStorage,
     ) -> <Self::IntervalEval as TracingEvaluator>::Tape;
 
+    /// Returns an evaluation tape for an interval slice evaluator
+    fn interval_slice_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::IntervalSliceEval as MultiTracingEvaluator>::Tape;
+
     /// Returns an evaluation tape for a float slice evaluator
     fn float_slice_tape(
         &self,
@@ -167,6 +234,18 @@ pub trait Function: Send + Sync + Clone {
         storage: Self::TapeStorage,
     ) -> <Self::GradSliceEval as BulkEvaluator>::Tape;
 
+    /// Returns an evaluation tape for a tracing float slice evaluator
+    fn float_slice_tracing_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::FloatSliceTracingEval as BulkTracingEvaluator>::Tape;
+
+    /// Returns an evaluation tape for a dual number slice evaluator
+    fn dual_slice_tape<const P: usize>(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::DualSliceEval<P> as BulkEvaluator>::Tape;
+
     /// Computes a simplified tape using the given trace, and reusing storage
     fn simplify(
         &self,
@@ -191,6 +270,13 @@ pub trait Function: Send + Sync + Clone {
 
     /// Returns the map from [`Var`](crate::var::Var) to input index
     fn vars(&self) -> &VarMap;
+
+    /// Calls `f` on each operation in the function's tape
+    ///
+    /// This is only used for profiling (see [`crate::stats`]); the default
+    /// implementation does nothing, so functions which aren't built from a
+    /// [`RegOp`] tape may leave it unimplemented.
+    fn visit_ops(&self, _f: &mut dyn FnMut(RegOp)) {}
 }
 
 /// A [`Function`] which can be built from a math expression
@@ -200,3 +286,86 @@ pub trait MathFunction: Function {
     where
         Self: Sized;
 }
+
+/// A [`Function`] which can also be evaluated in double precision
+///
+/// This is a sibling of the `f32` evaluators in [`Function`], using the same
+/// tapes, traces, and simplification machinery.  Traces captured by the `f64`
+/// tracing evaluators may be passed to [`Function::simplify`].
+///
+/// Constants in the original expression keep their full `f64` precision,
+/// even though the `f32` evaluators round them to single precision.
+pub trait FunctionF64: Function {
+    /// Associated type for single-point tracing evaluation in `f64`
+    type PointEvalF64: TracingEvaluator<
+            Data = f64,
+            Trace = Self::Trace,
+            TapeStorage = Self::TapeStorage,
+        > + Send
+        + Sync;
+
+    /// Associated type for single interval tracing evaluation in `f64`
+    type IntervalEvalF64: TracingEvaluator<
+            Data = IntervalF64,
+            Trace = Self::Trace,
+            TapeStorage = Self::TapeStorage,
+        > + Send
+        + Sync;
+
+    /// Associated type for evaluating many `f64` points in one call
+    type FloatSliceEvalF64: BulkEvaluator<Data = f64, TapeStorage = Self::TapeStorage>
+        + Send
+        + Sync;
+
+    /// Associated type for evaluating many `f64` gradients in one call
+    type GradSliceEvalF64: BulkEvaluator<Data = GradF64, TapeStorage = Self::TapeStorage>
+        + Send
+        + Sync;
+
+    /// Returns an evaluation tape for an `f64` point evaluator
+    fn point_tape_f64(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::PointEvalF64 as TracingEvaluator>::Tape;
+
+    /// Returns an evaluation tape for an `f64` interval evaluator
+    fn interval_tape_f64(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::IntervalEvalF64 as TracingEvaluator>::Tape;
+
+    /// Returns an evaluation tape for an `f64` float slice evaluator
+    fn float_slice_tape_f64(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::FloatSliceEvalF64 as BulkEvaluator>::Tape;
+
+    /// Returns an evaluation tape for an `f64` gradient slice evaluator
+    fn grad_slice_tape_f64(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::GradSliceEvalF64 as BulkEvaluator>::Tape;
+}
+
+/// A [`Function`] which can also evaluate second partial derivatives
+///
+/// Like [`FunctionF64`], this is an optional sibling of [`Function`], since
+/// not every backend implements it; it uses the same tapes as the other
+/// evaluators.
+pub trait FunctionHess: Function {
+    /// Associated type for evaluating many Hessians in one call
+    type HessSliceEval: BulkEvaluator<Data = Hess, TapeStorage = Self::TapeStorage>
+        + Send
+        + Sync;
+
+    /// Builds a new Hessian slice evaluator
+    fn new_hess_slice_eval() -> Self::HessSliceEval {
+        Self::HessSliceEval::new()
+    }
+
+    /// Returns an evaluation tape for a Hessian slice evaluator
+    fn hess_slice_tape(
+        &self,
+        storage: Self::TapeStorage,
+    ) -> <Self::HessSliceEval as BulkEvaluator>::Tape;
+}
@@ -0,0 +1,231 @@
+//! Test suite for dual number evaluation
+//!
+//! If the `eval-tests` feature is set, then this exposes a standard test suite
+//! for dual number evaluators; otherwise, the module has no public exports.
+//!
+//! Results with three partial derivatives are checked against the same
+//! function's gradient evaluator, which performs identical arithmetic.
+use super::{build_stress_fn, test_args, CanonicalBinaryOp, CanonicalUnaryOp};
+use crate::{
+    context::{Context, Node},
+    eval::{BulkEvaluator, Function, MathFunction, Tape},
+    types::{Dual, Grad},
+    var::Var,
+};
+
+/// Helper struct to put constrains on our `Shape` object
+pub struct TestDualSlice<F>(std::marker::PhantomData<*const F>);
+
+impl<F: Function + MathFunction> TestDualSlice<F> {
+    /// Evaluates the given node with unit partials for `x`, `y`, and `z`
+    ///
+    /// Returns results from the dual number evaluator and from the function's
+    /// gradient evaluator.
+    fn eval_xyz(
+        ctx: &Context,
+        node: Node,
+        xs: &[f32],
+        ys: &[f32],
+        zs: &[f32],
+    ) -> (Vec<Dual<3>>, Vec<Grad>) {
+        assert_eq!(xs.len(), ys.len());
+        assert_eq!(ys.len(), zs.len());
+        let seed = |vs: &[f32], i: usize| -> Vec<Dual<3>> {
+            vs.iter().map(|v| Dual::var(*v, i)).collect()
+        };
+        let args = [
+            (Var::X, seed(xs, 0)),
+            (Var::Y, seed(ys, 1)),
+            (Var::Z, seed(zs, 2)),
+        ];
+
+        let shape = F::new(ctx, &[node]).unwrap();
+        let tape = shape.dual_slice_tape::<3>(Default::default());
+        let mut vars = vec![vec![]; tape.vars().len()];
+        for (v, a) in &args {
+            if let Some(i) = tape.vars().get(v) {
+                vars[i] = a.clone();
+            }
+        }
+        let mut eval = F::new_dual_slice_eval::<3>();
+        let out = eval.eval(&tape, &vars).unwrap()[0].to_owned();
+
+        let tape = shape.grad_slice_tape(Default::default());
+        let mut vars = vec![vec![]; tape.vars().len()];
+        for (v, a) in &args {
+            if let Some(i) = tape.vars().get(v) {
+                vars[i] = a.iter().map(|d| Grad::from(*d)).collect();
+            }
+        }
+        let mut eval = F::new_grad_slice_eval();
+        let expected = eval.eval(&tape, &vars).unwrap()[0].to_owned();
+
+        (out, expected)
+    }
+
+    fn compare_results(out: &[Dual<3>], expected: &[Grad], name: &str) {
+        assert_eq!(out.len(), expected.len());
+        let same = |a: f32, b: f32| a == b || (a.is_nan() && b.is_nan());
+        for (o, e) in out.iter().zip(expected) {
+            let o = Grad::from(*o);
+            assert!(
+                same(o.v, e.v)
+                    && same(o.dx, e.dx)
+                    && same(o.dy, e.dy)
+                    && same(o.dz, e.dz),
+                "mismatch in '{name}': {o} != {e}"
+            );
+        }
+    }
+
+    pub fn test_d_stress() {
+        let (ctx, node) = build_stress_fn(16);
+        let args = test_args();
+        let mut ys = args.clone();
+        ys.rotate_left(1);
+        let mut zs = args.clone();
+        zs.rotate_left(2);
+        let (out, expected) = Self::eval_xyz(&ctx, node, &args, &ys, &zs);
+        Self::compare_results(&out, &expected, "stress");
+    }
+
+    pub fn test_d_many_vars() {
+        // sum(v[i] * v[i + 1] * (i + 1)), with periodic indexing
+        const N: usize = 40;
+        let vs = (0..N).map(|_| Var::new()).collect::<Vec<_>>();
+        let mut ctx = Context::new();
+        let mut sum = ctx.constant(0.0);
+        for i in 0..N {
+            let a = ctx.var(vs[i]);
+            let b = ctx.var(vs[(i + 1) % N]);
+            let p = ctx.mul(a, b).unwrap();
+            let p = ctx.mul(p, (i + 1) as f32).unwrap();
+            sum = ctx.add(sum, p).unwrap();
+        }
+        let shape = F::new(&ctx, &[sum]).unwrap();
+        let tape = shape.dual_slice_tape::<N>(Default::default());
+
+        let values = [0.5, -1.0, 2.0];
+        let pos = |i: usize, j: usize| (i + j) as f32 * 0.25 - 3.0;
+        let mut vars = vec![vec![]; N];
+        for (i, v) in vs.iter().enumerate() {
+            let index = tape.vars()[v];
+            vars[index] = (0..values.len())
+                .map(|j| Dual::var(pos(i, j) * values[j], i))
+                .collect();
+        }
+        let mut eval = F::new_dual_slice_eval::<N>();
+        let out = eval.eval(&tape, &vars).unwrap();
+
+        for (j, scale) in values.iter().enumerate() {
+            let x = |i: usize| pos(i % N, j) * scale;
+            let o = out[0][j];
+            let mut v = 0.0;
+            for i in 0..N {
+                v += x(i) * x(i + 1) * (i + 1) as f32;
+            }
+            assert!((o.v - v).abs() <= v.abs() * 1e-5, "{} != {v}", o.v);
+            for i in 0..N {
+                // v[i] appears in the terms for i (with v[i + 1]) and i - 1
+                let prev = (i + N - 1) % N;
+                let d = x(i + 1) * (i + 1) as f32 + x(prev) * (prev + 1) as f32;
+                assert_eq!(o.d[i], d, "bad partial {i} in sample {j}");
+            }
+        }
+    }
+
+    pub fn test_d_unused_partials() {
+        // Partials for variables which aren't present in the tape stay at zero
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let node = ctx.square(x).unwrap();
+        let shape = F::new(&ctx, &[node]).unwrap();
+        let tape = shape.dual_slice_tape::<5>(Default::default());
+        let mut eval = F::new_dual_slice_eval::<5>();
+        let out = eval.eval(&tape, &[vec![Dual::var(3.0, 2)]]).unwrap();
+        assert_eq!(out[0][0], Dual::new(9.0, [0.0, 0.0, 6.0, 0.0, 0.0]));
+    }
+
+    pub fn test_unary<C: CanonicalUnaryOp>() {
+        let args = test_args();
+        let zero = vec![0.0; args.len()];
+
+        let mut ctx = Context::new();
+        for (i, v) in [ctx.x(), ctx.y(), ctx.z()].into_iter().enumerate() {
+            let node = C::build(&mut ctx, v);
+            let (out, expected) = match i {
+                0 => Self::eval_xyz(&ctx, node, &args, &zero, &zero),
+                1 => Self::eval_xyz(&ctx, node, &zero, &args, &zero),
+                2 => Self::eval_xyz(&ctx, node, &zero, &zero, &args),
+                _ => unreachable!(),
+            };
+            Self::compare_results(&out, &expected, C::NAME);
+        }
+    }
+
+    pub fn test_binary<C: CanonicalBinaryOp>() {
+        let args = test_args();
+        let mut rgsa = args.clone();
+        rgsa.rotate_left(3);
+        let zero = vec![0.0; args.len()];
+
+        let mut ctx = Context::new();
+        let (x, y) = (ctx.x(), ctx.y());
+
+        let name = format!("{}(reg, reg)", C::NAME);
+        let node = C::build(&mut ctx, x, y);
+        let (out, expected) = Self::eval_xyz(&ctx, node, &args, &rgsa, &zero);
+        Self::compare_results(&out, &expected, &name);
+
+        let node = C::build(&mut ctx, x, x);
+        let (out, expected) = Self::eval_xyz(&ctx, node, &args, &rgsa, &zero);
+        Self::compare_results(&out, &expected, &name);
+
+        for &imm in args.iter() {
+            let name = format!("{}(reg, imm)", C::NAME);
+            let node = C::build(&mut ctx, y, imm);
+            let (out, expected) =
+                Self::eval_xyz(&ctx, node, &zero, &args, &zero);
+            Self::compare_results(&out, &expected, &name);
+
+            let name = format!("{}(imm, reg)", C::NAME);
+            let node = C::build(&mut ctx, imm, y);
+            let (out, expected) =
+                Self::eval_xyz(&ctx, node, &zero, &args, &zero);
+            Self::compare_results(&out, &expected, &name);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! dual_test {
+    ($i:ident, $t:ty) => {
+        #[test]
+        fn $i() {
+            $crate::eval::test::dual_slice::TestDualSlice::<$t>::$i()
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! dual_slice_tests {
+    ($t:ty) => {
+        $crate::dual_test!(test_d_stress, $t);
+        $crate::dual_test!(test_d_many_vars, $t);
+        $crate::dual_test!(test_d_unused_partials, $t);
+
+        mod d_unary {
+            use super::*;
+            $crate::all_unary_tests!(
+                $crate::eval::test::dual_slice::TestDualSlice::<$t>
+            );
+        }
+
+        mod d_binary {
+            use super::*;
+            $crate::all_binary_tests!(
+                $crate::eval::test::dual_slice::TestDualSlice::<$t>
+            );
+        }
+    };
+}
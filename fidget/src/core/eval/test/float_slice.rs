@@ -108,6 +108,28 @@ impl<F: Function + MathFunction> TestFloatSlice<F> {
         );
     }
 
+    pub fn test_f_select() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let v = ctx.if_nonzero_else(x, y, z).unwrap();
+
+        let xs = [1.0, 0.0, f32::NAN, -1.0, 0.0];
+        let ys = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [10.0, 20.0, 30.0, 40.0, 50.0];
+
+        let shape = F::new(&ctx, &[v]).unwrap();
+        let mut eval = F::new_float_slice_eval();
+        let tape = shape.float_slice_tape(Default::default());
+
+        let vs = bind_xyz::<_, &[f32], &[f32]>(&tape);
+        assert_eq!(
+            eval.eval(&tape, &vs(&xs, &ys, &zs)).unwrap()[0],
+            [1.0, 20.0, 3.0, 4.0, 50.0]
+        );
+    }
+
     pub fn test_f_shape_var() {
         let v = Var::new();
         let mut ctx = Context::new();
@@ -132,8 +154,9 @@ impl<F: Function + MathFunction> TestFloatSlice<F> {
         let index = v.index().unwrap();
         h.insert(index, &[4.0, 5.0]);
         assert_eq!(
-            eval.eval_vs(&tape, &[1.0, 2.0], &[2.0, 3.0], &[0.0, 0.0], &h)
-                .unwrap(),
+            &eval
+                .eval_vs(&tape, &[1.0, 2.0], &[2.0, 3.0], &[0.0, 0.0], &h)
+                .unwrap()[0],
             &[7.0, 10.0]
         );
 
@@ -195,7 +218,7 @@ impl<F: Function + MathFunction> TestFloatSlice<F> {
         let tape = shape.ez_float_slice_tape();
 
         let cmp = eval.eval(&tape, &x, &y, &z).unwrap();
-        for (i, (a, b)) in out[0].iter().zip(cmp.iter()).enumerate() {
+        for (i, (a, b)) in out[0].iter().zip(cmp[0].iter()).enumerate() {
             let err = (a - b).abs();
             assert!(
                 err < 1e-6,
@@ -381,6 +404,7 @@ macro_rules! float_slice_tests {
         $crate::float_slice_test!(test_give_take, $t);
         $crate::float_slice_test!(test_vectorized, $t);
         $crate::float_slice_test!(test_f_sin, $t);
+        $crate::float_slice_test!(test_f_select, $t);
         $crate::float_slice_test!(test_f_shape_var, $t);
         $crate::float_slice_test!(test_f_stress, $t);
 
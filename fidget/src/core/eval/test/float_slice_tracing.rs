@@ -0,0 +1,318 @@
+//! Test suite for tracing bulk evaluation
+//!
+//! If the `eval-tests` feature is set, then this exposes a standard test suite
+//! for tracing float slice evaluators; otherwise, the module has no public
+//! exports.
+//!
+//! Values are checked against the VM's float slice evaluator, and traces are
+//! checked against the union of traces from the point evaluator.
+use super::{test_args, CanonicalBinaryOp, CanonicalUnaryOp};
+use crate::{
+    context::{Context, Node},
+    eval::{
+        BulkEvaluator, BulkTracingEvaluator, Function, MathFunction, Tape,
+        TracingEvaluator,
+    },
+    shape::Shape,
+    var::Var,
+    vm::{Choice, VmFunction},
+};
+
+/// Helper struct to put constrains on our `Shape` object
+pub struct TestFloatSliceTracing<F>(std::marker::PhantomData<*const F>);
+
+impl<F> TestFloatSliceTracing<F>
+where
+    F: Function + MathFunction,
+    <F as Function>::Trace: AsRef<[Choice]>,
+{
+    /// Evaluates the given node at many `(x, y)` positions
+    ///
+    /// Checks that values match the VM's float slice evaluator exactly, and
+    /// that the trace matches the union of point evaluator traces, then
+    /// returns the values and trace.
+    fn eval_xy(
+        ctx: &Context,
+        node: Node,
+        xs: &[f32],
+        ys: &[f32],
+        name: &str,
+    ) -> (Vec<f32>, Option<Vec<Choice>>) {
+        assert_eq!(xs.len(), ys.len());
+        let shape = F::new(ctx, &[node]).unwrap();
+        let args = [(Var::X, xs), (Var::Y, ys)];
+
+        let tape = shape.float_slice_tracing_tape(Default::default());
+        let mut vars = vec![[].as_slice(); tape.vars().len()];
+        for (v, a) in &args {
+            if let Some(i) = tape.vars().get(v) {
+                vars[i] = a;
+            }
+        }
+        let mut eval = F::new_float_slice_tracing_eval();
+        let (out, trace) = eval.eval(&tape, &vars).unwrap();
+        let out = out[0].to_owned();
+        let trace = trace.map(|t| t.as_ref().to_owned());
+
+        let vm = VmFunction::new(ctx, &[node]).unwrap();
+        let tape = vm.float_slice_tape(Default::default());
+        let mut eval = VmFunction::new_float_slice_eval();
+        let expected = eval.eval(&tape, &vars).unwrap();
+        for (o, e) in out.iter().zip(expected[0].iter()) {
+            assert!(
+                o.to_bits() == e.to_bits() || (o.is_nan() && e.is_nan()),
+                "value mismatch in '{name}': {o} != {e}"
+            );
+        }
+
+        // A point trace of `None` means that every choice was `Both`, which
+        // poisons the aggregated trace.
+        let tape = shape.point_tape(Default::default());
+        let mut eval = F::new_point_eval();
+        let mut expected: Option<Vec<Choice>> = Some(vec![]);
+        for k in 0..xs.len() {
+            let mut vs = vec![0.0; tape.vars().len()];
+            for (v, a) in &args {
+                if let Some(i) = tape.vars().get(v) {
+                    vs[i] = a[k];
+                }
+            }
+            let (_, t) = eval.eval(&tape, &vs).unwrap();
+            expected = match (expected, t) {
+                (Some(mut prev), Some(t)) => {
+                    let t = t.as_ref();
+                    prev.resize(t.len(), Choice::Unknown);
+                    for (p, c) in prev.iter_mut().zip(t) {
+                        *p |= *c;
+                    }
+                    Some(prev)
+                }
+                _ => None,
+            };
+        }
+        let expected = expected
+            .filter(|e| !e.is_empty() && e.iter().any(|c| *c != Choice::Both));
+        assert_eq!(trace, expected, "trace mismatch in '{name}'");
+
+        (out, trace)
+    }
+
+    pub fn test_t_min() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let min = ctx.min(x, y).unwrap();
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, min, &[0.0, 1.0, -2.0], &[1.0, 2.0, 3.0], "");
+        assert_eq!(out, [0.0, 1.0, -2.0]);
+        assert_eq!(trace.unwrap(), [Choice::Left]);
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, min, &[2.0, 3.0], &[1.0, -1.0], "");
+        assert_eq!(out, [1.0, -1.0]);
+        assert_eq!(trace.unwrap(), [Choice::Right]);
+
+        // Mixed choices can't be simplified
+        let (out, trace) =
+            Self::eval_xy(&ctx, min, &[0.0, 3.0], &[1.0, -1.0], "");
+        assert_eq!(out, [0.0, -1.0]);
+        assert!(trace.is_none());
+
+        // Neither can ties or NaN
+        let (_out, trace) =
+            Self::eval_xy(&ctx, min, &[0.0, 1.0], &[1.0, 1.0], "");
+        assert!(trace.is_none());
+        let (out, trace) =
+            Self::eval_xy(&ctx, min, &[0.0, f32::NAN], &[1.0, 2.0], "");
+        assert_eq!(out[0], 0.0);
+        assert!(out[1].is_nan());
+        assert!(trace.is_none());
+    }
+
+    pub fn test_t_max() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let max = ctx.max(x, y).unwrap();
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, max, &[1.0, 2.0, 3.0], &[0.0, 1.0, -2.0], "");
+        assert_eq!(out, [1.0, 2.0, 3.0]);
+        assert_eq!(trace.unwrap(), [Choice::Left]);
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, max, &[1.0, -1.0], &[2.0, 3.0], "");
+        assert_eq!(out, [2.0, 3.0]);
+        assert_eq!(trace.unwrap(), [Choice::Right]);
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, max, &[1.0, -1.0], &[0.0, 3.0], "");
+        assert_eq!(out, [1.0, 3.0]);
+        assert!(trace.is_none());
+    }
+
+    pub fn test_t_select() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let neg_y = ctx.neg(y).unwrap();
+        let s = ctx.if_nonzero_else(x, y, neg_y).unwrap();
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, s, &[1.0, -1.0], &[2.0, 3.0], "select");
+        assert_eq!(out, [2.0, 3.0]);
+        assert!(trace.unwrap().contains(&Choice::Left));
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, s, &[0.0, 0.0], &[2.0, 3.0], "select");
+        assert_eq!(out, [-2.0, -3.0]);
+        assert!(trace.unwrap().contains(&Choice::Right));
+
+        let (out, trace) =
+            Self::eval_xy(&ctx, s, &[1.0, 0.0], &[2.0, 3.0], "select");
+        assert_eq!(out, [2.0, -3.0]);
+        assert!(trace.is_none());
+    }
+
+    pub fn test_t_empty() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let min = ctx.min(x, y).unwrap();
+        let (out, trace) = Self::eval_xy(&ctx, min, &[], &[], "empty");
+        assert!(out.is_empty());
+        assert!(trace.is_none());
+    }
+
+    pub fn test_t_simplify() {
+        // Union of three circles, sampled on a tile that only touches one
+        let mut ctx = Context::new();
+        let [x, y, _z] = ctx.axes();
+        let mut circle = |cx: f32, cy: f32| {
+            let dx = ctx.sub(x, cx).unwrap();
+            let dy = ctx.sub(y, cy).unwrap();
+            let dx2 = ctx.square(dx).unwrap();
+            let dy2 = ctx.square(dy).unwrap();
+            let r = ctx.add(dx2, dy2).unwrap();
+            let r = ctx.sqrt(r).unwrap();
+            ctx.sub(r, 0.5).unwrap()
+        };
+        let a = circle(-1.0, 0.0);
+        let b = circle(1.0, 0.0);
+        let c = circle(0.0, 3.0);
+        let ab = ctx.min(a, b).unwrap();
+        let node = ctx.min(ab, c).unwrap();
+
+        let shape = Shape::<F>::new(&ctx, node).unwrap();
+        let tape = shape.float_slice_tracing_tape(Default::default());
+        let mut eval = Shape::<F>::new_float_slice_tracing_eval();
+
+        let mut xs = vec![];
+        let mut ys = vec![];
+        for i in 0..8 {
+            for j in 0..8 {
+                xs.push(i as f32 * 0.1 - 1.4);
+                ys.push(j as f32 * 0.08 - 0.3);
+            }
+        }
+        let zs = vec![0.0; xs.len()];
+        let (out, trace) = eval.eval(&tape, &xs, &ys, &zs).unwrap();
+        let out = out[0].to_owned();
+        let choices = trace.unwrap().as_ref();
+        assert_eq!(choices.len(), 2);
+        assert!(choices.iter().all(|c| *c != Choice::Both));
+
+        let next = shape
+            .simplify(
+                trace.unwrap(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        assert!(next.size() < shape.size());
+
+        // The simplified shape is identical at the sampled points
+        let tape = next.float_slice_tape(Default::default());
+        let mut eval = Shape::<F>::new_float_slice_eval();
+        let simplified = eval.eval(&tape, &xs, &ys, &zs).unwrap();
+        assert_eq!(out, &simplified[0]);
+    }
+
+    pub fn test_unary<C: CanonicalUnaryOp>() {
+        let args = test_args();
+        let zero = vec![0.0; args.len()];
+
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let node = C::build(&mut ctx, x);
+        Self::eval_xy(&ctx, node, &args, &zero, C::NAME);
+    }
+
+    pub fn test_binary<C: CanonicalBinaryOp>() {
+        let args = test_args();
+
+        let mut ctx = Context::new();
+        let (x, y) = (ctx.x(), ctx.y());
+
+        // Rotating the arguments gives a mix of choices
+        let name = format!("{}(reg, reg)", C::NAME);
+        let node = C::build(&mut ctx, x, y);
+        for rot in [0, 1, 7] {
+            let mut rgsa = args.clone();
+            rgsa.rotate_left(rot);
+            Self::eval_xy(&ctx, node, &args, &rgsa, &name);
+        }
+
+        // Evaluating a subset of the arguments gives uniform choices
+        let (lo, hi) = args.split_at(args.len() / 4);
+        let hi = &hi[..lo.len()];
+        Self::eval_xy(&ctx, node, lo, hi, &name);
+        Self::eval_xy(&ctx, node, hi, lo, &name);
+
+        for &imm in args.iter().step_by(4) {
+            let name = format!("{}(reg, imm)", C::NAME);
+            let node = C::build(&mut ctx, x, imm);
+            Self::eval_xy(&ctx, node, &args, &args, &name);
+
+            let name = format!("{}(imm, reg)", C::NAME);
+            let node = C::build(&mut ctx, imm, x);
+            Self::eval_xy(&ctx, node, &args, &args, &name);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! float_slice_tracing_test {
+    ($i:ident, $t:ty) => {
+        #[test]
+        fn $i() {
+            $crate::eval::test::float_slice_tracing::TestFloatSliceTracing::<$t>::$i()
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! float_slice_tracing_tests {
+    ($t:ty) => {
+        $crate::float_slice_tracing_test!(test_t_min, $t);
+        $crate::float_slice_tracing_test!(test_t_max, $t);
+        $crate::float_slice_tracing_test!(test_t_select, $t);
+        $crate::float_slice_tracing_test!(test_t_empty, $t);
+        $crate::float_slice_tracing_test!(test_t_simplify, $t);
+
+        mod t_unary {
+            use super::*;
+            $crate::all_unary_tests!(
+                $crate::eval::test::float_slice_tracing::TestFloatSliceTracing::<$t>
+            );
+        }
+
+        mod t_binary {
+            use super::*;
+            $crate::all_binary_tests!(
+                $crate::eval::test::float_slice_tracing::TestFloatSliceTracing::<$t>
+            );
+        }
+    };
+}
@@ -269,6 +269,25 @@ impl<F: Function + MathFunction> TestGradSlice<F> {
         );
     }
 
+    pub fn test_g_select() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let v = ctx.if_nonzero_else(x, y, z).unwrap();
+        let shape = F::new(&ctx, &[v]).unwrap();
+
+        let tape = shape.grad_slice_tape(Default::default());
+        assert_eq!(
+            Self::eval_xyz(&tape, &[1.0], &[2.0], &[3.0])[0],
+            Grad::new(2.0, 0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            Self::eval_xyz(&tape, &[0.0], &[2.0], &[3.0])[0],
+            Grad::new(3.0, 0.0, 0.0, 1.0)
+        );
+    }
+
     pub fn test_g_max() {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -689,6 +708,7 @@ macro_rules! grad_slice_tests {
         $crate::grad_test!(test_g_min, $t);
         $crate::grad_test!(test_g_max, $t);
         $crate::grad_test!(test_g_min_max, $t);
+        $crate::grad_test!(test_g_select, $t);
         $crate::grad_test!(test_g_not, $t);
         $crate::grad_test!(test_g_div, $t);
         $crate::grad_test!(test_g_recip, $t);
@@ -0,0 +1,339 @@
+//! Test suite for second partial derivative (Hessian) evaluation
+//!
+//! If the `eval-tests` feature is set, then this exposes a standard test suite
+//! for Hessian evaluators; otherwise, the module has no public exports.
+//!
+//! Values and first derivatives must match the VM's gradient evaluator
+//! exactly; second derivatives are checked against the symbolic Hessian from
+//! [`Context::hessian`], evaluated in double precision.
+use super::{test_args, CanonicalBinaryOp, CanonicalUnaryOp};
+use crate::{
+    context::{Context, Node},
+    eval::{BulkEvaluator, Function, FunctionHess, MathFunction, Tape},
+    types::{Grad, Hess},
+    var::Var,
+    vm::VmFunction,
+};
+
+/// Helper struct to put constrains on our `Shape` object
+pub struct TestHessSlice<F>(std::marker::PhantomData<*const F>);
+
+impl<F: FunctionHess + MathFunction> TestHessSlice<F> {
+    /// Evaluates the given node with unit partials for `x`, `y`, and `z`
+    ///
+    /// Returns results from the Hessian evaluator and from the reference
+    /// gradient evaluator.
+    fn eval_xyz(
+        ctx: &Context,
+        node: Node,
+        xs: &[f32],
+        ys: &[f32],
+        zs: &[f32],
+    ) -> (Vec<Hess>, Vec<Grad>) {
+        assert_eq!(xs.len(), ys.len());
+        assert_eq!(ys.len(), zs.len());
+        let seed = |vs: &[f32], i: usize| -> Vec<Grad> {
+            vs.iter()
+                .map(|v| match i {
+                    0 => Grad::new(*v, 1.0, 0.0, 0.0),
+                    1 => Grad::new(*v, 0.0, 1.0, 0.0),
+                    2 => Grad::new(*v, 0.0, 0.0, 1.0),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+        let args = [
+            (Var::X, seed(xs, 0)),
+            (Var::Y, seed(ys, 1)),
+            (Var::Z, seed(zs, 2)),
+        ];
+
+        let shape = F::new(ctx, &[node]).unwrap();
+        let tape = shape.hess_slice_tape(Default::default());
+        let mut vars = vec![vec![]; tape.vars().len()];
+        for (v, a) in &args {
+            if let Some(i) = tape.vars().get(v) {
+                vars[i] = a.iter().map(|g| Hess::from(*g)).collect();
+            }
+        }
+        let mut eval = F::new_hess_slice_eval();
+        let out = eval.eval(&tape, &vars).unwrap()[0].to_owned();
+
+        let shape = VmFunction::new(ctx, &[node]).unwrap();
+        let tape = shape.grad_slice_tape(Default::default());
+        let mut vars = vec![vec![]; tape.vars().len()];
+        for (v, a) in &args {
+            if let Some(i) = tape.vars().get(v) {
+                vars[i] = a.clone();
+            }
+        }
+        let mut eval = VmFunction::new_grad_slice_eval();
+        let expected = eval.eval(&tape, &vars).unwrap()[0].to_owned();
+
+        (out, expected)
+    }
+
+    /// Checks that values and first derivatives match exactly
+    fn compare_grads(out: &[Hess], expected: &[Grad], name: &str) {
+        assert_eq!(out.len(), expected.len());
+        let same = |a: f32, b: f32| a == b || (a.is_nan() && b.is_nan());
+        for (o, e) in out.iter().zip(expected) {
+            let g = o.grad();
+            assert!(
+                same(g.v, e.v)
+                    && same(g.dx, e.dx)
+                    && same(g.dy, e.dy)
+                    && same(g.dz, e.dz),
+                "gradient mismatch in '{name}': {g} != {e}"
+            );
+        }
+    }
+
+    /// Checks second derivatives against the symbolic Hessian of `node`
+    ///
+    /// `skip` is called with the position of each sample, and returns `true`
+    /// if that sample should not be checked (e.g. due to discontinuities).
+    fn compare_hessians(
+        ctx: &mut Context,
+        node: Node,
+        pos: &[[f32; 3]],
+        out: &[Hess],
+        skip: impl Fn([f32; 3]) -> bool,
+        name: &str,
+    ) {
+        let h = ctx.hessian(node, &[Var::X, Var::Y, Var::Z]).unwrap();
+        for (p, o) in pos.iter().zip(out) {
+            if skip(*p) || !o.v.is_finite() {
+                continue;
+            }
+            for (i, row) in h.iter().enumerate() {
+                for (j, n) in row.iter().enumerate() {
+                    let [x, y, z] = p.map(f64::from);
+                    let e = ctx.eval_xyz(*n, x, y, z).unwrap();
+                    let a = f64::from(o.h[i][j]);
+                    if !a.is_finite() || !e.is_finite() {
+                        continue;
+                    }
+                    let err = (a - e).abs();
+                    assert!(
+                        err < 1e-5 || err / a.abs().max(e.abs()) < 1e-4,
+                        "mismatch in h[{i}][{j}] of '{name}' at {p:?}: \
+                         {a} != {e}"
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn test_h_symmetric() {
+        // f = sin(x y) * exp(z / 3) + x / (y² + 1)
+        let mut ctx = Context::new();
+        let [x, y, z] = ctx.axes();
+        let xy = ctx.mul(x, y).unwrap();
+        let a = ctx.sin(xy).unwrap();
+        let e = ctx.div(z, 3.0).unwrap();
+        let e = ctx.exp(e).unwrap();
+        let a = ctx.mul(a, e).unwrap();
+        let y2 = ctx.square(y).unwrap();
+        let d = ctx.add(y2, 1.0).unwrap();
+        let b = ctx.div(x, d).unwrap();
+        let node = ctx.add(a, b).unwrap();
+
+        // Keep arguments small, so that sin(x y) is well-conditioned in f32
+        let args: Vec<f32> = test_args().into_iter().map(|v| v * 0.3).collect();
+        let mut ys = args.clone();
+        ys.rotate_left(5);
+        let mut zs = args.clone();
+        zs.rotate_left(11);
+        let (out, expected) = Self::eval_xyz(&ctx, node, &args, &ys, &zs);
+        Self::compare_grads(&out, &expected, "symmetric");
+
+        let pos = (0..args.len())
+            .map(|i| [args[i], ys[i], zs[i]])
+            .collect::<Vec<_>>();
+        Self::compare_hessians(
+            &mut ctx,
+            node,
+            &pos,
+            &out,
+            |_| false,
+            "symmetric",
+        );
+        for o in &out {
+            for i in 0..3 {
+                for j in 0..3 {
+                    let (a, b) = (o.h[i][j], o.h[j][i]);
+                    assert!(a == b || (a.is_nan() && b.is_nan()), "{o}");
+                }
+            }
+        }
+    }
+
+    pub fn test_h_shape_transform() {
+        use crate::shape::Shape;
+
+        // f(x, y, z) = x² y, evaluated with x and y scaled by 2
+        let mut ctx = Context::new();
+        let [x, y, _z] = ctx.axes();
+        let x2 = ctx.square(x).unwrap();
+        let node = ctx.mul(x2, y).unwrap();
+        let shape = Shape::<F>::new(&ctx, node).unwrap().apply_transform(
+            nalgebra::Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(
+                2.0, 2.0, 1.0,
+            )),
+        );
+        let tape = shape.hess_slice_tape(Default::default());
+        let mut eval = Shape::<F>::new_hess_slice_eval();
+        let seed = |v, i| {
+            let mut d = [0.0; 3];
+            d[i] = 1.0;
+            Hess::from(Grad::new(v, d[0], d[1], d[2]))
+        };
+        let out = eval
+            .eval(&tape, &[seed(1.0, 0)], &[seed(3.0, 1)], &[seed(0.0, 2)])
+            .unwrap();
+
+        // g(x, y) = (2x)² (2y) = 8 x² y
+        let o = out[0][0];
+        assert_eq!(o.v, 24.0);
+        assert_eq!([o.dx, o.dy, o.dz], [48.0, 8.0, 0.0]);
+        assert_eq!(o.h, [[48.0, 16.0, 0.0], [16.0, 0.0, 0.0], [0.0, 0.0, 0.0]]);
+    }
+
+    pub fn test_unary<C: CanonicalUnaryOp>() {
+        let args = test_args();
+        let zero = vec![0.0; args.len()];
+
+        let mut ctx = Context::new();
+        for (i, v) in [ctx.x(), ctx.y(), ctx.z()].into_iter().enumerate() {
+            let node = C::build(&mut ctx, v);
+            let (xs, ys, zs) = match i {
+                0 => (&args, &zero, &zero),
+                1 => (&zero, &args, &zero),
+                2 => (&zero, &zero, &args),
+                _ => unreachable!(),
+            };
+            let (out, expected) = Self::eval_xyz(&ctx, node, xs, ys, zs);
+            Self::compare_grads(&out, &expected, C::NAME);
+
+            let pos = (0..args.len())
+                .map(|k| [xs[k], ys[k], zs[k]])
+                .collect::<Vec<_>>();
+            Self::compare_hessians(
+                &mut ctx,
+                node,
+                &pos,
+                &out,
+                |p| C::discontinuous_at(p[i]),
+                C::NAME,
+            );
+        }
+    }
+
+    pub fn test_binary<C: CanonicalBinaryOp>() {
+        let args = test_args();
+        let zero = vec![0.0; args.len()];
+
+        let mut ctx = Context::new();
+        let (x, y) = (ctx.x(), ctx.y());
+
+        for rot in [1, 7] {
+            let mut rgsa = args.clone();
+            rgsa.rotate_left(rot);
+            let pos = (0..args.len())
+                .map(|k| [args[k], rgsa[k], 0.0])
+                .collect::<Vec<_>>();
+
+            let name = format!("{}(reg, reg)", C::NAME);
+            let node = C::build(&mut ctx, x, y);
+            let (out, expected) =
+                Self::eval_xyz(&ctx, node, &args, &rgsa, &zero);
+            Self::compare_grads(&out, &expected, &name);
+            Self::compare_hessians(
+                &mut ctx,
+                node,
+                &pos,
+                &out,
+                |p| C::discontinuous_at(p[0], p[1]),
+                &name,
+            );
+        }
+
+        let pos = (0..args.len())
+            .map(|k| [args[k], 0.0, 0.0])
+            .collect::<Vec<_>>();
+        let node = C::build(&mut ctx, x, x);
+        let (out, expected) = Self::eval_xyz(&ctx, node, &args, &zero, &zero);
+        Self::compare_grads(&out, &expected, C::NAME);
+        Self::compare_hessians(
+            &mut ctx,
+            node,
+            &pos,
+            &out,
+            |p| C::discontinuous_at(p[0], p[0]),
+            C::NAME,
+        );
+
+        for &imm in args.iter().step_by(4) {
+            let name = format!("{}(reg, imm)", C::NAME);
+            let node = C::build(&mut ctx, x, imm);
+            let (out, expected) =
+                Self::eval_xyz(&ctx, node, &args, &zero, &zero);
+            Self::compare_grads(&out, &expected, &name);
+            Self::compare_hessians(
+                &mut ctx,
+                node,
+                &pos,
+                &out,
+                |p| C::discontinuous_at(p[0], imm),
+                &name,
+            );
+
+            let name = format!("{}(imm, reg)", C::NAME);
+            let node = C::build(&mut ctx, imm, x);
+            let (out, expected) =
+                Self::eval_xyz(&ctx, node, &args, &zero, &zero);
+            Self::compare_grads(&out, &expected, &name);
+            Self::compare_hessians(
+                &mut ctx,
+                node,
+                &pos,
+                &out,
+                |p| C::discontinuous_at(imm, p[0]),
+                &name,
+            );
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! hess_test {
+    ($i:ident, $t:ty) => {
+        #[test]
+        fn $i() {
+            $crate::eval::test::hess_slice::TestHessSlice::<$t>::$i()
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! hess_slice_tests {
+    ($t:ty) => {
+        $crate::hess_test!(test_h_symmetric, $t);
+        $crate::hess_test!(test_h_shape_transform, $t);
+
+        mod h_unary {
+            use super::*;
+            $crate::all_unary_tests!(
+                $crate::eval::test::hess_slice::TestHessSlice::<$t>
+            );
+        }
+
+        mod h_binary {
+            use super::*;
+            $crate::all_binary_tests!(
+                $crate::eval::test::hess_slice::TestHessSlice::<$t>
+            );
+        }
+    };
+}
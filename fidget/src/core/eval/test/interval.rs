@@ -4,7 +4,7 @@
 //! for interval evaluators; otherwise, the module has no public exports.
 
 use super::{
-    bind_xy, bind_xyz, build_stress_fn, test_args, test_args_n,
+    bind_xy, bind_xyz, build_stress_fn, interval_test_args, test_args,
     CanonicalBinaryOp, CanonicalUnaryOp,
 };
 use crate::{
@@ -779,9 +779,8 @@ where
             .eval(&tape, &vs([-1.0, 2.0], [1.0, 2.0], [3.0, 4.0]))
             .unwrap();
 
-        // Alas, we lose the information that the conditional is correlated, so
-        // the interval naively must include 0
-        assert_eq!(out[0], [0.0, 4.0].into());
+        // The output could come from either branch
+        assert_eq!(out[0], [1.0, 4.0].into());
         assert!(data.is_none());
 
         // Confirm that simplification of the right side works
@@ -827,6 +826,114 @@ where
         assert_eq!(s_y.size(), s_z.size())
     }
 
+    pub fn test_i_select() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let v = ctx.if_nonzero_else(x, y, z).unwrap();
+
+        let shape = F::new(&ctx, &[v]).unwrap();
+        let tape = shape.interval_tape(Default::default());
+        let vs = bind_xyz(&tape);
+        let mut eval = F::new_interval_eval();
+
+        let (r, trace) = eval
+            .eval(&tape, &vs([1.0, 2.0], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        assert_eq!(r[0], [1.0, 2.0].into());
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
+
+        let (r, trace) = eval
+            .eval(&tape, &vs([-2.0, -1.0], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        assert_eq!(r[0], [1.0, 2.0].into());
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
+
+        let (r, trace) = eval
+            .eval(&tape, &vs([0.0, 0.0], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        assert_eq!(r[0], [3.0, 4.0].into());
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Right]);
+
+        let (r, trace) = eval
+            .eval(&tape, &vs([0.0, 1.0], [1.0, 2.0], [-3.0, 4.0]))
+            .unwrap();
+        assert_eq!(r[0], [-3.0, 4.0].into());
+        assert!(trace.is_none());
+
+        let (r, trace) = eval
+            .eval(&tape, &vs([f32::NAN; 2], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        assert!(r[0].lower().is_nan());
+        assert!(r[0].upper().is_nan());
+        assert!(trace.is_none());
+
+        // When the condition is proven, the other branch (and the condition
+        // itself) are removed from the tape entirely.
+        let a = ctx.square(y).unwrap();
+        let b = ctx.cos(z).unwrap();
+        let b = ctx.mul(b, z).unwrap();
+        let v = ctx.if_nonzero_else(x, a, b).unwrap();
+        let shape = F::new(&ctx, &[v]).unwrap();
+        let tape = shape.interval_tape(Default::default());
+        let (_r, trace) = eval
+            .eval(&tape, &vs([1.0, 2.0], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        let next = shape
+            .simplify(
+                trace.unwrap(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        assert_eq!(next.size(), 3); // input, square, output
+        let tape = next.interval_tape(Default::default());
+        let (r, trace) = eval
+            .eval(&tape, &vs([-1.0, 1.0], [2.0, 3.0], [f32::NAN; 2]))
+            .unwrap();
+        assert_eq!(r[0], [4.0, 9.0].into());
+        assert!(trace.is_none());
+
+        // A NaN in the branch which isn't selected is ignored
+        let dead = ctx.square(z).unwrap();
+        let dead = ctx.sub(-1.0, dead).unwrap();
+        let dead = ctx.sqrt(dead).unwrap();
+        let v = ctx.if_nonzero_else(x, y, dead).unwrap();
+        let shape = F::new(&ctx, &[v]).unwrap();
+        let tape = shape.interval_tape(Default::default());
+        let vs = bind_xyz(&tape);
+        let (r, trace) = eval
+            .eval(&tape, &vs([1.0, 2.0], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        assert_eq!(r[0], [1.0, 2.0].into());
+        let next = shape
+            .simplify(
+                trace.unwrap(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        assert_eq!(next.size(), 2); // input, output
+
+        let (r, trace) = eval
+            .eval(&tape, &vs([0.0, 1.0], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        assert!(r[0].lower().is_nan());
+        assert!(r[0].upper().is_nan());
+        assert!(trace.is_none());
+
+        let v = ctx.if_nonzero_else(x, dead, y).unwrap();
+        let shape = F::new(&ctx, &[v]).unwrap();
+        let tape = shape.interval_tape(Default::default());
+        let vs = bind_xyz(&tape);
+        let (r, trace) = eval
+            .eval(&tape, &vs([0.0, 0.0], [1.0, 2.0], [3.0, 4.0]))
+            .unwrap();
+        assert_eq!(r[0], [1.0, 2.0].into());
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Right]);
+    }
+
     pub fn test_i_max_imm() {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -908,22 +1015,8 @@ where
         }
     }
 
-    pub fn interval_test_args() -> Vec<Interval> {
-        let args = test_args_n(8);
-        let mut out = vec![];
-        for &lower in &args {
-            for &size in &args {
-                if size >= 0.0 {
-                    out.push(Interval::new(lower, lower + size));
-                }
-            }
-        }
-        out.push(Interval::new(f32::NAN, f32::NAN));
-        out
-    }
-
     pub fn test_unary<C: CanonicalUnaryOp>() {
-        let args = Self::interval_test_args();
+        let args = interval_test_args();
 
         let mut ctx = Context::new();
         let mut eval = F::new_interval_eval();
@@ -1007,8 +1100,9 @@ where
         }
     }
 
-    pub fn test_binary_reg_reg<C: CanonicalBinaryOp>() {
-        let args = Self::interval_test_args();
+    /// Tests a binary operation on two distinct variables
+    pub fn test_binary_reg_reg_distinct<C: CanonicalBinaryOp>() {
+        let args = interval_test_args();
 
         let mut ctx = Context::new();
         let va = Var::new();
@@ -1055,7 +1149,20 @@ where
                 );
             }
         }
+    }
 
+    pub fn test_binary_reg_reg<C: CanonicalBinaryOp>() {
+        Self::test_binary_reg_reg_distinct::<C>();
+
+        let args = interval_test_args();
+
+        let mut ctx = Context::new();
+        let va = Var::new();
+        let a = ctx.var(va);
+
+        let name = format!("{}(reg, reg)", C::NAME);
+        let mut tape_data = None;
+        let mut eval = F::new_interval_eval();
         for &lhs in args.iter() {
             let node = C::build(&mut ctx, a, a);
 
@@ -1090,7 +1197,7 @@ where
 
     pub fn test_binary_reg_imm<C: CanonicalBinaryOp>() {
         let values = test_args();
-        let args = Self::interval_test_args();
+        let args = interval_test_args();
 
         let mut ctx = Context::new();
         let va = Var::new();
@@ -1122,7 +1229,7 @@ where
 
     pub fn test_binary_imm_reg<C: CanonicalBinaryOp>() {
         let values = test_args();
-        let args = Self::interval_test_args();
+        let args = interval_test_args();
 
         let mut ctx = Context::new();
         let va = Var::new();
@@ -1191,6 +1298,7 @@ macro_rules! interval_tests {
         $crate::interval_test!(test_i_max_imm, $t);
         $crate::interval_test!(test_i_and, $t);
         $crate::interval_test!(test_i_or, $t);
+        $crate::interval_test!(test_i_select, $t);
         $crate::interval_test!(test_i_compare, $t);
         $crate::interval_test!(test_i_modulo, $t);
         $crate::interval_test!(test_i_simplify, $t);
@@ -0,0 +1,281 @@
+//! Test suite for bulk interval evaluation
+//!
+//! If the `eval-tests` feature is set, then this exposes a standard test suite
+//! for interval slice evaluators; otherwise, the module has no public exports.
+//!
+//! Each interval's result and trace are checked against the same function's
+//! single interval evaluator.
+use super::{
+    interval_test_args, test_args, CanonicalBinaryOp, CanonicalUnaryOp,
+};
+use crate::{
+    context::{Context, Node},
+    eval::{
+        Function, MathFunction, MultiTracingEvaluator, Tape, Trace,
+        TracingEvaluator,
+    },
+    shape::Shape,
+    types::Interval,
+    var::Var,
+    vm::Choice,
+};
+
+/// Helper struct to put constrains on our `Shape` object
+pub struct TestIntervalSlice<F>(std::marker::PhantomData<*const F>);
+
+impl<F> TestIntervalSlice<F>
+where
+    F: Function + MathFunction,
+{
+    /// Evaluates the given node for many `(x, y)` intervals
+    ///
+    /// Checks that results and traces match the single interval evaluator
+    /// exactly, then returns them.
+    fn eval_xy(
+        ctx: &Context,
+        node: Node,
+        xs: &[Interval],
+        ys: &[Interval],
+        name: &str,
+    ) -> (Vec<Interval>, Vec<Option<Vec<Choice>>>) {
+        assert_eq!(xs.len(), ys.len());
+        let args = [(Var::X, xs), (Var::Y, ys)];
+
+        let shape = F::new(ctx, &[node]).unwrap();
+        let tape = shape.interval_slice_tape(Default::default());
+        let mut vars = vec![[].as_slice(); tape.vars().len()];
+        for (v, a) in &args {
+            if let Some(i) = tape.vars().get(v) {
+                vars[i] = a;
+            }
+        }
+        let mut eval = F::new_interval_slice_eval();
+        let (out, traces) = eval.eval(&tape, &vars).unwrap();
+        let out = out[0].to_owned();
+        let traces = traces
+            .iter()
+            .map(|t| t.map(|t| t.choices().to_owned()))
+            .collect::<Vec<_>>();
+
+        // Tapes without any variables (e.g. if the node was folded into a
+        // constant) produce empty results, because there's nothing to set the
+        // slice length.
+        if tape.vars().is_empty() {
+            assert!(out.is_empty());
+        } else {
+            assert_eq!(out.len(), xs.len());
+        }
+        assert_eq!(traces.len(), out.len());
+
+        let tape = shape.interval_tape(Default::default());
+        let mut eval = F::new_interval_eval();
+        for (k, (o, t)) in out.iter().zip(&traces).enumerate() {
+            let mut vs = vec![Interval::from(0.0); tape.vars().len()];
+            for (v, a) in &args {
+                if let Some(i) = tape.vars().get(v) {
+                    vs[i] = a[k];
+                }
+            }
+            let (e, trace) = eval.eval(&tape, &vs).unwrap();
+            let e = e[0];
+            assert!(
+                (o.lower().to_bits() == e.lower().to_bits()
+                    && o.upper().to_bits() == e.upper().to_bits())
+                    || (o.has_nan() && e.has_nan()),
+                "value mismatch in '{name}' at {vs:?}: {o} != {e}"
+            );
+            assert_eq!(
+                t.as_deref(),
+                trace.map(|t| t.choices()),
+                "trace mismatch in '{name}' at {vs:?}"
+            );
+        }
+
+        (out, traces)
+    }
+
+    pub fn test_is_min() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let min = ctx.min(x, y).unwrap();
+
+        let xs = [Interval::new(0.0, 1.0), Interval::new(-3.0, -2.0)];
+        let ys = [Interval::new(2.0, 3.0), Interval::new(-1.0, 5.0)];
+        let (out, traces) = Self::eval_xy(&ctx, min, &xs, &ys, "min");
+        assert_eq!(out, xs);
+        assert_eq!(
+            traces,
+            [Some(vec![Choice::Left]), Some(vec![Choice::Left])]
+        );
+
+        let (out, traces) = Self::eval_xy(&ctx, min, &ys, &xs, "min");
+        assert_eq!(out, xs);
+        assert_eq!(
+            traces,
+            [Some(vec![Choice::Right]), Some(vec![Choice::Right])]
+        );
+
+        // Each lane is unambiguous, but they disagree
+        let ys = [Interval::new(2.0, 3.0), Interval::new(-5.0, -4.0)];
+        let (out, traces) = Self::eval_xy(&ctx, min, &xs, &ys, "min");
+        assert_eq!(out, [xs[0], ys[1]]);
+        assert_eq!(
+            traces,
+            [Some(vec![Choice::Left]), Some(vec![Choice::Right])]
+        );
+
+        // One lane is ambiguous
+        let ys = [Interval::new(2.0, 3.0), Interval::new(-2.5, 0.0)];
+        let (out, traces) = Self::eval_xy(&ctx, min, &xs, &ys, "min");
+        assert_eq!(out, [xs[0], Interval::new(-3.0, -2.0)]);
+        assert_eq!(traces, [Some(vec![Choice::Left]), None]);
+    }
+
+    pub fn test_is_empty() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let max = ctx.max(x, y).unwrap();
+        let (out, traces) = Self::eval_xy(&ctx, max, &[], &[], "empty");
+        assert!(out.is_empty());
+        assert!(traces.is_empty());
+    }
+
+    pub fn test_is_simplify() {
+        // Union of three circles, evaluated on four cells: two of them touch
+        // the left circle, and two touch the right circle
+        let mut ctx = Context::new();
+        let [x, y, _z] = ctx.axes();
+        let mut circle = |cx: f32, cy: f32| {
+            let dx = ctx.sub(x, cx).unwrap();
+            let dy = ctx.sub(y, cy).unwrap();
+            let dx2 = ctx.square(dx).unwrap();
+            let dy2 = ctx.square(dy).unwrap();
+            let r = ctx.add(dx2, dy2).unwrap();
+            let r = ctx.sqrt(r).unwrap();
+            ctx.sub(r, 0.5).unwrap()
+        };
+        let a = circle(-1.0, 0.0);
+        let b = circle(1.0, 0.0);
+        let c = circle(0.0, 3.0);
+        let ab = ctx.min(a, b).unwrap();
+        let node = ctx.min(ab, c).unwrap();
+
+        let shape = Shape::<F>::new(&ctx, node).unwrap();
+        let tape = shape.interval_slice_tape(Default::default());
+        let mut eval = Shape::<F>::new_interval_slice_eval();
+
+        let xs = [[-1.4, -1.0], [-1.0, -0.6], [0.6, 1.0], [1.0, 1.4]]
+            .map(Interval::from);
+        let ys = [Interval::new(-0.3, 0.3); 4];
+        let zs = [Interval::from(0.0); 4];
+        let (out, traces) = eval.eval(&tape, &xs, &ys, &zs).unwrap();
+        let out = out[0].to_owned();
+        let traces = traces
+            .iter()
+            .map(|t| t.unwrap().clone())
+            .collect::<Vec<_>>();
+        for t in &traces {
+            assert_eq!(t.choices().len(), 2);
+            assert!(t.choices().iter().all(|c| *c != Choice::Both));
+        }
+        assert!(traces[0] == traces[1]);
+        assert!(traces[2] == traces[3]);
+        assert!(traces[0] != traces[2]);
+
+        // Each cell gets the same result from its own simplified shape
+        for (i, t) in traces.iter().enumerate() {
+            let next = shape
+                .simplify(t, Default::default(), &mut Default::default())
+                .unwrap();
+            assert!(next.size() < shape.size());
+
+            let tape = next.interval_slice_tape(Default::default());
+            let (simplified, traces) = eval
+                .eval(&tape, &xs[i..=i], &ys[i..=i], &zs[i..=i])
+                .unwrap();
+            assert_eq!(out[i], simplified[0][0]);
+            assert!(traces.get(0).is_none());
+        }
+    }
+
+    pub fn test_unary<C: CanonicalUnaryOp>() {
+        let args = interval_test_args();
+        let zero = vec![Interval::from(0.0); args.len()];
+
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let node = C::build(&mut ctx, x);
+        Self::eval_xy(&ctx, node, &args, &zero, C::NAME);
+    }
+
+    pub fn test_binary<C: CanonicalBinaryOp>() {
+        let args = interval_test_args();
+
+        let mut ctx = Context::new();
+        let (x, y) = (ctx.x(), ctx.y());
+
+        // Rotating the arguments gives a mix of choices
+        let name = format!("{}(reg, reg)", C::NAME);
+        let node = C::build(&mut ctx, x, y);
+        for rot in [0, 1, 7] {
+            let mut rgsa = args.clone();
+            rgsa.rotate_left(rot);
+            Self::eval_xy(&ctx, node, &args, &rgsa, &name);
+        }
+
+        // Narrow, well-separated intervals give uniform choices
+        let lo = (0..8)
+            .map(|i| Interval::new(i as f32 - 10.0, i as f32 - 9.5))
+            .collect::<Vec<_>>();
+        let hi = (0..8)
+            .map(|i| Interval::new(i as f32 + 2.0, i as f32 + 2.5))
+            .collect::<Vec<_>>();
+        Self::eval_xy(&ctx, node, &lo, &hi, &name);
+        Self::eval_xy(&ctx, node, &hi, &lo, &name);
+
+        for &imm in test_args().iter().step_by(4) {
+            let name = format!("{}(reg, imm)", C::NAME);
+            let node = C::build(&mut ctx, x, imm);
+            Self::eval_xy(&ctx, node, &args, &args, &name);
+
+            let name = format!("{}(imm, reg)", C::NAME);
+            let node = C::build(&mut ctx, imm, x);
+            Self::eval_xy(&ctx, node, &args, &args, &name);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! interval_slice_test {
+    ($i:ident, $t:ty) => {
+        #[test]
+        fn $i() {
+            $crate::eval::test::interval_slice::TestIntervalSlice::<$t>::$i()
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! interval_slice_tests {
+    ($t:ty) => {
+        $crate::interval_slice_test!(test_is_min, $t);
+        $crate::interval_slice_test!(test_is_empty, $t);
+        $crate::interval_slice_test!(test_is_simplify, $t);
+
+        mod is_unary {
+            use super::*;
+            $crate::all_unary_tests!(
+                $crate::eval::test::interval_slice::TestIntervalSlice::<$t>
+            );
+        }
+
+        mod is_binary {
+            use super::*;
+            $crate::all_binary_tests!(
+                $crate::eval::test::interval_slice::TestIntervalSlice::<$t>
+            );
+        }
+    };
+}
@@ -1,7 +1,11 @@
 //! Test suites for each evaluator type
+pub mod dual_slice;
 pub mod float_slice;
+pub mod float_slice_tracing;
 pub mod grad_slice;
+pub mod hess_slice;
 pub mod interval;
+pub mod interval_slice;
 pub mod point;
 
 // Internal-only tests
@@ -10,6 +14,7 @@ mod symbolic_deriv;
 use crate::{
     context::{Context, IntoNode, Node},
     eval::Tape,
+    types::Interval,
     var::Var,
 };
 
@@ -63,6 +68,21 @@ fn test_args() -> Vec<f32> {
     test_args_n(32)
 }
 
+/// Builds a set of intervals from [`test_args_n`], including an all-NaN one
+pub(crate) fn interval_test_args() -> Vec<Interval> {
+    let args = test_args_n(8);
+    let mut out = vec![];
+    for &lower in &args {
+        for &size in &args {
+            if size >= 0.0 {
+                out.push(Interval::new(lower, lower + size));
+            }
+        }
+    }
+    out.push(Interval::new(f32::NAN, f32::NAN));
+    out
+}
+
 fn bind_xy<T: Tape, V, G: Into<V>>(tape: &T) -> Box<dyn Fn(G, G) -> [V; 2]> {
     let vars = tape.vars();
     let ix = vars[&Var::X];
@@ -262,6 +282,22 @@ pub mod canonical {
     declare_canonical_unary!(Context::ceil, |a| a.ceil());
     declare_canonical_unary!(Context::round, |a| a.round());
     declare_canonical_unary!(Context::not, |a| (a == 0.0).into(), |a| a == 0.0);
+    declare_canonical_unary!(Context::sinh, |a| a.sinh());
+    declare_canonical_unary!(Context::cosh, |a| a.cosh());
+    declare_canonical_unary!(Context::tanh, |a| a.tanh());
+    declare_canonical_unary!(
+        Context::sign,
+        |a| if a > 0.0 {
+            1.0
+        } else if a < 0.0 {
+            -1.0
+        } else {
+            a
+        },
+        |a| a == 0.0
+    );
+    declare_canonical_unary!(Context::cbrt, |a| a.cbrt());
+    declare_canonical_unary!(Context::log2, |a| a.log2());
 
     declare_canonical_binary!(Context::add, |a, b| a + b);
     declare_canonical_binary!(Context::sub, |a, b| a - b);
@@ -320,6 +356,11 @@ pub mod canonical {
         |a, _b| a == 0.0 // discontinuity, because either side snaps to a
     );
     declare_canonical_binary!(Context::atan2, |y, x| y.atan2(x));
+    declare_canonical_binary!(
+        Context::pow,
+        |a, b| a.powf(b),
+        |a, _b| a == 0.0 // fractional powers have unbounded slope at zero
+    );
 }
 
 #[macro_export]
@@ -362,6 +403,12 @@ macro_rules! all_unary_tests {
         $crate::one_unary_test!($tester, ceil);
         $crate::one_unary_test!($tester, round);
         $crate::one_unary_test!($tester, sqrt);
+        $crate::one_unary_test!($tester, sinh);
+        $crate::one_unary_test!($tester, cosh);
+        $crate::one_unary_test!($tester, tanh);
+        $crate::one_unary_test!($tester, sign);
+        $crate::one_unary_test!($tester, cbrt);
+        $crate::one_unary_test!($tester, log2);
     };
 }
 
@@ -379,5 +426,6 @@ macro_rules! all_binary_tests {
         $crate::one_binary_test!($tester, modulo);
         $crate::one_binary_test!($tester, and);
         $crate::one_binary_test!($tester, or);
+        $crate::one_binary_test!($tester, pow);
     };
 }
@@ -207,6 +207,110 @@ where
         assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
     }
 
+    pub fn test_p_select() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let v = ctx.if_nonzero_else(x, y, z).unwrap();
+
+        let shape = F::new(&ctx, &[v]).unwrap();
+        let tape = shape.point_tape(Default::default());
+        let vs = bind_xyz(&tape);
+        let mut eval = F::new_point_eval();
+
+        let (r, trace) = eval.eval(&tape, &vs(1.0, 2.0, 3.0)).unwrap();
+        assert_eq!(r[0], 2.0);
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
+
+        let (r, trace) = eval.eval(&tape, &vs(-0.5, 2.0, 3.0)).unwrap();
+        assert_eq!(r[0], 2.0);
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
+
+        let (r, trace) = eval.eval(&tape, &vs(0.0, 2.0, 3.0)).unwrap();
+        assert_eq!(r[0], 3.0);
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Right]);
+
+        let (r, trace) = eval.eval(&tape, &vs(0.0, f32::NAN, 3.0)).unwrap();
+        assert_eq!(r[0], 3.0);
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Right]);
+
+        let (r, trace) = eval.eval(&tape, &vs(f32::NAN, 2.0, 3.0)).unwrap();
+        assert_eq!(r[0], 2.0);
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
+
+        // Immediate branches are copied into registers
+        let v = ctx.if_nonzero_else(x, 1.5, y).unwrap();
+        let shape = F::new(&ctx, &[v]).unwrap();
+        let tape = shape.point_tape(Default::default());
+        let vs = bind_xy(&tape);
+
+        let (r, trace) = eval.eval(&tape, &vs(1.0, 2.0)).unwrap();
+        assert_eq!(r[0], 1.5);
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
+
+        let (r, trace) = eval.eval(&tape, &vs(0.0, 2.0)).unwrap();
+        assert_eq!(r[0], 2.0);
+        assert_eq!(trace.unwrap().as_ref(), &[Choice::Right]);
+
+        let next = shape
+            .simplify(
+                trace.unwrap(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        assert_eq!(next.size(), 2); // input, output
+        let tape = next.point_tape(Default::default());
+        let (r, trace) = eval.eval(&tape, &vs(1.0, 2.0)).unwrap();
+        assert_eq!(r[0], 2.0);
+        assert!(trace.is_none());
+    }
+
+    pub fn test_p_select_stress() {
+        let mut ctx = Context::new();
+        let axes = ctx.axes();
+
+        // Build a set of values which all stay alive until the end of the
+        // tape, then select between them, to put pressure on the register
+        // allocator.
+        let n = 32;
+        let mut values = vec![];
+        for i in 0..n {
+            let v = ctx.mul(i as f32 / 4.0, axes[i % 3]).unwrap();
+            values.push(v);
+        }
+        let mut sum = ctx.constant(0.0);
+        for i in 0..n {
+            let c = ctx.floor(values[i]).unwrap();
+            let c = ctx.modulo(c, 2.0).unwrap();
+            let s = ctx
+                .if_nonzero_else(c, values[(i + 1) % n], values[(i + 5) % n])
+                .unwrap();
+            sum = ctx.add(sum, s).unwrap();
+        }
+        for v in values.iter().rev() {
+            sum = ctx.add(sum, *v).unwrap();
+        }
+
+        let shape = F::new(&ctx, &[sum]).unwrap();
+        let mut eval = F::new_point_eval();
+        let tape = shape.point_tape(Default::default());
+        let vs = bind_xyz(&tape);
+        // Points are chosen so that no value lands near an integer, where f32 and
+        // f64 evaluation could disagree about the floor.
+        for (x, y, z) in [
+            (0.13, -0.37, 2.71),
+            (1.73, -0.41, 3.17),
+            (-2.53, 0.91, 0.33),
+        ] {
+            let (r, _trace) = eval.eval(&tape, &vs(x, y, z)).unwrap();
+            let q = ctx.eval_xyz(sum, x as f64, y as f64, z as f64).unwrap();
+            let err = (r[0] as f64 - q).abs();
+            assert!(err < 1e-3, "mismatch at ({x}, {y}, {z}): {} != {q}", r[0]);
+        }
+    }
+
     pub fn test_p_sin() {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -620,6 +724,14 @@ where
         let (out, _trace) = eval.eval(&tape, &vs(1.0, 2.0)).unwrap();
         assert_eq!(out[0], 1.0);
         assert_eq!(out[1], 2.0);
+
+        // Constant outputs must not clobber other outputs
+        let c = ctx.constant(2.5);
+        let shape = F::new(&ctx, &[a, b, c]).unwrap();
+        let tape = shape.point_tape(Default::default());
+        let vs = bind_xy(&tape);
+        let (out, _trace) = eval.eval(&tape, &vs(1.0, 2.0)).unwrap();
+        assert_eq!(out, &[1.0, 2.0, 2.5]);
     }
 }
 
@@ -644,6 +756,8 @@ macro_rules! point_tests {
         $crate::point_test!(test_p_sin, $t);
         $crate::point_test!(test_p_and, $t);
         $crate::point_test!(test_p_or, $t);
+        $crate::point_test!(test_p_select, $t);
+        $crate::point_test!(test_p_select_stress, $t);
         $crate::point_test!(basic_interpreter, $t);
         $crate::point_test!(test_push, $t);
         $crate::point_test!(test_basic, $t);
@@ -155,5 +155,138 @@ impl TestSymbolicDerivs {
     }
 }
 
-crate::all_unary_tests!(TestSymbolicDerivs);
-crate::all_binary_tests!(TestSymbolicDerivs);
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Builds a smooth function of X, Y, and Z which exercises many opcodes
+    fn build_smooth(ctx: &mut Context) -> crate::context::Node {
+        let [x, y, z] = ctx.axes();
+        let xy = ctx.mul(x, y).unwrap();
+        let a = ctx.sin(xy).unwrap();
+
+        let x2 = ctx.square(x).unwrap();
+        let z2 = ctx.square(z).unwrap();
+        let r = ctx.add(x2, z2).unwrap();
+        let r = ctx.add(r, 1.0).unwrap();
+        let r = ctx.sqrt(r).unwrap();
+        let e = ctx.mul(y, -0.3).unwrap();
+        let e = ctx.exp(e).unwrap();
+        let b = ctx.mul(r, e).unwrap();
+        let c = ctx.cos(z).unwrap();
+        let c = ctx.add(c, 2.0).unwrap();
+        let b = ctx.div(b, c).unwrap();
+
+        let p = ctx.pow(x, 3.0).unwrap();
+        let p = ctx.mul(p, y).unwrap();
+        let x3 = ctx.add(x, 3.0).unwrap();
+        let t = ctx.atan2(y, x3).unwrap();
+        let t = ctx.tanh(t).unwrap();
+
+        let out = ctx.add(a, b).unwrap();
+        let out = ctx.add(out, p).unwrap();
+        ctx.sub(out, t).unwrap()
+    }
+
+    /// Points at which to compare symbolic and numeric derivatives
+    const POINTS: [[f64; 3]; 4] = [
+        [0.3, -0.7, 1.1],
+        [-1.2, 0.4, -0.5],
+        [0.9, 1.3, 0.2],
+        [-0.4, -1.1, 2.3],
+    ];
+
+    /// Step size for central differences
+    const H: f64 = 1e-5;
+
+    fn check_close(a: f64, b: f64, what: &str) {
+        let err = (a - b).abs();
+        assert!(
+            err < 1e-5 * a.abs().max(1.0),
+            "mismatch in {what}: {a} != {b}"
+        );
+    }
+
+    fn eval_at(ctx: &Context, n: crate::context::Node, p: [f64; 3]) -> f64 {
+        ctx.eval_xyz(n, p[0], p[1], p[2]).unwrap()
+    }
+
+    #[test]
+    fn test_gradient_finite_differences() {
+        let mut ctx = Context::new();
+        let f = build_smooth(&mut ctx);
+        let vars = [Var::X, Var::Y, Var::Z];
+        let g = ctx.gradient(f, &vars).unwrap();
+        assert_eq!(g.len(), 3);
+
+        for p in POINTS {
+            for (i, g) in g.iter().enumerate() {
+                let mut lo = p;
+                let mut hi = p;
+                lo[i] -= H;
+                hi[i] += H;
+                let fd =
+                    (eval_at(&ctx, f, hi) - eval_at(&ctx, f, lo)) / (2.0 * H);
+                check_close(
+                    eval_at(&ctx, *g, p),
+                    fd,
+                    &format!("d/d{:?}", vars[i]),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_gradient_matches_deriv() {
+        let mut ctx = Context::new();
+        let f = build_smooth(&mut ctx);
+        let vars = [Var::Z, Var::X, Var::Y];
+        let g = ctx.gradient(f, &vars).unwrap();
+        for (v, g) in vars.iter().zip(&g) {
+            assert_eq!(ctx.deriv(f, *v).unwrap(), *g);
+        }
+
+        // Differentiating again doesn't create any new nodes
+        let len = ctx.len();
+        let g2 = ctx.gradient(f, &vars).unwrap();
+        assert_eq!(g, g2);
+        assert_eq!(ctx.len(), len);
+
+        assert!(ctx.gradient(f, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hessian_finite_differences() {
+        let mut ctx = Context::new();
+        let f = build_smooth(&mut ctx);
+        let vars = [Var::X, Var::Y, Var::Z];
+        let g = ctx.gradient(f, &vars).unwrap();
+        let h = ctx.hessian(f, &vars).unwrap();
+        assert_eq!(h.len(), 3);
+
+        for p in POINTS {
+            for i in 0..3 {
+                assert_eq!(h[i].len(), 3);
+                for j in 0..3 {
+                    // Differentiate the symbolic gradient numerically
+                    let mut lo = p;
+                    let mut hi = p;
+                    lo[j] -= H;
+                    hi[j] += H;
+                    let fd = (eval_at(&ctx, g[i], hi)
+                        - eval_at(&ctx, g[i], lo))
+                        / (2.0 * H);
+                    let v = eval_at(&ctx, h[i][j], p);
+                    let what = format!("d²/d{:?}d{:?}", vars[i], vars[j]);
+                    check_close(v, fd, &what);
+
+                    // The Hessian should be symmetric
+                    check_close(v, eval_at(&ctx, h[j][i], p), &what);
+                }
+            }
+        }
+    }
+
+    crate::all_unary_tests!(TestSymbolicDerivs);
+    crate::all_binary_tests!(TestSymbolicDerivs);
+}
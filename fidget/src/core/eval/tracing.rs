@@ -56,4 +56,5 @@ pub trait TracingEvaluator: Default {
 }
 
 /// Tuple of tracing evaluation result
-type TracingResult<'a, Data, Trace> = (&'a [Data], Option<&'a Trace>);
+pub(crate) type TracingResult<'a, Data, Trace> =
+    (&'a [Data], Option<&'a Trace>);
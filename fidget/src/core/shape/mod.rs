@@ -28,13 +28,19 @@
 
 use crate::{
     context::{Context, Node, Tree},
-    eval::{BulkEvaluator, Function, MathFunction, Tape, TracingEvaluator},
-    types::{Grad, Interval},
+    eval::{
+        BulkEvaluator, BulkOutput, BulkTracingEvaluator, BulkTracingResult,
+        Function, FunctionF64, FunctionHess, MathFunction,
+        MultiTracingEvaluator, MultiTracingResult, Tape, TracingEvaluator,
+        TracingResult,
+    },
+    types::{Grad, GradF64, Hess, Interval, IntervalF64},
     var::{Var, VarIndex, VarMap},
     Error,
 };
 use nalgebra::{Matrix4, Point3};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// A shape represents an implicit surface
 ///
@@ -53,6 +59,9 @@ pub struct Shape<F> {
 
     /// Optional transform to apply to the shape
     transform: Option<Matrix4<f32>>,
+
+    /// Optional names for each output
+    names: Option<Arc<[String]>>,
 }
 
 impl<F: Function + Clone> Shape<F> {
@@ -72,6 +81,15 @@ impl<F: Function + Clone> Shape<F> {
         }
     }
 
+    /// Builds a new interval slice evaluator
+    pub fn new_interval_slice_eval(
+    ) -> ShapeMultiTracingEval<F::IntervalSliceEval> {
+        ShapeMultiTracingEval {
+            eval: F::IntervalSliceEval::default(),
+            scratch: vec![],
+        }
+    }
+
     /// Builds a new float slice evaluator
     pub fn new_float_slice_eval() -> ShapeBulkEval<F::FloatSliceEval> {
         ShapeBulkEval {
@@ -88,6 +106,15 @@ impl<F: Function + Clone> Shape<F> {
         }
     }
 
+    /// Builds a new tracing float slice evaluator
+    pub fn new_float_slice_tracing_eval(
+    ) -> ShapeBulkTracingEval<F::FloatSliceTracingEval> {
+        ShapeBulkTracingEval {
+            eval: F::FloatSliceTracingEval::default(),
+            scratch: vec![],
+        }
+    }
+
     /// Returns an evaluation tape for a point evaluator
     pub fn point_tape(
         &self,
@@ -118,6 +145,21 @@ impl<F: Function + Clone> Shape<F> {
         }
     }
 
+    /// Returns an evaluation tape for an interval slice evaluator
+    pub fn interval_slice_tape(
+        &self,
+        storage: F::TapeStorage,
+    ) -> ShapeTape<<F::IntervalSliceEval as MultiTracingEvaluator>::Tape> {
+        let tape = self.f.interval_slice_tape(storage);
+        let vars = tape.vars();
+        let axes = self.axes.map(|v| vars.get(&v));
+        ShapeTape {
+            tape,
+            axes,
+            transform: self.transform,
+        }
+    }
+
     /// Returns an evaluation tape for a float slice evaluator
     pub fn float_slice_tape(
         &self,
@@ -148,6 +190,22 @@ impl<F: Function + Clone> Shape<F> {
         }
     }
 
+    /// Returns an evaluation tape for a tracing float slice evaluator
+    pub fn float_slice_tracing_tape(
+        &self,
+        storage: F::TapeStorage,
+    ) -> ShapeTape<<F::FloatSliceTracingEval as BulkTracingEvaluator>::Tape>
+    {
+        let tape = self.f.float_slice_tracing_tape(storage);
+        let vars = tape.vars();
+        let axes = self.axes.map(|v| vars.get(&v));
+        ShapeTape {
+            tape,
+            axes,
+            transform: self.transform,
+        }
+    }
+
     /// Computes a simplified tape using the given trace, and reusing storage
     pub fn simplify(
         &self,
@@ -163,6 +221,7 @@ impl<F: Function + Clone> Shape<F> {
             f,
             axes: self.axes,
             transform: self.transform,
+            names: self.names.clone(),
         })
     }
 
@@ -183,6 +242,126 @@ impl<F: Function + Clone> Shape<F> {
     }
 }
 
+impl<F: FunctionF64 + Clone> Shape<F> {
+    /// Builds a new double-precision point evaluator
+    pub fn new_point_eval_f64() -> ShapeTracingEval<F::PointEvalF64> {
+        ShapeTracingEval {
+            eval: F::PointEvalF64::default(),
+            scratch: vec![],
+        }
+    }
+
+    /// Builds a new double-precision interval evaluator
+    pub fn new_interval_eval_f64() -> ShapeTracingEval<F::IntervalEvalF64> {
+        ShapeTracingEval {
+            eval: F::IntervalEvalF64::default(),
+            scratch: vec![],
+        }
+    }
+
+    /// Builds a new double-precision float slice evaluator
+    pub fn new_float_slice_eval_f64() -> ShapeBulkEval<F::FloatSliceEvalF64> {
+        ShapeBulkEval {
+            eval: F::FloatSliceEvalF64::default(),
+            scratch: vec![],
+        }
+    }
+
+    /// Builds a new double-precision gradient slice evaluator
+    pub fn new_grad_slice_eval_f64() -> ShapeBulkEval<F::GradSliceEvalF64> {
+        ShapeBulkEval {
+            eval: F::GradSliceEvalF64::default(),
+            scratch: vec![],
+        }
+    }
+
+    /// Returns an evaluation tape for a double-precision point evaluator
+    pub fn point_tape_f64(
+        &self,
+        storage: F::TapeStorage,
+    ) -> ShapeTape<<F::PointEvalF64 as TracingEvaluator>::Tape> {
+        let tape = self.f.point_tape_f64(storage);
+        let vars = tape.vars();
+        let axes = self.axes.map(|v| vars.get(&v));
+        ShapeTape {
+            tape,
+            axes,
+            transform: self.transform,
+        }
+    }
+
+    /// Returns an evaluation tape for a double-precision interval evaluator
+    pub fn interval_tape_f64(
+        &self,
+        storage: F::TapeStorage,
+    ) -> ShapeTape<<F::IntervalEvalF64 as TracingEvaluator>::Tape> {
+        let tape = self.f.interval_tape_f64(storage);
+        let vars = tape.vars();
+        let axes = self.axes.map(|v| vars.get(&v));
+        ShapeTape {
+            tape,
+            axes,
+            transform: self.transform,
+        }
+    }
+
+    /// Returns an evaluation tape for a double-precision float slice evaluator
+    pub fn float_slice_tape_f64(
+        &self,
+        storage: F::TapeStorage,
+    ) -> ShapeTape<<F::FloatSliceEvalF64 as BulkEvaluator>::Tape> {
+        let tape = self.f.float_slice_tape_f64(storage);
+        let vars = tape.vars();
+        let axes = self.axes.map(|v| vars.get(&v));
+        ShapeTape {
+            tape,
+            axes,
+            transform: self.transform,
+        }
+    }
+
+    /// Returns an evaluation tape for a double-precision gradient slice
+    /// evaluator
+    pub fn grad_slice_tape_f64(
+        &self,
+        storage: F::TapeStorage,
+    ) -> ShapeTape<<F::GradSliceEvalF64 as BulkEvaluator>::Tape> {
+        let tape = self.f.grad_slice_tape_f64(storage);
+        let vars = tape.vars();
+        let axes = self.axes.map(|v| vars.get(&v));
+        ShapeTape {
+            tape,
+            axes,
+            transform: self.transform,
+        }
+    }
+}
+
+impl<F: FunctionHess + Clone> Shape<F> {
+    /// Builds a new Hessian slice evaluator
+    pub fn new_hess_slice_eval() -> ShapeBulkEval<F::HessSliceEval> {
+        ShapeBulkEval {
+            eval: F::HessSliceEval::default(),
+            scratch: vec![],
+        }
+    }
+
+    /// Returns an evaluation tape for a Hessian slice evaluator
+    pub fn hess_slice_tape(
+        &self,
+        storage: F::TapeStorage,
+    ) -> ShapeTape<<F::HessSliceEval as BulkEvaluator>::Tape> {
+        let tape = self.f.hess_slice_tape(storage);
+        let vars = tape.vars();
+        let axes = self.axes.map(|v| vars.get(&v));
+        ShapeTape {
+            tape,
+            axes,
+            transform: self.transform,
+        }
+    }
+}
+
 impl<F> Shape<F> {
     /// Borrows the inner [`Function`] object
     pub fn inner(&self) -> &F {
@@ -200,8 +379,26 @@ impl<F> Shape<F> {
             f,
             axes,
             transform: None,
+            names: None,
         }
     }
+
+    /// Returns the index of the output with the given name
+    ///
+    /// This is only available for shapes built with [`Shape::new_named`] (or
+    /// [`Shape::new_named_with_axes`]); other shapes return `None`.
+    pub fn output_index(&self, name: &str) -> Option<usize> {
+        self.names.as_ref()?.iter().position(|n| n == name)
+    }
+
+    /// Returns the name of each output, in evaluation order
+    ///
+    /// This is only available for shapes built with [`Shape::new_named`] (or
+    /// [`Shape::new_named_with_axes`]); other shapes return `None`.
+    pub fn output_names(&self) -> Option<&[String]> {
+        self.names.as_deref()
+    }
+
     /// Returns a shape with the given transform applied
     pub fn apply_transform(mut self, mat: Matrix4<f32>) -> Self {
         if let Some(prev) = self.transform.as_mut() {
@@ -334,14 +531,101 @@ impl<F: MathFunction> Shape<F> {
         node: Node,
         axes: [Var; 3],
     ) -> Result<Self, Error> {
-        let f = F::new(ctx, &[node])?;
+        Self::new_multi_with_axes(ctx, &[node], axes)
+    }
+
+    /// Builds a new multi-output shape with the given axes
+    ///
+    /// All outputs are evaluated in a single pass over a shared tape, with
+    /// results returned in the same order as `nodes`.  By convention, the
+    /// first output is the shape's distance field; it's used by tracing
+    /// evaluation (and therefore tape simplification) in the renderers.
+    pub fn new_multi_with_axes(
+        ctx: &Context,
+        nodes: &[Node],
+        axes: [Var; 3],
+    ) -> Result<Self, Error> {
+        let f = F::new(ctx, nodes)?;
         Ok(Self {
             f,
             axes,
             transform: None,
+            names: None,
         })
     }
 
+    /// Builds a new multi-output shape with named outputs and the given axes
+    ///
+    /// Outputs are evaluated in the order given, as in
+    /// [`Shape::new_multi_with_axes`], and can be looked up by name with
+    /// [`Shape::output_index`].  Returns [`Error::DuplicateOutputName`] if
+    /// two outputs have the same name.
+    pub fn new_named_with_axes(
+        ctx: &Context,
+        outputs: &[(&str, Node)],
+        axes: [Var; 3],
+    ) -> Result<Self, Error> {
+        let mut seen = HashSet::new();
+        for (name, _) in outputs {
+            if !seen.insert(*name) {
+                return Err(Error::DuplicateOutputName(name.to_string()));
+            }
+        }
+        let nodes = outputs.iter().map(|(_, n)| *n).collect::<Vec<_>>();
+        let mut out = Self::new_multi_with_axes(ctx, &nodes, axes)?;
+        out.names = Some(outputs.iter().map(|(n, _)| n.to_string()).collect());
+        Ok(out)
+    }
+
+    /// Builds a new multi-output shape with named outputs and default (X, Y,
+    /// Z) axes
+    ///
+    /// ```
+    /// use fidget::{context::Tree, shape::EzShape, vm::VmShape, Context};
+    ///
+    /// let (x, y, z) = Tree::axes();
+    /// let mut ctx = Context::new();
+    /// let dist = ctx.import(&(x.square() + y.square() + z.square() - 1.0));
+    /// let red = ctx.import(&(Tree::x() * 0.5 + 0.5));
+    /// let shape = VmShape::new_named(&ctx, &[("dist", dist), ("red", red)])?;
+    /// let red = shape.output_index("red").unwrap();
+    ///
+    /// let mut eval = VmShape::new_point_eval();
+    /// let tape = shape.ez_point_tape();
+    /// let (out, _) = eval.eval_all(&tape, 1.0, 0.0, 0.0)?;
+    /// assert_eq!(out[red], 1.0);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn new_named(
+        ctx: &Context,
+        outputs: &[(&str, Node)],
+    ) -> Result<Self, Error> {
+        Self::new_named_with_axes(ctx, outputs, [Var::X, Var::Y, Var::Z])
+    }
+
+    /// Builds a new multi-output shape with default (X, Y, Z) axes
+    ///
+    /// ```
+    /// use fidget::{context::Tree, shape::EzShape, vm::VmShape, Context};
+    ///
+    /// let (x, y, z) = Tree::axes();
+    /// let mut ctx = Context::new();
+    /// let dist = ctx.import(&(x.square() + y.square() + z.square() - 1.0));
+    /// let red = ctx.import(&(Tree::x() * 0.5 + 0.5));
+    /// let shape = VmShape::new_multi(&ctx, &[dist, red])?;
+    ///
+    /// let mut eval = VmShape::new_float_slice_eval();
+    /// let tape = shape.ez_float_slice_tape();
+    /// let out = eval.eval(&tape, &[0.0, 1.0], &[0.0; 2], &[0.0; 2])?;
+    /// assert_eq!(out.len(), 2);
+    /// assert_eq!(&out[0], &[-1.0, 0.0]);
+    /// assert_eq!(&out[1], &[0.5, 1.0]);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn new_multi(ctx: &Context, nodes: &[Node]) -> Result<Self, Error> {
+        Self::new_multi_with_axes(ctx, nodes, [Var::X, Var::Y, Var::Z])
+    }
+
     /// Builds a new shape from the given node with default (X, Y, Z) axes
     pub fn new(ctx: &Context, node: Node) -> Result<Self, Error>
     where
@@ -382,6 +666,11 @@ impl<T: Tape> ShapeTape<T> {
     pub fn vars(&self) -> &VarMap {
         self.tape.vars()
     }
+
+    /// Returns the number of outputs written by this tape
+    pub fn output_count(&self) -> usize {
+        self.tape.output_count()
+    }
 }
 
 /// Wrapper around a [`TracingEvaluator`]
@@ -427,6 +716,9 @@ where
     /// Tracing evaluation of a single sample
     ///
     /// Before evaluation, the tape's transform matrix is applied (if present).
+    ///
+    /// If the tape has multiple outputs, only the first is returned; use
+    /// [`eval_all_v`](Self::eval_all_v) to get all of them.
     pub fn eval_v<F: Into<E::Data> + Copy, V: Into<E::Data> + Copy>(
         &mut self,
         tape: &ShapeTape<E::Tape>,
@@ -435,12 +727,39 @@ where
         z: F,
         vars: &ShapeVars<V>,
     ) -> Result<(E::Data, Option<&E::Trace>), Error> {
-        assert_eq!(
-            tape.tape.output_count(),
-            1,
-            "ShapeTape has multiple outputs"
-        );
+        let (out, trace) = self.eval_all_v(tape, x, y, z, vars)?;
+        Ok((out[0], trace))
+    }
+
+    /// Tracing evaluation of every output, with X, Y, Z input arguments
+    ///
+    /// Returns one value per output, in the order passed to
+    /// [`Shape::new_multi`].  The trace covers all outputs.
+    ///
+    /// If the tape has other variables, [`eval_all_v`](Self::eval_all_v)
+    /// should be called instead (and this function will return an error).
+    pub fn eval_all<F: Into<E::Data> + Copy>(
+        &mut self,
+        tape: &ShapeTape<E::Tape>,
+        x: F,
+        y: F,
+        z: F,
+    ) -> Result<TracingResult<'_, E::Data, E::Trace>, Error> {
+        let h = ShapeVars::<f32>::new();
+        self.eval_all_v(tape, x, y, z, &h)
+    }
 
+    /// Tracing evaluation of every output for a single sample
+    ///
+    /// Before evaluation, the tape's transform matrix is applied (if present).
+    pub fn eval_all_v<F: Into<E::Data> + Copy, V: Into<E::Data> + Copy>(
+        &mut self,
+        tape: &ShapeTape<E::Tape>,
+        x: F,
+        y: F,
+        z: F,
+        vars: &ShapeVars<V>,
+    ) -> Result<TracingResult<'_, E::Data, E::Trace>, Error> {
         let x = x.into();
         let y = y.into();
         let z = z.into();
@@ -481,8 +800,7 @@ where
             }
         }
 
-        let (out, trace) = self.eval.eval(&tape.tape, &self.scratch)?;
-        Ok((out[0], trace))
+        self.eval.eval(&tape.tape, &self.scratch)
     }
 }
 
@@ -502,6 +820,10 @@ where
 {
     /// Bulk evaluation of many samples, without any variables
     ///
+    /// The result is indexed first by output (in the order passed to
+    /// [`Shape::new_multi`]), then by sample; single-output shapes should use
+    /// `out[0]`.
+    ///
     /// If the shape includes variables other than `X`, `Y`, `Z`,
     /// [`eval_v`](Self::eval_v) or [`eval_vs`](Self::eval_vs) should be used
     /// instead (and this function will return an error).
@@ -513,7 +835,7 @@ where
         x: &[E::Data],
         y: &[E::Data],
         z: &[E::Data],
-    ) -> Result<&[E::Data], Error> {
+    ) -> Result<BulkOutput<'_, E::Data>, Error> {
         let h: ShapeVars<&[E::Data]> = ShapeVars::new();
         self.eval_vs(tape, x, y, z, &h)
     }
@@ -527,63 +849,7 @@ where
         z: &[E::Data],
         vars: &ShapeVars<V>,
     ) -> Result<usize, Error> {
-        assert_eq!(
-            tape.tape.output_count(),
-            1,
-            "ShapeTape has multiple outputs"
-        );
-
-        // Make sure our scratch arrays are big enough for this evaluation
-        if x.len() != y.len() || x.len() != z.len() {
-            return Err(Error::MismatchedSlices);
-        }
-        let n = x.len();
-
-        let vs = tape.vars();
-        let expected_vars = vs.len()
-            - vs.get(&Var::X).is_some() as usize
-            - vs.get(&Var::Y).is_some() as usize
-            - vs.get(&Var::Z).is_some() as usize;
-        if expected_vars != vars.len() {
-            return Err(Error::BadVarSlice(vars.len(), expected_vars));
-        }
-
-        self.scratch.resize_with(vs.len(), Vec::new);
-        for s in &mut self.scratch {
-            s.resize(n, 0.0.into());
-        }
-
-        if let Some(mat) = tape.transform {
-            self.scratch.resize_with(tape.vars().len(), Vec::new);
-            for s in &mut self.scratch {
-                s.resize(n, 0.0.into());
-            }
-            for i in 0..n {
-                let (x, y, z) = Transformable::transform(x[i], y[i], z[i], mat);
-                if let Some(a) = tape.axes[0] {
-                    self.scratch[a][i] = x;
-                }
-                if let Some(b) = tape.axes[1] {
-                    self.scratch[b][i] = y;
-                }
-                if let Some(c) = tape.axes[2] {
-                    self.scratch[c][i] = z;
-                }
-            }
-        } else {
-            if let Some(a) = tape.axes[0] {
-                self.scratch[a].copy_from_slice(x);
-            }
-            if let Some(b) = tape.axes[1] {
-                self.scratch[b].copy_from_slice(y);
-            }
-            if let Some(c) = tape.axes[2] {
-                self.scratch[c].copy_from_slice(z);
-            }
-            // TODO fast path if there are no extra vars, reusing slices
-        };
-
-        Ok(n)
+        setup_bulk(&mut self.scratch, tape, x, y, z, vars)
     }
     /// Bulk evaluation of many samples, with slices of variables
     ///
@@ -604,7 +870,7 @@ where
         y: &[E::Data],
         z: &[E::Data],
         vars: &ShapeVars<V>,
-    ) -> Result<&[E::Data], Error> {
+    ) -> Result<BulkOutput<'_, E::Data>, Error> {
         let n = self.setup(tape, x, y, z, vars)?;
 
         if vars.values().any(|vs| vs.len() != n) {
@@ -629,8 +895,7 @@ where
             }
         }
 
-        let out = self.eval.eval(&tape.tape, &self.scratch)?;
-        Ok(out.borrow(0))
+        self.eval.eval(&tape.tape, &self.scratch)
     }
 
     /// Bulk evaluation of many samples, with fixed variables
@@ -648,7 +913,7 @@ where
         y: &[E::Data],
         z: &[E::Data],
         vars: &ShapeVars<G>,
-    ) -> Result<&[E::Data], Error> {
+    ) -> Result<BulkOutput<'_, E::Data>, Error> {
         self.setup(tape, x, y, z, vars)?;
         let vs = tape.vars();
         for (var, value) in vars {
@@ -663,8 +928,215 @@ where
             }
         }
 
-        let out = self.eval.eval(&tape.tape, &self.scratch)?;
-        Ok(out.borrow(0))
+        self.eval.eval(&tape.tape, &self.scratch)
+    }
+}
+
+/// Helper function to write X, Y, Z inputs into bulk evaluation scratch arrays
+///
+/// Returns the number of samples
+fn setup_bulk<D: From<f32> + Copy + Transformable, T: Tape, V>(
+    scratch: &mut Vec<Vec<D>>,
+    tape: &ShapeTape<T>,
+    x: &[D],
+    y: &[D],
+    z: &[D],
+    vars: &ShapeVars<V>,
+) -> Result<usize, Error> {
+    // Make sure our scratch arrays are big enough for this evaluation
+    if x.len() != y.len() || x.len() != z.len() {
+        return Err(Error::MismatchedSlices);
+    }
+    let n = x.len();
+
+    let vs = tape.vars();
+    let expected_vars = vs.len()
+        - vs.get(&Var::X).is_some() as usize
+        - vs.get(&Var::Y).is_some() as usize
+        - vs.get(&Var::Z).is_some() as usize;
+    if expected_vars != vars.len() {
+        return Err(Error::BadVarSlice(vars.len(), expected_vars));
+    }
+
+    scratch.resize_with(vs.len(), Vec::new);
+    for s in scratch.iter_mut() {
+        s.resize(n, 0.0.into());
+    }
+
+    if let Some(mat) = tape.transform {
+        scratch.resize_with(tape.vars().len(), Vec::new);
+        for s in scratch.iter_mut() {
+            s.resize(n, 0.0.into());
+        }
+        for i in 0..n {
+            let (x, y, z) = Transformable::transform(x[i], y[i], z[i], mat);
+            if let Some(a) = tape.axes[0] {
+                scratch[a][i] = x;
+            }
+            if let Some(b) = tape.axes[1] {
+                scratch[b][i] = y;
+            }
+            if let Some(c) = tape.axes[2] {
+                scratch[c][i] = z;
+            }
+        }
+    } else {
+        if let Some(a) = tape.axes[0] {
+            scratch[a].copy_from_slice(x);
+        }
+        if let Some(b) = tape.axes[1] {
+            scratch[b].copy_from_slice(y);
+        }
+        if let Some(c) = tape.axes[2] {
+            scratch[c].copy_from_slice(z);
+        }
+        // TODO fast path if there are no extra vars, reusing slices
+    };
+
+    Ok(n)
+}
+
+/// Wrapper around a [`BulkTracingEvaluator`]
+///
+/// Unlike the raw evaluator, a [`ShapeBulkTracingEval`] knows about the tape's
+/// X, Y, Z axes and optional transform matrix.
+#[derive(Debug, Default)]
+pub struct ShapeBulkTracingEval<E: BulkTracingEvaluator> {
+    eval: E,
+    scratch: Vec<Vec<E::Data>>,
+}
+
+impl<E: BulkTracingEvaluator> ShapeBulkTracingEval<E>
+where
+    E::Data: From<f32> + Transformable,
+{
+    /// Tracing evaluation of many samples, without any variables
+    ///
+    /// Returns per-output results (as in [`ShapeBulkEval::eval`]) and a trace
+    /// which is aggregated across every sample.  The trace is only valid for
+    /// these samples, rather than the region that contains them.
+    ///
+    /// If the shape includes variables other than `X`, `Y`, `Z`,
+    /// [`eval_v`](Self::eval_v) should be used instead (and this function
+    /// will return an error).
+    ///
+    /// Before evaluation, the tape's transform matrix is applied (if present).
+    pub fn eval(
+        &mut self,
+        tape: &ShapeTape<E::Tape>,
+        x: &[E::Data],
+        y: &[E::Data],
+        z: &[E::Data],
+    ) -> Result<BulkTracingResult<'_, E::Data, E::Trace>, Error> {
+        let h: ShapeVars<E::Data> = ShapeVars::new();
+        self.eval_v(tape, x, y, z, &h)
+    }
+
+    /// Tracing evaluation of many samples, with fixed variables
+    ///
+    /// Each variable has a single value, which is used for every position in
+    /// the `x`, `y`, `z` slices.
+    ///
+    /// Before evaluation, the tape's transform matrix is applied (if present).
+    pub fn eval_v<G: Into<E::Data> + Copy>(
+        &mut self,
+        tape: &ShapeTape<E::Tape>,
+        x: &[E::Data],
+        y: &[E::Data],
+        z: &[E::Data],
+        vars: &ShapeVars<G>,
+    ) -> Result<BulkTracingResult<'_, E::Data, E::Trace>, Error> {
+        setup_bulk(&mut self.scratch, tape, x, y, z, vars)?;
+        let vs = tape.vars();
+        for (var, value) in vars {
+            if let Some(i) = vs.get(&Var::V(*var)) {
+                if i < self.scratch.len() {
+                    self.scratch[i].fill((*value).into());
+                } else {
+                    return Err(Error::BadVarIndex(i, self.scratch.len()));
+                }
+            } else {
+                // Passing in Bonus Variables is allowed (for now)
+            }
+        }
+
+        self.eval.eval(&tape.tape, &self.scratch)
+    }
+}
+
+/// Wrapper around a [`MultiTracingEvaluator`]
+///
+/// Unlike the raw evaluator, a [`ShapeMultiTracingEval`] knows about the tape's
+/// X, Y, Z axes and optional transform matrix.
+#[derive(Debug)]
+pub struct ShapeMultiTracingEval<E: MultiTracingEvaluator> {
+    eval: E,
+    scratch: Vec<Vec<E::Data>>,
+}
+
+impl<E: MultiTracingEvaluator> Default for ShapeMultiTracingEval<E> {
+    fn default() -> Self {
+        Self {
+            eval: E::default(),
+            scratch: vec![],
+        }
+    }
+}
+
+impl<E: MultiTracingEvaluator> ShapeMultiTracingEval<E>
+where
+    E::Data: From<f32> + Transformable,
+{
+    /// Tracing evaluation of many samples, without any variables
+    ///
+    /// Returns per-output results (as in [`ShapeBulkEval::eval`]) and a
+    /// separate trace for each sample.
+    ///
+    /// If the shape includes variables other than `X`, `Y`, `Z`,
+    /// [`eval_v`](Self::eval_v) should be used instead (and this function
+    /// will return an error).
+    ///
+    /// Before evaluation, the tape's transform matrix is applied (if present).
+    pub fn eval(
+        &mut self,
+        tape: &ShapeTape<E::Tape>,
+        x: &[E::Data],
+        y: &[E::Data],
+        z: &[E::Data],
+    ) -> Result<MultiTracingResult<'_, E::Data, E::Trace>, Error> {
+        let h: ShapeVars<E::Data> = ShapeVars::new();
+        self.eval_v(tape, x, y, z, &h)
+    }
+
+    /// Tracing evaluation of many samples, with fixed variables
+    ///
+    /// Each variable has a single value, which is used for every position in
+    /// the `x`, `y`, `z` slices.
+    ///
+    /// Before evaluation, the tape's transform matrix is applied (if present).
+    pub fn eval_v<G: Into<E::Data> + Copy>(
+        &mut self,
+        tape: &ShapeTape<E::Tape>,
+        x: &[E::Data],
+        y: &[E::Data],
+        z: &[E::Data],
+        vars: &ShapeVars<G>,
+    ) -> Result<MultiTracingResult<'_, E::Data, E::Trace>, Error> {
+        setup_bulk(&mut self.scratch, tape, x, y, z, vars)?;
+        let vs = tape.vars();
+        for (var, value) in vars {
+            if let Some(i) = vs.get(&Var::V(*var)) {
+                if i < self.scratch.len() {
+                    self.scratch[i].fill((*value).into());
+                } else {
+                    return Err(Error::BadVarIndex(i, self.scratch.len()));
+                }
+            } else {
+                // Passing in Bonus Variables is allowed (for now)
+            }
+        }
+
+        self.eval.eval(&tape.tape, &self.scratch)
     }
 }
 
@@ -720,6 +1192,63 @@ impl Transformable for Grad {
     }
 }
 
+impl Transformable for Hess {
+    fn transform(
+        x: Hess,
+        y: Hess,
+        z: Hess,
+        mat: Matrix4<f32>,
+    ) -> (Hess, Hess, Hess) {
+        let out = [0, 1, 2, 3].map(|i| {
+            let row = mat.row(i);
+            x * row[0] + y * row[1] + z * row[2] + Hess::from(row[3])
+        });
+
+        (out[0] / out[3], out[1] / out[3], out[2] / out[3])
+    }
+}
+
+impl Transformable for f64 {
+    fn transform(x: f64, y: f64, z: f64, mat: Matrix4<f32>) -> (f64, f64, f64) {
+        let out = mat.cast::<f64>().transform_point(&Point3::new(x, y, z));
+        (out.x, out.y, out.z)
+    }
+}
+
+impl Transformable for IntervalF64 {
+    fn transform(
+        x: IntervalF64,
+        y: IntervalF64,
+        z: IntervalF64,
+        mat: Matrix4<f32>,
+    ) -> (IntervalF64, IntervalF64, IntervalF64) {
+        let mat = mat.cast::<f64>();
+        let out = [0, 1, 2, 3].map(|i| {
+            let row = mat.row(i);
+            x * row[0] + y * row[1] + z * row[2] + IntervalF64::from(row[3])
+        });
+
+        (out[0] / out[3], out[1] / out[3], out[2] / out[3])
+    }
+}
+
+impl Transformable for GradF64 {
+    fn transform(
+        x: GradF64,
+        y: GradF64,
+        z: GradF64,
+        mat: Matrix4<f32>,
+    ) -> (GradF64, GradF64, GradF64) {
+        let mat = mat.cast::<f64>();
+        let out = [0, 1, 2, 3].map(|i| {
+            let row = mat.row(i);
+            x * row[0] + y * row[1] + z * row[2] + GradF64::from(row[3])
+        });
+
+        (out[0] / out[3], out[1] / out[3], out[2] / out[3])
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -748,4 +1277,135 @@ mod test {
         }
         assert!(seen.iter().all(|i| *i));
     }
+
+    fn check_multi_output<F: MathFunction>() {
+        let (x, y, z) = Tree::axes();
+        let mut ctx = Context::new();
+        let dist = ctx.import(&(x.clone().max(y.clone()) - 0.5));
+        let red = ctx.import(&(x.clone() * 0.5 + 0.5));
+        let green = ctx.import(&(y.clone() + z.clone()));
+        let blue = ctx.import(&Tree::constant(0.25));
+        let material = ctx.import(&x.clone().min(z.clone() * 2.0));
+        let shape =
+            Shape::<F>::new_multi(&ctx, &[dist, red, green, blue, material])
+                .unwrap();
+
+        let mut eval = Shape::<F>::new_point_eval();
+        let tape = shape.point_tape(Default::default());
+        assert_eq!(tape.output_count(), 5);
+        let (out, _) = eval.eval_all(&tape, 1.0, 2.0, 3.0).unwrap();
+        assert_eq!(out, &[1.5, 1.0, 5.0, 0.25, 1.0]);
+        let (d, _) = eval.eval(&tape, 1.0, 2.0, 3.0).unwrap();
+        assert_eq!(d, 1.5);
+
+        let mut eval = Shape::<F>::new_interval_eval();
+        let tape = shape.interval_tape(Default::default());
+        let (out, trace) = eval
+            .eval_all(
+                &tape,
+                Interval::new(2.0, 3.0),
+                Interval::new(0.0, 1.0),
+                Interval::new(2.0, 3.0),
+            )
+            .unwrap();
+        assert_eq!(out[0], Interval::new(1.5, 2.5));
+        assert_eq!(out[4], Interval::new(2.0, 3.0));
+
+        // Simplification keeps every output
+        let trace = trace.unwrap().clone();
+        let shape = shape
+            .simplify(&trace, Default::default(), &mut Default::default())
+            .unwrap();
+        let mut eval = Shape::<F>::new_float_slice_eval();
+        let tape = shape.float_slice_tape(Default::default());
+        assert_eq!(tape.output_count(), 5);
+        let out = eval
+            .eval(&tape, &[2.0, 3.0], &[1.0, 0.0], &[2.5, 2.0])
+            .unwrap();
+        assert_eq!(out.len(), 5);
+        assert_eq!(&out[0], &[1.5, 2.5]);
+        assert_eq!(&out[1], &[1.5, 2.0]);
+        assert_eq!(&out[2], &[3.5, 2.0]);
+        assert_eq!(&out[3], &[0.25, 0.25]);
+        assert_eq!(&out[4], &[2.0, 3.0]);
+
+        // The transform applies to every output
+        let shape = shape.apply_transform(Matrix4::new_translation(
+            &nalgebra::Vector3::new(1.0, 0.0, 0.0),
+        ));
+        let mut eval = Shape::<F>::new_grad_slice_eval();
+        let tape = shape.grad_slice_tape(Default::default());
+        let out = eval
+            .eval(
+                &tape,
+                &[Grad::new(1.0, 1.0, 0.0, 0.0)],
+                &[Grad::new(1.0, 0.0, 1.0, 0.0)],
+                &[Grad::new(2.5, 0.0, 0.0, 1.0)],
+            )
+            .unwrap();
+        assert_eq!(out[0][0], Grad::new(1.5, 1.0, 0.0, 0.0));
+        assert_eq!(out[1][0], Grad::new(1.5, 0.5, 0.0, 0.0));
+        assert_eq!(out[2][0], Grad::new(3.5, 0.0, 1.0, 1.0));
+        assert_eq!(out[3][0], Grad::new(0.25, 0.0, 0.0, 0.0));
+        assert_eq!(out[4][0], Grad::new(2.0, 1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_named_outputs() {
+        let mut ctx = Context::new();
+        let [x, y, _z] = ctx.axes();
+        let d = ctx.add(y, 2.0).unwrap();
+        let dist = ctx.min(x, d).unwrap();
+        let shape =
+            VmShape::new_named(&ctx, &[("dist", dist), ("color", y)]).unwrap();
+        assert_eq!(shape.output_index("dist"), Some(0));
+        assert_eq!(shape.output_index("color"), Some(1));
+        assert_eq!(shape.output_index("material"), None);
+        assert_eq!(
+            shape.output_names().unwrap(),
+            &["dist".to_owned(), "color".to_owned()]
+        );
+
+        // Names are kept through simplification and transforms
+        let mut eval = VmShape::new_interval_eval();
+        let tape = shape.interval_tape(Default::default());
+        let (_, trace) = eval
+            .eval_all(
+                &tape,
+                Interval::new(0.0, 1.0),
+                Interval::new(0.0, 1.0),
+                Interval::new(0.0, 1.0),
+            )
+            .unwrap();
+        let next = shape
+            .simplify(
+                trace.unwrap(),
+                Default::default(),
+                &mut Default::default(),
+            )
+            .unwrap();
+        assert!(next.size() < shape.size());
+        let next = next.apply_transform(Matrix4::identity());
+        assert_eq!(next.output_index("color"), Some(1));
+
+        let unnamed = VmShape::new_multi(&ctx, &[x, y]).unwrap();
+        assert_eq!(unnamed.output_index("dist"), None);
+        assert!(unnamed.output_names().is_none());
+
+        assert!(matches!(
+            VmShape::new_named(&ctx, &[("a", x), ("a", y)]),
+            Err(Error::DuplicateOutputName(n)) if n == "a"
+        ));
+    }
+
+    #[test]
+    fn test_multi_output_vm() {
+        check_multi_output::<crate::vm::VmFunction>();
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn test_multi_output_jit() {
+        check_multi_output::<crate::jit::JitFunction>();
+    }
 }
@@ -0,0 +1,376 @@
+/// A value with an arbitrary number of associated partial derivatives
+///
+/// This is a forward-mode dual number: where [`Grad`](super::Grad) is limited
+/// to partial derivatives with respect to `x`, `y`, and `z`, a `Dual<N>` tracks
+/// `N` partial derivatives with respect to arbitrary inputs.  Seed each input
+/// with [`Dual::var`] to get the full gradient from a single evaluation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct Dual<const N: usize> {
+    /// Value of the function at this point
+    pub v: f32,
+    /// Partial derivatives
+    pub d: [f32; N],
+}
+
+impl<const N: usize> Default for Dual<N> {
+    fn default() -> Self {
+        Self::from(0.0)
+    }
+}
+
+impl<const N: usize> std::fmt::Display for Dual<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}", self.v)?;
+        for d in &self.d {
+            write!(f, ", {d}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<const N: usize> Dual<N> {
+    /// Constructs a new dual number
+    pub fn new(v: f32, d: [f32; N]) -> Self {
+        Self { v, d }
+    }
+
+    /// Constructs a dual number representing the `i`'th input variable
+    ///
+    /// The partial derivative with respect to `i` is one; all others are zero.
+    ///
+    /// # Panics
+    /// If `i >= N`
+    pub fn var(v: f32, i: usize) -> Self {
+        let mut d = [0.0; N];
+        d[i] = 1.0;
+        Self { v, d }
+    }
+
+    /// Builds a new value using the chain rule
+    ///
+    /// Each partial derivative is transformed by `f`, which should apply the
+    /// local derivative of the operation.
+    fn chain(self, v: f32, f: impl Fn(f32) -> f32) -> Self {
+        Self {
+            v,
+            d: self.d.map(f),
+        }
+    }
+
+    /// Builds a new value with all partial derivatives set to zero
+    fn flat(v: f32) -> Self {
+        Self::from(v)
+    }
+
+    /// Absolute value
+    pub fn abs(self) -> Self {
+        if self.v < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    /// Square root
+    pub fn sqrt(self) -> Self {
+        let v = self.v.sqrt();
+        self.chain(v, |d| d / (2.0 * v))
+    }
+
+    /// Sine
+    pub fn sin(self) -> Self {
+        let c = self.v.cos();
+        self.chain(self.v.sin(), |d| d * c)
+    }
+    /// Cosine
+    pub fn cos(self) -> Self {
+        let s = -self.v.sin();
+        self.chain(self.v.cos(), |d| d * s)
+    }
+    /// Tangent
+    pub fn tan(self) -> Self {
+        let c = self.v.cos().powi(2);
+        self.chain(self.v.tan(), |d| d / c)
+    }
+    /// Arcsin
+    pub fn asin(self) -> Self {
+        let r = (1.0 - self.v.powi(2)).sqrt();
+        self.chain(self.v.asin(), |d| d / r)
+    }
+    /// Arccos
+    pub fn acos(self) -> Self {
+        let r = (1.0 - self.v.powi(2)).sqrt();
+        self.chain(self.v.acos(), |d| -d / r)
+    }
+    /// Arctangent
+    pub fn atan(self) -> Self {
+        let r = self.v.powi(2) + 1.0;
+        self.chain(self.v.atan(), |d| d / r)
+    }
+    /// Exponential function
+    pub fn exp(self) -> Self {
+        let v = self.v.exp();
+        self.chain(v, |d| v * d)
+    }
+    /// Natural log
+    pub fn ln(self) -> Self {
+        self.chain(self.v.ln(), |d| d / self.v)
+    }
+    /// Base-2 log
+    pub fn log2(self) -> Self {
+        let r = self.v * std::f32::consts::LN_2;
+        self.chain(self.v.log2(), |d| d / r)
+    }
+    /// Hyperbolic sine
+    pub fn sinh(self) -> Self {
+        let c = self.v.cosh();
+        self.chain(self.v.sinh(), |d| d * c)
+    }
+    /// Hyperbolic cosine
+    pub fn cosh(self) -> Self {
+        let s = self.v.sinh();
+        self.chain(self.v.cosh(), |d| d * s)
+    }
+    /// Hyperbolic tangent
+    pub fn tanh(self) -> Self {
+        let c = self.v.cosh().powi(2);
+        self.chain(self.v.tanh(), |d| d / c)
+    }
+    /// Sign (-1, 0, or +1), with a zero derivative
+    pub fn sign(self) -> Self {
+        let v = if self.v > 0.0 {
+            1.0
+        } else if self.v < 0.0 {
+            -1.0
+        } else {
+            self.v
+        };
+        Self::flat(v)
+    }
+    /// Cube root
+    pub fn cbrt(self) -> Self {
+        let v = self.v.cbrt();
+        let r = 3.0 * v.powi(2);
+        self.chain(v, |d| d / r)
+    }
+
+    /// Reciprocal
+    pub fn recip(self) -> Self {
+        let v2 = -self.v.powi(2);
+        self.chain(1.0 / self.v, |d| d / v2)
+    }
+
+    /// Minimum of two values
+    pub fn min(self, rhs: Self) -> Self {
+        if self.v < rhs.v {
+            self
+        } else {
+            rhs
+        }
+    }
+
+    /// Maximum of two values
+    pub fn max(self, rhs: Self) -> Self {
+        if self.v > rhs.v {
+            self
+        } else {
+            rhs
+        }
+    }
+
+    /// Least non-negative remainder
+    pub fn rem_euclid(&self, rhs: Self) -> Self {
+        let e = self.v.div_euclid(rhs.v);
+        Self {
+            v: self.v.rem_euclid(rhs.v),
+            d: std::array::from_fn(|i| self.d[i] - rhs.d[i] * e),
+        }
+    }
+
+    /// Snap to the largest less-than-or-equal value
+    pub fn floor(&self) -> Self {
+        Self::flat(self.v.floor())
+    }
+
+    /// Snap to the smallest greater-than-or-equal value
+    pub fn ceil(&self) -> Self {
+        Self::flat(self.v.ceil())
+    }
+
+    /// Rounds to the nearest integer
+    pub fn round(&self) -> Self {
+        Self::flat(self.v.round())
+    }
+
+    /// Four-quadrant arctangent
+    pub fn atan2(self, x: Self) -> Self {
+        let y = self;
+
+        let r = x.v.powi(2) + y.v.powi(2);
+        Self {
+            v: y.v.atan2(x.v),
+            d: std::array::from_fn(|i| (x.v * y.d[i] - y.v * x.d[i]) / r),
+        }
+    }
+
+    /// Raises the value to the power of another value
+    ///
+    /// As with [`Grad::pow`](super::Grad::pow), terms with a zero partial
+    /// derivative are skipped.
+    pub fn pow(self, rhs: Self) -> Self {
+        let v = self.v.powf(rhs.v);
+        let da = rhs.v * self.v.powf(rhs.v - 1.0);
+        let db = v * self.v.ln();
+        let term = |d: f32, k: f32| if d == 0.0 { 0.0 } else { d * k };
+        Self {
+            v,
+            d: std::array::from_fn(|i| {
+                term(self.d[i], da) + term(rhs.d[i], db)
+            }),
+        }
+    }
+}
+
+impl<const N: usize> From<f32> for Dual<N> {
+    fn from(v: f32) -> Self {
+        Self { v, d: [0.0; N] }
+    }
+}
+
+impl From<super::Grad> for Dual<3> {
+    fn from(g: super::Grad) -> Self {
+        Self {
+            v: g.v,
+            d: [g.dx, g.dy, g.dz],
+        }
+    }
+}
+
+impl From<Dual<3>> for super::Grad {
+    fn from(g: Dual<3>) -> Self {
+        Self::new(g.v, g.d[0], g.d[1], g.d[2])
+    }
+}
+
+impl<const N: usize> std::ops::Add<Dual<N>> for Dual<N> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            v: self.v + rhs.v,
+            d: std::array::from_fn(|i| self.d[i] + rhs.d[i]),
+        }
+    }
+}
+
+impl<const N: usize> std::ops::Mul<Dual<N>> for Dual<N> {
+    type Output = Self;
+    // The product rule uses `+` for each partial derivative
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            v: self.v * rhs.v,
+            d: std::array::from_fn(|i| self.v * rhs.d[i] + rhs.v * self.d[i]),
+        }
+    }
+}
+
+impl<const N: usize> std::ops::Mul<f32> for Dual<N> {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self {
+        self.chain(self.v * rhs, |d| d * rhs)
+    }
+}
+
+impl<const N: usize> std::ops::Div<Dual<N>> for Dual<N> {
+    type Output = Self;
+    // The quotient rule uses `*` and `-` for each partial derivative
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        let r = rhs.v.powi(2);
+        Self {
+            v: self.v / rhs.v,
+            d: std::array::from_fn(|i| {
+                (rhs.v * self.d[i] - self.v * rhs.d[i]) / r
+            }),
+        }
+    }
+}
+
+impl<const N: usize> std::ops::Sub<Dual<N>> for Dual<N> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            v: self.v - rhs.v,
+            d: std::array::from_fn(|i| self.d[i] - rhs.d[i]),
+        }
+    }
+}
+
+impl<const N: usize> std::ops::Neg for Dual<N> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        self.chain(-self.v, |d| -d)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Grad;
+
+    #[test]
+    fn test_dual_matches_grad() {
+        let a = Grad::new(0.3, 1.0, 0.5, -2.0);
+        let b = Grad::new(-1.7, 0.25, -1.0, 3.0);
+        let (da, db) = (Dual::from(a), Dual::from(b));
+
+        let check = |g: Grad, d: Dual<3>| {
+            let d = Grad::from(d);
+            assert!(g == d || (g.v.is_nan() && d.v.is_nan()), "{g} != {d}");
+        };
+        check(a + b, da + db);
+        check(a - b, da - db);
+        check(a * b, da * db);
+        check(a / b, da / db);
+        check(a * 2.5, da * 2.5);
+        check(-a, -da);
+        check(b.abs(), db.abs());
+        check(a.sqrt(), da.sqrt());
+        check(a.sin(), da.sin());
+        check(a.cos(), da.cos());
+        check(a.tan(), da.tan());
+        check(a.asin(), da.asin());
+        check(a.acos(), da.acos());
+        check(a.atan(), da.atan());
+        check(b.exp(), db.exp());
+        check(a.ln(), da.ln());
+        check(a.log2(), da.log2());
+        check(b.sinh(), db.sinh());
+        check(b.cosh(), db.cosh());
+        check(b.tanh(), db.tanh());
+        check(b.sign(), db.sign());
+        check(b.cbrt(), db.cbrt());
+        check(b.recip(), db.recip());
+        check(a.atan2(b), da.atan2(db));
+        check(a.pow(b), da.pow(db));
+        check(b.rem_euclid(a), db.rem_euclid(da));
+        check(b.floor(), db.floor());
+    }
+
+    #[test]
+    fn test_dual_var() {
+        let x = Dual::<8>::var(2.0, 5);
+        let y = Dual::<8>::var(3.0, 1);
+        let z = x * y + x.sin();
+        assert_eq!(z.v, 6.0 + 2f32.sin());
+        for (i, d) in z.d.iter().enumerate() {
+            let expected = match i {
+                1 => 2.0,
+                5 => 3.0 + 2f32.cos(),
+                _ => 0.0,
+            };
+            assert_eq!(*d, expected, "bad partial {i}");
+        }
+    }
+}
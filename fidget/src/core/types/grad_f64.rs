@@ -0,0 +1,28 @@
+use super::grad::grad_impl;
+use crate::types::Grad;
+
+grad_impl!(
+    /// A double-precision point in space with associated partial derivatives.
+    ///
+    /// This is the `f64` equivalent of [`Grad`], used by
+    /// [`FunctionF64`](crate::eval::FunctionF64) evaluators.
+    GradF64,
+    f64
+);
+
+impl From<f32> for GradF64 {
+    fn from(v: f32) -> Self {
+        GradF64::from(f64::from(v))
+    }
+}
+
+impl From<Grad> for GradF64 {
+    fn from(g: Grad) -> Self {
+        GradF64 {
+            v: g.v.into(),
+            dx: g.dx.into(),
+            dy: g.dy.into(),
+            dz: g.dz.into(),
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use super::Grad;
+
+/// A point in space with associated first and second partial derivatives
+///
+/// The value and first partial derivatives are computed with the same
+/// arithmetic as [`Grad`], so they match it exactly; the Hessian is carried
+/// alongside them using the second-order chain rule.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Hess {
+    /// Value of the distance field at this point
+    pub v: f32,
+    /// Partial derivative with respect to `x`
+    pub dx: f32,
+    /// Partial derivative with respect to `y`
+    pub dy: f32,
+    /// Partial derivative with respect to `z`
+    pub dz: f32,
+    /// Second partial derivatives, where `h[i][j]` is `∂²f / ∂i∂j`
+    ///
+    /// Indices are 0 = x, 1 = y, 2 = z; the matrix is symmetric.
+    pub h: [[f32; 3]; 3],
+}
+
+impl std::fmt::Display for Hess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({}, {}, {}, {}, {:?})",
+            self.v, self.dx, self.dy, self.dz, self.h
+        )
+    }
+}
+
+impl Hess {
+    /// Constructs a new value with the given first and second derivatives
+    pub fn new(v: f32, dx: f32, dy: f32, dz: f32, h: [[f32; 3]; 3]) -> Self {
+        Self { v, dx, dy, dz, h }
+    }
+
+    /// Returns the value and first partial derivatives
+    pub fn grad(&self) -> Grad {
+        Grad::new(self.v, self.dx, self.dy, self.dz)
+    }
+
+    /// Builds a value from a gradient and a Hessian
+    fn from_parts(g: Grad, h: [[f32; 3]; 3]) -> Self {
+        Self {
+            v: g.v,
+            dx: g.dx,
+            dy: g.dy,
+            dz: g.dz,
+            h,
+        }
+    }
+
+    /// Applies a unary function with derivatives `d1` and `d2`
+    ///
+    /// `g` is the result of applying the function to `self.grad()`; the
+    /// Hessian is `d1 * H + d2 * ∇u ∇uᵀ`.
+    fn chain(self, g: Grad, d1: f32, d2: f32) -> Self {
+        let u = self.grad();
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| d1 * self.h[i][j] + d2 * (u.d(i) * u.d(j)))
+        });
+        Self::from_parts(g, h)
+    }
+
+    /// Absolute value
+    pub fn abs(self) -> Self {
+        if self.v < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    /// Square root
+    pub fn sqrt(self) -> Self {
+        let g = self.grad().sqrt();
+        let d1 = 1.0 / (2.0 * g.v);
+        self.chain(g, d1, -d1 / (2.0 * self.v))
+    }
+
+    /// Sine
+    pub fn sin(self) -> Self {
+        let g = self.grad().sin();
+        self.chain(g, self.v.cos(), -g.v)
+    }
+    /// Cosine
+    pub fn cos(self) -> Self {
+        let g = self.grad().cos();
+        self.chain(g, -self.v.sin(), -g.v)
+    }
+    /// Tangent
+    pub fn tan(self) -> Self {
+        let g = self.grad().tan();
+        let d1 = 1.0 / self.v.cos().powi(2);
+        self.chain(g, d1, 2.0 * g.v * d1)
+    }
+    /// Arcsin
+    pub fn asin(self) -> Self {
+        let g = self.grad().asin();
+        let r = 1.0 - self.v.powi(2);
+        let d1 = 1.0 / r.sqrt();
+        self.chain(g, d1, self.v * d1 / r)
+    }
+    /// Arccos
+    pub fn acos(self) -> Self {
+        let g = self.grad().acos();
+        let r = 1.0 - self.v.powi(2);
+        let d1 = 1.0 / r.sqrt();
+        self.chain(g, -d1, -self.v * d1 / r)
+    }
+    /// Arctangent
+    pub fn atan(self) -> Self {
+        let g = self.grad().atan();
+        let d1 = 1.0 / (self.v.powi(2) + 1.0);
+        self.chain(g, d1, -2.0 * self.v * d1 * d1)
+    }
+    /// Exponential function
+    pub fn exp(self) -> Self {
+        let g = self.grad().exp();
+        self.chain(g, g.v, g.v)
+    }
+    /// Natural log
+    pub fn ln(self) -> Self {
+        let g = self.grad().ln();
+        let d1 = 1.0 / self.v;
+        self.chain(g, d1, -d1 * d1)
+    }
+    /// Base-2 log
+    pub fn log2(self) -> Self {
+        let g = self.grad().log2();
+        let d1 = 1.0 / (self.v * std::f32::consts::LN_2);
+        self.chain(g, d1, -d1 / self.v)
+    }
+    /// Hyperbolic sine
+    pub fn sinh(self) -> Self {
+        let g = self.grad().sinh();
+        self.chain(g, self.v.cosh(), g.v)
+    }
+    /// Hyperbolic cosine
+    pub fn cosh(self) -> Self {
+        let g = self.grad().cosh();
+        self.chain(g, self.v.sinh(), g.v)
+    }
+    /// Hyperbolic tangent
+    pub fn tanh(self) -> Self {
+        let g = self.grad().tanh();
+        let d1 = 1.0 / self.v.cosh().powi(2);
+        self.chain(g, d1, -2.0 * g.v * d1)
+    }
+    /// Sign (-1, 0, or +1), with zero derivatives
+    pub fn sign(self) -> Self {
+        Self::from(self.grad().sign().v)
+    }
+    /// Cube root
+    pub fn cbrt(self) -> Self {
+        let g = self.grad().cbrt();
+        let d1 = 1.0 / (3.0 * g.v.powi(2));
+        self.chain(g, d1, -2.0 * d1 / (3.0 * self.v))
+    }
+
+    /// Reciprocal
+    pub fn recip(self) -> Self {
+        let g = self.grad().recip();
+        self.chain(g, -g.v * g.v, 2.0 * g.v * g.v * g.v)
+    }
+
+    /// Minimum of two values
+    pub fn min(self, rhs: Self) -> Self {
+        if self.v < rhs.v {
+            self
+        } else {
+            rhs
+        }
+    }
+
+    /// Maximum of two values
+    pub fn max(self, rhs: Self) -> Self {
+        if self.v > rhs.v {
+            self
+        } else {
+            rhs
+        }
+    }
+
+    /// Least non-negative remainder
+    pub fn rem_euclid(&self, rhs: Hess) -> Self {
+        let g = self.grad().rem_euclid(rhs.grad());
+        let e = self.v.div_euclid(rhs.v);
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.h[i][j] - rhs.h[i][j] * e)
+        });
+        Self::from_parts(g, h)
+    }
+
+    /// Snap to the largest less-than-or-equal value
+    pub fn floor(&self) -> Self {
+        Self::from(self.v.floor())
+    }
+
+    /// Snap to the smallest greater-than-or-equal value
+    pub fn ceil(&self) -> Self {
+        Self::from(self.v.ceil())
+    }
+
+    /// Rounds to the nearest integer
+    pub fn round(&self) -> Self {
+        Self::from(self.v.round())
+    }
+
+    /// Four-quadrant arctangent
+    pub fn atan2(self, x: Self) -> Self {
+        let y = self;
+        let g = y.grad().atan2(x.grad());
+
+        // First and second partial derivatives of atan2(y, x)
+        let r = x.v.powi(2) + y.v.powi(2);
+        let (fy, fx) = (x.v / r, -y.v / r);
+        let r2 = r * r;
+        let fyy = -2.0 * x.v * y.v / r2;
+        let fxy = (y.v.powi(2) - x.v.powi(2)) / r2;
+
+        let (gy, gx) = (y.grad(), x.grad());
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                fy * y.h[i][j]
+                    + fx * x.h[i][j]
+                    + fyy * (gy.d(i) * gy.d(j) - gx.d(i) * gx.d(j))
+                    + fxy * (gy.d(i) * gx.d(j) + gx.d(i) * gy.d(j))
+            })
+        });
+        Self::from_parts(g, h)
+    }
+
+    /// Raises the value to the power of another value
+    ///
+    /// As with [`Grad::pow`], terms with a zero partial derivative are
+    /// skipped, so that a negative base with a constant exponent has a finite
+    /// Hessian.
+    pub fn pow(self, rhs: Self) -> Self {
+        let g = self.grad().pow(rhs.grad());
+        let (a, b) = (self.v, rhs.v);
+        let ln = a.ln();
+
+        // First and second partial derivatives of a^b
+        let fa = b * a.powf(b - 1.0);
+        let fb = g.v * ln;
+        let faa = b * (b - 1.0) * a.powf(b - 2.0);
+        let fab = a.powf(b - 1.0) * (1.0 + b * ln);
+        let fbb = fb * ln;
+
+        let term = |d: f32, k: f32| if d == 0.0 { 0.0 } else { d * k };
+        let (ga, gb) = (self.grad(), rhs.grad());
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                term(self.h[i][j], fa)
+                    + term(rhs.h[i][j], fb)
+                    + term(ga.d(i) * ga.d(j), faa)
+                    + term(ga.d(i) * gb.d(j) + gb.d(i) * ga.d(j), fab)
+                    + term(gb.d(i) * gb.d(j), fbb)
+            })
+        });
+        Self::from_parts(g, h)
+    }
+}
+
+impl From<f32> for Hess {
+    fn from(v: f32) -> Self {
+        Self::from(Grad::from(v))
+    }
+}
+
+impl From<Grad> for Hess {
+    fn from(g: Grad) -> Self {
+        Self::from_parts(g, [[0.0; 3]; 3])
+    }
+}
+
+impl std::ops::Add<Hess> for Hess {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        let g = self.grad() + rhs.grad();
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.h[i][j] + rhs.h[i][j])
+        });
+        Self::from_parts(g, h)
+    }
+}
+
+impl std::ops::Mul<Hess> for Hess {
+    type Output = Self;
+    // The product rule uses `+` for each second derivative
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: Self) -> Self {
+        let g = self.grad() * rhs.grad();
+        let (a, b) = (self.grad(), rhs.grad());
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                a.v * rhs.h[i][j]
+                    + b.v * self.h[i][j]
+                    + (a.d(i) * b.d(j) + b.d(i) * a.d(j))
+            })
+        });
+        Self::from_parts(g, h)
+    }
+}
+
+impl std::ops::Mul<f32> for Hess {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self {
+        let g = self.grad() * rhs;
+        Self::from_parts(g, self.h.map(|row| row.map(|h| h * rhs)))
+    }
+}
+
+impl std::ops::Div<Hess> for Hess {
+    type Output = Self;
+    // The quotient rule uses `*` and `-` for each second derivative
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        // For q = a / b, differentiating a = q * b twice gives
+        // H_q = (H_a - q H_b - ∇q ∇bᵀ - ∇b ∇qᵀ) / b
+        let q = self.grad() / rhs.grad();
+        let b = rhs.grad();
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                (self.h[i][j]
+                    - q.v * rhs.h[i][j]
+                    - (q.d(i) * b.d(j) + b.d(i) * q.d(j)))
+                    / b.v
+            })
+        });
+        Self::from_parts(q, h)
+    }
+}
+
+impl std::ops::Sub<Hess> for Hess {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        let g = self.grad() - rhs.grad();
+        let h = std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.h[i][j] - rhs.h[i][j])
+        });
+        Self::from_parts(g, h)
+    }
+}
+
+impl std::ops::Neg for Hess {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::from_parts(-self.grad(), self.h.map(|row| row.map(|h| -h)))
+    }
+}
@@ -1,330 +1,724 @@
+use super::round::single::{
+    add_down, add_up, div_down, div_up, mul_down, mul_up, powi_bounds,
+    sqrt_down, sqrt_up, sub_down, sub_up, widen_down, widen_up,
+};
 use crate::vm::Choice;
 
-/// Stores a range, with conservative calculations to guarantee that it always
-/// contains the actual value.
+/// Implements an interval type for the given float type
 ///
-/// # Warning
-/// This implementation does not set rounding modes, so it may not be _perfect_.
-#[derive(Copy, Clone, PartialEq)]
-#[repr(C)]
-pub struct Interval {
-    lower: f32,
-    upper: f32,
-}
-
-impl std::fmt::Debug for Interval {
-    fn fmt(
-        &self,
-        f: &mut std::fmt::Formatter<'_>,
-    ) -> Result<(), std::fmt::Error> {
-        f.debug_tuple("")
-            .field(&self.lower)
-            .field(&self.upper)
-            .finish()
-    }
-}
+/// The directed rounding functions from `super::round` (for the same float
+/// type) and [`Choice`] must be in scope at the invocation site.
+macro_rules! interval_impl {
+    ($(#[$meta:meta])* $name:ident, $t:ident) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, PartialEq)]
+        #[repr(C)]
+        pub struct $name {
+            lower: $t,
+            upper: $t,
+        }
 
-impl Interval {
-    /// Builds a new interval
-    ///
-    /// There are two kinds of valid interval:
-    /// - `[lower, upper]` where `lower <= upper`
-    /// - `[NaN, NaN]`
-    ///
-    /// # Panics
-    /// Panics if the resulting interval would be invalid
-    #[inline]
-    pub fn new(lower: f32, upper: f32) -> Self {
-        assert!(
-            upper >= lower || (lower.is_nan() && upper.is_nan()),
-            "invalid interval [{lower}, {upper}]"
-        );
-        Self { lower, upper }
-    }
-    /// Returns the lower bound of the interval
-    #[inline]
-    pub fn lower(&self) -> f32 {
-        self.lower
-    }
-    /// Returns the upper bound of the interval
-    #[inline]
-    pub fn upper(&self) -> f32 {
-        self.upper
-    }
-    /// Checks whether the given value is (strictly) contained in the interval
-    #[inline]
-    pub fn contains(&self, v: f32) -> bool {
-        v >= self.lower && v <= self.upper
-    }
-    /// Returns `true` if either bound of the interval is `NaN`
-    pub fn has_nan(&self) -> bool {
-        self.lower.is_nan() || self.upper.is_nan()
-    }
-    /// Calculates the absolute value of the interval
-    pub fn abs(self) -> Self {
-        if self.lower < 0.0 {
-            if self.upper > 0.0 {
-                Interval::new(0.0, self.upper.max(-self.lower))
-            } else {
-                Interval::new(-self.upper, -self.lower)
+        impl std::fmt::Debug for $name {
+            fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+            ) -> Result<(), std::fmt::Error> {
+                f.debug_tuple("")
+                    .field(&self.lower)
+                    .field(&self.upper)
+                    .finish()
             }
-        } else {
-            self
         }
-    }
-    /// Squares the interval
-    ///
-    /// Note that this has tighter bounds than multiplication, because we know
-    /// that both sides of the multiplication are the same value.
-    pub fn square(self) -> Self {
-        if self.upper < 0.0 {
-            Interval::new(self.upper.powi(2), self.lower.powi(2))
-        } else if self.lower > 0.0 {
-            Interval::new(self.lower.powi(2), self.upper.powi(2))
-        } else if self.has_nan() {
-            f32::NAN.into()
-        } else {
-            Interval::new(0.0, self.lower.abs().max(self.upper.abs()).powi(2))
-        }
-    }
-    /// Computes the sine of the interval
-    ///
-    /// Right now, this always returns the maximum range of `[-1, 1]`
-    pub fn sin(self) -> Self {
-        if self.has_nan() {
-            f32::NAN.into()
-        } else {
-            // TODO: make this smarter
-            Interval::new(-1.0, 1.0)
-        }
-    }
-    /// Computes the cosine of the interval
-    ///
-    /// Right now, this always returns the maximum range of `[-1, 1]`
-    pub fn cos(self) -> Self {
-        if self.has_nan() {
-            f32::NAN.into()
-        } else {
-            // TODO: make this smarter
-            Interval::new(-1.0, 1.0)
-        }
-    }
-    /// Computes the tangent of the interval
-    ///
-    /// Returns the `NAN` interval if the result contains a undefined point
-    pub fn tan(self) -> Self {
-        let size = self.upper - self.lower;
-        if size >= std::f32::consts::PI {
-            f32::NAN.into()
-        } else {
-            let lower = self.lower.tan();
-            let upper = self.upper.tan();
-            if upper >= lower {
-                Interval::new(lower, upper)
-            } else {
-                f32::NAN.into()
+
+        impl $name {
+            /// Builds a new interval
+            ///
+            /// There are two kinds of valid interval:
+            /// - `[lower, upper]` where `lower <= upper`
+            /// - `[NaN, NaN]`
+            ///
+            /// # Panics
+            /// Panics if the resulting interval would be invalid
+            #[inline]
+            pub fn new(lower: $t, upper: $t) -> Self {
+                assert!(
+                    upper >= lower || (lower.is_nan() && upper.is_nan()),
+                    "invalid interval [{lower}, {upper}]"
+                );
+                Self { lower, upper }
+            }
+            /// Returns the lower bound of the interval
+            #[inline]
+            pub fn lower(&self) -> $t {
+                self.lower
+            }
+            /// Returns the upper bound of the interval
+            #[inline]
+            pub fn upper(&self) -> $t {
+                self.upper
+            }
+            /// Checks whether the given value is (strictly) contained in the
+            /// interval
+            #[inline]
+            pub fn contains(&self, v: $t) -> bool {
+                v >= self.lower && v <= self.upper
+            }
+            /// Returns `true` if either bound of the interval is `NaN`
+            pub fn has_nan(&self) -> bool {
+                self.lower.is_nan() || self.upper.is_nan()
+            }
+            /// Calculates the absolute value of the interval
+            pub fn abs(self) -> Self {
+                if self.lower < 0.0 {
+                    if self.upper > 0.0 {
+                        $name::new(0.0, self.upper.max(-self.lower))
+                    } else {
+                        $name::new(-self.upper, -self.lower)
+                    }
+                } else {
+                    self
+                }
+            }
+            /// Squares the interval
+            ///
+            /// Note that this has tighter bounds than multiplication, because
+            /// we know that both sides of the multiplication are the same
+            /// value.
+            pub fn square(self) -> Self {
+                let sq_down = |v: $t| mul_down(v, v).max(0.0);
+                let sq_up = |v: $t| mul_up(v, v);
+                if self.upper < 0.0 {
+                    $name::new(sq_down(self.upper), sq_up(self.lower))
+                } else if self.lower > 0.0 {
+                    $name::new(sq_down(self.lower), sq_up(self.upper))
+                } else if self.has_nan() {
+                    $t::NAN.into()
+                } else {
+                    $name::new(
+                        0.0,
+                        sq_up(self.lower.abs().max(self.upper.abs())),
+                    )
+                }
+            }
+            /// Computes the sine of the interval
+            ///
+            /// Right now, this always returns the maximum range of `[-1, 1]`
+            pub fn sin(self) -> Self {
+                if self.has_nan() {
+                    $t::NAN.into()
+                } else {
+                    // TODO: make this smarter
+                    $name::new(-1.0, 1.0)
+                }
+            }
+            /// Computes the cosine of the interval
+            ///
+            /// Right now, this always returns the maximum range of `[-1, 1]`
+            pub fn cos(self) -> Self {
+                if self.has_nan() {
+                    $t::NAN.into()
+                } else {
+                    // TODO: make this smarter
+                    $name::new(-1.0, 1.0)
+                }
+            }
+            /// Computes the tangent of the interval
+            ///
+            /// Returns the `NAN` interval if the result contains a undefined
+            /// point
+            pub fn tan(self) -> Self {
+                let size = self.upper - self.lower;
+                if size >= std::$t::consts::PI {
+                    $t::NAN.into()
+                } else {
+                    let lower = self.lower.tan();
+                    let upper = self.upper.tan();
+                    if upper >= lower {
+                        $name::new(widen_down(lower), widen_up(upper))
+                    } else {
+                        $t::NAN.into()
+                    }
+                }
+            }
+            /// Computes the arcsine of the interval
+            ///
+            /// Returns the `NAN` interval if the input is invalid
+            pub fn asin(self) -> Self {
+                if self.lower < -1.0 || self.upper > 1.0 {
+                    $t::NAN.into()
+                } else {
+                    $name::new(
+                        widen_down(self.lower.asin()),
+                        widen_up(self.upper.asin()),
+                    )
+                }
+            }
+            /// Computes the arccosine of the interval
+            ///
+            /// Returns the `NAN` interval if the input is invalid
+            pub fn acos(self) -> Self {
+                if self.lower < -1.0 || self.upper > 1.0 {
+                    $t::NAN.into()
+                } else {
+                    $name::new(
+                        widen_down(self.upper.acos())
+                            .clamp(0.0, $t::INFINITY),
+                        widen_up(self.lower.acos()),
+                    )
+                }
+            }
+            /// Computes the arctangent of the interval
+            pub fn atan(self) -> Self {
+                $name::new(
+                    widen_down(self.lower.atan()),
+                    widen_up(self.upper.atan()),
+                )
+            }
+            /// Computes the exponent function applied to the interval
+            pub fn exp(self) -> Self {
+                $name::new(
+                    widen_down(self.lower.exp()).clamp(0.0, $t::INFINITY),
+                    widen_up(self.upper.exp()),
+                )
+            }
+            /// Computes the natural log of the input interval
+            ///
+            /// Returns the `NAN` interval if the input contains zero
+            pub fn ln(self) -> Self {
+                if self.lower <= 0.0 {
+                    $t::NAN.into()
+                } else {
+                    $name::new(
+                        widen_down(self.lower.ln()),
+                        widen_up(self.upper.ln()),
+                    )
+                }
+            }
+            /// Computes the base-2 log of the input interval
+            ///
+            /// Returns the `NAN` interval if the input contains zero
+            pub fn log2(self) -> Self {
+                if self.lower <= 0.0 {
+                    $t::NAN.into()
+                } else {
+                    $name::new(
+                        widen_down(self.lower.log2()),
+                        widen_up(self.upper.log2()),
+                    )
+                }
+            }
+            /// Computes the hyperbolic sine of the interval
+            pub fn sinh(self) -> Self {
+                $name::new(
+                    widen_down(self.lower.sinh()),
+                    widen_up(self.upper.sinh()),
+                )
+            }
+            /// Computes the hyperbolic cosine of the interval
+            ///
+            /// If the interval contains zero, the lower bound is exactly 1
+            pub fn cosh(self) -> Self {
+                if self.has_nan() {
+                    $t::NAN.into()
+                } else if self.upper < 0.0 {
+                    $name::new(
+                        widen_down(self.upper.cosh()).max(1.0),
+                        widen_up(self.lower.cosh()),
+                    )
+                } else if self.lower > 0.0 {
+                    $name::new(
+                        widen_down(self.lower.cosh()).max(1.0),
+                        widen_up(self.upper.cosh()),
+                    )
+                } else {
+                    $name::new(
+                        1.0,
+                        widen_up(self.lower.cosh().max(self.upper.cosh())),
+                    )
+                }
+            }
+            /// Computes the hyperbolic tangent of the interval
+            pub fn tanh(self) -> Self {
+                $name::new(
+                    widen_down(self.lower.tanh()).clamp(-1.0, 1.0),
+                    widen_up(self.upper.tanh()).clamp(-1.0, 1.0),
+                )
+            }
+            /// Computes the sign of the interval
+            ///
+            /// The result is in the range `[-1, 1]`, and is a single value if
+            /// the input does not contain zero.
+            pub fn sign(self) -> Self {
+                let sign = |v: $t| {
+                    if v > 0.0 {
+                        1.0
+                    } else if v < 0.0 {
+                        -1.0
+                    } else {
+                        v
+                    }
+                };
+                $name::new(sign(self.lower), sign(self.upper))
+            }
+            /// Computes the cube root of the interval
+            pub fn cbrt(self) -> Self {
+                $name::new(
+                    widen_down(self.lower.cbrt()),
+                    widen_up(self.upper.cbrt()),
+                )
+            }
+            /// Calculates the square root of the interval
+            ///
+            /// If the interval contains values below 0, returns a `NAN`
+            /// interval.
+            pub fn sqrt(self) -> Self {
+                if self.lower < 0.0 {
+                    $t::NAN.into()
+                } else {
+                    $name::new(sqrt_down(self.lower), sqrt_up(self.upper))
+                }
+            }
+            /// Calculates the reciprocal of the interval
+            ///
+            /// If the interval includes 0, returns the `NAN` interval
+            pub fn recip(self) -> Self {
+                if self.lower > 0.0 || self.upper < 0.0 {
+                    $name::new(
+                        div_down(1.0, self.upper),
+                        div_up(1.0, self.lower),
+                    )
+                } else {
+                    $t::NAN.into()
+                }
+            }
+            /// Calculates the minimum of two intervals
+            ///
+            /// Returns both the result and a [`Choice`] indicating whether one
+            /// side is always less than the other.
+            ///
+            /// If either side is `NAN`, returns the `NAN` interval and
+            /// `Choice::Both`.
+            pub fn min_choice(self, rhs: Self) -> (Self, Choice) {
+                if self.has_nan() || rhs.has_nan() {
+                    return ($t::NAN.into(), Choice::Both);
+                }
+                let choice = if self.upper < rhs.lower {
+                    Choice::Left
+                } else if rhs.upper < self.lower {
+                    Choice::Right
+                } else {
+                    Choice::Both
+                };
+                (
+                    $name::new(
+                        self.lower.min(rhs.lower),
+                        self.upper.min(rhs.upper),
+                    ),
+                    choice,
+                )
+            }
+            /// Calculates the maximum of two intervals
+            ///
+            /// Returns both the result and a [`Choice`] indicating whether one
+            /// side is always greater than the other.
+            ///
+            /// If either side is `NAN`, returns the `NAN` interval and
+            /// `Choice::Both`.
+            pub fn max_choice(self, rhs: Self) -> (Self, Choice) {
+                if self.has_nan() || rhs.has_nan() {
+                    return ($t::NAN.into(), Choice::Both);
+                }
+                let choice = if self.lower > rhs.upper {
+                    Choice::Left
+                } else if rhs.lower > self.upper {
+                    Choice::Right
+                } else {
+                    Choice::Both
+                };
+                (
+                    $name::new(
+                        self.lower.max(rhs.lower),
+                        self.upper.max(rhs.upper),
+                    ),
+                    choice,
+                )
+            }
+
+            /// Calculates the short-circuiting `AND` of two intervals
+            ///
+            /// Returns both the result and a [`Choice`] indicating whether one
+            /// side is always selected. An unambiguous 0 in `self` selects
+            /// itself; an unambiguous 1 selects the opposite branch.
+            pub fn and_choice(self, rhs: Self) -> (Self, Choice) {
+                if self.has_nan() || rhs.has_nan() {
+                    ($t::NAN.into(), Choice::Both)
+                } else if self.lower == 0.0 && self.upper == 0.0 {
+                    (0.0.into(), Choice::Left)
+                } else if !self.contains(0.0) {
+                    (rhs, Choice::Right)
+                } else {
+                    // The output will either be the RHS or zero, so extend the
+                    // interval to include zero in it.
+                    (
+                        $name::new(rhs.lower.min(0.0), rhs.upper.max(0.0)),
+                        Choice::Both,
+                    )
+                }
+            }
+
+            /// Calculates the short-circuiting `OR` of two intervals
+            ///
+            /// Returns both the result and a [`Choice`] indicating whether one
+            /// side is always selected. An unambiguous 0 in `self` selects the
+            /// opposite branch; an unambiguous 1 selects itself.
+            pub fn or_choice(self, rhs: Self) -> (Self, Choice) {
+                if self.has_nan() || rhs.has_nan() {
+                    ($t::NAN.into(), Choice::Both)
+                } else if !self.contains(0.0) {
+                    (self, Choice::Left)
+                } else if self.lower == 0.0 && self.upper == 0.0 {
+                    (rhs, Choice::Right)
+                } else {
+                    // The output could be anywhere in either interval
+                    (
+                        $name::new(
+                            self.lower.min(rhs.lower),
+                            self.upper.max(rhs.upper),
+                        ),
+                        Choice::Both,
+                    )
+                }
+            }
+
+            /// Selects between `a` and `b` based on the condition in `self`
+            ///
+            /// Returns both the result and a [`Choice`] indicating whether one
+            /// side is always selected. An unambiguous non-zero value in `self`
+            /// selects `a` (the left branch); an unambiguous 0 selects `b` (the
+            /// right branch).
+            ///
+            /// A `NaN` in the branch which isn't selected doesn't affect the
+            /// result.
+            pub fn select_choice(self, a: Self, b: Self) -> (Self, Choice) {
+                if self.has_nan() {
+                    ($t::NAN.into(), Choice::Both)
+                } else if !self.contains(0.0) {
+                    (a, Choice::Left)
+                } else if self.lower == 0.0 && self.upper == 0.0 {
+                    (b, Choice::Right)
+                } else if a.has_nan() || b.has_nan() {
+                    ($t::NAN.into(), Choice::Both)
+                } else {
+                    // The output could be anywhere in either interval
+                    (
+                        $name::new(
+                            a.lower.min(b.lower),
+                            a.upper.max(b.upper),
+                        ),
+                        Choice::Both,
+                    )
+                }
+            }
+
+            /// Returns the midpoint of the interval
+            pub fn midpoint(self) -> $t {
+                (self.lower + self.upper) / 2.0
+            }
+
+            /// Splits the interval at the midpoint
+            ///
+            /// ```
+            #[doc = concat!(" # use fidget::types::", stringify!($name), ";")]
+            #[doc = concat!(" let a = ", stringify!($name), "::new(0.0, 1.0);")]
+            /// let (lo, hi) = a.split();
+            #[doc = concat!(
+                " assert_eq!(lo, ",
+                stringify!($name),
+                "::new(0.0, 0.5));"
+            )]
+            #[doc = concat!(
+                " assert_eq!(hi, ",
+                stringify!($name),
+                "::new(0.5, 1.0));"
+            )]
+            /// ```
+            pub fn split(self) -> (Self, Self) {
+                let mid = self.midpoint();
+                (
+                    $name::new(self.lower, mid),
+                    $name::new(mid, self.upper),
+                )
+            }
+
+            /// Linear interpolation from `lower` to `upper`
+            ///
+            /// ```
+            #[doc = concat!(" # use fidget::types::", stringify!($name), ";")]
+            #[doc = concat!(" let a = ", stringify!($name), "::new(0.0, 2.0);")]
+            /// assert_eq!(a.lerp(0.5), 1.0);
+            /// assert_eq!(a.lerp(0.75), 1.5);
+            /// assert_eq!(a.lerp(2.0), 4.0);
+            /// ```
+            pub fn lerp(self, frac: $t) -> $t {
+                self.lower * (1.0 - frac) + self.upper * frac
+            }
+
+            /// Calculates the width of the interval
+            ///
+            /// ```
+            #[doc = concat!(" # use fidget::types::", stringify!($name), ";")]
+            #[doc = concat!(" let a = ", stringify!($name), "::new(2.0, 3.0);")]
+            /// assert_eq!(a.width(), 1.0);
+            #[doc = concat!(" let b = ", stringify!($name), "::new(2.0, 5.0);")]
+            /// assert_eq!(b.width(), 3.0);
+            /// ```
+            pub fn width(self) -> $t {
+                self.upper - self.lower
+            }
+
+            /// Least non-negative remainder
+            pub fn rem_euclid(&self, other: $name) -> Self {
+                // TODO optimize this more?
+                if self.has_nan() || other.has_nan() || other.contains(0.0) {
+                    $t::NAN.into()
+                } else if other.lower == other.upper && other.lower > 0.0 {
+                    let a = self.lower / other.lower;
+                    let b = self.upper / other.lower;
+                    if a != a.floor() && a.floor() == b.floor() {
+                        // Equivalent to `rem_euclid`, with directed rounding
+                        // (the remainder itself is exact)
+                        let rem =
+                            |v: $t, add: fn($t, $t) -> $t| {
+                                let r = v % other.lower;
+                                if r < 0.0 {
+                                    add(r, other.lower)
+                                } else {
+                                    r
+                                }
+                            };
+                        $name::new(
+                            rem(self.lower, add_down),
+                            rem(self.upper, add_up),
+                        )
+                    } else {
+                        $name::new(0.0, other.abs().upper())
+                    }
+                } else {
+                    $name::new(0.0, other.abs().upper())
+                }
+            }
+
+            /// Largest value that is less-than-or-equal to this value
+            pub fn floor(&self) -> Self {
+                $name::new(self.lower.floor(), self.upper.floor())
+            }
+
+            /// Smallest value that is greater-than-or-equal to this value
+            pub fn ceil(&self) -> Self {
+                $name::new(self.lower.ceil(), self.upper.ceil())
+            }
+
+            /// Rounded value
+            pub fn round(&self) -> Self {
+                $name::new(self.lower.round(), self.upper.round())
+            }
+
+            /// Four-quadrant arctangent
+            pub fn atan2(self, x: Self) -> Self {
+                if self.has_nan() || x.has_nan() {
+                    $t::NAN.into()
+                } else {
+                    // TODO optimize this further
+                    $name::new(
+                        -std::$t::consts::PI,
+                        std::$t::consts::PI,
+                    )
+                }
+            }
+
+            /// Raises the interval to the power of another interval
+            ///
+            /// If the exponent is a single integer, the result is tight (e.g.
+            /// even powers are never negative) and negative bases are allowed.
+            ///
+            /// Otherwise, returns the `NAN` interval if the base contains
+            /// negative values (because non-integer powers of them are
+            /// undefined), or if the base contains zero and the exponent is
+            /// negative.
+            pub fn pow(self, rhs: Self) -> Self {
+                if self.has_nan() || rhs.has_nan() {
+                    $t::NAN.into()
+                } else if rhs.lower == rhs.upper && rhs.lower.fract() == 0.0 {
+                    let n = rhs.lower;
+                    let (a_lo, a_hi) = powi_bounds(self.lower, n);
+                    let (b_lo, b_hi) = powi_bounds(self.upper, n);
+                    if n < 0.0 && self.contains(0.0) {
+                        $t::NAN.into()
+                    } else if n > 0.0 && n % 2.0 == 0.0 && self.contains(0.0) {
+                        $name::new(0.0, a_hi.max(b_hi))
+                    } else {
+                        $name::new(a_lo.min(b_lo), a_hi.max(b_hi))
+                    }
+                } else if self.lower < 0.0
+                    || (self.lower == 0.0 && rhs.lower < 0.0)
+                {
+                    $t::NAN.into()
+                } else {
+                    // For non-negative bases, `a^b` is monotonic in both
+                    // arguments, so its extrema are at the corners.
+                    let mut lower = $t::INFINITY;
+                    let mut upper = $t::NEG_INFINITY;
+                    for a in [self.lower, self.upper] {
+                        for b in [rhs.lower, rhs.upper] {
+                            let v = a.powf(b);
+                            lower = lower.min(v);
+                            upper = upper.max(v);
+                        }
+                    }
+                    $name::new(widen_down(lower).max(0.0), widen_up(upper))
+                }
             }
         }
-    }
-    /// Computes the arcsine of the interval
-    ///
-    /// Returns the `NAN` interval if the input is invalid
-    pub fn asin(self) -> Self {
-        if self.lower < -1.0 || self.upper > 1.0 {
-            f32::NAN.into()
-        } else {
-            Interval::new(self.lower.asin(), self.upper.asin())
-        }
-    }
-    /// Computes the arccosine of the interval
-    ///
-    /// Returns the `NAN` interval if the input is invalid
-    pub fn acos(self) -> Self {
-        if self.lower < -1.0 || self.upper > 1.0 {
-            f32::NAN.into()
-        } else {
-            Interval::new(self.upper.acos(), self.lower.acos())
-        }
-    }
-    /// Computes the arctangent of the interval
-    pub fn atan(self) -> Self {
-        Interval::new(self.lower.atan(), self.upper.atan())
-    }
-    /// Computes the exponent function applied to the interval
-    pub fn exp(self) -> Self {
-        Interval::new(self.lower.exp(), self.upper.exp())
-    }
-    /// Computes the natural log of the input interval
-    ///
-    /// Returns the `NAN` interval if the input contains zero
-    pub fn ln(self) -> Self {
-        if self.lower <= 0.0 {
-            f32::NAN.into()
-        } else {
-            Interval::new(self.lower.ln(), self.upper.ln())
-        }
-    }
-    /// Calculates the square root of the interval
-    ///
-    /// If the interval contains values below 0, returns a `NAN` interval.
-    pub fn sqrt(self) -> Self {
-        if self.lower < 0.0 {
-            f32::NAN.into()
-        } else {
-            Interval::new(self.lower.sqrt(), self.upper.sqrt())
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "({}, {})", self.lower, self.upper)
+            }
         }
-    }
-    /// Calculates the reciprocal of the interval
-    ///
-    /// If the interval includes 0, returns the `NAN` interval
-    pub fn recip(self) -> Self {
-        if self.lower > 0.0 || self.upper < 0.0 {
-            Interval::new(1.0 / self.upper, 1.0 / self.lower)
-        } else {
-            f32::NAN.into()
+
+        impl From<[$t; 2]> for $name {
+            fn from(i: [$t; 2]) -> $name {
+                $name::new(i[0], i[1])
+            }
         }
-    }
-    /// Calculates the minimum of two intervals
-    ///
-    /// Returns both the result and a [`Choice`] indicating whether one side is
-    /// always less than the other.
-    ///
-    /// If either side is `NAN`, returns the `NAN` interval and `Choice::Both`.
-    pub fn min_choice(self, rhs: Self) -> (Self, Choice) {
-        if self.has_nan() || rhs.has_nan() {
-            return (f32::NAN.into(), Choice::Both);
+
+        impl From<$t> for $name {
+            fn from(f: $t) -> Self {
+                $name::new(f, f)
+            }
         }
-        let choice = if self.upper < rhs.lower {
-            Choice::Left
-        } else if rhs.upper < self.lower {
-            Choice::Right
-        } else {
-            Choice::Both
-        };
-        (
-            Interval::new(self.lower.min(rhs.lower), self.upper.min(rhs.upper)),
-            choice,
-        )
-    }
-    /// Calculates the maximum of two intervals
-    ///
-    /// Returns both the result and a [`Choice`] indicating whether one side is
-    /// always greater than the other.
-    ///
-    /// If either side is `NAN`, returns the `NAN` interval and `Choice::Both`.
-    pub fn max_choice(self, rhs: Self) -> (Self, Choice) {
-        if self.has_nan() || rhs.has_nan() {
-            return (f32::NAN.into(), Choice::Both);
+
+        impl std::ops::Add<$name> for $name {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self {
+                $name::new(
+                    add_down(self.lower, rhs.lower),
+                    add_up(self.upper, rhs.upper),
+                )
+            }
         }
-        let choice = if self.lower > rhs.upper {
-            Choice::Left
-        } else if rhs.lower > self.upper {
-            Choice::Right
-        } else {
-            Choice::Both
-        };
-        (
-            Interval::new(self.lower.max(rhs.lower), self.upper.max(rhs.upper)),
-            choice,
-        )
-    }
 
-    /// Calculates the short-circuiting `AND` of two intervals
-    ///
-    /// Returns both the result and a [`Choice`] indicating whether one side is
-    /// always selected.  An unambiguous 0 in `self` selects itself; an
-    /// unambiguous 1 selects the opposite branch.
-    pub fn and_choice(self, rhs: Self) -> (Self, Choice) {
-        if self.has_nan() || rhs.has_nan() {
-            (f32::NAN.into(), Choice::Both)
-        } else if self.lower == 0.0 && self.upper == 0.0 {
-            (0.0.into(), Choice::Left)
-        } else if !self.contains(0.0) {
-            (rhs, Choice::Right)
-        } else {
-            // The output will either be the RHS or zero, so extend the interval
-            // to include zero in it.
-            (
-                Interval::new(rhs.lower.min(0.0), rhs.upper.max(0.0)),
-                Choice::Both,
-            )
+        impl std::ops::Mul<$name> for $name {
+            type Output = Self;
+            fn mul(self, rhs: Self) -> Self {
+                if self.has_nan() || rhs.has_nan() {
+                    return $t::NAN.into();
+                }
+                let mut lo = [0.0; 4];
+                let mut hi = [0.0; 4];
+                let mut k = 0;
+                for i in [self.lower, self.upper] {
+                    for j in [rhs.lower, rhs.upper] {
+                        lo[k] = mul_down(i, j);
+                        hi[k] = mul_up(i, j);
+                        k += 1;
+                    }
+                }
+                let mut lower = lo[0];
+                let mut upper = hi[0];
+                for k in 1..4 {
+                    lower = lower.min(lo[k]);
+                    upper = upper.max(hi[k]);
+                }
+                $name::new(lower, upper)
+            }
         }
-    }
 
-    /// Calculates the short-circuiting `OR` of two intervals
-    ///
-    /// Returns both the result and a [`Choice`] indicating whether one side is
-    /// always selected.  An unambiguous 0 in `self` selects the opposite
-    /// branch; an unambiguous 1 selects itself.
-    pub fn or_choice(self, rhs: Self) -> (Self, Choice) {
-        if self.has_nan() || rhs.has_nan() {
-            (f32::NAN.into(), Choice::Both)
-        } else if !self.contains(0.0) {
-            (self, Choice::Left)
-        } else if self.lower == 0.0 && self.upper == 0.0 {
-            (rhs, Choice::Right)
-        } else {
-            // The output could be anywhere in either interval
-            (
-                Interval::new(
-                    self.lower.min(rhs.lower),
-                    self.upper.max(rhs.upper),
-                ),
-                Choice::Both,
-            )
+        impl std::ops::Mul<$t> for $name {
+            type Output = Self;
+            fn mul(self, rhs: $t) -> Self {
+                if self.has_nan() || rhs.is_nan() {
+                    $t::NAN.into()
+                } else if rhs < 0.0 {
+                    $name::new(
+                        mul_down(self.upper, rhs),
+                        mul_up(self.lower, rhs),
+                    )
+                } else {
+                    $name::new(
+                        mul_down(self.lower, rhs),
+                        mul_up(self.upper, rhs),
+                    )
+                }
+            }
         }
-    }
 
-    /// Returns the midpoint of the interval
-    pub fn midpoint(self) -> f32 {
-        (self.lower + self.upper) / 2.0
-    }
+        impl std::ops::Div<$name> for $name {
+            type Output = Self;
+            fn div(self, rhs: Self) -> Self {
+                if self.has_nan() {
+                    return $t::NAN.into();
+                }
+                if rhs.lower > 0.0 || rhs.upper < 0.0 {
+                    let mut lo = [0.0; 4];
+                    let mut hi = [0.0; 4];
+                    let mut k = 0;
+                    for i in [self.lower, self.upper] {
+                        for j in [rhs.lower, rhs.upper] {
+                            lo[k] = div_down(i, j);
+                            hi[k] = div_up(i, j);
+                            k += 1;
+                        }
+                    }
+                    let mut lower = lo[0];
+                    let mut upper = hi[0];
+                    for k in 1..4 {
+                        lower = lower.min(lo[k]);
+                        upper = upper.max(hi[k]);
+                    }
+                    $name::new(lower, upper)
+                } else {
+                    $t::NAN.into()
+                }
+            }
+        }
 
-    /// Splits the interval at the midpoint
-    ///
-    /// ```
-    /// # use fidget::types::Interval;
-    /// let a = Interval::new(0.0, 1.0);
-    /// let (lo, hi) = a.split();
-    /// assert_eq!(lo, Interval::new(0.0, 0.5));
-    /// assert_eq!(hi, Interval::new(0.5, 1.0));
-    /// ```
-    pub fn split(self) -> (Self, Self) {
-        let mid = self.midpoint();
-        (
-            Interval::new(self.lower, mid),
-            Interval::new(mid, self.upper),
-        )
-    }
+        impl std::ops::Sub<$name> for $name {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self {
+                $name::new(
+                    sub_down(self.lower, rhs.upper),
+                    sub_up(self.upper, rhs.lower),
+                )
+            }
+        }
 
-    /// Linear interpolation from `lower` to `upper`
-    ///
-    /// ```
-    /// # use fidget::types::Interval;
-    /// let a = Interval::new(0.0, 2.0);
-    /// assert_eq!(a.lerp(0.5), 1.0);
-    /// assert_eq!(a.lerp(0.75), 1.5);
-    /// assert_eq!(a.lerp(2.0), 4.0);
-    /// ```
-    pub fn lerp(self, frac: f32) -> f32 {
-        self.lower * (1.0 - frac) + self.upper * frac
-    }
+        impl std::ops::Neg for $name {
+            type Output = Self;
+            fn neg(self) -> Self {
+                $name::new(-self.upper, -self.lower)
+            }
+        }
+    };
+}
+pub(super) use interval_impl;
 
-    /// Calculates the width of the interval
+interval_impl!(
+    /// Stores a range, with conservative calculations to guarantee that it
+    /// always contains the actual value.
     ///
-    /// ```
-    /// # use fidget::types::Interval;
-    /// let a = Interval::new(2.0, 3.0);
-    /// assert_eq!(a.width(), 1.0);
-    /// let b = Interval::new(2.0, 5.0);
-    /// assert_eq!(b.width(), 3.0);
-    /// ```
-    pub fn width(self) -> f32 {
-        self.upper - self.lower
-    }
+    /// # Warning
+    /// By default, this implementation uses round-to-nearest arithmetic and the
+    /// platform's math library, so bounds may be slightly too tight (by an ULP
+    /// or so). Enable the `rigorous-intervals` feature to round every bound
+    /// outwards.
+    Interval,
+    f32
+);
 
+impl Interval {
     /// Checks that the two values are roughly equal, panicking otherwise
-    #[cfg(test)]
+    #[cfg(any(test, feature = "eval-tests"))]
     pub(crate) fn compare_eq(&self, other: Self) {
         let d = (self.lower - other.lower)
             .abs()
@@ -333,168 +727,176 @@ impl Interval {
             panic!("lhs != rhs ({self:?} != {other:?})");
         }
     }
+}
 
-    /// Least non-negative remainder
-    pub fn rem_euclid(&self, other: Interval) -> Self {
-        // TODO optimize this more?
-        if self.has_nan() || other.has_nan() || other.contains(0.0) {
-            f32::NAN.into()
-        } else if other.lower == other.upper && other.lower > 0.0 {
-            let a = self.lower / other.lower;
-            let b = self.upper / other.lower;
-            if a != a.floor() && a.floor() == b.floor() {
-                Interval::new(
-                    self.lower.rem_euclid(other.lower),
-                    self.upper.rem_euclid(other.lower),
-                )
-            } else {
-                Interval::new(0.0, other.abs().upper())
-            }
-        } else {
-            Interval::new(0.0, other.abs().upper())
-        }
-    }
-
-    /// Largest value that is less-than-or-equal to this value
-    pub fn floor(&self) -> Self {
-        Interval::new(self.lower.floor(), self.upper.floor())
-    }
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    /// Smallest value that is greater-than-or-equal to this value
-    pub fn ceil(&self) -> Self {
-        Interval::new(self.lower.ceil(), self.upper.ceil())
+    #[test]
+    fn test_interval() {
+        let a = Interval::new(0.0, 1.0);
+        let b = Interval::new(0.5, 1.5);
+        let (v, c) = a.min_choice(b);
+        assert_eq!(v, [0.0, 1.0].into());
+        assert_eq!(c, Choice::Both);
     }
 
-    /// Rounded value
-    pub fn round(&self) -> Self {
-        Interval::new(self.lower.round(), self.upper.round())
-    }
+    #[test]
+    fn test_pow() {
+        let a = Interval::new(-2.0, 3.0);
+        assert_eq!(a.pow(2.0.into()), Interval::new(0.0, 9.0));
+        assert_eq!(a.pow(3.0.into()), Interval::new(-8.0, 27.0));
+        assert_eq!(a.pow(4.0.into()), Interval::new(0.0, 81.0));
+        assert_eq!(a.pow(0.0.into()), Interval::new(1.0, 1.0));
+        assert!(a.pow((-2.0).into()).has_nan());
+        assert!(a.pow(0.5.into()).has_nan());
 
-    /// Four-quadrant arctangent
-    pub fn atan2(self, x: Self) -> Self {
-        if self.has_nan() || x.has_nan() {
-            f32::NAN.into()
-        } else {
-            // TODO optimize this further
-            Interval::new(-std::f32::consts::PI, std::f32::consts::PI)
-        }
-    }
-}
+        let b = Interval::new(-3.0, -1.0);
+        assert_eq!(b.pow(2.0.into()), Interval::new(1.0, 9.0));
 
-impl std::fmt::Display for Interval {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}, {})", self.lower, self.upper)
-    }
-}
+        // These results are inexact, so they're rounded outwards in rigorous
+        // mode (and may differ from round-to-nearest by an ULP)
+        #[cfg(not(feature = "rigorous-intervals"))]
+        {
+            assert_eq!(b.pow((-1.0).into()), Interval::new(-1.0, -1.0 / 3.0));
+            assert_eq!(b.pow((-2.0).into()), Interval::new(1.0 / 9.0, 1.0));
 
-impl From<[f32; 2]> for Interval {
-    fn from(i: [f32; 2]) -> Interval {
-        Interval::new(i[0], i[1])
-    }
-}
+            let c = Interval::new(1.0, 4.0);
+            assert_eq!(c.pow(0.5.into()), Interval::new(1.0, 2.0));
+            assert_eq!(
+                c.pow(Interval::new(-1.0, 2.0)),
+                Interval::new(0.25, 16.0)
+            );
+        }
+        #[cfg(feature = "rigorous-intervals")]
+        {
+            let v = b.pow((-1.0).into());
+            assert_eq!(v.lower(), -1.0);
+            assert!(f64::from(v.upper()) > -1.0 / 3.0);
+            let v = b.pow((-2.0).into());
+            assert!(f64::from(v.lower()) < 1.0 / 9.0);
+            assert_eq!(v.upper(), 1.0);
 
-impl From<f32> for Interval {
-    fn from(f: f32) -> Self {
-        Interval::new(f, f)
+            let c = Interval::new(1.0, 4.0);
+            let v = c.pow(0.5.into());
+            assert!(v.lower() <= 1.0 && v.upper() >= 2.0);
+            assert!(v.width() < 1.0 + 1e-6);
+        }
     }
-}
 
-impl std::ops::Add<Interval> for Interval {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self {
-        Interval::new(self.lower + rhs.lower, self.upper + rhs.upper)
+    #[test]
+    fn test_hyperbolic() {
+        let a = Interval::new(-1.0, 2.0);
+        a.cosh().compare_eq(Interval::new(1.0, 2f32.cosh()));
+        a.sinh()
+            .compare_eq(Interval::new((-1f32).sinh(), 2f32.sinh()));
+        assert_eq!(a.sign(), Interval::new(-1.0, 1.0));
+        assert_eq!(Interval::new(0.0, 2.0).sign(), Interval::new(0.0, 1.0));
     }
-}
 
-impl std::ops::Mul<Interval> for Interval {
-    type Output = Self;
-    fn mul(self, rhs: Self) -> Self {
-        if self.has_nan() || rhs.has_nan() {
-            return f32::NAN.into();
-        }
-        let mut out = [0.0; 4];
-        let mut k = 0;
-        for i in [self.lower, self.upper] {
-            for j in [rhs.lower, rhs.upper] {
-                out[k] = i * j;
-                k += 1;
-            }
-        }
-        let mut lower = out[0];
-        let mut upper = out[0];
-        for &v in &out[1..] {
-            lower = lower.min(v);
-            upper = upper.max(v);
-        }
-        Interval::new(lower, upper)
+    /// Picks a random interval, with a spread of magnitudes and widths
+    fn random_interval(rng: &mut impl rand::Rng) -> Interval {
+        let scale = 10f32.powi(rng.gen_range(-3..4));
+        let lower = rng.gen_range(-1.0..1.0) * scale;
+        let width = rng.gen_range(0.0..1.0) * scale;
+        Interval::new(lower, lower + width)
     }
-}
 
-impl std::ops::Mul<f32> for Interval {
-    type Output = Self;
-    fn mul(self, rhs: f32) -> Self {
-        if self.has_nan() || rhs.is_nan() {
-            f32::NAN.into()
-        } else if rhs < 0.0 {
-            Interval::new(self.upper * rhs, self.lower * rhs)
+    /// Checks that a sampled point is within an interval result
+    ///
+    /// In rigorous mode, the sample is evaluated in double precision, so it
+    /// approximates the exact result; otherwise, it's evaluated in `f32`
+    fn check_sample(name: &str, out: Interval, v32: f32, v64: f64) {
+        let v = if cfg!(feature = "rigorous-intervals") {
+            v64
         } else {
-            Interval::new(self.lower * rhs, self.upper * rhs)
+            f64::from(v32)
+        };
+        if out.has_nan() || v.is_nan() {
+            return;
         }
+        assert!(
+            f64::from(out.lower()) <= v && v <= f64::from(out.upper()),
+            "{name}: {v} not in {out}"
+        );
     }
-}
 
-impl std::ops::Div<Interval> for Interval {
-    type Output = Self;
-    fn div(self, rhs: Self) -> Self {
-        if self.has_nan() {
-            return f32::NAN.into();
-        }
-        if rhs.lower > 0.0 || rhs.upper < 0.0 {
-            let mut out = [0.0; 4];
-            let mut k = 0;
-            for i in [self.lower, self.upper] {
-                for j in [rhs.lower, rhs.upper] {
-                    out[k] = i / j;
-                    k += 1;
+    #[test]
+    fn test_random_samples_unary() {
+        use rand::{Rng, SeedableRng};
+        type Op = (&'static str, fn(Interval) -> Interval, fn(f64) -> f64);
+        let ops: [Op; 15] = [
+            ("square", Interval::square, |v| v * v),
+            ("sqrt", Interval::sqrt, f64::sqrt),
+            ("recip", Interval::recip, f64::recip),
+            ("tan", Interval::tan, f64::tan),
+            ("asin", Interval::asin, f64::asin),
+            ("acos", Interval::acos, f64::acos),
+            ("atan", Interval::atan, f64::atan),
+            ("exp", Interval::exp, f64::exp),
+            ("ln", Interval::ln, f64::ln),
+            ("log2", Interval::log2, f64::log2),
+            ("sinh", Interval::sinh, f64::sinh),
+            ("cosh", Interval::cosh, f64::cosh),
+            ("tanh", Interval::tanh, f64::tanh),
+            ("cbrt", Interval::cbrt, f64::cbrt),
+            (
+                "rem_euclid",
+                |i| i.rem_euclid(0.3.into()),
+                |v| v.rem_euclid(f64::from(0.3f32)),
+            ),
+        ];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x1234);
+        for (name, f, g) in ops {
+            for _ in 0..1000 {
+                let i = random_interval(&mut rng);
+                let out = f(i);
+                for _ in 0..8 {
+                    let v = i.lerp(rng.gen_range(0.0..=1.0));
+                    let v = v.clamp(i.lower(), i.upper());
+                    let v32 = f(v.into()).lower();
+                    check_sample(name, out, v32, g(f64::from(v)));
                 }
             }
-            let mut lower = out[0];
-            let mut upper = out[0];
-            for &v in &out[1..] {
-                lower = lower.min(v);
-                upper = upper.max(v);
-            }
-            Interval::new(lower, upper)
-        } else {
-            f32::NAN.into()
         }
     }
-}
-
-impl std::ops::Sub<Interval> for Interval {
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self {
-        Interval::new(self.lower - rhs.upper, self.upper - rhs.lower)
-    }
-}
-
-impl std::ops::Neg for Interval {
-    type Output = Self;
-    fn neg(self) -> Self {
-        Interval::new(-self.upper, -self.lower)
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
 
     #[test]
-    fn test_interval() {
-        let a = Interval::new(0.0, 1.0);
-        let b = Interval::new(0.5, 1.5);
-        let (v, c) = a.min_choice(b);
-        assert_eq!(v, [0.0, 1.0].into());
-        assert_eq!(c, Choice::Both);
+    fn test_random_samples_binary() {
+        use rand::{Rng, SeedableRng};
+        type Op = (
+            &'static str,
+            fn(Interval, Interval) -> Interval,
+            fn(f64, f64) -> f64,
+        );
+        let ops: [Op; 5] = [
+            ("add", |a, b| a + b, |a, b| a + b),
+            ("sub", |a, b| a - b, |a, b| a - b),
+            ("mul", |a, b| a * b, |a, b| a * b),
+            ("div", |a, b| a / b, |a, b| a / b),
+            ("pow", |a, b| a.pow(b.round()), |a, b| a.powf(b.round())),
+        ];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x5678);
+        for (name, f, g) in ops {
+            for _ in 0..1000 {
+                let a = random_interval(&mut rng);
+                let b = if name == "pow" {
+                    Interval::from(rng.gen_range(-4..8) as f32)
+                } else {
+                    random_interval(&mut rng)
+                };
+                let out = f(a, b);
+                for _ in 0..8 {
+                    let va = a.lerp(rng.gen_range(0.0..=1.0));
+                    let va = va.clamp(a.lower(), a.upper());
+                    let vb = b.lerp(rng.gen_range(0.0..=1.0));
+                    let vb = vb.clamp(b.lower(), b.upper());
+                    let v32 = f(va.into(), vb.into()).lower();
+                    let v64 = g(f64::from(va), f64::from(vb));
+                    check_sample(name, out, v32, v64);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,50 @@
+use super::{
+    interval::interval_impl,
+    round::double::{
+        add_down, add_up, div_down, div_up, mul_down, mul_up, powi_bounds,
+        sqrt_down, sqrt_up, sub_down, sub_up, widen_down, widen_up,
+    },
+};
+use crate::{types::Interval, vm::Choice};
+
+interval_impl!(
+    /// Stores a double-precision range, with conservative calculations to
+    /// guarantee that it always contains the actual value.
+    ///
+    /// This is the `f64` equivalent of [`Interval`], used by
+    /// [`FunctionF64`](crate::eval::FunctionF64) evaluators, and has the same
+    /// rounding behavior.
+    IntervalF64,
+    f64
+);
+
+impl From<f32> for IntervalF64 {
+    fn from(f: f32) -> Self {
+        IntervalF64::new(f.into(), f.into())
+    }
+}
+
+impl From<Interval> for IntervalF64 {
+    fn from(i: Interval) -> Self {
+        IntervalF64::new(i.lower().into(), i.upper().into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interval_f64() {
+        // Values which aren't representable as `f32`
+        let a = IntervalF64::new(1e8, 1e8 + 0.25);
+        let b = a - IntervalF64::from(1e8);
+        assert_eq!(b, IntervalF64::new(0.0, 0.25));
+        assert_eq!((a * 2.0).width(), 0.5);
+
+        let i = IntervalF64::from(Interval::new(-1.0, 2.5));
+        assert_eq!(i, IntervalF64::new(-1.0, 2.5));
+        assert_eq!(i.square(), IntervalF64::new(0.0, 6.25));
+        assert_eq!(IntervalF64::from(3.0f32), IntervalF64::new(3.0, 3.0));
+    }
+}
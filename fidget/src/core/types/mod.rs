@@ -1,6 +1,15 @@
 //! Custom types used during evaluation
 
+mod dual;
 mod grad;
+mod grad_f64;
+mod hess;
 mod interval;
+mod interval_f64;
+mod round;
+pub use dual::Dual;
 pub use grad::Grad;
+pub use grad_f64::GradF64;
+pub use hess::Hess;
 pub use interval::Interval;
+pub use interval_f64::IntervalF64;
@@ -0,0 +1,280 @@
+//! Directed rounding helpers for interval arithmetic
+//!
+//! When the `rigorous-intervals` feature is enabled, each `*_down` / `*_up`
+//! function returns a value which is guaranteed to be on the correct side of
+//! the exact result.  Basic arithmetic uses error-free transformations to
+//! detect inexact results (so exact results aren't widened), while functions
+//! which rely on the platform's math library are widened by one ULP.
+//!
+//! Without the feature, these are plain round-to-nearest operations.
+//!
+//! The functions are implemented for both `f32` (in [`single`]) and `f64` (in
+//! [`double`]).
+
+const RIGOROUS: bool = cfg!(feature = "rigorous-intervals");
+
+/// Implements the rounding functions for the given float type, in a module
+/// with the given name
+macro_rules! round_impl {
+    ($name:ident, $t:ident) => {
+        pub mod $name {
+            use super::RIGOROUS;
+
+            /// Results below this magnitude may have inexact error terms (due
+            /// to underflow), so they're always widened.
+            const TINY: $t =
+                $t::MIN_POSITIVE * (1u64 << $t::MANTISSA_DIGITS) as $t;
+
+            /// Rounds `v` down, given the sign of `exact - v` (or `NaN` if
+            /// unknown)
+            #[inline]
+            fn down(v: $t, err: $t) -> $t {
+                if !RIGOROUS || err >= 0.0 {
+                    v
+                } else {
+                    v.next_down()
+                }
+            }
+
+            /// Rounds `v` up, given the sign of `exact - v` (or `NaN` if
+            /// unknown)
+            #[inline]
+            fn up(v: $t, err: $t) -> $t {
+                if !RIGOROUS || err <= 0.0 {
+                    v
+                } else {
+                    v.next_up()
+                }
+            }
+
+            /// Returns `exact - s` for `s = a + b`, using Knuth's TwoSum
+            #[inline]
+            fn add_err(a: $t, b: $t, s: $t) -> $t {
+                if a.is_infinite() || b.is_infinite() {
+                    0.0
+                } else {
+                    let bb = s - a;
+                    (a - (s - bb)) + (b - bb)
+                }
+            }
+
+            /// Returns `exact - p` for `p = a * b`, using a fused multiply-add
+            #[inline]
+            fn mul_err(a: $t, b: $t, p: $t) -> $t {
+                if a == 0.0 || b == 0.0 || a.is_infinite() || b.is_infinite() {
+                    0.0
+                } else if p.abs() < TINY {
+                    $t::NAN
+                } else {
+                    a.mul_add(b, -p)
+                }
+            }
+
+            /// Returns the sign of `exact - r` for `r = a / b`
+            #[inline]
+            fn div_err(a: $t, b: $t, r: $t) -> $t {
+                if a == 0.0 || a.is_infinite() || b.is_infinite() {
+                    0.0
+                } else if !r.is_finite() || r.abs() < TINY {
+                    $t::NAN
+                } else {
+                    (-r).mul_add(b, a) * b.signum()
+                }
+            }
+
+            /// Returns the sign of `exact - r` for `r = √a`
+            #[inline]
+            fn sqrt_err(a: $t, r: $t) -> $t {
+                if a == 0.0 || a.is_infinite() {
+                    0.0
+                } else if r < TINY {
+                    $t::NAN
+                } else {
+                    (-r).mul_add(r, a)
+                }
+            }
+
+            #[inline]
+            pub fn add_down(a: $t, b: $t) -> $t {
+                let s = a + b;
+                if RIGOROUS {
+                    down(s, add_err(a, b, s))
+                } else {
+                    s
+                }
+            }
+
+            #[inline]
+            pub fn add_up(a: $t, b: $t) -> $t {
+                let s = a + b;
+                if RIGOROUS {
+                    up(s, add_err(a, b, s))
+                } else {
+                    s
+                }
+            }
+
+            #[inline]
+            pub fn sub_down(a: $t, b: $t) -> $t {
+                add_down(a, -b)
+            }
+
+            #[inline]
+            pub fn sub_up(a: $t, b: $t) -> $t {
+                add_up(a, -b)
+            }
+
+            #[inline]
+            pub fn mul_down(a: $t, b: $t) -> $t {
+                let p = a * b;
+                if RIGOROUS {
+                    down(p, mul_err(a, b, p))
+                } else {
+                    p
+                }
+            }
+
+            #[inline]
+            pub fn mul_up(a: $t, b: $t) -> $t {
+                let p = a * b;
+                if RIGOROUS {
+                    up(p, mul_err(a, b, p))
+                } else {
+                    p
+                }
+            }
+
+            #[inline]
+            pub fn div_down(a: $t, b: $t) -> $t {
+                let r = a / b;
+                if RIGOROUS {
+                    down(r, div_err(a, b, r))
+                } else {
+                    r
+                }
+            }
+
+            #[inline]
+            pub fn div_up(a: $t, b: $t) -> $t {
+                let r = a / b;
+                if RIGOROUS {
+                    up(r, div_err(a, b, r))
+                } else {
+                    r
+                }
+            }
+
+            #[inline]
+            pub fn sqrt_down(a: $t) -> $t {
+                let r = a.sqrt();
+                if RIGOROUS {
+                    down(r, sqrt_err(a, r))
+                } else {
+                    r
+                }
+            }
+
+            #[inline]
+            pub fn sqrt_up(a: $t) -> $t {
+                let r = a.sqrt();
+                if RIGOROUS {
+                    up(r, sqrt_err(a, r))
+                } else {
+                    r
+                }
+            }
+
+            /// Widens the result of a math library function downwards by one
+            /// ULP
+            #[inline]
+            pub fn widen_down(v: $t) -> $t {
+                down(v, $t::NAN)
+            }
+
+            /// Widens the result of a math library function upwards by one ULP
+            #[inline]
+            pub fn widen_up(v: $t) -> $t {
+                up(v, $t::NAN)
+            }
+
+            /// Bounds `x^n` for an integer `n`, returning `(lower, upper)`
+            ///
+            /// Small exponents are computed with directed multiplication, so
+            /// exact results remain exact; larger exponents use `powf` and are
+            /// widened.
+            pub fn powi_bounds(x: $t, n: $t) -> ($t, $t) {
+                if !RIGOROUS || n.abs() > 64.0 {
+                    let v = x.powf(n);
+                    return (widen_down(v), widen_up(v));
+                }
+                let pow = |m: $t, mul: fn($t, $t) -> $t| {
+                    let (mut out, mut base, mut k) = (1.0, m, n.abs() as u32);
+                    while k > 0 {
+                        if k & 1 == 1 {
+                            out = mul(out, base);
+                        }
+                        base = mul(base, base);
+                        k >>= 1;
+                    }
+                    out
+                };
+                let m = x.abs();
+                let (lo, hi) = if n >= 0.0 {
+                    (pow(m, mul_down), pow(m, mul_up))
+                } else {
+                    (
+                        div_down(1.0, pow(m, mul_up)),
+                        div_up(1.0, pow(m, mul_down)),
+                    )
+                };
+                if x < 0.0 && n % 2.0 != 0.0 {
+                    (-hi, -lo)
+                } else {
+                    (lo, hi)
+                }
+            }
+        }
+    };
+}
+
+round_impl!(single, f32);
+round_impl!(double, f64);
+
+#[cfg(all(test, feature = "rigorous-intervals"))]
+mod test {
+    use super::single::*;
+
+    #[test]
+    fn test_exact_results() {
+        assert_eq!(add_down(1.0, 2.0), 3.0);
+        assert_eq!(add_up(1.0, 2.0), 3.0);
+        assert_eq!(mul_down(3.0, -2.0), -6.0);
+        assert_eq!(div_up(1.0, 4.0), 0.25);
+        assert_eq!(sqrt_down(9.0), 3.0);
+    }
+
+    #[test]
+    fn test_inexact_results() {
+        let a = add_down(1.0, 1e-8);
+        let b = add_up(1.0, 1e-8);
+        assert_eq!(a, 1.0);
+        assert_eq!(b, 1.0f32.next_up());
+
+        let a = div_down(1.0, 3.0);
+        let b = div_up(1.0, 3.0);
+        assert!(f64::from(a) < 1.0 / 3.0 && f64::from(b) > 1.0 / 3.0);
+        assert_eq!(a.next_up(), b);
+
+        let a = sqrt_down(2.0);
+        let b = sqrt_up(2.0);
+        assert!(f64::from(a) < 2f64.sqrt() && f64::from(b) > 2f64.sqrt());
+
+        let (lo, hi) = powi_bounds(1.1, 3.0);
+        assert!(f64::from(lo) < 1.331 && f64::from(hi) > 1.331);
+        assert_eq!(powi_bounds(-2.0, 3.0), (-8.0, -8.0));
+        assert_eq!(powi_bounds(-2.0, -2.0), (0.25, 0.25));
+
+        assert_eq!(add_down(f32::MAX, f32::MAX), f32::MAX);
+        assert_eq!(add_up(f32::MAX, f32::MAX), f32::INFINITY);
+    }
+}
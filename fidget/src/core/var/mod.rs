@@ -79,6 +79,18 @@ impl Var {
     }
 }
 
+impl VarIndex {
+    /// Returns the raw value of this index
+    pub(crate) fn get(&self) -> u64 {
+        self.0
+    }
+
+    /// Builds an index from a raw value, e.g. when parsing a text format
+    pub(crate) fn from_raw(v: u64) -> Self {
+        Self(v)
+    }
+}
+
 impl std::fmt::Display for Var {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,1186 @@
+//! Affine arithmetic evaluation for the VM backend
+use super::{
+    Choice, EmptyTapeStorage, GenericVmFunction, GenericVmTape, SlotArray,
+    VmData, VmTrace, VmWorkspace,
+};
+use crate::{
+    compiler::RegOp,
+    context::Node,
+    eval::{
+        BulkOutput, BulkTraces, Function, MathFunction, MultiTracingEvaluator,
+        MultiTracingResult, Tape, Trace, TracingEvaluator, TracingResult,
+    },
+    render::{RenderHints, TileSizes},
+    types::Interval,
+    var::VarMap,
+    Context, Error,
+};
+
+/// Number of input variables which are tracked as independent noise symbols
+///
+/// Inputs beyond this count are folded into the accumulated error term, so
+/// they don't benefit from correlation tracking (but remain conservative).
+const NOISE_SYMBOLS: usize = 4;
+
+/// Relative padding applied when converting an affine form to bounds
+///
+/// Rounding errors in each operation are accumulated into the form's error
+/// term (see [`rounding_err`]); this padding only absorbs the rounding from
+/// summing the terms themselves in [`AffineForm::range`].
+const ROUNDING_PAD: f32 = 8.0 * f32::EPSILON;
+
+/// Returns an upper bound on the rounding error of an `f32` result
+///
+/// Round-to-nearest is off by at most half an ULP, which is below `|v| * ε`.
+/// As with [`Interval`], products which underflow are not accounted for.
+fn rounding_err(v: f32) -> f32 {
+    v.abs() * f32::EPSILON
+}
+
+/// Returns `a + b` and the exact rounding error of that sum (TwoSum)
+///
+/// Unlike [`rounding_err`], this isn't pessimistic when the sum cancels, which
+/// matters for the center of an affine form: it's often a large value that's
+/// offset, then shifted back.
+fn two_sum(a: f32, b: f32) -> (f32, f32) {
+    let s = a + b;
+    let bb = s - a;
+    let e = (a - (s - bb)) + (b - bb);
+    (s, e)
+}
+
+/// Affine form `center + Σ coeffs[i] ε_i + err ε_err`, with each `ε ∈ [-1, 1]`
+///
+/// Noise symbols `ε_i` are shared between every form in an evaluation, which is
+/// what lets us cancel correlated terms (e.g. `x - x`).  Every non-linear
+/// operation adds to the single error term, rather than allocating a new noise
+/// symbol; this keeps forms fixed-size at the cost of some precision.
+///
+/// A `NaN` center marks a form which carries no information (e.g. because its
+/// inputs were unbounded); its value must be taken from the accompanying
+/// interval bounds instead.
+#[derive(Copy, Clone, Debug)]
+struct AffineForm {
+    center: f32,
+    coeffs: [f32; NOISE_SYMBOLS],
+    err: f32,
+}
+
+impl AffineForm {
+    const INVALID: Self = Self {
+        center: f32::NAN,
+        coeffs: [0.0; NOISE_SYMBOLS],
+        err: 0.0,
+    };
+
+    fn constant(v: f32) -> Self {
+        Self {
+            center: v,
+            coeffs: [0.0; NOISE_SYMBOLS],
+            err: 0.0,
+        }
+    }
+
+    /// Builds a form for the input variable with the given index
+    fn input(i: Interval, index: usize) -> Self {
+        let mut out = Self::from_interval(i);
+        if index < NOISE_SYMBOLS {
+            out.coeffs[index] = out.err;
+            out.err = 0.0;
+        }
+        out
+    }
+
+    /// Builds a form which only uses the error term
+    fn from_interval(i: Interval) -> Self {
+        if !i.lower().is_finite() || !i.upper().is_finite() {
+            return Self::INVALID;
+        }
+        let center = i.midpoint();
+        Self {
+            center,
+            coeffs: [0.0; NOISE_SYMBOLS],
+            err: (i.upper() - center).max(center - i.lower()),
+        }
+    }
+
+    /// Returns the total magnitude of all noise terms
+    fn radius(&self) -> f32 {
+        self.coeffs.iter().map(|c| c.abs()).sum::<f32>() + self.err
+    }
+
+    /// Returns the (padded) range of the form, or `None` if it's invalid
+    fn range(&self) -> Option<(f32, f32)> {
+        let r = self.radius();
+        let pad = (self.center.abs() + r) * ROUNDING_PAD;
+        let lo = self.center - r - pad;
+        let hi = self.center + r + pad;
+        if lo.is_nan() || hi.is_nan() {
+            None
+        } else {
+            Some((lo, hi))
+        }
+    }
+
+    /// Returns `alpha * self + zeta ± delta`
+    ///
+    /// `zeta` and `delta` are typically computed from the bounds of `self`,
+    /// so they're also treated as inexact.
+    fn linear(self, alpha: f32, zeta: f32, delta: f32) -> Self {
+        let mut out = self * alpha;
+        let (center, e) = two_sum(out.center, zeta);
+        out.center = center;
+        out.err += delta + e.abs() + rounding_err(zeta) + rounding_err(delta);
+        out
+    }
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut coeffs = [0.0; NOISE_SYMBOLS];
+        let mut err = 0.0;
+        for (i, c) in coeffs.iter_mut().enumerate() {
+            let a = self.center * rhs.coeffs[i];
+            let b = rhs.center * self.coeffs[i];
+            *c = a + b;
+            err += rounding_err(a) + rounding_err(b) + rounding_err(*c);
+        }
+        let center = self.center * rhs.center;
+        Self {
+            center,
+            coeffs,
+            err: self.center.abs() * rhs.err
+                + rhs.center.abs() * self.err
+                + self.radius() * rhs.radius()
+                + rounding_err(center)
+                + err,
+        }
+    }
+}
+
+impl std::ops::Add<AffineForm> for AffineForm {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        let (center, e) = two_sum(self.center, rhs.center);
+        let mut err = self.err + rhs.err + e.abs();
+        let mut coeffs = self.coeffs;
+        for (c, r) in coeffs.iter_mut().zip(rhs.coeffs) {
+            *c += r;
+            err += rounding_err(*c);
+        }
+        Self {
+            center,
+            coeffs,
+            err,
+        }
+    }
+}
+
+impl std::ops::Sub<AffineForm> for AffineForm {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl std::ops::Mul<f32> for AffineForm {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self {
+        let center = self.center * rhs;
+        let coeffs = self.coeffs.map(|c| c * rhs);
+        Self {
+            center,
+            coeffs,
+            err: self.err * rhs.abs()
+                + rounding_err(center)
+                + coeffs.iter().map(|c| rounding_err(*c)).sum::<f32>(),
+        }
+    }
+}
+
+impl std::ops::Div<f32> for AffineForm {
+    type Output = Self;
+    fn div(self, rhs: f32) -> Self {
+        let center = self.center / rhs;
+        let coeffs = self.coeffs.map(|c| c / rhs);
+        Self {
+            center,
+            coeffs,
+            err: self.err / rhs.abs()
+                + rounding_err(center)
+                + coeffs.iter().map(|c| rounding_err(*c)).sum::<f32>(),
+        }
+    }
+}
+
+impl std::ops::Neg for AffineForm {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            center: -self.center,
+            coeffs: self.coeffs.map(|c| -c),
+            err: self.err,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Value stored in each slot during affine evaluation
+///
+/// We track both an affine form and plain interval bounds, then intersect them
+/// after every operation.  This means that results are never looser than
+/// interval arithmetic, and lets linearizations of non-linear operations use
+/// the tighter of the two ranges.
+#[derive(Copy, Clone, Debug)]
+struct AffineValue {
+    form: AffineForm,
+    bounds: Interval,
+}
+
+impl From<f32> for AffineValue {
+    fn from(v: f32) -> Self {
+        Self {
+            form: AffineForm::constant(v),
+            bounds: v.into(),
+        }
+    }
+}
+
+impl AffineValue {
+    /// Builds a new value, tightening `bounds` using the range of `form`
+    fn new(form: AffineForm, bounds: Interval) -> Self {
+        if bounds.has_nan() {
+            return Self {
+                form: AffineForm::INVALID,
+                bounds,
+            };
+        }
+        let bounds = match form.range() {
+            Some((lo, hi)) => {
+                let lo = lo.max(bounds.lower());
+                let hi = hi.min(bounds.upper());
+                if lo <= hi {
+                    Interval::new(lo, hi)
+                } else {
+                    bounds
+                }
+            }
+            None => bounds,
+        };
+        Self { form, bounds }
+    }
+
+    /// Builds a value from interval bounds alone
+    ///
+    /// This is used for operations without an affine approximation.
+    fn from_bounds(bounds: Interval) -> Self {
+        Self {
+            form: AffineForm::from_interval(bounds),
+            bounds,
+        }
+    }
+
+    fn input(i: Interval, index: usize) -> Self {
+        Self::new(AffineForm::input(i, index), i)
+    }
+
+    fn neg(self) -> Self {
+        Self::new(-self.form, -self.bounds)
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.form + rhs.form, self.bounds + rhs.bounds)
+    }
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.form - rhs.form, self.bounds - rhs.bounds)
+    }
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.form.mul(rhs.form), self.bounds * rhs.bounds)
+    }
+
+    fn mul_imm(self, imm: f32) -> Self {
+        Self::new(self.form * imm, self.bounds * imm)
+    }
+
+    fn div_imm(self, imm: f32) -> Self {
+        Self::new(self.form / imm, self.bounds / imm.into())
+    }
+
+    /// Chebyshev approximation of `x²` over the current bounds
+    fn square(self) -> Self {
+        let (l, u) = (self.bounds.lower(), self.bounds.upper());
+        let alpha = l + u;
+        // x² - αx ranges over [-α²/4, -lu] within [l, u]
+        let lo = -alpha * alpha / 4.0;
+        let hi = -l * u;
+        let form =
+            self.form
+                .linear(alpha, (lo + hi) / 2.0, (hi - lo).abs() / 2.0);
+        Self::new(form, self.bounds.square())
+    }
+
+    /// Chebyshev approximation of `|x|` over the current bounds
+    fn abs(self) -> Self {
+        let (l, u) = (self.bounds.lower(), self.bounds.upper());
+        let form = if l >= 0.0 {
+            self.form
+        } else if u <= 0.0 {
+            -self.form
+        } else {
+            let alpha = (u + l) / (u - l);
+            // |x| - αx ranges over [0, -2lu / (u - l)] within [l, u]
+            let delta = -l * u / (u - l);
+            self.form.linear(alpha, delta, delta)
+        };
+        Self::new(form, self.bounds.abs())
+    }
+
+    /// Min-range approximation of `√x` over the current bounds
+    fn sqrt(self) -> Self {
+        let (l, u) = (self.bounds.lower(), self.bounds.upper());
+        let form = if l < 0.0 {
+            AffineForm::INVALID
+        } else if u == 0.0 {
+            AffineForm::constant(0.0)
+        } else {
+            // √x - αx is increasing on [l, u] when α is the slope at u
+            let alpha = 0.5 / u.sqrt();
+            let lo = l.sqrt() - alpha * l;
+            let hi = u.sqrt() - alpha * u;
+            self.form
+                .linear(alpha, (lo + hi) / 2.0, (hi - lo).abs() / 2.0)
+        };
+        Self::new(form, self.bounds.sqrt())
+    }
+
+    /// Builds the result of an operation which may pick one of its arguments
+    ///
+    /// If the choice is unambiguous, the selected argument's form is reused;
+    /// otherwise, `both` is called to build a form for the result.
+    fn choose(
+        lhs: Self,
+        rhs: Self,
+        (bounds, choice): (Interval, Choice),
+        both: impl FnOnce() -> AffineForm,
+    ) -> (Self, Choice) {
+        let form = match choice {
+            Choice::Left => lhs.form,
+            Choice::Right => rhs.form,
+            Choice::Both | Choice::Unknown => both(),
+        };
+        (Self::new(form, bounds), choice)
+    }
+
+    /// Computes `min(a, b) = (a + b - |a - b|) / 2`
+    ///
+    /// The choice also checks the affine bounds of `a - b`, which may be
+    /// unambiguous even if the intervals of `a` and `b` overlap.
+    fn min_choice(self, rhs: Self) -> (Self, Choice) {
+        let d = self.sub(rhs);
+        let out = match self.bounds.min_choice(rhs.bounds) {
+            (_, Choice::Both) if d.bounds.upper() < 0.0 => {
+                (self.bounds, Choice::Left)
+            }
+            (_, Choice::Both) if d.bounds.lower() > 0.0 => {
+                (rhs.bounds, Choice::Right)
+            }
+            out => out,
+        };
+        Self::choose(self, rhs, out, || {
+            (self.form + rhs.form - d.abs().form) * 0.5
+        })
+    }
+
+    /// Computes `max(a, b) = (a + b + |a - b|) / 2`
+    ///
+    /// As with [`min_choice`](Self::min_choice), the choice is made using the
+    /// affine bounds of `a - b`.
+    fn max_choice(self, rhs: Self) -> (Self, Choice) {
+        let d = self.sub(rhs);
+        let out = match self.bounds.max_choice(rhs.bounds) {
+            (_, Choice::Both) if d.bounds.lower() > 0.0 => {
+                (self.bounds, Choice::Left)
+            }
+            (_, Choice::Both) if d.bounds.upper() < 0.0 => {
+                (rhs.bounds, Choice::Right)
+            }
+            out => out,
+        };
+        Self::choose(self, rhs, out, || {
+            (self.form + rhs.form + d.abs().form) * 0.5
+        })
+    }
+
+    fn and_choice(self, rhs: Self) -> (Self, Choice) {
+        let (bounds, choice) = self.bounds.and_choice(rhs.bounds);
+        Self::choose(self, rhs, (bounds, choice), || {
+            AffineForm::from_interval(bounds)
+        })
+    }
+
+    fn or_choice(self, rhs: Self) -> (Self, Choice) {
+        let (bounds, choice) = self.bounds.or_choice(rhs.bounds);
+        Self::choose(self, rhs, (bounds, choice), || {
+            AffineForm::from_interval(bounds)
+        })
+    }
+
+    fn select_choice(self, a: Self, b: Self) -> (Self, Choice) {
+        let (bounds, choice) = self.bounds.select_choice(a.bounds, b.bounds);
+        Self::choose(a, b, (bounds, choice), || {
+            AffineForm::from_interval(bounds)
+        })
+    }
+
+    /// Applies an interval operation, discarding correlation information
+    fn map(self, f: impl FnOnce(Interval) -> Interval) -> Self {
+        Self::from_bounds(f(self.bounds))
+    }
+
+    /// Applies a binary interval operation, discarding correlation information
+    fn map2(
+        self,
+        rhs: Self,
+        f: impl FnOnce(Interval, Interval) -> Interval,
+    ) -> Self {
+        Self::from_bounds(f(self.bounds, rhs.bounds))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// VM-based tracing evaluator using affine arithmetic
+///
+/// This is a drop-in replacement for [`VmIntervalEval`](super::VmIntervalEval):
+/// it takes and returns [`Interval`] values and records the same [`VmTrace`],
+/// but tracks linear correlations between intermediate values (within a single
+/// evaluation) using [affine arithmetic](https://en.wikipedia.org/wiki/Affine_arithmetic).
+/// This makes bounds much tighter for expressions like `x - x`, or where the
+/// same inputs appear in many branches.
+///
+/// Values are also tracked as plain intervals and intersected with the affine
+/// bounds at every step, so results (and therefore choices) are never looser
+/// than those from [`VmIntervalEval`](super::VmIntervalEval).  The trade-off
+/// is evaluation speed.
+///
+/// The first few input variables (typically `x`, `y`, and `z`) are tracked as
+/// independent noise symbols; any further inputs are treated as uncorrelated.
+#[derive(Default)]
+pub struct VmAffineEval<const N: usize> {
+    slots: Vec<AffineValue>,
+    out: Vec<Interval>,
+    choices: VmTrace,
+}
+
+impl<const N: usize> VmAffineEval<N> {
+    fn resize_slots(&mut self, tape: &VmData<N>) {
+        self.slots.resize(tape.slot_count(), f32::NAN.into());
+        self.choices.resize(tape.choice_count(), Choice::Unknown);
+        self.out.resize(tape.output_count(), f32::NAN.into());
+        self.choices.fill(Choice::Unknown);
+    }
+}
+
+impl<const N: usize> TracingEvaluator for VmAffineEval<N> {
+    type Data = Interval;
+    type Tape = GenericVmTape<N>;
+    type Trace = VmTrace;
+    type TapeStorage = EmptyTapeStorage;
+
+    fn eval(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[Interval],
+    ) -> Result<TracingResult<'_, Interval, VmTrace>, Error> {
+        tape.vars().check_tracing_arguments(vars)?;
+        let tape = tape.data();
+        self.resize_slots(tape);
+
+        let mut simplify = false;
+        let mut v = SlotArray(&mut self.slots);
+        let mut choices = self.choices.as_mut_slice().iter_mut();
+        let mut record = |choice| {
+            *choices.next().unwrap() |= choice;
+            simplify |= choice != Choice::Both;
+        };
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Output(arg, i) => {
+                    self.out[i as usize] = v[arg].bounds;
+                }
+                RegOp::Input(out, i) => {
+                    v[out] = AffineValue::input(vars[i as usize], i as usize);
+                }
+                RegOp::NegReg(out, arg) => v[out] = v[arg].neg(),
+                RegOp::AbsReg(out, arg) => v[out] = v[arg].abs(),
+                RegOp::RecipReg(out, arg) => {
+                    v[out] = v[arg].map(Interval::recip)
+                }
+                RegOp::SqrtReg(out, arg) => v[out] = v[arg].sqrt(),
+                RegOp::SquareReg(out, arg) => v[out] = v[arg].square(),
+                RegOp::FloorReg(out, arg) => {
+                    v[out] = v[arg].map(|i| i.floor());
+                }
+                RegOp::CeilReg(out, arg) => v[out] = v[arg].map(|i| i.ceil()),
+                RegOp::RoundReg(out, arg) => {
+                    v[out] = v[arg].map(|i| i.round());
+                }
+                RegOp::SinReg(out, arg) => v[out] = v[arg].map(Interval::sin),
+                RegOp::CosReg(out, arg) => v[out] = v[arg].map(Interval::cos),
+                RegOp::TanReg(out, arg) => v[out] = v[arg].map(Interval::tan),
+                RegOp::AsinReg(out, arg) => v[out] = v[arg].map(Interval::asin),
+                RegOp::AcosReg(out, arg) => v[out] = v[arg].map(Interval::acos),
+                RegOp::AtanReg(out, arg) => v[out] = v[arg].map(Interval::atan),
+                RegOp::ExpReg(out, arg) => v[out] = v[arg].map(Interval::exp),
+                RegOp::LnReg(out, arg) => v[out] = v[arg].map(Interval::ln),
+                RegOp::NotReg(out, arg) => {
+                    v[out] = v[arg].map(|i| {
+                        if !i.contains(0.0) && !i.has_nan() {
+                            Interval::new(0.0, 0.0)
+                        } else if i.lower() == 0.0 && i.upper() == 0.0 {
+                            Interval::new(1.0, 1.0)
+                        } else {
+                            Interval::new(0.0, 1.0)
+                        }
+                    });
+                }
+                RegOp::SinhReg(out, arg) => v[out] = v[arg].map(Interval::sinh),
+                RegOp::CoshReg(out, arg) => v[out] = v[arg].map(Interval::cosh),
+                RegOp::TanhReg(out, arg) => v[out] = v[arg].map(Interval::tanh),
+                RegOp::SignReg(out, arg) => v[out] = v[arg].map(Interval::sign),
+                RegOp::CbrtReg(out, arg) => v[out] = v[arg].map(Interval::cbrt),
+                RegOp::Log2Reg(out, arg) => v[out] = v[arg].map(Interval::log2),
+                RegOp::CopyReg(out, arg) => v[out] = v[arg],
+                RegOp::AddRegImm(out, arg, imm) => {
+                    v[out] = v[arg].add(imm.into());
+                }
+                RegOp::MulRegImm(out, arg, imm) => {
+                    v[out] = v[arg].mul_imm(imm);
+                }
+                RegOp::DivRegImm(out, arg, imm) => {
+                    v[out] = v[arg].div_imm(imm);
+                }
+                RegOp::DivImmReg(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| Interval::from(imm) / i);
+                }
+                RegOp::AtanRegImm(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| i.atan2(imm.into()));
+                }
+                RegOp::AtanImmReg(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| Interval::from(imm).atan2(i));
+                }
+                RegOp::AtanRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs].map2(v[rhs], Interval::atan2);
+                }
+                RegOp::SubImmReg(out, arg, imm) => {
+                    v[out] = AffineValue::from(imm).sub(v[arg]);
+                }
+                RegOp::SubRegImm(out, arg, imm) => {
+                    v[out] = v[arg].sub(imm.into());
+                }
+                RegOp::MinRegImm(out, arg, imm) => {
+                    let (value, choice) = v[arg].min_choice(imm.into());
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::MaxRegImm(out, arg, imm) => {
+                    let (value, choice) = v[arg].max_choice(imm.into());
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::AndRegReg(out, lhs, rhs) => {
+                    let (value, choice) = v[lhs].and_choice(v[rhs]);
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::AndRegImm(out, arg, imm) => {
+                    let (value, choice) = v[arg].and_choice(imm.into());
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::OrRegReg(out, lhs, rhs) => {
+                    let (value, choice) = v[lhs].or_choice(v[rhs]);
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::OrRegImm(out, arg, imm) => {
+                    let (value, choice) = v[arg].or_choice(imm.into());
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::ModRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs].map2(v[rhs], |a, b| a.rem_euclid(b));
+                }
+                RegOp::ModRegImm(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| i.rem_euclid(imm.into()));
+                }
+                RegOp::ModImmReg(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| Interval::from(imm).rem_euclid(i));
+                }
+                RegOp::PowRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs].map2(v[rhs], Interval::pow);
+                }
+                RegOp::PowRegImm(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| i.pow(imm.into()));
+                }
+                RegOp::PowImmReg(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| Interval::from(imm).pow(i));
+                }
+                RegOp::AddRegReg(out, lhs, rhs) => v[out] = v[lhs].add(v[rhs]),
+                RegOp::MulRegReg(out, lhs, rhs) => v[out] = v[lhs].mul(v[rhs]),
+                RegOp::DivRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs].map2(v[rhs], |a, b| a / b);
+                }
+                RegOp::SubRegReg(out, lhs, rhs) => v[out] = v[lhs].sub(v[rhs]),
+                RegOp::CompareRegReg(out, lhs, rhs) => {
+                    v[out] = v[lhs].map2(v[rhs], compare);
+                }
+                RegOp::CompareRegImm(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| compare(i, imm.into()));
+                }
+                RegOp::CompareImmReg(out, arg, imm) => {
+                    v[out] = v[arg].map(|i| compare(imm.into(), i));
+                }
+                RegOp::MinRegReg(out, lhs, rhs) => {
+                    let (value, choice) = v[lhs].min_choice(v[rhs]);
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::MaxRegReg(out, lhs, rhs) => {
+                    let (value, choice) = v[lhs].max_choice(v[rhs]);
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::SelectRegRegReg(out, cond, a, b) => {
+                    let (value, choice) = v[cond].select_choice(v[a], v[b]);
+                    v[out] = value;
+                    record(choice);
+                }
+                RegOp::CopyImm(out, imm) => {
+                    v[out] = imm.into();
+                }
+                RegOp::Load(out, mem) => {
+                    v[out] = v[mem];
+                }
+                RegOp::Store(out, mem) => {
+                    v[mem] = v[out];
+                }
+            }
+        }
+        Ok((&self.out, if simplify { Some(&self.choices) } else { None }))
+    }
+}
+
+/// Interval comparison, returning -1, 0, or 1 (or a range thereof)
+fn compare(lhs: Interval, rhs: Interval) -> Interval {
+    if lhs.has_nan() || rhs.has_nan() {
+        f32::NAN.into()
+    } else if lhs.upper() < rhs.lower() {
+        Interval::from(-1.0)
+    } else if lhs.lower() > rhs.upper() {
+        Interval::from(1.0)
+    } else {
+        Interval::new(-1.0, 1.0)
+    }
+}
+
+/// Bulk evaluator for arrays of intervals, using affine arithmetic
+///
+/// Each interval is evaluated independently by a [`VmAffineEval`], so results
+/// and per-interval traces are identical to it.  Affine forms aren't shared
+/// between intervals in the array.
+#[derive(Default)]
+pub struct VmAffineSliceEval<const N: usize> {
+    eval: VmAffineEval<N>,
+    args: Vec<Interval>,
+    out: Vec<Vec<Interval>>,
+    traces: Vec<VmTrace>,
+    simplify: Vec<bool>,
+}
+
+impl<const N: usize> MultiTracingEvaluator for VmAffineSliceEval<N> {
+    type Data = Interval;
+    type Tape = GenericVmTape<N>;
+    type TapeStorage = EmptyTapeStorage;
+    type Trace = VmTrace;
+
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<MultiTracingResult<'_, Interval, VmTrace>, Error> {
+        tape.vars().check_bulk_arguments(vars)?;
+        let size = vars.first().map(|v| v.len()).unwrap_or(0);
+
+        self.out.resize_with(tape.output_count(), Vec::new);
+        for o in &mut self.out {
+            o.resize(size, f32::NAN.into());
+        }
+        self.traces.resize_with(size, VmTrace::default);
+        self.simplify.resize(size, false);
+
+        for i in 0..size {
+            self.args.clear();
+            self.args.extend(vars.iter().map(|v| v[i]));
+            let (out, trace) = self.eval.eval(tape, &self.args)?;
+            for (o, v) in self.out.iter_mut().zip(out) {
+                o[i] = *v;
+            }
+            self.simplify[i] = trace.is_some();
+            if let Some(t) = trace {
+                self.traces[i].copy_from(t);
+            }
+        }
+        Ok((
+            BulkOutput::new(&self.out, size),
+            BulkTraces::new(&self.traces[..size], &self.simplify[..size]),
+        ))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Function which uses the VM backend, with affine arithmetic for tracing
+///
+/// This is identical to [`GenericVmFunction`], except that its interval
+/// evaluators are [`VmAffineEval`] and [`VmAffineSliceEval`].  Tighter bounds mean that renderers and
+/// meshing can prune more of the tape (and skip more empty or filled regions),
+/// at the cost of slower interval evaluation.
+///
+/// You are unlikely to use this directly; [`VmAffineShape`](super::VmAffineShape)
+/// should be used for affine-arithmetic evaluation.
+#[derive(Clone)]
+pub struct GenericVmAffineFunction<const N: usize>(GenericVmFunction<N>);
+
+impl<const N: usize> From<GenericVmFunction<N>> for GenericVmAffineFunction<N> {
+    fn from(f: GenericVmFunction<N>) -> Self {
+        Self(f)
+    }
+}
+
+impl<const N: usize> From<GenericVmAffineFunction<N>> for GenericVmFunction<N> {
+    fn from(f: GenericVmAffineFunction<N>) -> Self {
+        f.0
+    }
+}
+
+impl<const N: usize> Function for GenericVmAffineFunction<N> {
+    type Storage = VmData<N>;
+    type Workspace = VmWorkspace<N>;
+
+    type TapeStorage = EmptyTapeStorage;
+
+    type FloatSliceEval = super::VmFloatSliceEval<N>;
+    fn float_slice_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
+        self.0.tape()
+    }
+    type GradSliceEval = super::VmGradSliceEval<N>;
+    fn grad_slice_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
+        self.0.tape()
+    }
+    type FloatSliceTracingEval = super::VmFloatSliceTracingEval<N>;
+    fn float_slice_tracing_tape(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.0.tape()
+    }
+    type DualSliceEval<const P: usize> = super::VmDualSliceEval<N, P>;
+    fn dual_slice_tape<const P: usize>(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.0.tape()
+    }
+    type PointEval = super::VmPointEval<N>;
+    fn point_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
+        self.0.tape()
+    }
+    type IntervalEval = VmAffineEval<N>;
+    fn interval_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
+        self.0.tape()
+    }
+    type IntervalSliceEval = VmAffineSliceEval<N>;
+    fn interval_slice_tape(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.0.tape()
+    }
+    type Trace = VmTrace;
+    fn simplify(
+        &self,
+        trace: &Self::Trace,
+        storage: Self::Storage,
+        workspace: &mut Self::Workspace,
+    ) -> Result<Self, Error> {
+        self.0.simplify_with(trace, storage, workspace).map(Self)
+    }
+
+    fn recycle(self) -> Option<Self::Storage> {
+        self.0.recycle()
+    }
+
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+
+    fn vars(&self) -> &VarMap {
+        Function::vars(&self.0)
+    }
+
+    fn visit_ops(&self, f: &mut dyn FnMut(RegOp)) {
+        self.0.visit_ops(f)
+    }
+}
+
+impl<const N: usize> RenderHints for GenericVmAffineFunction<N> {
+    fn tile_sizes_3d() -> TileSizes {
+        GenericVmFunction::<N>::tile_sizes_3d()
+    }
+
+    fn tile_sizes_2d() -> TileSizes {
+        GenericVmFunction::<N>::tile_sizes_2d()
+    }
+}
+
+impl<const N: usize> MathFunction for GenericVmAffineFunction<N> {
+    fn new(ctx: &Context, nodes: &[Node]) -> Result<Self, Error> {
+        GenericVmFunction::new(ctx, nodes).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        eval::test::{
+            interval::TestInterval, interval_test_args, CanonicalBinaryOp,
+            CanonicalUnaryOp,
+        },
+        render::{DebugPixel, DebugRenderMode},
+        shape::EzShape,
+        stats::without_stats,
+        var::Var,
+        vm::{VmAffineFunction, VmAffineShape, VmShape},
+    };
+
+    const PROSPERO: &str = include_str!("../../../../models/prospero.vm");
+
+    /// Returns the number of tiles in `[-1, 1]²` which are proven to be empty
+    /// or full, using interval and affine evaluation respectively
+    fn count_pruned(ctx: &Context, root: Node, n: usize) -> (usize, usize) {
+        let ishape = VmShape::new(ctx, root).unwrap();
+        let ashape = VmAffineShape::new(ctx, root).unwrap();
+        let itape = ishape.ez_interval_tape();
+        let atape = ashape.ez_interval_tape();
+        let ptape = ishape.ez_point_tape();
+        let mut ieval = VmShape::new_interval_eval();
+        let mut aeval = VmAffineShape::new_interval_eval();
+        let mut peval = VmShape::new_point_eval();
+
+        let (mut i_pruned, mut a_pruned) = (0, 0);
+        let pos = |i: usize| i as f32 / n as f32 * 2.0 - 1.0;
+        for i in 0..n {
+            for j in 0..n {
+                let x = Interval::new(pos(i), pos(i + 1));
+                let y = Interval::new(pos(j), pos(j + 1));
+                let z = Interval::from(0.0);
+                let (iv, _) = ieval.eval(&itape, x, y, z).unwrap();
+                let (av, _) = aeval.eval(&atape, x, y, z).unwrap();
+                assert!(
+                    av.lower() >= iv.lower() && av.upper() <= iv.upper(),
+                    "affine result {av} is looser than interval {iv}"
+                );
+                i_pruned += !iv.contains(0.0) as usize;
+                a_pruned += !av.contains(0.0) as usize;
+
+                for (px, py) in [(0.0, 0.0), (0.3, 0.7), (1.0, 1.0)] {
+                    let (p, _) = peval
+                        .eval(&ptape, x.lerp(px), y.lerp(py), 0.0)
+                        .unwrap();
+                    assert!(av.contains(p), "{p} not in {av}");
+                }
+            }
+        }
+        (i_pruned, a_pruned)
+    }
+
+    #[test]
+    fn test_affine_cancellation() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let root = ctx.sub(x, x).unwrap();
+        let shape = VmAffineShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let mut eval = VmAffineShape::new_interval_eval();
+        let z = Interval::from(0.0);
+        let (out, _) = eval.eval(&tape, [-1.0, 3.0].into(), z, z).unwrap();
+        assert_eq!(out, Interval::new(0.0, 0.0));
+
+        // (x + y) - (x - y) = 2y
+        let sum = ctx.add(x, y).unwrap();
+        let diff = ctx.sub(x, y).unwrap();
+        let root = ctx.sub(sum, diff).unwrap();
+        let shape = VmAffineShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let (out, _) = eval
+            .eval(&tape, [-1.0, 3.0].into(), [1.0, 2.0].into(), z)
+            .unwrap();
+        assert!(
+            out.lower() >= 2.0 - 1e-5 && out.upper() <= 4.0 + 1e-5,
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_affine_rounding() {
+        // The center of x + 1e8 isn't representable, so rounding error must be
+        // tracked even though the coefficients cancel afterwards.
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let a = ctx.add(x, 1e8).unwrap();
+        let root = ctx.sub(a, 1e8).unwrap();
+        let shape = VmAffineShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let mut eval = VmAffineShape::new_interval_eval();
+        let z = Interval::from(0.0);
+        let (out, _) = eval.eval(&tape, [0.0, 8.0].into(), z, z).unwrap();
+        assert!(out.contains(8.0), "{out}");
+        assert!(out.contains(0.0), "{out}");
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_point_tape();
+        let mut peval = VmShape::new_point_eval();
+        for i in 0..=8 {
+            let (p, _) = peval.eval(&tape, i as f32, 0.0, 0.0).unwrap();
+            assert!(out.contains(p), "{p} not in {out}");
+        }
+
+        // Same thing, but with the offset applied through multiplication
+        let b = ctx.mul(x, 1e8).unwrap();
+        let c = ctx.add(b, 1.0).unwrap();
+        let root = ctx.sub(c, b).unwrap();
+        let shape = VmAffineShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let (out, _) = eval.eval(&tape, [0.0, 1.0].into(), z, z).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_point_tape();
+        for i in 0..=16 {
+            let x = i as f32 / 16.0;
+            let (p, _) = peval.eval(&tape, x, 0.0, 0.0).unwrap();
+            assert!(out.contains(p), "{p} not in {out}");
+        }
+    }
+
+    /// Builds a rectangle rotated by 30°
+    fn rotated_box(ctx: &mut Context) -> Node {
+        let x = ctx.x();
+        let y = ctx.y();
+        let (s, c) = 30f32.to_radians().sin_cos();
+        let xc = ctx.mul(x, c).unwrap();
+        let ys = ctx.mul(y, s).unwrap();
+        let u = ctx.sub(xc, ys).unwrap();
+        let xs = ctx.mul(x, s).unwrap();
+        let yc = ctx.mul(y, c).unwrap();
+        let v = ctx.add(xs, yc).unwrap();
+        let u = ctx.abs(u).unwrap();
+        let u = ctx.sub(u, 0.7).unwrap();
+        let v = ctx.abs(v).unwrap();
+        let v = ctx.sub(v, 0.3).unwrap();
+        ctx.max(u, v).unwrap()
+    }
+
+    #[test]
+    fn test_affine_rotated_box() {
+        let mut ctx = Context::new();
+        let root = rotated_box(&mut ctx);
+        let (i_pruned, a_pruned) = count_pruned(&ctx, root, 16);
+        assert!(a_pruned > i_pruned, "{a_pruned} <= {i_pruned}");
+    }
+
+    #[test]
+    fn test_affine_choices() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let a = ctx.add(x, 1.0).unwrap();
+        let root = ctx.min(x, a).unwrap();
+        let x = Interval::new(-2.0, 2.0);
+        let z = Interval::from(0.0);
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let mut ieval = VmShape::new_interval_eval();
+        let (_, trace) = ieval.eval(&tape, x, z, z).unwrap();
+        assert!(trace.is_none());
+
+        // Affine arithmetic knows that x is always less than x + 1
+        let shape = VmAffineShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let mut aeval = VmAffineShape::new_interval_eval();
+        let (out, trace) = aeval.eval(&tape, x, z, z).unwrap();
+        assert_eq!(out, x);
+        let trace = trace.unwrap();
+        assert_eq!(trace.as_slice(), &[Choice::Left]);
+
+        let next = shape.ez_simplify(trace).unwrap();
+        assert!(next.size() < shape.size());
+    }
+
+    #[test]
+    fn test_affine_prospero() {
+        let (ctx, root) = Context::from_text(PROSPERO.as_bytes()).unwrap();
+        let (i_pruned, a_pruned) = count_pruned(&ctx, root, 8);
+        assert!(a_pruned >= i_pruned);
+    }
+
+    #[test]
+    fn test_affine_render_and_mesh() {
+        let (ctx, root) = Context::from_text(PROSPERO.as_bytes()).unwrap();
+        let cfg = crate::render::ImageRenderConfig {
+            image_size: crate::render::ImageSize::from(256),
+            ..Default::default()
+        };
+        let ishape = VmShape::new(&ctx, root).unwrap();
+        let ashape = VmAffineShape::new(&ctx, root).unwrap();
+        let a = cfg
+            .run::<_, crate::render::BitRenderMode>(ishape)
+            .map(without_stats)
+            .unwrap();
+        let b = cfg
+            .run::<_, crate::render::BitRenderMode>(ashape)
+            .map(without_stats)
+            .unwrap();
+        assert!(a.iter().eq(b.iter()));
+
+        // Affine evaluation should classify more pixels at the tile level,
+        // leaving fewer to be evaluated individually
+        let mut ctx = Context::new();
+        let root = rotated_box(&mut ctx);
+        let ishape = VmShape::new(&ctx, root).unwrap();
+        let ashape = VmAffineShape::new(&ctx, root).unwrap();
+        let a = cfg
+            .run::<_, DebugRenderMode>(ishape)
+            .map(without_stats)
+            .unwrap();
+        let b = cfg
+            .run::<_, DebugRenderMode>(ashape)
+            .map(without_stats)
+            .unwrap();
+        assert!(a
+            .iter()
+            .zip(b.iter())
+            .all(|(a, b)| a.is_filled() == b.is_filled()));
+        let per_pixel = |img: &crate::render::Image<DebugPixel>| {
+            img.iter()
+                .filter(|p| matches!(p, DebugPixel::Empty | DebugPixel::Filled))
+                .count()
+        };
+        let (a, b) = (per_pixel(&a), per_pixel(&b));
+        assert!(b < a, "affine evaluated {b} pixels, interval evaluated {a}");
+
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let x2 = ctx.square(x).unwrap();
+        let y2 = ctx.square(y).unwrap();
+        let z2 = ctx.square(z).unwrap();
+        let r = ctx.add(x2, y2).unwrap();
+        let r = ctx.add(r, z2).unwrap();
+        let r = ctx.sqrt(r).unwrap();
+        let root = ctx.sub(r, 0.6).unwrap();
+        let settings = crate::mesh::Settings {
+            depth: 4,
+            ..Default::default()
+        };
+        let ishape = VmShape::new(&ctx, root).unwrap();
+        let ashape = VmAffineShape::new(&ctx, root).unwrap();
+        let a = without_stats(crate::mesh::Octree::build(&ishape, settings))
+            .walk_dual(settings);
+        let b = without_stats(crate::mesh::Octree::build(&ashape, settings))
+            .walk_dual(settings);
+        assert!(!b.triangles.is_empty());
+        assert_eq!(a.triangles.len(), b.triangles.len());
+    }
+
+    /// Tester which accounts for correlated arguments
+    ///
+    /// The standard interval tests evaluate `f(a, a)` and compare it against
+    /// every pair of values in `a × a`, which assumes that the evaluator can't
+    /// tell that both arguments are the same; affine arithmetic can.
+    struct TestAffine;
+    impl TestAffine {
+        fn test_unary<C: CanonicalUnaryOp>() {
+            TestInterval::<VmAffineFunction>::test_unary::<C>();
+        }
+
+        fn test_binary<C: CanonicalBinaryOp>() {
+            TestInterval::<VmAffineFunction>::test_binary_reg_reg_distinct::<C>(
+            );
+            TestInterval::<VmAffineFunction>::test_binary_reg_imm::<C>();
+            TestInterval::<VmAffineFunction>::test_binary_imm_reg::<C>();
+
+            let mut ctx = Context::new();
+            let a = ctx.var(Var::new());
+            let node = C::build(&mut ctx, a, a);
+            let shape = VmAffineFunction::new(&ctx, &[node]).unwrap();
+            let tape = shape.interval_tape(Default::default());
+            let mut eval = VmAffineFunction::new_interval_eval();
+            let name = format!("{}(reg, reg)", C::NAME);
+            for lhs in interval_test_args() {
+                let (out, _) = eval.eval(&tape, &[lhs]).unwrap();
+                TestInterval::<VmAffineFunction>::compare_interval_results(
+                    lhs,
+                    lhs,
+                    out[0],
+                    |a, _b| C::eval_reg_reg_f32(a, a),
+                    &name,
+                );
+            }
+        }
+    }
+
+    crate::interval_slice_test!(test_is_min, VmAffineFunction);
+    crate::interval_slice_test!(test_is_empty, VmAffineFunction);
+    crate::interval_slice_test!(test_is_simplify, VmAffineFunction);
+
+    crate::interval_test!(test_interval, VmAffineFunction);
+    crate::interval_test!(test_i_abs, VmAffineFunction);
+    crate::interval_test!(test_i_add_abs, VmAffineFunction);
+    crate::interval_test!(test_i_sqrt, VmAffineFunction);
+    crate::interval_test!(test_i_square, VmAffineFunction);
+    crate::interval_test!(test_i_sin, VmAffineFunction);
+    crate::interval_test!(test_i_neg, VmAffineFunction);
+    crate::interval_test!(test_i_not, VmAffineFunction);
+    crate::interval_test!(test_i_mul, VmAffineFunction);
+    crate::interval_test!(test_i_mul_imm, VmAffineFunction);
+    crate::interval_test!(test_i_sub, VmAffineFunction);
+    crate::interval_test!(test_i_sub_imm, VmAffineFunction);
+    crate::interval_test!(test_i_recip, VmAffineFunction);
+    crate::interval_test!(test_i_div, VmAffineFunction);
+    crate::interval_test!(test_i_min, VmAffineFunction);
+    crate::interval_test!(test_i_min_imm, VmAffineFunction);
+    crate::interval_test!(test_i_max, VmAffineFunction);
+    crate::interval_test!(test_i_max_imm, VmAffineFunction);
+    crate::interval_test!(test_i_and, VmAffineFunction);
+    crate::interval_test!(test_i_or, VmAffineFunction);
+    crate::interval_test!(test_i_select, VmAffineFunction);
+    crate::interval_test!(test_i_compare, VmAffineFunction);
+    crate::interval_test!(test_i_modulo, VmAffineFunction);
+    crate::interval_test!(test_i_simplify, VmAffineFunction);
+    crate::interval_test!(test_i_simplify_conditional, VmAffineFunction);
+    crate::interval_test!(test_i_stress, VmAffineFunction);
+
+    mod i_unary {
+        use super::*;
+        crate::all_unary_tests!(TestAffine);
+    }
+
+    mod i_binary {
+        use super::*;
+        crate::all_binary_tests!(TestAffine);
+    }
+}
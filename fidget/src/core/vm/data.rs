@@ -143,10 +143,19 @@ impl<const N: usize> VmData<N> {
 
         // Other iterators to consume various arrays in order
         let mut choice_iter = choices.iter().rev();
+        let mut imm_iter = self.ssa.imms.iter();
 
         let mut ops_out = tape.ssa.tape;
+        let mut imms_out = tape.ssa.imms;
 
         for mut op in self.ssa.tape.iter().cloned() {
+            // The exact immediate is consumed here, even if the operation is
+            // skipped; it's kept if the final operation still uses it.  If the
+            // list is empty (from an old serialized tape), the `f32` immediate
+            // is used instead.
+            let imm = op
+                .imm()
+                .map(|f| imm_iter.next().copied().unwrap_or(f as f64));
             let index = match &mut op {
                 SsaOp::Output(reg, _i) => {
                     *reg = workspace.get_or_insert_active(*reg);
@@ -191,7 +200,13 @@ impl<const N: usize> VmData<N> {
                 | SsaOp::AtanReg(index, arg)
                 | SsaOp::ExpReg(index, arg)
                 | SsaOp::LnReg(index, arg)
-                | SsaOp::NotReg(index, arg) => {
+                | SsaOp::NotReg(index, arg)
+                | SsaOp::SinhReg(index, arg)
+                | SsaOp::CoshReg(index, arg)
+                | SsaOp::TanhReg(index, arg)
+                | SsaOp::SignReg(index, arg)
+                | SsaOp::CbrtReg(index, arg)
+                | SsaOp::Log2Reg(index, arg) => {
                     *index = new_index;
                     *arg = workspace.get_or_insert_active(*arg);
                 }
@@ -269,13 +284,47 @@ impl<const N: usize> VmData<N> {
                         Choice::Unknown => panic!("oh no"),
                     }
                 }
+                SsaOp::SelectRegRegReg(index, cond, a, b) => {
+                    // If the condition is known, then the select collapses to
+                    // one branch, and neither the condition nor the other
+                    // branch are marked as active.
+                    match choice_iter.next().unwrap() {
+                        Choice::Left => match workspace.active(*a) {
+                            Some(new_a) => {
+                                op = SsaOp::CopyReg(new_index, new_a);
+                            }
+                            None => {
+                                workspace.set_active(*a, new_index);
+                                continue;
+                            }
+                        },
+                        Choice::Right => match workspace.active(*b) {
+                            Some(new_b) => {
+                                op = SsaOp::CopyReg(new_index, new_b);
+                            }
+                            None => {
+                                workspace.set_active(*b, new_index);
+                                continue;
+                            }
+                        },
+                        Choice::Both => {
+                            choice_count += 1;
+                            *index = new_index;
+                            *cond = workspace.get_or_insert_active(*cond);
+                            *a = workspace.get_or_insert_active(*a);
+                            *b = workspace.get_or_insert_active(*b);
+                        }
+                        Choice::Unknown => panic!("oh no"),
+                    }
+                }
                 SsaOp::AddRegReg(index, lhs, rhs)
                 | SsaOp::MulRegReg(index, lhs, rhs)
                 | SsaOp::SubRegReg(index, lhs, rhs)
                 | SsaOp::DivRegReg(index, lhs, rhs)
                 | SsaOp::AtanRegReg(index, lhs, rhs)
                 | SsaOp::CompareRegReg(index, lhs, rhs)
-                | SsaOp::ModRegReg(index, lhs, rhs) => {
+                | SsaOp::ModRegReg(index, lhs, rhs)
+                | SsaOp::PowRegReg(index, lhs, rhs) => {
                     *index = new_index;
                     *lhs = workspace.get_or_insert_active(*lhs);
                     *rhs = workspace.get_or_insert_active(*rhs);
@@ -291,21 +340,27 @@ impl<const N: usize> VmData<N> {
                 | SsaOp::CompareRegImm(index, arg, _imm)
                 | SsaOp::CompareImmReg(index, arg, _imm)
                 | SsaOp::ModRegImm(index, arg, _imm)
-                | SsaOp::ModImmReg(index, arg, _imm) => {
+                | SsaOp::ModImmReg(index, arg, _imm)
+                | SsaOp::PowRegImm(index, arg, _imm)
+                | SsaOp::PowImmReg(index, arg, _imm) => {
                     *index = new_index;
                     *arg = workspace.get_or_insert_active(*arg);
                 }
             }
             workspace.alloc.op(op);
             ops_out.push(op);
+            if op.imm().is_some() {
+                imms_out.push(imm.unwrap());
+            }
         }
 
-        assert_eq!(workspace.count as usize + 1, ops_out.len());
+        assert_eq!(workspace.count as usize + output_count, ops_out.len());
         let asm_tape = workspace.alloc.finalize();
 
         Ok(VmData {
             ssa: SsaTape {
                 tape: ops_out,
+                imms: imms_out,
                 choice_count,
                 output_count,
             },
@@ -314,11 +369,29 @@ impl<const N: usize> VmData<N> {
         })
     }
 
+    /// Produces an iterator that visits [`SsaOp`] values in evaluation order
+    pub fn iter_ssa(&self) -> impl Iterator<Item = SsaOp> + '_ {
+        self.ssa.tape.iter().cloned().rev()
+    }
+
     /// Produces an iterator that visits [`RegOp`] values in evaluation order
     pub fn iter_asm(&self) -> impl Iterator<Item = RegOp> + '_ {
         self.asm.iter().cloned().rev()
     }
 
+    /// Produces an iterator that visits exact immediate values in evaluation
+    /// order
+    ///
+    /// Immediates in the [`RegOp`] tape are stored as `f32`; this iterator
+    /// yields their original `f64` values, one per [`RegOp`] with an immediate
+    /// (in the same order as [`iter_asm`](Self::iter_asm)).
+    ///
+    /// This is empty for tapes deserialized from before exact immediates were
+    /// recorded.
+    pub fn iter_imm(&self) -> impl Iterator<Item = f64> + '_ {
+        self.ssa.imms.iter().cloned().rev()
+    }
+
     /// Pretty-prints the inner SSA tape
     pub fn pretty_print(&self) {
         self.ssa.pretty_print();
@@ -391,6 +464,84 @@ impl<const N: usize> VmWorkspace<N> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        eval::{FunctionF64, TracingEvaluator},
+        var::Var,
+        vm::{VmFunction, VmPointEvalF64},
+    };
+
+    #[test]
+    fn simplify_exact_imms() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let a = ctx.add(x, 0.1).unwrap();
+        let b = ctx.min(y, 1e8 + 1.0).unwrap();
+        let c = ctx.mul(a, b).unwrap();
+
+        let data = VmData::<255>::new(&ctx, &[c]).unwrap();
+        let mut imms = data.iter_imm().collect::<Vec<_>>();
+        imms.sort_by(f64::total_cmp);
+        assert_eq!(imms, [0.1, 1e8 + 1.0]);
+
+        // Picking the immediate turns `min` into a `CopyImm`, which keeps it
+        let next = data
+            .simplify::<255>(
+                &[Choice::Right],
+                &mut Default::default(),
+                Default::default(),
+            )
+            .unwrap();
+        let mut imms = next.iter_imm().collect::<Vec<_>>();
+        imms.sort_by(f64::total_cmp);
+        assert_eq!(imms, [0.1, 1e8 + 1.0]);
+
+        // Picking the register drops it
+        let next = data
+            .simplify::<255>(
+                &[Choice::Left],
+                &mut Default::default(),
+                Default::default(),
+            )
+            .unwrap();
+        assert_eq!(next.iter_imm().collect::<Vec<_>>(), [0.1]);
+    }
+
+    #[test]
+    fn missing_exact_imms() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let a = ctx.add(x, 0.1).unwrap();
+        let b = ctx.min(y, 1e8 + 1.0).unwrap();
+        let c = ctx.mul(a, b).unwrap();
+
+        // This is what an old serialized tape deserializes to
+        let mut data = VmData::<255>::new(&ctx, &[c]).unwrap();
+        data.ssa.imms.clear();
+        assert_eq!(data.iter_imm().count(), 0);
+
+        let next = data
+            .simplify::<255>(
+                &[Choice::Right],
+                &mut Default::default(),
+                Default::default(),
+            )
+            .unwrap();
+        let mut imms = next.iter_imm().collect::<Vec<_>>();
+        imms.sort_by(f64::total_cmp);
+        assert_eq!(imms, [0.1f32 as f64, (1e8 + 1.0) as f32 as f64]);
+
+        let vars = data.vars.clone();
+        let func = VmFunction::from(data);
+        let tape = func.point_tape_f64(Default::default());
+        let mut eval = VmPointEvalF64::<255>::default();
+        let mut args = [0.0; 2];
+        args[vars.get(&Var::X).unwrap()] = 1.0;
+        args[vars.get(&Var::Y).unwrap()] = 2.0;
+        let (out, _) = eval.eval(&tape, &args).unwrap();
+        assert_eq!(out[0], (1.0 + 0.1f32 as f64) * 2.0);
+    }
 
     #[test]
     fn simplify_reg_count_change() {
@@ -0,0 +1,396 @@
+//! Dual-number evaluation with an arbitrary number of partial derivatives
+use super::{BulkVmEval, EmptyTapeStorage, GenericVmTape, SlotArray};
+use crate::{
+    compiler::RegOp,
+    eval::{BulkEvaluator, BulkOutput, Tape},
+    types::Dual,
+    Error,
+};
+
+/// VM-based bulk evaluator for arrays of points, yielding dual numbers
+///
+/// Each value carries `P` partial derivatives; see [`Dual`] for details.
+#[derive(Default)]
+pub struct VmDualSliceEval<const N: usize, const P: usize>(BulkVmEval<Dual<P>>);
+impl<const N: usize, const P: usize> BulkEvaluator for VmDualSliceEval<N, P> {
+    type Data = Dual<P>;
+    type Tape = GenericVmTape<N>;
+    type TapeStorage = EmptyTapeStorage;
+
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<BulkOutput<'_, Dual<P>>, Error> {
+        tape.vars().check_bulk_arguments(vars)?;
+        let tape = tape.data();
+        let size = vars.first().map(|v| v.len()).unwrap_or(0);
+        self.0.resize_slots(tape, size);
+
+        let mut v = SlotArray(&mut self.0.slots);
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Output(arg, i) => {
+                    self.0.out[i as usize][0..size]
+                        .copy_from_slice(&v[arg][0..size]);
+                }
+                RegOp::Input(out, i) => {
+                    v[out][0..size].copy_from_slice(&vars[i as usize]);
+                }
+                RegOp::NegReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = -v[arg][i];
+                    }
+                }
+                RegOp::AbsReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].abs();
+                    }
+                }
+                RegOp::RecipReg(out, arg) => {
+                    let one: Dual<P> = 1.0.into();
+                    for i in 0..size {
+                        v[out][i] = one / v[arg][i];
+                    }
+                }
+                RegOp::SqrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sqrt();
+                    }
+                }
+                RegOp::SquareReg(out, arg) => {
+                    for i in 0..size {
+                        let s = v[arg][i];
+                        v[out][i] = s * s;
+                    }
+                }
+                RegOp::FloorReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].floor();
+                    }
+                }
+                RegOp::CeilReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ceil();
+                    }
+                }
+                RegOp::RoundReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].round();
+                    }
+                }
+                RegOp::SinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sin();
+                    }
+                }
+                RegOp::CosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cos();
+                    }
+                }
+                RegOp::TanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tan();
+                    }
+                }
+                RegOp::AsinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].asin();
+                    }
+                }
+                RegOp::AcosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].acos();
+                    }
+                }
+                RegOp::AtanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan();
+                    }
+                }
+                RegOp::ExpReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].exp();
+                    }
+                }
+                RegOp::LnReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ln();
+                    }
+                }
+                RegOp::NotReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = f32::from(v[arg][i].v == 0.0).into();
+                    }
+                }
+                RegOp::SinhReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sinh();
+                    }
+                }
+                RegOp::CoshReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cosh();
+                    }
+                }
+                RegOp::TanhReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tanh();
+                    }
+                }
+                RegOp::SignReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sign();
+                    }
+                }
+                RegOp::CbrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cbrt();
+                    }
+                }
+                RegOp::Log2Reg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].log2();
+                    }
+                }
+                RegOp::CopyReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i];
+                    }
+                }
+                RegOp::AddRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] + imm.into();
+                    }
+                }
+                RegOp::MulRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] * imm;
+                    }
+                }
+                RegOp::DivRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] / imm.into();
+                    }
+                }
+                RegOp::DivImmReg(out, arg, imm) => {
+                    let imm = Dual::from(imm);
+                    for i in 0..size {
+                        v[out][i] = imm / v[arg][i];
+                    }
+                }
+                RegOp::AtanRegImm(out, arg, imm) => {
+                    let imm = Dual::from(imm);
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan2(imm);
+                    }
+                }
+                RegOp::AtanImmReg(out, arg, imm) => {
+                    let imm = Dual::from(imm);
+                    for i in 0..size {
+                        v[out][i] = imm.atan2(v[arg][i]);
+                    }
+                }
+                RegOp::AtanRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                    }
+                }
+                RegOp::SubImmReg(out, arg, imm) => {
+                    let imm: Dual<P> = imm.into();
+                    for i in 0..size {
+                        v[out][i] = imm - v[arg][i];
+                    }
+                }
+                RegOp::SubRegImm(out, arg, imm) => {
+                    let imm: Dual<P> = imm.into();
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] - imm;
+                    }
+                }
+                RegOp::CompareImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        let p = imm
+                            .partial_cmp(&v[arg][i].v)
+                            .map(|c| c as i8 as f32)
+                            .unwrap_or(f32::NAN);
+                        v[out][i] = Dual::from(p);
+                    }
+                }
+                RegOp::CompareRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        let p = v[arg][i]
+                            .v
+                            .partial_cmp(&imm)
+                            .map(|c| c as i8 as f32)
+                            .unwrap_or(f32::NAN);
+                        v[out][i] = Dual::from(p);
+                    }
+                }
+                RegOp::MinRegImm(out, arg, imm) => {
+                    let imm: Dual<P> = imm.into();
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
+                            f32::NAN.into()
+                        } else {
+                            v[arg][i].min(imm)
+                        };
+                    }
+                }
+                RegOp::MaxRegImm(out, arg, imm) => {
+                    let imm: Dual<P> = imm.into();
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
+                            f32::NAN.into()
+                        } else {
+                            v[arg][i].max(imm)
+                        };
+                    }
+                }
+                RegOp::ModRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                    }
+                }
+                RegOp::ModRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].rem_euclid(imm.into());
+                    }
+                }
+                RegOp::ModImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = Dual::from(imm).rem_euclid(v[arg][i]);
+                    }
+                }
+                RegOp::PowRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].pow(v[rhs][i]);
+                    }
+                }
+                RegOp::PowRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].pow(imm.into());
+                    }
+                }
+                RegOp::PowImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = Dual::from(imm).pow(v[arg][i]);
+                    }
+                }
+                RegOp::AddRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] + v[rhs][i];
+                    }
+                }
+                RegOp::MulRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] * v[rhs][i];
+                    }
+                }
+                RegOp::AndRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i].v == 0.0 {
+                            v[lhs][i]
+                        } else {
+                            v[rhs][i]
+                        };
+                    }
+                }
+                RegOp::AndRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v == 0.0 {
+                            v[arg][i]
+                        } else {
+                            imm.into()
+                        };
+                    }
+                }
+                RegOp::OrRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i].v != 0.0 {
+                            v[lhs][i]
+                        } else {
+                            v[rhs][i]
+                        };
+                    }
+                }
+                RegOp::OrRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].v != 0.0 {
+                            v[arg][i]
+                        } else {
+                            imm.into()
+                        };
+                    }
+                }
+                RegOp::DivRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] / v[rhs][i];
+                    }
+                }
+                RegOp::SubRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] - v[rhs][i];
+                    }
+                }
+                RegOp::CompareRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        let p = v[lhs][i]
+                            .v
+                            .partial_cmp(&v[rhs][i].v)
+                            .map(|c| c as i8 as f32)
+                            .unwrap_or(f32::NAN);
+                        v[out][i] = Dual::from(p);
+                    }
+                }
+                RegOp::MinRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] =
+                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
+                                f32::NAN.into()
+                            } else {
+                                v[lhs][i].min(v[rhs][i])
+                            };
+                    }
+                }
+                RegOp::MaxRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] =
+                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
+                                f32::NAN.into()
+                            } else {
+                                v[lhs][i].max(v[rhs][i])
+                            };
+                    }
+                }
+                RegOp::SelectRegRegReg(out, cond, a, b) => {
+                    for i in 0..size {
+                        v[out][i] = if v[cond][i].v != 0.0 {
+                            v[a][i]
+                        } else {
+                            v[b][i]
+                        };
+                    }
+                }
+                RegOp::CopyImm(out, imm) => {
+                    let imm: Dual<P> = imm.into();
+                    for i in 0..size {
+                        v[out][i] = imm;
+                    }
+                }
+                RegOp::Load(out, mem) => {
+                    for i in 0..size {
+                        v[out][i] = v[mem][i];
+                    }
+                }
+                RegOp::Store(out, mem) => {
+                    for i in 0..size {
+                        v[mem][i] = v[out][i];
+                    }
+                }
+            }
+        }
+        Ok(BulkOutput::new(&self.0.out, size))
+    }
+}
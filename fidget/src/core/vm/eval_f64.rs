@@ -0,0 +1,399 @@
+//! Double-precision VM evaluators
+//!
+//! These are `f64` equivalents of the evaluators in the parent module, and
+//! share their tapes, traces, and slot allocation.  Immediates in the tape are
+//! stored as `f32`, so these evaluators read their exact values from
+//! [`VmData::iter_imm`](super::VmData::iter_imm) instead.
+use super::{
+    vm_float_slice_eval, vm_grad_slice_eval, vm_interval_eval, vm_point_eval,
+    BulkVmEval, EmptyTapeStorage, GenericVmTape, SlotArray, TracingVmEval,
+    VmImmediate, VmTrace,
+};
+use crate::{
+    compiler::RegOp,
+    eval::{BulkEvaluator, BulkOutput, Tape, TracingEvaluator},
+    types::{GradF64, IntervalF64},
+    vm::Choice,
+    Error,
+};
+
+vm_interval_eval!(
+    /// VM-based tracing evaluator for double-precision intervals
+    VmIntervalEvalF64,
+    IntervalF64,
+    f64
+);
+
+vm_point_eval!(
+    /// VM-based tracing evaluator for single double-precision points
+    VmPointEvalF64,
+    f64
+);
+
+vm_float_slice_eval!(
+    /// VM-based bulk evaluator for arrays of points, yielding `f64` values
+    VmFloatSliceEvalF64,
+    f64
+);
+
+vm_grad_slice_eval!(
+    /// VM-based bulk evaluator for arrays of points, yielding `f64` gradients
+    VmGradSliceEvalF64,
+    GradF64,
+    f64
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::{Context, Node},
+        eval::{
+            test::{CanonicalBinaryOp, CanonicalUnaryOp},
+            Function, FunctionF64, MathFunction,
+        },
+        shape::EzShape,
+        types::Grad,
+        var::Var,
+        vm::{VmFunction, VmShape},
+    };
+
+    /// Arguments which are exactly representable as `f32`, so that they can
+    /// also be used as immediates
+    fn test_args() -> Vec<f64> {
+        let mut args = (-16..=16)
+            .map(|i| (std::f32::consts::PI * i as f32 / 8.0) as f64)
+            .collect::<Vec<_>>();
+        args.extend([0.0, 0.5, 1.0, 1.5, 5.0, 10.0, f64::NAN]);
+        args
+    }
+
+    fn check(label: &str, expected: f64, actual: f64) {
+        let err = (expected - actual).abs();
+        assert!(
+            (expected.is_nan() && actual.is_nan())
+                || expected == actual
+                || err <= 1e-12 * expected.abs().max(1.0),
+            "{label}: expected {expected}, got {actual}"
+        );
+    }
+
+    /// Checks every `f64` evaluator for a function of `x` and `y`
+    fn check_xy(ctx: &Context, node: Node, f: impl Fn(f64, f64) -> f64) {
+        let func = VmFunction::new(ctx, &[node]).unwrap();
+        let args = test_args();
+        let vars = func.vars();
+        let bind = |x, y| {
+            let mut out = vec![0.0; vars.len()];
+            if let Some(i) = vars.get(&Var::X) {
+                out[i] = x;
+            }
+            if let Some(i) = vars.get(&Var::Y) {
+                out[i] = y;
+            }
+            out
+        };
+
+        let mut point = VmPointEvalF64::<255>::default();
+        let point_tape = func.point_tape_f64(Default::default());
+        let mut interval = VmIntervalEvalF64::<255>::default();
+        let interval_tape = func.interval_tape_f64(Default::default());
+        let mut xs = vec![];
+        let mut ys = vec![];
+        for &x in &args {
+            for &y in &args {
+                let expected = f(x, y);
+                let (out, _) = point.eval(&point_tape, &bind(x, y)).unwrap();
+                check(&format!("point({x}, {y})"), expected, out[0]);
+
+                let vs: Vec<IntervalF64> =
+                    bind(x, y).into_iter().map(IntervalF64::from).collect();
+                let (out, _) = interval.eval(&interval_tape, &vs).unwrap();
+                if !expected.is_nan() {
+                    assert!(
+                        out[0].has_nan() || out[0].contains(expected),
+                        "interval({x}, {y}): {expected} not in {:?}",
+                        out[0]
+                    );
+                }
+                xs.push(x);
+                ys.push(y);
+            }
+        }
+
+        let mut float = VmFloatSliceEvalF64::<255>::default();
+        let tape = func.float_slice_tape_f64(Default::default());
+        let mut slices = vec![vec![]; vars.len()];
+        for (x, y) in xs.iter().zip(&ys) {
+            for (s, v) in slices.iter_mut().zip(bind(*x, *y)) {
+                s.push(v);
+            }
+        }
+        let out = float.eval(&tape, &slices).unwrap();
+        for (i, v) in out[0].iter().enumerate() {
+            check(&format!("float({}, {})", xs[i], ys[i]), f(xs[i], ys[i]), *v);
+        }
+
+        // Seed partial derivatives with respect to X and Y, then compare them
+        // against the single-precision gradient evaluator
+        let seed = |i: usize, v: f64| {
+            let mut g = GradF64::from(v);
+            if vars.get(&Var::X) == Some(i) {
+                g.dx = 1.0;
+            }
+            if vars.get(&Var::Y) == Some(i) {
+                g.dy = 1.0;
+            }
+            g
+        };
+        let mut grad = VmGradSliceEvalF64::<255>::default();
+        let tape = func.grad_slice_tape_f64(Default::default());
+        let slices: Vec<Vec<GradF64>> = slices
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| s.into_iter().map(|v| seed(i, v)).collect())
+            .collect();
+        let out = grad.eval(&tape, &slices).unwrap();
+
+        let mut grad_f32 = VmFunction::new_grad_slice_eval();
+        let tape_f32 = func.grad_slice_tape(Default::default());
+        let slices_f32: Vec<Vec<Grad>> = slices
+            .iter()
+            .map(|s| {
+                s.iter()
+                    .map(|g| {
+                        Grad::new(g.v as f32, g.dx as f32, g.dy as f32, 0.0)
+                    })
+                    .collect()
+            })
+            .collect();
+        let out_f32 = grad_f32.eval(&tape_f32, &slices_f32).unwrap();
+
+        for (i, (v, e)) in out[0].iter().zip(&out_f32[0]).enumerate() {
+            let label = format!("grad({}, {})", xs[i], ys[i]);
+            check(&label, f(xs[i], ys[i]), v.v);
+
+            // At discontinuities (e.g. in `mod`), rounding may put the `f32`
+            // evaluator on a different branch; its derivatives then differ.
+            let (x, y) = (xs[i], ys[i]);
+            let h = 1e-6;
+            let jump = [(h, 0.0), (-h, 0.0), (0.0, h), (0.0, -h)]
+                .into_iter()
+                .any(|(dx, dy)| {
+                    let d = (f(x + dx, y + dy) - v.v).abs();
+                    d > 1e-3 * v.v.abs().max(1.0)
+                });
+            if jump {
+                continue;
+            }
+            check_f32(&format!("{label}.dx"), e.dx, v.dx);
+            check_f32(&format!("{label}.dy"), e.dy, v.dy);
+            check_f32(&format!("{label}.dz"), e.dz, v.dz);
+        }
+    }
+
+    /// Checks an `f64` result against the single-precision evaluator
+    fn check_f32(label: &str, expected: f32, actual: f64) {
+        let err = (expected as f64 - actual).abs();
+        assert!(
+            (expected.is_nan() && actual.is_nan())
+                || expected as f64 == actual
+                || err <= 1e-4 * actual.abs().max(1.0)
+                // The `f32` evaluator may overflow where `f64` doesn't
+                || (!expected.is_finite() && actual.abs() > f32::MAX as f64),
+            "{label}: expected {expected}, got {actual}"
+        );
+    }
+
+    struct TestF64;
+    impl TestF64 {
+        fn test_unary<C: CanonicalUnaryOp>() {
+            let mut ctx = Context::new();
+            let x = ctx.x();
+            let node = C::build(&mut ctx, x);
+            check_xy(&ctx, node, |x, _y| C::eval_f64(x));
+        }
+
+        fn test_binary<C: CanonicalBinaryOp>() {
+            let mut ctx = Context::new();
+            let x = ctx.x();
+            let y = ctx.y();
+            let node = C::build(&mut ctx, x, y);
+            check_xy(&ctx, node, C::eval_reg_reg_f64);
+
+            for imm in test_args() {
+                let mut ctx = Context::new();
+                let x = ctx.x();
+                let node = C::build(&mut ctx, x, imm);
+                check_xy(&ctx, node, |x, _y| C::eval_reg_imm_f64(x, imm));
+
+                let mut ctx = Context::new();
+                let x = ctx.x();
+                let node = C::build(&mut ctx, imm, x);
+                check_xy(&ctx, node, |x, _y| C::eval_imm_reg_f64(imm, x));
+            }
+        }
+    }
+
+    crate::all_unary_tests!(TestF64);
+    crate::all_binary_tests!(TestF64);
+
+    #[test]
+    fn test_f64_precision() {
+        // 1e8 is exactly representable as an `f32`, but 1e8 + 0.25 is not
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let d = ctx.sub(x, 1e8).unwrap();
+        let d = ctx.mul(d, 4.0).unwrap();
+        let root = ctx.add(d, y).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+
+        let mut eval = VmShape::new_point_eval();
+        let tape = shape.ez_point_tape();
+        let (v, _) = eval.eval(&tape, 1e8 + 0.25, 0.0, 0.0).unwrap();
+        assert_eq!(v, 0.0); // precision is lost
+
+        let mut eval = VmShape::new_point_eval_f64();
+        let tape = shape.point_tape_f64(Default::default());
+        let (v, _) = eval.eval(&tape, 1e8 + 0.25, 0.5, 0.0).unwrap();
+        assert_eq!(v, 1.5);
+
+        let mut eval = VmShape::new_interval_eval_f64();
+        let tape = shape.interval_tape_f64(Default::default());
+        let (v, _) = eval
+            .eval(
+                &tape,
+                IntervalF64::new(1e8 + 0.25, 1e8 + 0.5),
+                IntervalF64::from(0.0),
+                IntervalF64::from(0.0),
+            )
+            .unwrap();
+        assert_eq!(v, IntervalF64::new(1.0, 2.0));
+
+        let mut eval = VmShape::new_float_slice_eval_f64();
+        let tape = shape.float_slice_tape_f64(Default::default());
+        let out = eval
+            .eval(&tape, &[1e8 + 0.25, 1e8 - 0.5], &[0.0, 1.0], &[0.0, 0.0])
+            .unwrap();
+        assert_eq!(&out[0], &[1.0, -1.0]);
+
+        let mut eval = VmShape::new_grad_slice_eval_f64();
+        let tape = shape.grad_slice_tape_f64(Default::default());
+        let out = eval
+            .eval(
+                &tape,
+                &[GradF64::new(1e8 + 0.25, 1.0, 0.0, 0.0)],
+                &[GradF64::new(0.0, 0.0, 1.0, 0.0)],
+                &[GradF64::new(0.0, 0.0, 0.0, 1.0)],
+            )
+            .unwrap();
+        assert_eq!(out[0][0], GradF64::new(1.0, 4.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_f64_exact_imm() {
+        // Neither constant is exactly representable as an `f32`
+        const C: f64 = 1e8 + 1.0;
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let d = ctx.sub(x, C).unwrap();
+        let d = ctx.mul(d, 10.0).unwrap();
+        let root = ctx.add(d, 0.1).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let f = |x: f64| (x - C) * 10.0 + 0.1;
+
+        let mut eval = VmShape::new_point_eval_f64();
+        let tape = shape.point_tape_f64(Default::default());
+        for x in [C, C + 0.5, C - 3.0] {
+            let (v, _) = eval.eval(&tape, x, 0.0, 0.0).unwrap();
+            assert_eq!(v, f(x));
+        }
+
+        let mut eval = VmShape::new_interval_eval_f64();
+        let tape = shape.interval_tape_f64(Default::default());
+        let i = IntervalF64::new(C, C + 0.5);
+        let (v, _) = eval.eval(&tape, i, 0.0.into(), 0.0.into()).unwrap();
+        assert_eq!(v, (i - C.into()) * 10.0 + 0.1.into());
+        assert_eq!(v.lower(), 0.1);
+
+        let mut eval = VmShape::new_float_slice_eval_f64();
+        let tape = shape.float_slice_tape_f64(Default::default());
+        let out = eval
+            .eval(&tape, &[C, C + 0.5], &[0.0; 2], &[0.0; 2])
+            .unwrap();
+        assert_eq!(&out[0], &[f(C), f(C + 0.5)]);
+
+        let mut eval = VmShape::new_grad_slice_eval_f64();
+        let tape = shape.grad_slice_tape_f64(Default::default());
+        let out = eval
+            .eval(
+                &tape,
+                &[GradF64::new(C, 1.0, 0.0, 0.0)],
+                &[GradF64::new(0.0, 0.0, 1.0, 0.0)],
+                &[GradF64::new(0.0, 0.0, 0.0, 1.0)],
+            )
+            .unwrap();
+        let g = out[0][0];
+        assert_eq!(g.v, 0.1);
+        assert_eq!([g.dx, g.dy, g.dz], [10.0, 0.0, 0.0]);
+
+        // Immediates are also exact in selects, and after simplification
+        let s = ctx.if_nonzero_else(y, C, 0.1).unwrap();
+        let root = ctx.min(x, s).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let mut eval = VmShape::new_point_eval_f64();
+        let tape = shape.point_tape_f64(Default::default());
+        assert_eq!(eval.eval(&tape, 2e8, 1.0, 0.0).unwrap().0, C);
+        assert_eq!(eval.eval(&tape, 2e8, 0.0, 0.0).unwrap().0, 0.1);
+
+        let mut eval = VmShape::new_interval_eval_f64();
+        let tape = shape.interval_tape_f64(Default::default());
+        let (_, trace) = eval
+            .eval(
+                &tape,
+                IntervalF64::new(2e8, 3e8),
+                IntervalF64::from(1.0),
+                IntervalF64::from(0.0),
+            )
+            .unwrap();
+        let next = shape.ez_simplify(trace.unwrap()).unwrap();
+        assert!(next.size() < shape.size());
+
+        let mut eval = VmShape::new_point_eval_f64();
+        let tape = next.point_tape_f64(Default::default());
+        let (v, _) = eval.eval(&tape, 2.5e8, 0.0, 0.0).unwrap();
+        assert_eq!(v, C);
+    }
+
+    #[test]
+    fn test_f64_simplify() {
+        // Traces from f64 evaluation can be used to simplify the function
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let root = ctx.min(x, y).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+
+        let mut eval = VmShape::new_interval_eval_f64();
+        let tape = shape.interval_tape_f64(Default::default());
+        let (v, trace) = eval
+            .eval(
+                &tape,
+                IntervalF64::new(0.0, 1.0),
+                IntervalF64::new(2.0, 3.0),
+                IntervalF64::from(0.0),
+            )
+            .unwrap();
+        assert_eq!(v, IntervalF64::new(0.0, 1.0));
+        let next = shape.ez_simplify(trace.unwrap()).unwrap();
+        assert!(next.size() < shape.size());
+
+        let mut eval = VmShape::new_point_eval_f64();
+        let tape = next.point_tape_f64(Default::default());
+        let (v, trace) = eval.eval(&tape, 0.5, 2.5, 0.0).unwrap();
+        assert_eq!(v, 0.5);
+        assert!(trace.is_none());
+    }
+}
@@ -0,0 +1,434 @@
+//! Bulk interval evaluation, capturing a trace for each interval
+use super::{
+    BulkVmEval, Choice, EmptyTapeStorage, GenericVmTape, SlotArray, VmTrace,
+};
+use crate::{
+    compiler::RegOp,
+    eval::{
+        BulkOutput, BulkTraces, MultiTracingEvaluator, MultiTracingResult, Tape,
+    },
+    types::Interval,
+    Error,
+};
+
+/// VM-based bulk evaluator for arrays of intervals
+///
+/// Each interval is evaluated with the same arithmetic as
+/// [`VmIntervalEval`](super::VmIntervalEval), so results and per-interval
+/// traces are identical; the tape is only walked once for the whole array.
+#[derive(Default)]
+pub struct VmIntervalSliceEval<const N: usize> {
+    bulk: BulkVmEval<Interval>,
+    traces: Vec<VmTrace>,
+    simplify: Vec<bool>,
+}
+impl<const N: usize> MultiTracingEvaluator for VmIntervalSliceEval<N> {
+    type Data = Interval;
+    type Tape = GenericVmTape<N>;
+    type TapeStorage = EmptyTapeStorage;
+    type Trace = VmTrace;
+
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<MultiTracingResult<'_, Interval, VmTrace>, Error> {
+        tape.vars().check_bulk_arguments(vars)?;
+        let tape = tape.data();
+
+        let size = vars.first().map(|v| v.len()).unwrap_or(0);
+        let Self {
+            bulk,
+            traces,
+            simplify,
+        } = self;
+        bulk.resize_slots(tape, size);
+        traces.resize_with(size, VmTrace::default);
+        for t in traces.iter_mut() {
+            t.resize(tape.choice_count(), Choice::Unknown);
+            t.fill(Choice::Unknown);
+        }
+        simplify.resize(size, false);
+        simplify.fill(false);
+
+        // Index of the next choice in the trace
+        let mut k = 0;
+        let mut v = SlotArray(&mut bulk.slots);
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Output(arg, i) => {
+                    bulk.out[i as usize][0..size]
+                        .copy_from_slice(&v[arg][0..size]);
+                }
+                RegOp::Input(out, i) => {
+                    v[out][0..size].copy_from_slice(&vars[i as usize]);
+                }
+                RegOp::NegReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = -v[arg][i];
+                    }
+                }
+                RegOp::AbsReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].abs();
+                    }
+                }
+                RegOp::RecipReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].recip();
+                    }
+                }
+                RegOp::SqrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sqrt();
+                    }
+                }
+                RegOp::SquareReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].square();
+                    }
+                }
+                RegOp::FloorReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].floor();
+                    }
+                }
+                RegOp::CeilReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ceil();
+                    }
+                }
+                RegOp::RoundReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].round();
+                    }
+                }
+                RegOp::SinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sin();
+                    }
+                }
+                RegOp::CosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cos();
+                    }
+                }
+                RegOp::TanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tan();
+                    }
+                }
+                RegOp::AsinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].asin();
+                    }
+                }
+                RegOp::AcosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].acos();
+                    }
+                }
+                RegOp::AtanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan();
+                    }
+                }
+                RegOp::ExpReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].exp();
+                    }
+                }
+                RegOp::LnReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ln();
+                    }
+                }
+                RegOp::NotReg(out, arg) => {
+                    for i in 0..size {
+                        let a = v[arg][i];
+                        v[out][i] = if !a.contains(0.0) && !a.has_nan() {
+                            Interval::new(0.0, 0.0)
+                        } else if a.lower() == 0.0 && a.upper() == 0.0 {
+                            Interval::new(1.0, 1.0)
+                        } else {
+                            Interval::new(0.0, 1.0)
+                        };
+                    }
+                }
+                RegOp::SinhReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sinh();
+                    }
+                }
+                RegOp::CoshReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cosh();
+                    }
+                }
+                RegOp::TanhReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tanh();
+                    }
+                }
+                RegOp::SignReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sign();
+                    }
+                }
+                RegOp::CbrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cbrt();
+                    }
+                }
+                RegOp::Log2Reg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].log2();
+                    }
+                }
+                RegOp::CopyReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i];
+                    }
+                }
+                RegOp::AddRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] + imm.into();
+                    }
+                }
+                RegOp::MulRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] * imm;
+                    }
+                }
+                RegOp::DivRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] / imm.into();
+                    }
+                }
+                RegOp::DivImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        let imm: Interval = imm.into();
+                        v[out][i] = imm / v[arg][i];
+                    }
+                }
+                RegOp::AtanRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan2(imm.into());
+                    }
+                }
+                RegOp::AtanImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        let imm: Interval = imm.into();
+                        v[out][i] = imm.atan2(v[arg][i]);
+                    }
+                }
+                RegOp::AtanRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                    }
+                }
+                RegOp::SubImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = Interval::from(imm) - v[arg][i];
+                    }
+                }
+                RegOp::SubRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] - imm.into();
+                    }
+                }
+                RegOp::MinRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        let (value, c) = v[arg][i].min_choice(imm.into());
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::MaxRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        let (value, c) = v[arg][i].max_choice(imm.into());
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::AndRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        let (value, c) = v[lhs][i].and_choice(v[rhs][i]);
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::AndRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        let (value, c) = v[arg][i].and_choice(imm.into());
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::OrRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        let (value, c) = v[lhs][i].or_choice(v[rhs][i]);
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::OrRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        let (value, c) = v[arg][i].or_choice(imm.into());
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::ModRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                    }
+                }
+                RegOp::ModRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].rem_euclid(imm.into());
+                    }
+                }
+                RegOp::ModImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = Interval::from(imm).rem_euclid(v[arg][i]);
+                    }
+                }
+                RegOp::PowRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].pow(v[rhs][i]);
+                    }
+                }
+                RegOp::PowRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].pow(imm.into());
+                    }
+                }
+                RegOp::PowImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = Interval::from(imm).pow(v[arg][i]);
+                    }
+                }
+                RegOp::AddRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] + v[rhs][i];
+                    }
+                }
+                RegOp::MulRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] * v[rhs][i];
+                    }
+                }
+                RegOp::DivRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] / v[rhs][i];
+                    }
+                }
+                RegOp::SubRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] - v[rhs][i];
+                    }
+                }
+                RegOp::CompareRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] =
+                            if v[lhs][i].has_nan() || v[rhs][i].has_nan() {
+                                f32::NAN.into()
+                            } else if v[lhs][i].upper() < v[rhs][i].lower() {
+                                Interval::from(-1.0)
+                            } else if v[lhs][i].lower() > v[rhs][i].upper() {
+                                Interval::from(1.0)
+                            } else {
+                                Interval::new(-1.0, 1.0)
+                            };
+                    }
+                }
+                RegOp::CompareRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].has_nan() || imm.is_nan() {
+                            f32::NAN.into()
+                        } else if v[arg][i].upper() < imm {
+                            Interval::from(-1.0)
+                        } else if v[arg][i].lower() > imm {
+                            Interval::from(1.0)
+                        } else {
+                            Interval::new(-1.0, 1.0)
+                        };
+                    }
+                }
+                RegOp::CompareImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = if v[arg][i].has_nan() || imm.is_nan() {
+                            f32::NAN.into()
+                        } else if imm < v[arg][i].lower() {
+                            Interval::from(-1.0)
+                        } else if imm > v[arg][i].upper() {
+                            Interval::from(1.0)
+                        } else {
+                            Interval::new(-1.0, 1.0)
+                        };
+                    }
+                }
+                RegOp::MinRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        let (value, c) = v[lhs][i].min_choice(v[rhs][i]);
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::MaxRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        let (value, c) = v[lhs][i].max_choice(v[rhs][i]);
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::SelectRegRegReg(out, cond, a, b) => {
+                    for i in 0..size {
+                        let (value, c) =
+                            v[cond][i].select_choice(v[a][i], v[b][i]);
+                        v[out][i] = value;
+                        traces[i].0[k] |= c;
+                        simplify[i] |= c != Choice::Both;
+                    }
+                    k += 1;
+                }
+                RegOp::CopyImm(out, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm.into();
+                    }
+                }
+                RegOp::Load(out, mem) => {
+                    for i in 0..size {
+                        v[out][i] = v[mem][i];
+                    }
+                }
+                RegOp::Store(out, mem) => {
+                    for i in 0..size {
+                        v[mem][i] = v[out][i];
+                    }
+                }
+            }
+        }
+        Ok((
+            BulkOutput::new(&bulk.out, size),
+            BulkTraces::new(&traces[..size], &simplify[..size]),
+        ))
+    }
+}
@@ -3,8 +3,8 @@ use crate::{
     compiler::RegOp,
     context::Node,
     eval::{
-        BulkEvaluator, BulkOutput, Function, MathFunction, Tape, Trace,
-        TracingEvaluator,
+        BulkEvaluator, BulkOutput, Function, FunctionF64, FunctionHess,
+        MathFunction, Tape, Trace, TracingEvaluator,
     },
     render::{RenderHints, TileSizes},
     shape::Shape,
@@ -14,11 +14,25 @@ use crate::{
 };
 use std::sync::Arc;
 
+mod affine;
 mod choice;
 mod data;
+mod dual;
+mod eval_f64;
+mod hess;
+mod interval_slice;
+mod tracing_slice;
 
+pub use affine::{GenericVmAffineFunction, VmAffineEval, VmAffineSliceEval};
 pub use choice::Choice;
 pub use data::{VmData, VmWorkspace};
+pub use dual::VmDualSliceEval;
+pub use eval_f64::{
+    VmFloatSliceEvalF64, VmGradSliceEvalF64, VmIntervalEvalF64, VmPointEvalF64,
+};
+pub use hess::VmHessSliceEval;
+pub use interval_slice::VmIntervalSliceEval;
+pub use tracing_slice::VmFloatSliceTracingEval;
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -34,6 +48,17 @@ pub type VmFunction = GenericVmFunction<{ u8::MAX as usize }>;
 /// Shape that uses the [`VmFunction`] backend for evaluation
 pub type VmShape = Shape<VmFunction>;
 
+/// Function which uses the VM backend, with affine arithmetic for tracing
+///
+/// See [`VmAffineEval`] for details on the interval evaluator.
+pub type VmAffineFunction = GenericVmAffineFunction<{ u8::MAX as usize }>;
+
+/// Shape that uses the [`VmAffineFunction`] backend for evaluation
+///
+/// This can be used in place of a [`VmShape`] for rendering and meshing,
+/// trading slower interval evaluation for tighter bounds.
+pub type VmAffineShape = Shape<VmAffineFunction>;
+
 /// Tape storage type which indicates that there's no actual backing storage
 #[derive(Default)]
 pub struct EmptyTapeStorage;
@@ -70,7 +95,7 @@ impl<const N: usize> Tape for GenericVmTape<N> {
 /// A trace captured by a VM evaluation
 ///
 /// This is a thin wrapper around a [`Vec<Choice>`](Choice).
-#[derive(Clone, Default, Eq, PartialEq)]
+#[derive(Clone, Default, Eq, Hash, PartialEq)]
 pub struct VmTrace(Vec<Choice>);
 
 impl VmTrace {
@@ -101,6 +126,10 @@ impl Trace for VmTrace {
         self.0.resize(other.0.len(), Choice::Unknown);
         self.0.copy_from_slice(&other.0);
     }
+
+    fn choices(&self) -> &[Choice] {
+        &self.0
+    }
 }
 
 #[cfg(test)]
@@ -110,7 +139,7 @@ impl From<Vec<Choice>> for VmTrace {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "eval-tests"))]
 impl AsRef<[Choice]> for VmTrace {
     fn as_ref(&self) -> &[Choice] {
         &self.0
@@ -187,6 +216,20 @@ impl<const N: usize> Function for GenericVmFunction<N> {
     fn grad_slice_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
         self.tape()
     }
+    type FloatSliceTracingEval = VmFloatSliceTracingEval<N>;
+    fn float_slice_tracing_tape(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.tape()
+    }
+    type DualSliceEval<const P: usize> = VmDualSliceEval<N, P>;
+    fn dual_slice_tape<const P: usize>(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.tape()
+    }
     type PointEval = VmPointEval<N>;
     fn point_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
         self.tape()
@@ -195,6 +238,13 @@ impl<const N: usize> Function for GenericVmFunction<N> {
     fn interval_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
         self.tape()
     }
+    type IntervalSliceEval = VmIntervalSliceEval<N>;
+    fn interval_slice_tape(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.tape()
+    }
     type Trace = VmTrace;
     fn simplify(
         &self,
@@ -216,6 +266,46 @@ impl<const N: usize> Function for GenericVmFunction<N> {
     fn vars(&self) -> &VarMap {
         &self.0.vars
     }
+
+    fn visit_ops(&self, f: &mut dyn FnMut(RegOp)) {
+        self.0.iter_asm().for_each(f)
+    }
+}
+
+impl<const N: usize> FunctionF64 for GenericVmFunction<N> {
+    type PointEvalF64 = VmPointEvalF64<N>;
+    type IntervalEvalF64 = VmIntervalEvalF64<N>;
+    type FloatSliceEvalF64 = VmFloatSliceEvalF64<N>;
+    type GradSliceEvalF64 = VmGradSliceEvalF64<N>;
+
+    fn point_tape_f64(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
+        self.tape()
+    }
+    fn interval_tape_f64(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.tape()
+    }
+    fn float_slice_tape_f64(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.tape()
+    }
+    fn grad_slice_tape_f64(
+        &self,
+        _storage: EmptyTapeStorage,
+    ) -> GenericVmTape<N> {
+        self.tape()
+    }
+}
+
+impl<const N: usize> FunctionHess for GenericVmFunction<N> {
+    type HessSliceEval = VmHessSliceEval<N>;
+    fn hess_slice_tape(&self, _storage: EmptyTapeStorage) -> GenericVmTape<N> {
+        self.tape()
+    }
 }
 
 impl<const N: usize> RenderHints for GenericVmFunction<N> {
@@ -264,6 +354,35 @@ impl<T> std::ops::IndexMut<u32> for SlotArray<'_, T> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Float types which can be used by VM evaluators
+///
+/// Immediates in a [`RegOp`] tape are stored as `f32`, so double-precision
+/// evaluators read their exact values from [`VmData::iter_imm`] instead.
+trait VmImmediate {
+    /// Returns the value of an immediate
+    ///
+    /// This is called exactly once for each [`RegOp`] with an immediate, in
+    /// evaluation order; `imms` is the iterator from [`VmData::iter_imm`].
+    fn from_imm(imms: &mut impl Iterator<Item = f64>, imm: f32) -> Self;
+}
+
+impl VmImmediate for f32 {
+    #[inline]
+    fn from_imm(_imms: &mut impl Iterator<Item = f64>, imm: f32) -> Self {
+        imm
+    }
+}
+
+impl VmImmediate for f64 {
+    #[inline]
+    fn from_imm(imms: &mut impl Iterator<Item = f64>, imm: f32) -> Self {
+        // The list of exact immediates is empty for old serialized tapes
+        imms.next().unwrap_or(imm as f64)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 /// Generic VM evaluator for tracing evaluation
 struct TracingVmEval<T> {
     slots: Vec<T>,
@@ -290,540 +409,702 @@ impl<T: From<f32> + Clone> TracingVmEval<T> {
     }
 }
 
-/// VM-based tracing evaluator for intervals
-#[derive(Default)]
-pub struct VmIntervalEval<const N: usize>(TracingVmEval<Interval>);
-impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
-    type Data = Interval;
-    type Tape = GenericVmTape<N>;
-    type Trace = VmTrace;
-    type TapeStorage = EmptyTapeStorage;
+/// Implements a tracing interval evaluator for the given interval type
+///
+/// Immediates are read with [`VmImmediate::from_imm`], so that double-precision
+/// evaluators use their exact values.
+macro_rules! vm_interval_eval {
+    ($(#[$meta:meta])* $name:ident, $Interval:ident, $t:ident) => {
+        $(#[$meta])*
+        #[derive(Default)]
+        pub struct $name<const N: usize>(TracingVmEval<$Interval>);
+        impl<const N: usize> TracingEvaluator for $name<N> {
+            type Data = $Interval;
+            type Tape = GenericVmTape<N>;
+            type Trace = VmTrace;
+            type TapeStorage = EmptyTapeStorage;
 
-    fn eval(
-        &mut self,
-        tape: &Self::Tape,
-        vars: &[Interval],
-    ) -> Result<(&[Interval], Option<&VmTrace>), Error> {
-        tape.vars().check_tracing_arguments(vars)?;
-        let tape = tape.data();
-        self.0.resize_slots(tape);
-
-        let mut simplify = false;
-        let mut v = SlotArray(&mut self.0.slots);
-        let mut choices = self.0.choices.as_mut_slice().iter_mut();
-        for op in tape.iter_asm() {
-            match op {
-                RegOp::Output(arg, i) => {
-                    self.0.out[i as usize] = v[arg];
-                }
-                RegOp::Input(out, i) => {
-                    v[out] = vars[i as usize];
-                }
-                RegOp::NegReg(out, arg) => {
-                    v[out] = -v[arg];
-                }
-                RegOp::AbsReg(out, arg) => {
-                    v[out] = v[arg].abs();
-                }
-                RegOp::RecipReg(out, arg) => {
-                    v[out] = v[arg].recip();
-                }
-                RegOp::SqrtReg(out, arg) => {
-                    v[out] = v[arg].sqrt();
-                }
-                RegOp::SquareReg(out, arg) => {
-                    v[out] = v[arg].square();
-                }
-                RegOp::FloorReg(out, arg) => {
-                    v[out] = v[arg].floor();
-                }
-                RegOp::CeilReg(out, arg) => {
-                    v[out] = v[arg].ceil();
-                }
-                RegOp::RoundReg(out, arg) => {
-                    v[out] = v[arg].round();
-                }
-                RegOp::SinReg(out, arg) => {
-                    v[out] = v[arg].sin();
-                }
-                RegOp::CosReg(out, arg) => {
-                    v[out] = v[arg].cos();
-                }
-                RegOp::TanReg(out, arg) => {
-                    v[out] = v[arg].tan();
-                }
-                RegOp::AsinReg(out, arg) => {
-                    v[out] = v[arg].asin();
-                }
-                RegOp::AcosReg(out, arg) => {
-                    v[out] = v[arg].acos();
-                }
-                RegOp::AtanReg(out, arg) => {
-                    v[out] = v[arg].atan();
-                }
-                RegOp::ExpReg(out, arg) => {
-                    v[out] = v[arg].exp();
-                }
-                RegOp::LnReg(out, arg) => {
-                    v[out] = v[arg].ln();
-                }
-                RegOp::NotReg(out, arg) => {
-                    v[out] = if !v[arg].contains(0.0) && !v[arg].has_nan() {
-                        Interval::new(0.0, 0.0)
-                    } else if v[arg].lower() == 0.0 && v[arg].upper() == 0.0 {
-                        Interval::new(1.0, 1.0)
-                    } else {
-                        Interval::new(0.0, 1.0)
-                    };
-                }
-                RegOp::CopyReg(out, arg) => v[out] = v[arg],
-                RegOp::AddRegImm(out, arg, imm) => {
-                    v[out] = v[arg] + imm.into();
-                }
-                RegOp::MulRegImm(out, arg, imm) => {
-                    v[out] = v[arg] * imm;
-                }
-                RegOp::DivRegImm(out, arg, imm) => {
-                    v[out] = v[arg] / imm.into();
-                }
-                RegOp::DivImmReg(out, arg, imm) => {
-                    let imm: Interval = imm.into();
-                    v[out] = imm / v[arg];
-                }
-                RegOp::AtanRegImm(out, arg, imm) => {
-                    v[out] = v[arg].atan2(imm.into());
-                }
-                RegOp::AtanImmReg(out, arg, imm) => {
-                    let imm: Interval = imm.into();
-                    v[out] = imm.atan2(v[arg]);
-                }
-                RegOp::AtanRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].atan2(v[rhs]);
-                }
-                RegOp::SubImmReg(out, arg, imm) => {
-                    v[out] = Interval::from(imm) - v[arg];
-                }
-                RegOp::SubRegImm(out, arg, imm) => {
-                    v[out] = v[arg] - imm.into();
-                }
-                RegOp::MinRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].min_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].max_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].and_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].and_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].or_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].or_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::ModRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].rem_euclid(v[rhs]);
-                }
-                RegOp::ModRegImm(out, arg, imm) => {
-                    v[out] = v[arg].rem_euclid(imm.into());
-                }
-                RegOp::ModImmReg(out, arg, imm) => {
-                    v[out] = Interval::from(imm).rem_euclid(v[arg]);
-                }
-                RegOp::AddRegReg(out, lhs, rhs) => v[out] = v[lhs] + v[rhs],
-                RegOp::MulRegReg(out, lhs, rhs) => v[out] = v[lhs] * v[rhs],
-                RegOp::DivRegReg(out, lhs, rhs) => v[out] = v[lhs] / v[rhs],
-                RegOp::SubRegReg(out, lhs, rhs) => v[out] = v[lhs] - v[rhs],
-                RegOp::CompareRegReg(out, lhs, rhs) => {
-                    v[out] = if v[lhs].has_nan() || v[rhs].has_nan() {
-                        f32::NAN.into()
-                    } else if v[lhs].upper() < v[rhs].lower() {
-                        Interval::from(-1.0)
-                    } else if v[lhs].lower() > v[rhs].upper() {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
-                }
-                RegOp::CompareRegImm(out, arg, imm) => {
-                    v[out] = if v[arg].has_nan() || imm.is_nan() {
-                        f32::NAN.into()
-                    } else if v[arg].upper() < imm {
-                        Interval::from(-1.0)
-                    } else if v[arg].lower() > imm {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
-                }
-                RegOp::CompareImmReg(out, arg, imm) => {
-                    v[out] = if v[arg].has_nan() || imm.is_nan() {
-                        f32::NAN.into()
-                    } else if imm < v[arg].lower() {
-                        Interval::from(-1.0)
-                    } else if imm > v[arg].upper() {
-                        Interval::from(1.0)
+            fn eval(
+                &mut self,
+                tape: &Self::Tape,
+                vars: &[$Interval],
+            ) -> Result<(&[$Interval], Option<&VmTrace>), Error> {
+                tape.vars().check_tracing_arguments(vars)?;
+                let tape = tape.data();
+                self.0.resize_slots(tape);
+
+                let mut simplify = false;
+                let mut v = SlotArray(&mut self.0.slots);
+                let mut choices = self.0.choices.as_mut_slice().iter_mut();
+                let mut imms = tape.iter_imm();
+                for op in tape.iter_asm() {
+                    match op {
+                        RegOp::Output(arg, i) => {
+                            self.0.out[i as usize] = v[arg];
+                        }
+                        RegOp::Input(out, i) => {
+                            v[out] = vars[i as usize];
+                        }
+                        RegOp::NegReg(out, arg) => {
+                            v[out] = -v[arg];
+                        }
+                        RegOp::AbsReg(out, arg) => {
+                            v[out] = v[arg].abs();
+                        }
+                        RegOp::RecipReg(out, arg) => {
+                            v[out] = v[arg].recip();
+                        }
+                        RegOp::SqrtReg(out, arg) => {
+                            v[out] = v[arg].sqrt();
+                        }
+                        RegOp::SquareReg(out, arg) => {
+                            v[out] = v[arg].square();
+                        }
+                        RegOp::FloorReg(out, arg) => {
+                            v[out] = v[arg].floor();
+                        }
+                        RegOp::CeilReg(out, arg) => {
+                            v[out] = v[arg].ceil();
+                        }
+                        RegOp::RoundReg(out, arg) => {
+                            v[out] = v[arg].round();
+                        }
+                        RegOp::SinReg(out, arg) => {
+                            v[out] = v[arg].sin();
+                        }
+                        RegOp::CosReg(out, arg) => {
+                            v[out] = v[arg].cos();
+                        }
+                        RegOp::TanReg(out, arg) => {
+                            v[out] = v[arg].tan();
+                        }
+                        RegOp::AsinReg(out, arg) => {
+                            v[out] = v[arg].asin();
+                        }
+                        RegOp::AcosReg(out, arg) => {
+                            v[out] = v[arg].acos();
+                        }
+                        RegOp::AtanReg(out, arg) => {
+                            v[out] = v[arg].atan();
+                        }
+                        RegOp::ExpReg(out, arg) => {
+                            v[out] = v[arg].exp();
+                        }
+                        RegOp::LnReg(out, arg) => {
+                            v[out] = v[arg].ln();
+                        }
+                        RegOp::NotReg(out, arg) => {
+                            v[out] =
+                                if !v[arg].contains(0.0) && !v[arg].has_nan() {
+                                    $Interval::new(0.0, 0.0)
+                                } else if v[arg].lower() == 0.0
+                                    && v[arg].upper() == 0.0
+                                {
+                                    $Interval::new(1.0, 1.0)
+                                } else {
+                                    $Interval::new(0.0, 1.0)
+                                };
+                        }
+                        RegOp::SinhReg(out, arg) => {
+                            v[out] = v[arg].sinh();
+                        }
+                        RegOp::CoshReg(out, arg) => {
+                            v[out] = v[arg].cosh();
+                        }
+                        RegOp::TanhReg(out, arg) => {
+                            v[out] = v[arg].tanh();
+                        }
+                        RegOp::SignReg(out, arg) => {
+                            v[out] = v[arg].sign();
+                        }
+                        RegOp::CbrtReg(out, arg) => {
+                            v[out] = v[arg].cbrt();
+                        }
+                        RegOp::Log2Reg(out, arg) => {
+                            v[out] = v[arg].log2();
+                        }
+                        RegOp::CopyReg(out, arg) => v[out] = v[arg],
+                        RegOp::AddRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] + imm.into();
+                        }
+                        RegOp::MulRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] * imm;
+                        }
+                        RegOp::DivRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] / imm.into();
+                        }
+                        RegOp::DivImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm: $Interval = imm.into();
+                            v[out] = imm / v[arg];
+                        }
+                        RegOp::AtanRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg].atan2(imm.into());
+                        }
+                        RegOp::AtanImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm: $Interval = imm.into();
+                            v[out] = imm.atan2(v[arg]);
+                        }
+                        RegOp::AtanRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs].atan2(v[rhs]);
+                        }
+                        RegOp::SubImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = $Interval::from(imm) - v[arg];
+                        }
+                        RegOp::SubRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] - imm.into();
+                        }
+                        RegOp::MinRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let (value, choice) = v[arg].min_choice(imm.into());
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::MaxRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let (value, choice) = v[arg].max_choice(imm.into());
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::AndRegReg(out, lhs, rhs) => {
+                            let (value, choice) = v[lhs].and_choice(v[rhs]);
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::AndRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let (value, choice) = v[arg].and_choice(imm.into());
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::OrRegReg(out, lhs, rhs) => {
+                            let (value, choice) = v[lhs].or_choice(v[rhs]);
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::OrRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let (value, choice) = v[arg].or_choice(imm.into());
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::ModRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs].rem_euclid(v[rhs]);
+                        }
+                        RegOp::ModRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg].rem_euclid(imm.into());
+                        }
+                        RegOp::ModImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] =
+                                $Interval::from(imm).rem_euclid(v[arg]);
+                        }
+                        RegOp::PowRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs].pow(v[rhs]);
+                        }
+                        RegOp::PowRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg].pow(imm.into());
+                        }
+                        RegOp::PowImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = $Interval::from(imm).pow(v[arg]);
+                        }
+                        RegOp::AddRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] + v[rhs]
+                        }
+                        RegOp::MulRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] * v[rhs]
+                        }
+                        RegOp::DivRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] / v[rhs]
+                        }
+                        RegOp::SubRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] - v[rhs]
+                        }
+                        RegOp::CompareRegReg(out, lhs, rhs) => {
+                            v[out] = if v[lhs].has_nan() || v[rhs].has_nan() {
+                                $t::NAN.into()
+                            } else if v[lhs].upper() < v[rhs].lower() {
+                                $Interval::from(-1.0)
+                            } else if v[lhs].lower() > v[rhs].upper() {
+                                $Interval::from(1.0)
+                            } else {
+                                $Interval::new(-1.0, 1.0)
+                            };
+                        }
+                        RegOp::CompareRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = if v[arg].has_nan() || imm.is_nan() {
+                                $t::NAN.into()
+                            } else if v[arg].upper() < imm {
+                                $Interval::from(-1.0)
+                            } else if v[arg].lower() > imm {
+                                $Interval::from(1.0)
+                            } else {
+                                $Interval::new(-1.0, 1.0)
+                            };
+                        }
+                        RegOp::CompareImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = if v[arg].has_nan() || imm.is_nan() {
+                                $t::NAN.into()
+                            } else if imm < v[arg].lower() {
+                                $Interval::from(-1.0)
+                            } else if imm > v[arg].upper() {
+                                $Interval::from(1.0)
+                            } else {
+                                $Interval::new(-1.0, 1.0)
+                            };
+                        }
+                        RegOp::MinRegReg(out, lhs, rhs) => {
+                            let (value, choice) = v[lhs].min_choice(v[rhs]);
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::MaxRegReg(out, lhs, rhs) => {
+                            let (value, choice) = v[lhs].max_choice(v[rhs]);
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::SelectRegRegReg(out, cond, a, b) => {
+                            let (value, choice) =
+                                v[cond].select_choice(v[a], v[b]);
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::CopyImm(out, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm.into();
+                        }
+                        RegOp::Load(out, mem) => {
+                            v[out] = v[mem];
+                        }
+                        RegOp::Store(out, mem) => {
+                            v[mem] = v[out];
+                        }
+                    }
+                }
+                Ok((
+                    &self.0.out,
+                    if simplify {
+                        Some(&self.0.choices)
                     } else {
-                        Interval::new(-1.0, 1.0)
-                    };
-                }
-                RegOp::MinRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].min_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].max_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::CopyImm(out, imm) => {
-                    v[out] = imm.into();
-                }
-                RegOp::Load(out, mem) => {
-                    v[out] = v[mem];
-                }
-                RegOp::Store(out, mem) => {
-                    v[mem] = v[out];
-                }
+                        None
+                    },
+                ))
             }
         }
-        Ok((
-            &self.0.out,
-            if simplify {
-                Some(&self.0.choices)
-            } else {
-                None
-            },
-        ))
-    }
+    };
 }
+pub(crate) use vm_interval_eval;
 
-/// VM-based tracing evaluator for single points
-#[derive(Default)]
-pub struct VmPointEval<const N: usize>(TracingVmEval<f32>);
-impl<const N: usize> TracingEvaluator for VmPointEval<N> {
-    type Data = f32;
-    type Tape = GenericVmTape<N>;
-    type Trace = VmTrace;
-    type TapeStorage = EmptyTapeStorage;
+vm_interval_eval!(
+    /// VM-based tracing evaluator for intervals
+    VmIntervalEval,
+    Interval,
+    f32
+);
 
-    fn eval(
-        &mut self,
-        tape: &Self::Tape,
-        vars: &[f32],
-    ) -> Result<(&[f32], Option<&VmTrace>), Error> {
-        tape.vars().check_tracing_arguments(vars)?;
-        let tape = tape.data();
-        self.0.resize_slots(tape);
-
-        let mut choices = self.0.choices.as_mut_slice().iter_mut();
-        let mut simplify = false;
-        let mut v = SlotArray(&mut self.0.slots);
-        for op in tape.iter_asm() {
-            match op {
-                RegOp::Output(arg, i) => {
-                    self.0.out[i as usize] = v[arg];
-                }
-                RegOp::Input(out, i) => {
-                    v[out] = vars[i as usize];
-                }
-                RegOp::NegReg(out, arg) => {
-                    v[out] = -v[arg];
-                }
-                RegOp::AbsReg(out, arg) => {
-                    v[out] = v[arg].abs();
-                }
-                RegOp::RecipReg(out, arg) => {
-                    v[out] = 1.0 / v[arg];
-                }
-                RegOp::SqrtReg(out, arg) => {
-                    v[out] = v[arg].sqrt();
-                }
-                RegOp::SquareReg(out, arg) => {
-                    let s = v[arg];
-                    v[out] = s * s;
-                }
-                RegOp::FloorReg(out, arg) => {
-                    v[out] = v[arg].floor();
-                }
-                RegOp::CeilReg(out, arg) => {
-                    v[out] = v[arg].ceil();
-                }
-                RegOp::RoundReg(out, arg) => {
-                    v[out] = v[arg].round();
-                }
-                RegOp::SinReg(out, arg) => {
-                    v[out] = v[arg].sin();
-                }
-                RegOp::CosReg(out, arg) => {
-                    v[out] = v[arg].cos();
-                }
-                RegOp::TanReg(out, arg) => {
-                    v[out] = v[arg].tan();
-                }
-                RegOp::AsinReg(out, arg) => {
-                    v[out] = v[arg].asin();
-                }
-                RegOp::AcosReg(out, arg) => {
-                    v[out] = v[arg].acos();
-                }
-                RegOp::AtanReg(out, arg) => {
-                    v[out] = v[arg].atan();
-                }
-                RegOp::ExpReg(out, arg) => {
-                    v[out] = v[arg].exp();
-                }
-                RegOp::LnReg(out, arg) => {
-                    v[out] = v[arg].ln();
-                }
-                RegOp::NotReg(out, arg) => v[out] = (v[arg] == 0.0).into(),
-                RegOp::CopyReg(out, arg) => {
-                    v[out] = v[arg];
-                }
-                RegOp::AddRegImm(out, arg, imm) => {
-                    v[out] = v[arg] + imm;
-                }
-                RegOp::MulRegImm(out, arg, imm) => {
-                    v[out] = v[arg] * imm;
-                }
-                RegOp::DivRegImm(out, arg, imm) => {
-                    v[out] = v[arg] / imm;
-                }
-                RegOp::DivImmReg(out, arg, imm) => {
-                    v[out] = imm / v[arg];
-                }
-                RegOp::AtanRegImm(out, arg, imm) => {
-                    v[out] = v[arg].atan2(imm);
-                }
-                RegOp::AtanImmReg(out, arg, imm) => {
-                    v[out] = imm.atan2(v[arg]);
-                }
-                RegOp::AtanRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].atan2(v[rhs]);
-                }
-                RegOp::SubImmReg(out, arg, imm) => {
-                    v[out] = imm - v[arg];
-                }
-                RegOp::SubRegImm(out, arg, imm) => {
-                    v[out] = v[arg] - imm;
-                }
-                RegOp::MinRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a < imm {
-                        (Choice::Left, a)
-                    } else if imm < a {
-                        (Choice::Right, imm)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || imm.is_nan() {
-                                f32::NAN
+/// Implements a tracing point evaluator for the given float type
+///
+/// Immediates are read with [`VmImmediate::from_imm`], so that double-precision
+/// evaluators use their exact values.
+macro_rules! vm_point_eval {
+    ($(#[$meta:meta])* $name:ident, $t:ident) => {
+        $(#[$meta])*
+        #[derive(Default)]
+        pub struct $name<const N: usize>(TracingVmEval<$t>);
+        impl<const N: usize> TracingEvaluator for $name<N> {
+            type Data = $t;
+            type Tape = GenericVmTape<N>;
+            type Trace = VmTrace;
+            type TapeStorage = EmptyTapeStorage;
+
+            fn eval(
+                &mut self,
+                tape: &Self::Tape,
+                vars: &[$t],
+            ) -> Result<(&[$t], Option<&VmTrace>), Error> {
+                tape.vars().check_tracing_arguments(vars)?;
+                let tape = tape.data();
+                self.0.resize_slots(tape);
+
+                let mut choices = self.0.choices.as_mut_slice().iter_mut();
+                let mut simplify = false;
+                let mut v = SlotArray(&mut self.0.slots);
+                let mut imms = tape.iter_imm();
+                for op in tape.iter_asm() {
+                    match op {
+                        RegOp::Output(arg, i) => {
+                            self.0.out[i as usize] = v[arg];
+                        }
+                        RegOp::Input(out, i) => {
+                            v[out] = vars[i as usize];
+                        }
+                        RegOp::NegReg(out, arg) => {
+                            v[out] = -v[arg];
+                        }
+                        RegOp::AbsReg(out, arg) => {
+                            v[out] = v[arg].abs();
+                        }
+                        RegOp::RecipReg(out, arg) => {
+                            v[out] = 1.0 / v[arg];
+                        }
+                        RegOp::SqrtReg(out, arg) => {
+                            v[out] = v[arg].sqrt();
+                        }
+                        RegOp::SquareReg(out, arg) => {
+                            let s = v[arg];
+                            v[out] = s * s;
+                        }
+                        RegOp::FloorReg(out, arg) => {
+                            v[out] = v[arg].floor();
+                        }
+                        RegOp::CeilReg(out, arg) => {
+                            v[out] = v[arg].ceil();
+                        }
+                        RegOp::RoundReg(out, arg) => {
+                            v[out] = v[arg].round();
+                        }
+                        RegOp::SinReg(out, arg) => {
+                            v[out] = v[arg].sin();
+                        }
+                        RegOp::CosReg(out, arg) => {
+                            v[out] = v[arg].cos();
+                        }
+                        RegOp::TanReg(out, arg) => {
+                            v[out] = v[arg].tan();
+                        }
+                        RegOp::AsinReg(out, arg) => {
+                            v[out] = v[arg].asin();
+                        }
+                        RegOp::AcosReg(out, arg) => {
+                            v[out] = v[arg].acos();
+                        }
+                        RegOp::AtanReg(out, arg) => {
+                            v[out] = v[arg].atan();
+                        }
+                        RegOp::ExpReg(out, arg) => {
+                            v[out] = v[arg].exp();
+                        }
+                        RegOp::LnReg(out, arg) => {
+                            v[out] = v[arg].ln();
+                        }
+                        RegOp::NotReg(out, arg) => {
+                            v[out] = (v[arg] == 0.0).into()
+                        }
+                        RegOp::SinhReg(out, arg) => {
+                            v[out] = v[arg].sinh();
+                        }
+                        RegOp::CoshReg(out, arg) => {
+                            v[out] = v[arg].cosh();
+                        }
+                        RegOp::TanhReg(out, arg) => {
+                            v[out] = v[arg].tanh();
+                        }
+                        RegOp::SignReg(out, arg) => {
+                            let a = v[arg];
+                            v[out] = if a > 0.0 {
+                                1.0
+                            } else if a < 0.0 {
+                                -1.0
                             } else {
-                                imm
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a > imm {
-                        (Choice::Left, a)
-                    } else if imm > a {
-                        (Choice::Right, imm)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || imm.is_nan() {
-                                f32::NAN
+                                a
+                            };
+                        }
+                        RegOp::CbrtReg(out, arg) => {
+                            v[out] = v[arg].cbrt();
+                        }
+                        RegOp::Log2Reg(out, arg) => {
+                            v[out] = v[arg].log2();
+                        }
+                        RegOp::CopyReg(out, arg) => {
+                            v[out] = v[arg];
+                        }
+                        RegOp::AddRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] + imm;
+                        }
+                        RegOp::MulRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] * imm;
+                        }
+                        RegOp::DivRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] / imm;
+                        }
+                        RegOp::DivImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm / v[arg];
+                        }
+                        RegOp::AtanRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg].atan2(imm);
+                        }
+                        RegOp::AtanImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm.atan2(v[arg]);
+                        }
+                        RegOp::AtanRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs].atan2(v[rhs]);
+                        }
+                        RegOp::SubImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm - v[arg];
+                        }
+                        RegOp::SubRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg] - imm;
+                        }
+                        RegOp::MinRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let a = v[arg];
+                            let (choice, value) = if a < imm {
+                                (Choice::Left, a)
+                            } else if imm < a {
+                                (Choice::Right, imm)
                             } else {
-                                imm
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a == 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, imm)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a != 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, imm)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::ModRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].rem_euclid(v[rhs]);
-                }
-                RegOp::ModRegImm(out, arg, imm) => {
-                    v[out] = v[arg].rem_euclid(imm);
-                }
-                RegOp::ModImmReg(out, arg, imm) => {
-                    v[out] = imm.rem_euclid(v[arg]);
-                }
-                RegOp::AddRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] + v[rhs];
-                }
-                RegOp::MulRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] * v[rhs];
-                }
-                RegOp::DivRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] / v[rhs];
-                }
-                RegOp::CompareRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs]
-                        .partial_cmp(&v[rhs])
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::CompareRegImm(out, arg, imm) => {
-                    v[out] = v[arg]
-                        .partial_cmp(&imm)
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::CompareImmReg(out, arg, imm) => {
-                    v[out] = imm
-                        .partial_cmp(&v[arg])
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::SubRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] - v[rhs];
-                }
-                RegOp::MinRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a < b {
-                        (Choice::Left, a)
-                    } else if b < a {
-                        (Choice::Right, b)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || b.is_nan() {
-                                f32::NAN
+                                (
+                                    Choice::Both,
+                                    if a.is_nan() || imm.is_nan() {
+                                        $t::NAN
+                                    } else {
+                                        imm
+                                    },
+                                )
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::MaxRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let a = v[arg];
+                            let (choice, value) = if a > imm {
+                                (Choice::Left, a)
+                            } else if imm > a {
+                                (Choice::Right, imm)
                             } else {
-                                b
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a > b {
-                        (Choice::Left, a)
-                    } else if b > a {
-                        (Choice::Right, b)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || b.is_nan() {
-                                f32::NAN
+                                (
+                                    Choice::Both,
+                                    if a.is_nan() || imm.is_nan() {
+                                        $t::NAN
+                                    } else {
+                                        imm
+                                    },
+                                )
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::AndRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let a = v[arg];
+                            let (choice, value) = if a == 0.0 {
+                                (Choice::Left, a)
                             } else {
-                                b
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a == 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, b)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a != 0.0 {
-                        (Choice::Left, a)
+                                (Choice::Right, imm)
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::OrRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let a = v[arg];
+                            let (choice, value) = if a != 0.0 {
+                                (Choice::Left, a)
+                            } else {
+                                (Choice::Right, imm)
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::ModRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs].rem_euclid(v[rhs]);
+                        }
+                        RegOp::ModRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg].rem_euclid(imm);
+                        }
+                        RegOp::ModImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm.rem_euclid(v[arg]);
+                        }
+                        RegOp::PowRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs].powf(v[rhs]);
+                        }
+                        RegOp::PowRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg].powf(imm);
+                        }
+                        RegOp::PowImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm.powf(v[arg]);
+                        }
+                        RegOp::AddRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] + v[rhs];
+                        }
+                        RegOp::MulRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] * v[rhs];
+                        }
+                        RegOp::DivRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] / v[rhs];
+                        }
+                        RegOp::CompareRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs]
+                                .partial_cmp(&v[rhs])
+                                .map(|c| c as i8 as $t)
+                                .unwrap_or($t::NAN)
+                        }
+                        RegOp::CompareRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = v[arg]
+                                .partial_cmp(&imm)
+                                .map(|c| c as i8 as $t)
+                                .unwrap_or($t::NAN)
+                        }
+                        RegOp::CompareImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm
+                                .partial_cmp(&v[arg])
+                                .map(|c| c as i8 as $t)
+                                .unwrap_or($t::NAN)
+                        }
+                        RegOp::SubRegReg(out, lhs, rhs) => {
+                            v[out] = v[lhs] - v[rhs];
+                        }
+                        RegOp::MinRegReg(out, lhs, rhs) => {
+                            let a = v[lhs];
+                            let b = v[rhs];
+                            let (choice, value) = if a < b {
+                                (Choice::Left, a)
+                            } else if b < a {
+                                (Choice::Right, b)
+                            } else {
+                                (
+                                    Choice::Both,
+                                    if a.is_nan() || b.is_nan() {
+                                        $t::NAN
+                                    } else {
+                                        b
+                                    },
+                                )
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::MaxRegReg(out, lhs, rhs) => {
+                            let a = v[lhs];
+                            let b = v[rhs];
+                            let (choice, value) = if a > b {
+                                (Choice::Left, a)
+                            } else if b > a {
+                                (Choice::Right, b)
+                            } else {
+                                (
+                                    Choice::Both,
+                                    if a.is_nan() || b.is_nan() {
+                                        $t::NAN
+                                    } else {
+                                        b
+                                    },
+                                )
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::AndRegReg(out, lhs, rhs) => {
+                            let a = v[lhs];
+                            let b = v[rhs];
+                            let (choice, value) = if a == 0.0 {
+                                (Choice::Left, a)
+                            } else {
+                                (Choice::Right, b)
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::OrRegReg(out, lhs, rhs) => {
+                            let a = v[lhs];
+                            let b = v[rhs];
+                            let (choice, value) = if a != 0.0 {
+                                (Choice::Left, a)
+                            } else {
+                                (Choice::Right, b)
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::SelectRegRegReg(out, cond, a, b) => {
+                            let (choice, value) = if v[cond] != 0.0 {
+                                (Choice::Left, v[a])
+                            } else {
+                                (Choice::Right, v[b])
+                            };
+                            v[out] = value;
+                            *choices.next().unwrap() |= choice;
+                            simplify |= choice != Choice::Both;
+                        }
+                        RegOp::CopyImm(out, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            v[out] = imm;
+                        }
+                        RegOp::Load(out, mem) => {
+                            v[out] = v[mem];
+                        }
+                        RegOp::Store(out, mem) => {
+                            v[mem] = v[out];
+                        }
+                    }
+                }
+                Ok((
+                    &self.0.out,
+                    if simplify {
+                        Some(&self.0.choices)
                     } else {
-                        (Choice::Right, b)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::CopyImm(out, imm) => {
-                    v[out] = imm;
-                }
-                RegOp::Load(out, mem) => {
-                    v[out] = v[mem];
-                }
-                RegOp::Store(out, mem) => {
-                    v[mem] = v[out];
-                }
+                        None
+                    },
+                ))
             }
         }
-        Ok((
-            &self.0.out,
-            if simplify {
-                Some(&self.0.choices)
-            } else {
-                None
-            },
-        ))
-    }
+    };
 }
+pub(crate) use vm_point_eval;
+
+vm_point_eval!(
+    /// VM-based tracing evaluator for single points
+    VmPointEval,
+    f32
+);
 
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Bulk evaluator for VM tapes
-#[derive(Default)]
 struct BulkVmEval<T> {
     /// Workspace for data
     slots: Vec<Vec<T>>,
@@ -832,6 +1113,15 @@ struct BulkVmEval<T> {
     out: Vec<Vec<T>>,
 }
 
+impl<T> Default for BulkVmEval<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::default(),
+            out: Vec::default(),
+        }
+    }
+}
+
 impl<T: From<f32> + Clone> BulkVmEval<T> {
     /// Reserves slots for the given tape and slice size
     fn resize_slots<const N: usize>(&mut self, tape: &VmData<N>, size: usize) {
@@ -841,665 +1131,936 @@ impl<T: From<f32> + Clone> BulkVmEval<T> {
             s.resize(size, f32::NAN.into());
         }
 
-        const OUTPUT_COUNT: usize = 1;
         self.out
-            .resize_with(OUTPUT_COUNT, || vec![f32::NAN.into(); size]);
+            .resize_with(tape.output_count(), || vec![f32::NAN.into(); size]);
         for o in self.out.iter_mut() {
             o.resize(size, f32::NAN.into());
         }
     }
 }
 
-/// VM-based bulk evaluator for arrays of points, yielding point values
-#[derive(Default)]
-pub struct VmFloatSliceEval<const N: usize>(BulkVmEval<f32>);
-impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
-    type Data = f32;
-    type Tape = GenericVmTape<N>;
-    type TapeStorage = EmptyTapeStorage;
+/// Implements a bulk point evaluator for the given float type
+///
+/// Immediates are read with [`VmImmediate::from_imm`], so that double-precision
+/// evaluators use their exact values.
+macro_rules! vm_float_slice_eval {
+    ($(#[$meta:meta])* $name:ident, $t:ident) => {
+        $(#[$meta])*
+        #[derive(Default)]
+        pub struct $name<const N: usize>(BulkVmEval<$t>);
+        impl<const N: usize> BulkEvaluator for $name<N> {
+            type Data = $t;
+            type Tape = GenericVmTape<N>;
+            type TapeStorage = EmptyTapeStorage;
 
-    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
-        &mut self,
-        tape: &Self::Tape,
-        vars: &[V],
-    ) -> Result<BulkOutput<f32>, Error> {
-        tape.vars().check_bulk_arguments(vars)?;
-        let tape = tape.data();
-
-        let size = vars.first().map(|v| v.len()).unwrap_or(0);
-        self.0.resize_slots(tape, size);
-
-        let mut v = SlotArray(&mut self.0.slots);
-        for op in tape.iter_asm() {
-            match op {
-                RegOp::Output(arg, i) => {
-                    self.0.out[i as usize][0..size]
-                        .copy_from_slice(&v[arg][0..size]);
-                }
-                RegOp::Input(out, i) => {
-                    v[out][0..size].copy_from_slice(&vars[i as usize]);
-                }
-                RegOp::NegReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = -v[arg][i];
-                    }
-                }
-                RegOp::AbsReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].abs();
-                    }
-                }
-                RegOp::RecipReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = 1.0 / v[arg][i];
-                    }
-                }
-                RegOp::SqrtReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].sqrt();
-                    }
-                }
-                RegOp::SquareReg(out, arg) => {
-                    for i in 0..size {
-                        let s = v[arg][i];
-                        v[out][i] = s * s;
-                    }
-                }
-                RegOp::FloorReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].floor();
-                    }
-                }
-                RegOp::CeilReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].ceil();
-                    }
-                }
-                RegOp::RoundReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].round();
-                    }
-                }
-                RegOp::SinReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].sin();
-                    }
-                }
-                RegOp::CosReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].cos();
-                    }
-                }
-                RegOp::TanReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].tan();
-                    }
-                }
-                RegOp::AsinReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].asin();
-                    }
-                }
-                RegOp::AcosReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].acos();
-                    }
-                }
-                RegOp::AtanReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].atan();
-                    }
-                }
-                RegOp::ExpReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].exp();
-                    }
-                }
-                RegOp::LnReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].ln();
-                    }
-                }
-                RegOp::NotReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = (v[arg][i] == 0.0).into();
-                    }
-                }
-                RegOp::CopyReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i];
-                    }
-                }
-                RegOp::AddRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] + imm;
-                    }
-                }
-                RegOp::MulRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] * imm;
-                    }
-                }
-                RegOp::DivRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] / imm;
-                    }
-                }
-                RegOp::DivImmReg(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = imm / v[arg][i];
-                    }
-                }
-                RegOp::AtanRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].atan2(imm);
-                    }
-                }
-                RegOp::AtanImmReg(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = imm.atan2(v[arg][i]);
-                    }
-                }
-                RegOp::AtanRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
-                    }
-                }
-                RegOp::SubImmReg(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = imm - v[arg][i];
-                    }
-                }
-                RegOp::SubRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] - imm;
-                    }
-                }
-                RegOp::CompareImmReg(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = imm
-                            .partial_cmp(&v[arg][i])
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN)
-                    }
-                }
-                RegOp::CompareRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i]
-                            .partial_cmp(&imm)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN)
-                    }
-                }
-                RegOp::MinRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = if v[arg][i].is_nan() || imm.is_nan() {
-                            f32::NAN
-                        } else {
-                            v[arg][i].min(imm)
-                        };
-                    }
-                }
-                RegOp::MaxRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = if v[arg][i].is_nan() || imm.is_nan() {
-                            f32::NAN
-                        } else {
-                            v[arg][i].max(imm)
-                        };
-                    }
-                }
-                RegOp::AndRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] =
-                            if v[arg][i] == 0.0 { v[arg][i] } else { imm };
-                    }
-                }
-                RegOp::OrRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] =
-                            if v[arg][i] != 0.0 { v[arg][i] } else { imm };
-                    }
-                }
-                RegOp::ModRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
-                    }
-                }
-                RegOp::ModRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].rem_euclid(imm);
-                    }
-                }
-                RegOp::ModImmReg(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = imm.rem_euclid(v[arg][i]);
-                    }
-                }
-                RegOp::AddRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] + v[rhs][i];
-                    }
-                }
-                RegOp::MulRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] * v[rhs][i];
-                    }
-                }
-                RegOp::DivRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] / v[rhs][i];
-                    }
-                }
-                RegOp::SubRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] - v[rhs][i];
-                    }
-                }
-                RegOp::CompareRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i]
-                            .partial_cmp(&v[rhs][i])
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN)
-                    }
-                }
-                RegOp::MinRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = if v[lhs][i].is_nan() || v[rhs][i].is_nan()
-                        {
-                            f32::NAN
-                        } else {
-                            v[lhs][i].min(v[rhs][i])
-                        };
-                    }
-                }
-                RegOp::MaxRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = if v[lhs][i].is_nan() || v[rhs][i].is_nan()
-                        {
-                            f32::NAN
-                        } else {
-                            v[lhs][i].max(v[rhs][i])
-                        };
-                    }
-                }
-                RegOp::AndRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = if v[lhs][i] == 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
-                    }
-                }
-                RegOp::OrRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = if v[lhs][i] != 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
-                    }
-                }
-                RegOp::CopyImm(out, imm) => {
-                    for i in 0..size {
-                        v[out][i] = imm;
-                    }
-                }
-                RegOp::Load(out, mem) => {
-                    for i in 0..size {
-                        v[out][i] = v[mem][i];
-                    }
-                }
-                RegOp::Store(out, mem) => {
-                    for i in 0..size {
-                        v[mem][i] = v[out][i];
-                    }
-                }
+            fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+                &mut self,
+                tape: &Self::Tape,
+                vars: &[V],
+            ) -> Result<BulkOutput<'_, $t>, Error> {
+                tape.vars().check_bulk_arguments(vars)?;
+                let tape = tape.data();
+
+                let size = vars.first().map(|v| v.len()).unwrap_or(0);
+                self.0.resize_slots(tape, size);
+
+                let mut v = SlotArray(&mut self.0.slots);
+                let mut imms = tape.iter_imm();
+                for op in tape.iter_asm() {
+                    match op {
+                        RegOp::Output(arg, i) => {
+                            self.0.out[i as usize][0..size]
+                                .copy_from_slice(&v[arg][0..size]);
+                        }
+                        RegOp::Input(out, i) => {
+                            v[out][0..size].copy_from_slice(&vars[i as usize]);
+                        }
+                        RegOp::NegReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = -v[arg][i];
+                            }
+                        }
+                        RegOp::AbsReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].abs();
+                            }
+                        }
+                        RegOp::RecipReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = 1.0 / v[arg][i];
+                            }
+                        }
+                        RegOp::SqrtReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].sqrt();
+                            }
+                        }
+                        RegOp::SquareReg(out, arg) => {
+                            for i in 0..size {
+                                let s = v[arg][i];
+                                v[out][i] = s * s;
+                            }
+                        }
+                        RegOp::FloorReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].floor();
+                            }
+                        }
+                        RegOp::CeilReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].ceil();
+                            }
+                        }
+                        RegOp::RoundReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].round();
+                            }
+                        }
+                        RegOp::SinReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].sin();
+                            }
+                        }
+                        RegOp::CosReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].cos();
+                            }
+                        }
+                        RegOp::TanReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].tan();
+                            }
+                        }
+                        RegOp::AsinReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].asin();
+                            }
+                        }
+                        RegOp::AcosReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].acos();
+                            }
+                        }
+                        RegOp::AtanReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].atan();
+                            }
+                        }
+                        RegOp::ExpReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].exp();
+                            }
+                        }
+                        RegOp::LnReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].ln();
+                            }
+                        }
+                        RegOp::NotReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = (v[arg][i] == 0.0).into();
+                            }
+                        }
+                        RegOp::SinhReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].sinh();
+                            }
+                        }
+                        RegOp::CoshReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].cosh();
+                            }
+                        }
+                        RegOp::TanhReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].tanh();
+                            }
+                        }
+                        RegOp::SignReg(out, arg) => {
+                            for i in 0..size {
+                                let a = v[arg][i];
+                                v[out][i] = if a > 0.0 {
+                                    1.0
+                                } else if a < 0.0 {
+                                    -1.0
+                                } else {
+                                    a
+                                };
+                            }
+                        }
+                        RegOp::CbrtReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].cbrt();
+                            }
+                        }
+                        RegOp::Log2Reg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].log2();
+                            }
+                        }
+                        RegOp::CopyReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i];
+                            }
+                        }
+                        RegOp::AddRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] + imm;
+                            }
+                        }
+                        RegOp::MulRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] * imm;
+                            }
+                        }
+                        RegOp::DivRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] / imm;
+                            }
+                        }
+                        RegOp::DivImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = imm / v[arg][i];
+                            }
+                        }
+                        RegOp::AtanRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].atan2(imm);
+                            }
+                        }
+                        RegOp::AtanImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = imm.atan2(v[arg][i]);
+                            }
+                        }
+                        RegOp::AtanRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                            }
+                        }
+                        RegOp::SubImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = imm - v[arg][i];
+                            }
+                        }
+                        RegOp::SubRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] - imm;
+                            }
+                        }
+                        RegOp::CompareImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = imm
+                                    .partial_cmp(&v[arg][i])
+                                    .map(|c| c as i8 as $t)
+                                    .unwrap_or($t::NAN)
+                            }
+                        }
+                        RegOp::CompareRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i]
+                                    .partial_cmp(&imm)
+                                    .map(|c| c as i8 as $t)
+                                    .unwrap_or($t::NAN)
+                            }
+                        }
+                        RegOp::MinRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] =
+                                    if v[arg][i].is_nan() || imm.is_nan() {
+                                        $t::NAN
+                                    } else {
+                                        v[arg][i].min(imm)
+                                    };
+                            }
+                        }
+                        RegOp::MaxRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] =
+                                    if v[arg][i].is_nan() || imm.is_nan() {
+                                        $t::NAN
+                                    } else {
+                                        v[arg][i].max(imm)
+                                    };
+                            }
+                        }
+                        RegOp::AndRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = if v[arg][i] == 0.0 {
+                                    v[arg][i]
+                                } else {
+                                    imm
+                                };
+                            }
+                        }
+                        RegOp::OrRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = if v[arg][i] != 0.0 {
+                                    v[arg][i]
+                                } else {
+                                    imm
+                                };
+                            }
+                        }
+                        RegOp::ModRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                            }
+                        }
+                        RegOp::ModRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].rem_euclid(imm);
+                            }
+                        }
+                        RegOp::ModImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = imm.rem_euclid(v[arg][i]);
+                            }
+                        }
+                        RegOp::PowRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i].powf(v[rhs][i]);
+                            }
+                        }
+                        RegOp::PowRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].powf(imm);
+                            }
+                        }
+                        RegOp::PowImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = imm.powf(v[arg][i]);
+                            }
+                        }
+                        RegOp::AddRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] + v[rhs][i];
+                            }
+                        }
+                        RegOp::MulRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] * v[rhs][i];
+                            }
+                        }
+                        RegOp::DivRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] / v[rhs][i];
+                            }
+                        }
+                        RegOp::SubRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] - v[rhs][i];
+                            }
+                        }
+                        RegOp::CompareRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i]
+                                    .partial_cmp(&v[rhs][i])
+                                    .map(|c| c as i8 as $t)
+                                    .unwrap_or($t::NAN)
+                            }
+                        }
+                        RegOp::MinRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i].is_nan()
+                                    || v[rhs][i].is_nan()
+                                {
+                                    $t::NAN
+                                } else {
+                                    v[lhs][i].min(v[rhs][i])
+                                };
+                            }
+                        }
+                        RegOp::MaxRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i].is_nan()
+                                    || v[rhs][i].is_nan()
+                                {
+                                    $t::NAN
+                                } else {
+                                    v[lhs][i].max(v[rhs][i])
+                                };
+                            }
+                        }
+                        RegOp::AndRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i] == 0.0 {
+                                    v[lhs][i]
+                                } else {
+                                    v[rhs][i]
+                                };
+                            }
+                        }
+                        RegOp::OrRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i] != 0.0 {
+                                    v[lhs][i]
+                                } else {
+                                    v[rhs][i]
+                                };
+                            }
+                        }
+                        RegOp::SelectRegRegReg(out, cond, a, b) => {
+                            for i in 0..size {
+                                v[out][i] = if v[cond][i] != 0.0 {
+                                    v[a][i]
+                                } else {
+                                    v[b][i]
+                                };
+                            }
+                        }
+                        RegOp::CopyImm(out, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = imm;
+                            }
+                        }
+                        RegOp::Load(out, mem) => {
+                            for i in 0..size {
+                                v[out][i] = v[mem][i];
+                            }
+                        }
+                        RegOp::Store(out, mem) => {
+                            for i in 0..size {
+                                v[mem][i] = v[out][i];
+                            }
+                        }
+                    }
+                }
+                Ok(BulkOutput::new(&self.0.out, size))
             }
         }
-        Ok(BulkOutput::new(&self.0.out, size))
-    }
+    };
 }
+pub(crate) use vm_float_slice_eval;
 
-/// VM-based bulk evaluator for arrays of points, yielding gradient values
-#[derive(Default)]
-pub struct VmGradSliceEval<const N: usize>(BulkVmEval<Grad>);
-impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
-    type Data = Grad;
-    type Tape = GenericVmTape<N>;
-    type TapeStorage = EmptyTapeStorage;
+vm_float_slice_eval!(
+    /// VM-based bulk evaluator for arrays of points, yielding point values
+    VmFloatSliceEval,
+    f32
+);
 
-    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
-        &mut self,
-        tape: &Self::Tape,
-        vars: &[V],
-    ) -> Result<BulkOutput<Grad>, Error> {
-        tape.vars().check_bulk_arguments(vars)?;
-        let tape = tape.data();
-        let size = vars.first().map(|v| v.len()).unwrap_or(0);
-        self.0.resize_slots(tape, size);
-
-        let mut v = SlotArray(&mut self.0.slots);
-        for op in tape.iter_asm() {
-            match op {
-                RegOp::Output(arg, i) => {
-                    self.0.out[i as usize][0..size]
-                        .copy_from_slice(&v[arg][0..size]);
-                }
-                RegOp::Input(out, i) => {
-                    v[out][0..size].copy_from_slice(&vars[i as usize]);
-                }
-                RegOp::NegReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = -v[arg][i];
-                    }
-                }
-                RegOp::AbsReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].abs();
-                    }
-                }
-                RegOp::RecipReg(out, arg) => {
-                    let one: Grad = 1.0.into();
-                    for i in 0..size {
-                        v[out][i] = one / v[arg][i];
-                    }
-                }
-                RegOp::SqrtReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].sqrt();
-                    }
-                }
-                RegOp::SquareReg(out, arg) => {
-                    for i in 0..size {
-                        let s = v[arg][i];
-                        v[out][i] = s * s;
-                    }
-                }
-                RegOp::FloorReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].floor();
-                    }
-                }
-                RegOp::CeilReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].ceil();
-                    }
-                }
-                RegOp::RoundReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].round();
-                    }
-                }
-                RegOp::SinReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].sin();
-                    }
-                }
-                RegOp::CosReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].cos();
-                    }
-                }
-                RegOp::TanReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].tan();
-                    }
-                }
-                RegOp::AsinReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].asin();
-                    }
-                }
-                RegOp::AcosReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].acos();
-                    }
-                }
-                RegOp::AtanReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].atan();
-                    }
-                }
-                RegOp::ExpReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].exp();
-                    }
-                }
-                RegOp::LnReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].ln();
-                    }
-                }
-                RegOp::NotReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = f32::from(v[arg][i].v == 0.0).into();
-                    }
-                }
-                RegOp::CopyReg(out, arg) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i];
-                    }
-                }
-                RegOp::AddRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] + imm.into();
-                    }
-                }
-                RegOp::MulRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] * imm;
-                    }
-                }
-                RegOp::DivRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] / imm.into();
-                    }
-                }
-                RegOp::DivImmReg(out, arg, imm) => {
-                    let imm = Grad::from(imm);
-                    for i in 0..size {
-                        v[out][i] = imm / v[arg][i];
-                    }
-                }
-                RegOp::AtanRegImm(out, arg, imm) => {
-                    let imm = Grad::from(imm);
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].atan2(imm);
-                    }
-                }
-                RegOp::AtanImmReg(out, arg, imm) => {
-                    let imm = Grad::from(imm);
-                    for i in 0..size {
-                        v[out][i] = imm.atan2(v[arg][i]);
-                    }
-                }
-                RegOp::AtanRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
-                    }
-                }
-                RegOp::SubImmReg(out, arg, imm) => {
-                    let imm: Grad = imm.into();
-                    for i in 0..size {
-                        v[out][i] = imm - v[arg][i];
-                    }
-                }
-                RegOp::SubRegImm(out, arg, imm) => {
-                    let imm: Grad = imm.into();
-                    for i in 0..size {
-                        v[out][i] = v[arg][i] - imm;
-                    }
-                }
-                RegOp::CompareImmReg(out, arg, imm) => {
-                    for i in 0..size {
-                        let p = imm
-                            .partial_cmp(&v[arg][i].v)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN);
-                        v[out][i] = Grad::new(p, 0.0, 0.0, 0.0);
-                    }
-                }
-                RegOp::CompareRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        let p = v[arg][i]
-                            .v
-                            .partial_cmp(&imm)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN);
-                        v[out][i] = Grad::new(p, 0.0, 0.0, 0.0);
-                    }
-                }
-                RegOp::MinRegImm(out, arg, imm) => {
-                    let imm: Grad = imm.into();
-                    for i in 0..size {
-                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
-                            f32::NAN.into()
-                        } else {
-                            v[arg][i].min(imm)
-                        };
-                    }
-                }
-                RegOp::MaxRegImm(out, arg, imm) => {
-                    let imm: Grad = imm.into();
-                    for i in 0..size {
-                        v[out][i] = if v[arg][i].v.is_nan() || imm.v.is_nan() {
-                            f32::NAN.into()
-                        } else {
-                            v[arg][i].max(imm)
-                        };
-                    }
-                }
-                RegOp::ModRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
-                    }
-                }
-                RegOp::ModRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = v[arg][i].rem_euclid(imm.into());
-                    }
-                }
-                RegOp::ModImmReg(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = Grad::from(imm).rem_euclid(v[arg][i]);
-                    }
-                }
-                RegOp::AddRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] + v[rhs][i];
-                    }
-                }
-                RegOp::MulRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] * v[rhs][i];
-                    }
-                }
-                RegOp::AndRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = if v[lhs][i].v == 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
-                    }
-                }
-                RegOp::AndRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = if v[arg][i].v == 0.0 {
-                            v[arg][i]
-                        } else {
-                            imm.into()
-                        };
-                    }
-                }
-                RegOp::OrRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = if v[lhs][i].v != 0.0 {
-                            v[lhs][i]
-                        } else {
-                            v[rhs][i]
-                        };
-                    }
-                }
-                RegOp::OrRegImm(out, arg, imm) => {
-                    for i in 0..size {
-                        v[out][i] = if v[arg][i].v != 0.0 {
-                            v[arg][i]
-                        } else {
-                            imm.into()
-                        };
-                    }
-                }
-                RegOp::DivRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] / v[rhs][i];
-                    }
-                }
-                RegOp::SubRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] = v[lhs][i] - v[rhs][i];
-                    }
-                }
-                RegOp::CompareRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        let p = v[lhs][i]
-                            .v
-                            .partial_cmp(&v[rhs][i].v)
-                            .map(|c| c as i8 as f32)
-                            .unwrap_or(f32::NAN);
-                        v[out][i] = Grad::new(p, 0.0, 0.0, 0.0);
-                    }
-                }
-                RegOp::MinRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] =
-                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
-                                f32::NAN.into()
-                            } else {
-                                v[lhs][i].min(v[rhs][i])
-                            };
-                    }
-                }
-                RegOp::MaxRegReg(out, lhs, rhs) => {
-                    for i in 0..size {
-                        v[out][i] =
-                            if v[lhs][i].v.is_nan() || v[rhs][i].v.is_nan() {
-                                f32::NAN.into()
-                            } else {
-                                v[lhs][i].max(v[rhs][i])
-                            };
-                    }
-                }
-                RegOp::CopyImm(out, imm) => {
-                    let imm: Grad = imm.into();
-                    for i in 0..size {
-                        v[out][i] = imm;
-                    }
-                }
-                RegOp::Load(out, mem) => {
-                    for i in 0..size {
-                        v[out][i] = v[mem][i];
-                    }
-                }
-                RegOp::Store(out, mem) => {
-                    for i in 0..size {
-                        v[mem][i] = v[out][i];
-                    }
-                }
+/// Implements a bulk gradient evaluator for the given gradient type
+///
+/// Immediates are read with [`VmImmediate::from_imm`], so that double-precision
+/// evaluators use their exact values.
+macro_rules! vm_grad_slice_eval {
+    ($(#[$meta:meta])* $name:ident, $Grad:ident, $t:ident) => {
+        $(#[$meta])*
+        #[derive(Default)]
+        pub struct $name<const N: usize>(BulkVmEval<$Grad>);
+        impl<const N: usize> BulkEvaluator for $name<N> {
+            type Data = $Grad;
+            type Tape = GenericVmTape<N>;
+            type TapeStorage = EmptyTapeStorage;
+
+            fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+                &mut self,
+                tape: &Self::Tape,
+                vars: &[V],
+            ) -> Result<BulkOutput<'_, $Grad>, Error> {
+                tape.vars().check_bulk_arguments(vars)?;
+                let tape = tape.data();
+                let size = vars.first().map(|v| v.len()).unwrap_or(0);
+                self.0.resize_slots(tape, size);
+
+                let mut v = SlotArray(&mut self.0.slots);
+                let mut imms = tape.iter_imm();
+                for op in tape.iter_asm() {
+                    match op {
+                        RegOp::Output(arg, i) => {
+                            self.0.out[i as usize][0..size]
+                                .copy_from_slice(&v[arg][0..size]);
+                        }
+                        RegOp::Input(out, i) => {
+                            v[out][0..size].copy_from_slice(&vars[i as usize]);
+                        }
+                        RegOp::NegReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = -v[arg][i];
+                            }
+                        }
+                        RegOp::AbsReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].abs();
+                            }
+                        }
+                        RegOp::RecipReg(out, arg) => {
+                            let one: $Grad = 1.0.into();
+                            for i in 0..size {
+                                v[out][i] = one / v[arg][i];
+                            }
+                        }
+                        RegOp::SqrtReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].sqrt();
+                            }
+                        }
+                        RegOp::SquareReg(out, arg) => {
+                            for i in 0..size {
+                                let s = v[arg][i];
+                                v[out][i] = s * s;
+                            }
+                        }
+                        RegOp::FloorReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].floor();
+                            }
+                        }
+                        RegOp::CeilReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].ceil();
+                            }
+                        }
+                        RegOp::RoundReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].round();
+                            }
+                        }
+                        RegOp::SinReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].sin();
+                            }
+                        }
+                        RegOp::CosReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].cos();
+                            }
+                        }
+                        RegOp::TanReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].tan();
+                            }
+                        }
+                        RegOp::AsinReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].asin();
+                            }
+                        }
+                        RegOp::AcosReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].acos();
+                            }
+                        }
+                        RegOp::AtanReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].atan();
+                            }
+                        }
+                        RegOp::ExpReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].exp();
+                            }
+                        }
+                        RegOp::LnReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].ln();
+                            }
+                        }
+                        RegOp::NotReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] =
+                                    $t::from(v[arg][i].v == 0.0).into();
+                            }
+                        }
+                        RegOp::SinhReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].sinh();
+                            }
+                        }
+                        RegOp::CoshReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].cosh();
+                            }
+                        }
+                        RegOp::TanhReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].tanh();
+                            }
+                        }
+                        RegOp::SignReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].sign();
+                            }
+                        }
+                        RegOp::CbrtReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].cbrt();
+                            }
+                        }
+                        RegOp::Log2Reg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].log2();
+                            }
+                        }
+                        RegOp::CopyReg(out, arg) => {
+                            for i in 0..size {
+                                v[out][i] = v[arg][i];
+                            }
+                        }
+                        RegOp::AddRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] + imm.into();
+                            }
+                        }
+                        RegOp::MulRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] * imm;
+                            }
+                        }
+                        RegOp::DivRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] / imm.into();
+                            }
+                        }
+                        RegOp::DivImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm = $Grad::from(imm);
+                            for i in 0..size {
+                                v[out][i] = imm / v[arg][i];
+                            }
+                        }
+                        RegOp::AtanRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm = $Grad::from(imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].atan2(imm);
+                            }
+                        }
+                        RegOp::AtanImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm = $Grad::from(imm);
+                            for i in 0..size {
+                                v[out][i] = imm.atan2(v[arg][i]);
+                            }
+                        }
+                        RegOp::AtanRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                            }
+                        }
+                        RegOp::SubImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm: $Grad = imm.into();
+                            for i in 0..size {
+                                v[out][i] = imm - v[arg][i];
+                            }
+                        }
+                        RegOp::SubRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm: $Grad = imm.into();
+                            for i in 0..size {
+                                v[out][i] = v[arg][i] - imm;
+                            }
+                        }
+                        RegOp::CompareImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                let p = imm
+                                    .partial_cmp(&v[arg][i].v)
+                                    .map(|c| c as i8 as $t)
+                                    .unwrap_or($t::NAN);
+                                v[out][i] = $Grad::new(p, 0.0, 0.0, 0.0);
+                            }
+                        }
+                        RegOp::CompareRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                let p = v[arg][i]
+                                    .v
+                                    .partial_cmp(&imm)
+                                    .map(|c| c as i8 as $t)
+                                    .unwrap_or($t::NAN);
+                                v[out][i] = $Grad::new(p, 0.0, 0.0, 0.0);
+                            }
+                        }
+                        RegOp::MinRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm: $Grad = imm.into();
+                            for i in 0..size {
+                                v[out][i] =
+                                    if v[arg][i].v.is_nan() || imm.v.is_nan() {
+                                        $t::NAN.into()
+                                    } else {
+                                        v[arg][i].min(imm)
+                                    };
+                            }
+                        }
+                        RegOp::MaxRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm: $Grad = imm.into();
+                            for i in 0..size {
+                                v[out][i] =
+                                    if v[arg][i].v.is_nan() || imm.v.is_nan() {
+                                        $t::NAN.into()
+                                    } else {
+                                        v[arg][i].max(imm)
+                                    };
+                            }
+                        }
+                        RegOp::ModRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                            }
+                        }
+                        RegOp::ModRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].rem_euclid(imm.into());
+                            }
+                        }
+                        RegOp::ModImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] =
+                                    $Grad::from(imm).rem_euclid(v[arg][i]);
+                            }
+                        }
+                        RegOp::PowRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i].pow(v[rhs][i]);
+                            }
+                        }
+                        RegOp::PowRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = v[arg][i].pow(imm.into());
+                            }
+                        }
+                        RegOp::PowImmReg(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = $Grad::from(imm).pow(v[arg][i]);
+                            }
+                        }
+                        RegOp::AddRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] + v[rhs][i];
+                            }
+                        }
+                        RegOp::MulRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] * v[rhs][i];
+                            }
+                        }
+                        RegOp::AndRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i].v == 0.0 {
+                                    v[lhs][i]
+                                } else {
+                                    v[rhs][i]
+                                };
+                            }
+                        }
+                        RegOp::AndRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = if v[arg][i].v == 0.0 {
+                                    v[arg][i]
+                                } else {
+                                    imm.into()
+                                };
+                            }
+                        }
+                        RegOp::OrRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i].v != 0.0 {
+                                    v[lhs][i]
+                                } else {
+                                    v[rhs][i]
+                                };
+                            }
+                        }
+                        RegOp::OrRegImm(out, arg, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            for i in 0..size {
+                                v[out][i] = if v[arg][i].v != 0.0 {
+                                    v[arg][i]
+                                } else {
+                                    imm.into()
+                                };
+                            }
+                        }
+                        RegOp::DivRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] / v[rhs][i];
+                            }
+                        }
+                        RegOp::SubRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = v[lhs][i] - v[rhs][i];
+                            }
+                        }
+                        RegOp::CompareRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                let p = v[lhs][i]
+                                    .v
+                                    .partial_cmp(&v[rhs][i].v)
+                                    .map(|c| c as i8 as $t)
+                                    .unwrap_or($t::NAN);
+                                v[out][i] = $Grad::new(p, 0.0, 0.0, 0.0);
+                            }
+                        }
+                        RegOp::MinRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i].v.is_nan()
+                                    || v[rhs][i].v.is_nan()
+                                {
+                                    $t::NAN.into()
+                                } else {
+                                    v[lhs][i].min(v[rhs][i])
+                                };
+                            }
+                        }
+                        RegOp::MaxRegReg(out, lhs, rhs) => {
+                            for i in 0..size {
+                                v[out][i] = if v[lhs][i].v.is_nan()
+                                    || v[rhs][i].v.is_nan()
+                                {
+                                    $t::NAN.into()
+                                } else {
+                                    v[lhs][i].max(v[rhs][i])
+                                };
+                            }
+                        }
+                        RegOp::SelectRegRegReg(out, cond, a, b) => {
+                            for i in 0..size {
+                                v[out][i] = if v[cond][i].v != 0.0 {
+                                    v[a][i]
+                                } else {
+                                    v[b][i]
+                                };
+                            }
+                        }
+                        RegOp::CopyImm(out, imm) => {
+                            let imm = $t::from_imm(&mut imms, imm);
+                            let imm: $Grad = imm.into();
+                            for i in 0..size {
+                                v[out][i] = imm;
+                            }
+                        }
+                        RegOp::Load(out, mem) => {
+                            for i in 0..size {
+                                v[out][i] = v[mem][i];
+                            }
+                        }
+                        RegOp::Store(out, mem) => {
+                            for i in 0..size {
+                                v[mem][i] = v[out][i];
+                            }
+                        }
+                    }
+                }
+                Ok(BulkOutput::new(&self.0.out, size))
             }
         }
-        Ok(BulkOutput::new(&self.0.out, size))
-    }
+    };
 }
+pub(crate) use vm_grad_slice_eval;
+
+vm_grad_slice_eval!(
+    /// VM-based bulk evaluator for arrays of points, yielding gradient values
+    VmGradSliceEval,
+    Grad,
+    f32
+);
 
 #[cfg(test)]
 mod test {
     use super::*;
     crate::grad_slice_tests!(VmFunction);
+    crate::dual_slice_tests!(VmFunction);
+    crate::float_slice_tracing_tests!(VmFunction);
+    crate::interval_slice_tests!(VmFunction);
+    crate::hess_slice_tests!(VmFunction);
     crate::interval_tests!(VmFunction);
     crate::float_slice_tests!(VmFunction);
     crate::point_tests!(VmFunction);
+
+    #[test]
+    fn test_select_spilling() {
+        // Three registers forces the allocator to spill and reload the
+        // operands of every select operation
+        crate::eval::test::point::TestPoint::<GenericVmFunction<3>>::test_p_select_stress();
+    }
+
+    #[test]
+    fn test_interval_random_samples() {
+        use crate::shape::EzShape;
+        use rand::{Rng, SeedableRng};
+
+        // sqrt(x² + y²) - exp(z / 3) / (1.7 + y²) + x * y * z - 0.1
+        let mut ctx = Context::new();
+        let (x, y, z) = (ctx.x(), ctx.y(), ctx.z());
+        let x2 = ctx.square(x).unwrap();
+        let y2 = ctx.square(y).unwrap();
+        let r = ctx.add(x2, y2).unwrap();
+        let r = ctx.sqrt(r).unwrap();
+        let e = ctx.div(z, 3.0).unwrap();
+        let e = ctx.exp(e).unwrap();
+        let d = ctx.add(y2, 1.7).unwrap();
+        let e = ctx.div(e, d).unwrap();
+        let xy = ctx.mul(x, y).unwrap();
+        let xyz = ctx.mul(xy, z).unwrap();
+        let out = ctx.sub(r, e).unwrap();
+        let out = ctx.add(out, xyz).unwrap();
+        let root = ctx.sub(out, 0.1).unwrap();
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let itape = shape.ez_interval_tape();
+        let ptape = shape.ez_point_tape();
+        let ptape_f64 = shape.point_tape_f64(Default::default());
+        let mut ieval = VmShape::new_interval_eval();
+        let mut peval = VmShape::new_point_eval();
+        let mut peval_f64 = VmShape::new_point_eval_f64();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x9abc);
+        let mut random_interval = || {
+            let lower = rng.gen_range(-2.0..2.0);
+            Interval::new(lower, lower + rng.gen_range(0.0..1.0))
+        };
+        for _ in 0..200 {
+            let (ix, iy, iz) =
+                (random_interval(), random_interval(), random_interval());
+            let (out, _) = ieval.eval(&itape, ix, iy, iz).unwrap();
+            for k in 0..16 {
+                let t = k as f32 / 15.0;
+                let (px, py, pz) = (ix.lerp(t), iy.lerp(1.0 - t), iz.lerp(t));
+                // In rigorous mode, compare against a double-precision result
+                let v = if cfg!(feature = "rigorous-intervals") {
+                    peval_f64
+                        .eval(&ptape_f64, px as f64, py as f64, pz as f64)
+                        .unwrap()
+                        .0
+                } else {
+                    peval.eval(&ptape, px, py, pz).unwrap().0 as f64
+                };
+                assert!(
+                    out.lower() as f64 <= v && v <= out.upper() as f64,
+                    "{v} not in {out}"
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,465 @@
+//! Bulk evaluation of many points, capturing an aggregated trace
+use super::{
+    BulkVmEval, Choice, EmptyTapeStorage, GenericVmTape, SlotArray, VmTrace,
+};
+use crate::{
+    compiler::RegOp,
+    eval::{BulkOutput, BulkTracingEvaluator, BulkTracingResult, Tape},
+    Error,
+};
+
+/// Returns the choice made by `min(a, b)` at a single point
+///
+/// This matches the choices made by the VM's point evaluator; `max(a, b)` is
+/// equivalent to `min_choice(b, a)`.
+fn min_choice(a: f32, b: f32) -> Choice {
+    if a < b {
+        Choice::Left
+    } else if b < a {
+        Choice::Right
+    } else {
+        Choice::Both
+    }
+}
+
+/// VM-based bulk evaluator for arrays of points, yielding values and a trace
+///
+/// Values are identical to those from
+/// [`VmFloatSliceEval`](super::VmFloatSliceEval).  Each choice in the trace
+/// is the union of the choices made at every point, so it's only
+/// [`Choice::Left`] or [`Choice::Right`] if every point took the same branch.
+#[derive(Default)]
+pub struct VmFloatSliceTracingEval<const N: usize>(BulkVmEval<f32>, VmTrace);
+impl<const N: usize> BulkTracingEvaluator for VmFloatSliceTracingEval<N> {
+    type Data = f32;
+    type Tape = GenericVmTape<N>;
+    type TapeStorage = EmptyTapeStorage;
+    type Trace = VmTrace;
+
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<BulkTracingResult<'_, f32, VmTrace>, Error> {
+        tape.vars().check_bulk_arguments(vars)?;
+        let tape = tape.data();
+
+        let size = vars.first().map(|v| v.len()).unwrap_or(0);
+        self.0.resize_slots(tape, size);
+        self.1.resize(tape.choice_count(), Choice::Unknown);
+        self.1.fill(Choice::Unknown);
+
+        let mut simplify = false;
+        let mut v = SlotArray(&mut self.0.slots);
+        let mut choices = self.1.as_mut_slice().iter_mut();
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Output(arg, i) => {
+                    self.0.out[i as usize][0..size]
+                        .copy_from_slice(&v[arg][0..size]);
+                }
+                RegOp::Input(out, i) => {
+                    v[out][0..size].copy_from_slice(&vars[i as usize]);
+                }
+                RegOp::NegReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = -v[arg][i];
+                    }
+                }
+                RegOp::AbsReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].abs();
+                    }
+                }
+                RegOp::RecipReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = 1.0 / v[arg][i];
+                    }
+                }
+                RegOp::SqrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sqrt();
+                    }
+                }
+                RegOp::SquareReg(out, arg) => {
+                    for i in 0..size {
+                        let s = v[arg][i];
+                        v[out][i] = s * s;
+                    }
+                }
+                RegOp::FloorReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].floor();
+                    }
+                }
+                RegOp::CeilReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ceil();
+                    }
+                }
+                RegOp::RoundReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].round();
+                    }
+                }
+                RegOp::SinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sin();
+                    }
+                }
+                RegOp::CosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cos();
+                    }
+                }
+                RegOp::TanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tan();
+                    }
+                }
+                RegOp::AsinReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].asin();
+                    }
+                }
+                RegOp::AcosReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].acos();
+                    }
+                }
+                RegOp::AtanReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan();
+                    }
+                }
+                RegOp::ExpReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].exp();
+                    }
+                }
+                RegOp::LnReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].ln();
+                    }
+                }
+                RegOp::NotReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = (v[arg][i] == 0.0).into();
+                    }
+                }
+                RegOp::SinhReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].sinh();
+                    }
+                }
+                RegOp::CoshReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cosh();
+                    }
+                }
+                RegOp::TanhReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].tanh();
+                    }
+                }
+                RegOp::SignReg(out, arg) => {
+                    for i in 0..size {
+                        let a = v[arg][i];
+                        v[out][i] = if a > 0.0 {
+                            1.0
+                        } else if a < 0.0 {
+                            -1.0
+                        } else {
+                            a
+                        };
+                    }
+                }
+                RegOp::CbrtReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].cbrt();
+                    }
+                }
+                RegOp::Log2Reg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].log2();
+                    }
+                }
+                RegOp::CopyReg(out, arg) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i];
+                    }
+                }
+                RegOp::AddRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] + imm;
+                    }
+                }
+                RegOp::MulRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] * imm;
+                    }
+                }
+                RegOp::DivRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] / imm;
+                    }
+                }
+                RegOp::DivImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm / v[arg][i];
+                    }
+                }
+                RegOp::AtanRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].atan2(imm);
+                    }
+                }
+                RegOp::AtanImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm.atan2(v[arg][i]);
+                    }
+                }
+                RegOp::AtanRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].atan2(v[rhs][i]);
+                    }
+                }
+                RegOp::SubImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm - v[arg][i];
+                    }
+                }
+                RegOp::SubRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i] - imm;
+                    }
+                }
+                RegOp::CompareImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm
+                            .partial_cmp(&v[arg][i])
+                            .map(|c| c as i8 as f32)
+                            .unwrap_or(f32::NAN)
+                    }
+                }
+                RegOp::CompareRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i]
+                            .partial_cmp(&imm)
+                            .map(|c| c as i8 as f32)
+                            .unwrap_or(f32::NAN)
+                    }
+                }
+                RegOp::MinRegImm(out, arg, imm) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        let a = v[arg][i];
+                        choice |= min_choice(a, imm);
+                        v[out][i] = if a.is_nan() || imm.is_nan() {
+                            f32::NAN
+                        } else {
+                            a.min(imm)
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::MaxRegImm(out, arg, imm) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        let a = v[arg][i];
+                        choice |= min_choice(imm, a);
+                        v[out][i] = if a.is_nan() || imm.is_nan() {
+                            f32::NAN
+                        } else {
+                            a.max(imm)
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::AndRegImm(out, arg, imm) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        let a = v[arg][i];
+                        v[out][i] = if a == 0.0 {
+                            choice |= Choice::Left;
+                            a
+                        } else {
+                            choice |= Choice::Right;
+                            imm
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::OrRegImm(out, arg, imm) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        let a = v[arg][i];
+                        v[out][i] = if a != 0.0 {
+                            choice |= Choice::Left;
+                            a
+                        } else {
+                            choice |= Choice::Right;
+                            imm
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::ModRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].rem_euclid(v[rhs][i]);
+                    }
+                }
+                RegOp::ModRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].rem_euclid(imm);
+                    }
+                }
+                RegOp::ModImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm.rem_euclid(v[arg][i]);
+                    }
+                }
+                RegOp::PowRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i].powf(v[rhs][i]);
+                    }
+                }
+                RegOp::PowRegImm(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = v[arg][i].powf(imm);
+                    }
+                }
+                RegOp::PowImmReg(out, arg, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm.powf(v[arg][i]);
+                    }
+                }
+                RegOp::AddRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] + v[rhs][i];
+                    }
+                }
+                RegOp::MulRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] * v[rhs][i];
+                    }
+                }
+                RegOp::DivRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] / v[rhs][i];
+                    }
+                }
+                RegOp::SubRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i] - v[rhs][i];
+                    }
+                }
+                RegOp::CompareRegReg(out, lhs, rhs) => {
+                    for i in 0..size {
+                        v[out][i] = v[lhs][i]
+                            .partial_cmp(&v[rhs][i])
+                            .map(|c| c as i8 as f32)
+                            .unwrap_or(f32::NAN)
+                    }
+                }
+                RegOp::MinRegReg(out, lhs, rhs) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        let (a, b) = (v[lhs][i], v[rhs][i]);
+                        choice |= min_choice(a, b);
+                        v[out][i] = if a.is_nan() || b.is_nan() {
+                            f32::NAN
+                        } else {
+                            a.min(b)
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::MaxRegReg(out, lhs, rhs) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        let (a, b) = (v[lhs][i], v[rhs][i]);
+                        choice |= min_choice(b, a);
+                        v[out][i] = if a.is_nan() || b.is_nan() {
+                            f32::NAN
+                        } else {
+                            a.max(b)
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::AndRegReg(out, lhs, rhs) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i] == 0.0 {
+                            choice |= Choice::Left;
+                            v[lhs][i]
+                        } else {
+                            choice |= Choice::Right;
+                            v[rhs][i]
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::OrRegReg(out, lhs, rhs) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        v[out][i] = if v[lhs][i] != 0.0 {
+                            choice |= Choice::Left;
+                            v[lhs][i]
+                        } else {
+                            choice |= Choice::Right;
+                            v[rhs][i]
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::SelectRegRegReg(out, cond, a, b) => {
+                    let mut choice = Choice::Unknown;
+                    for i in 0..size {
+                        v[out][i] = if v[cond][i] != 0.0 {
+                            choice |= Choice::Left;
+                            v[a][i]
+                        } else {
+                            choice |= Choice::Right;
+                            v[b][i]
+                        };
+                    }
+                    *choices.next().unwrap() |= choice;
+                    simplify |= choice != Choice::Both;
+                }
+                RegOp::CopyImm(out, imm) => {
+                    for i in 0..size {
+                        v[out][i] = imm;
+                    }
+                }
+                RegOp::Load(out, mem) => {
+                    for i in 0..size {
+                        v[out][i] = v[mem][i];
+                    }
+                }
+                RegOp::Store(out, mem) => {
+                    for i in 0..size {
+                        v[mem][i] = v[out][i];
+                    }
+                }
+            }
+        }
+        Ok((
+            BulkOutput::new(&self.0.out, size),
+            if simplify && size > 0 {
+                Some(&self.1)
+            } else {
+                None
+            },
+        ))
+    }
+}
@@ -45,6 +45,37 @@ pub enum Error {
     #[error("empty file")]
     EmptyFile,
 
+    /// Parse error in a text format, with a 1-indexed position
+    #[error("parse error at line {line}, column {column}: {message}")]
+    ParseError {
+        /// Line number (1-indexed)
+        line: usize,
+        /// Column number (1-indexed)
+        column: usize,
+        /// Description of the error
+        message: String,
+    },
+
+    /// Text file declares more than one output
+    #[error("expected a single output, found {0}")]
+    MultipleOutputs(usize),
+
+    /// No outputs were provided when writing a text file
+    #[error("expected at least one output")]
+    NoOutputs,
+
+    /// Variable name can't be written to a text file
+    #[error("invalid variable name `{0}`")]
+    BadVarName(String),
+
+    /// Two outputs of a shape have the same name
+    #[error("duplicate output name `{0}`")]
+    DuplicateOutputName(String),
+
+    /// Generated code only supports the `x`, `y`, and `z` variables
+    #[error("generated code only supports the x, y, and z variables")]
+    UnsupportedVar,
+
     /// Choice slice length does not match choice count
     #[error("choice slice length ({0}) does not match choice count ({1})")]
     BadChoiceSlice(usize, usize),
@@ -223,6 +223,48 @@ impl Assembler for FloatSliceAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_log2(f: f32) -> f32 {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_sinh(f: f32) -> f32 {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_cosh(f: f32) -> f32 {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_tanh(f: f32) -> f32 {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_sign(f: f32) -> f32 {
+            if f > 0.0 {
+                1.0
+            } else if f < 0.0 {
+                -1.0
+            } else {
+                f
+            }
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_cbrt(f: f32) -> f32 {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cbrt);
+    }
     fn build_copy(&mut self, out_reg: u8, lhs_reg: u8) {
         dynasm!(self.0.ops ; mov V(reg(out_reg)).b16, V(reg(lhs_reg)).b16)
     }
@@ -317,6 +359,12 @@ impl Assembler for FloatSliceAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "C" fn float_pow(lhs: f32, rhs: f32) -> f32 {
+            lhs.powf(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_pow);
+    }
     fn build_max(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; fmax V(reg(out_reg)).s4, V(reg(lhs_reg)).s4, V(reg(rhs_reg)).s4
@@ -362,6 +410,21 @@ impl Assembler for FloatSliceAssembler {
             ; orr V(reg(out_reg)).b16, v6.b16, v7.b16
         );
     }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            ; fcmeq v6.s4, V(reg(cond_reg)).s4, 0.0
+            ; mvn v7.b16, v6.b16
+            ; and v6.b16, v6.b16, V(reg(b_reg)).b16
+            ; and v7.b16, v7.b16, V(reg(a_reg)).b16
+            ; orr V(reg(out_reg)).b16, v6.b16, v7.b16
+        );
+    }
 
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
@@ -225,6 +225,42 @@ impl Assembler for GradSliceAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn grad_log2(f: Grad) -> Grad {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn grad_sinh(f: Grad) -> Grad {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn grad_cosh(f: Grad) -> Grad {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn grad_tanh(f: Grad) -> Grad {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn grad_sign(f: Grad) -> Grad {
+            f.sign()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn grad_cbrt(f: Grad) -> Grad {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_cbrt);
+    }
     fn build_copy(&mut self, out_reg: u8, lhs_reg: u8) {
         dynasm!(self.0.ops ; mov V(reg(out_reg)).b16, V(reg(lhs_reg)).b16)
     }
@@ -373,6 +409,12 @@ impl Assembler for GradSliceAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, grad_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "C" fn grad_pow(lhs: Grad, rhs: Grad) -> Grad {
+            lhs.pow(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, grad_pow);
+    }
 
     fn build_max(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
@@ -449,6 +491,22 @@ impl Assembler for GradSliceAssembler {
             ; orr V(reg(out_reg)).b16, v6.b16, v7.b16
         );
     }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            ; fcmeq s6, S(reg(cond_reg)), 0.0
+            ; dup v6.s4, v6.s[0]
+            ; mvn v7.b16, v6.b16
+            ; and v6.b16, v6.b16, V(reg(b_reg)).b16
+            ; and v7.b16, v7.b16, V(reg(a_reg)).b16
+            ; orr V(reg(out_reg)).b16, v6.b16, v7.b16
+        );
+    }
 
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
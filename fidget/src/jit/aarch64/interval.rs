@@ -183,6 +183,42 @@ impl Assembler for IntervalAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn interval_log2(f: Interval) -> Interval {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn interval_sinh(f: Interval) -> Interval {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn interval_cosh(f: Interval) -> Interval {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn interval_tanh(f: Interval) -> Interval {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn interval_sign(f: Interval) -> Interval {
+            f.sign()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn interval_cbrt(f: Interval) -> Interval {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_cbrt);
+    }
     fn build_copy(&mut self, out_reg: u8, lhs_reg: u8) {
         dynasm!(self.0.ops ; fmov D(reg(out_reg)), D(reg(lhs_reg)))
     }
@@ -531,6 +567,12 @@ impl Assembler for IntervalAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, interval_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "C" fn interval_pow(lhs: Interval, rhs: Interval) -> Interval {
+            lhs.pow(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, interval_pow);
+    }
 
     fn build_not(&mut self, out_reg: u8, arg_reg: u8) {
         dynasm!(self.0.ops
@@ -684,6 +726,88 @@ impl Assembler for IntervalAssembler {
             ; strb w14, [x1], 1 // post-increment
         )
     }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            // Check whether the condition has a NAN; NANs in the branches
+            // only matter if we can't pick one of them
+            ; fcmeq v5.s2, V(reg(cond_reg)).s2, V(reg(cond_reg)).s2
+            ; fmov x15, d5
+
+            // Load the choice bit
+            ; ldrb w14, [x1]
+
+            // check the NAN flag
+            ; cmp x15, 0
+            ; b.ne 20 // -> skip over NAN handling into main logic
+
+            // NAN handling
+            ; orr w14, w14, CHOICE_BOTH
+            ; mov w15, f32::NAN.to_bits().into()
+            ; dup V(reg(out_reg)).s2, w15
+            ; b 152 // -> exit
+
+            // v7 = !cond.contains(0.0)
+            ; fcmgt s6, S(reg(cond_reg)), 0.0 // s6 = lower > 0.0
+            ; mov s5, V(reg(cond_reg)).s[1]   // s5 = upper
+            ; fcmlt s7, s5, 0.0              // s7 = upper < 0.0
+            ; orr v7.b8, v6.b8, v7.b8 // (lower > 0) || (upper < 0)
+            ; fmov w9, s7
+            ; cmp w9, 0
+            ; b.eq 20 // skip the !cond.contains(0.0) branch
+
+            // !cond.contains(0.0) -> A
+            ; fmov D(reg(out_reg)), D(reg(a_reg))
+            ; orr w14, w14, CHOICE_LEFT
+            ; strb w14, [x2, 0] // write a non-zero value to simplify
+            ; b 108 // -> exit
+
+            // v6 = (lower == 0) && (upper == 0)
+            ; fcmeq s6, S(reg(cond_reg)), 0.0
+            ; fcmeq s5, s5, 0.0
+            ; and v6.b8, v6.b8, v5.b8 // (lower == 0) && (upper == 0)
+            ; fmov w9, s6
+            ; cmp w9, 0
+            ; b.eq 20 // skip the (lower == 0) && (upper == 0) branch
+
+            // (cond.lower == 0) && (cond.upper == 0) -> B
+            ; fmov D(reg(out_reg)), D(reg(b_reg))
+            ; orr w14, w14, CHOICE_RIGHT
+            ; strb w14, [x2, 0] // write a non-zero value to simplify
+            ; b 68 // -> exit
+
+            // Check whether either branch has a NAN
+            ; fcmeq v5.s2, V(reg(a_reg)).s2, V(reg(a_reg)).s2
+            ; fmov x15, d5
+            ; fcmeq v5.s2, V(reg(b_reg)).s2, V(reg(b_reg)).s2
+            ; fmov x9, d5
+            ; and x15, x15, x9
+            ; cmp x15, 0
+            ; b.ne 20 // -> skip over NAN handling
+
+            // NAN handling
+            ; orr w14, w14, CHOICE_BOTH
+            ; mov w15, f32::NAN.to_bits().into()
+            ; dup V(reg(out_reg)).s2, w15
+            ; b 24 // -> exit
+
+            // s5 = min(a.lower, b.lower)
+            // s6 = max(a.upper, b.upper)
+            ; orr w14, w14, CHOICE_BOTH
+            ; fmin s5, S(reg(a_reg)), S(reg(b_reg))
+            ; fmax v6.s2, V(reg(a_reg)).s2, V(reg(b_reg)).s2
+            ; mov s6, v6.s[1]
+            ; zip1 V(reg(out_reg)).s2, v5.s2, v6.s2
+
+            // exit
+            ; strb w14, [x1], 1 // post-increment
+        )
+    }
 
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
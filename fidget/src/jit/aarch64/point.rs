@@ -182,6 +182,48 @@ impl Assembler for PointAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_log2(f: f32) -> f32 {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_sinh(f: f32) -> f32 {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_cosh(f: f32) -> f32 {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_tanh(f: f32) -> f32 {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_sign(f: f32) -> f32 {
+            if f > 0.0 {
+                1.0
+            } else if f < 0.0 {
+                -1.0
+            } else {
+                f
+            }
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "C" fn float_cbrt(f: f32) -> f32 {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cbrt);
+    }
     fn build_neg(&mut self, out_reg: u8, lhs_reg: u8) {
         dynasm!(self.0.ops ; fneg S(reg(out_reg)), S(reg(lhs_reg)))
     }
@@ -270,6 +312,12 @@ impl Assembler for PointAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "C" fn float_pow(lhs: f32, rhs: f32) -> f32 {
+            lhs.powf(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_pow);
+    }
     fn build_max(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; ldrb w14, [x1]
@@ -396,6 +444,39 @@ impl Assembler for PointAssembler {
             ; orr V(reg(out_reg)).b8, v5.b8, v6.b8
         );
     }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            ; fcmeq s6, S(reg(cond_reg)), 0.0
+            ; mvn v6.b8, v6.b8
+
+            ; fmov w10, s6 // s6 = w10 = (cond != 0)
+            ; mov w9, CHOICE_LEFT.into()
+            ; and w9, w9, w10 // w9 = (cond != 0) ? CHOICE_LEFT : 0
+
+            ; mvn w10, w10
+            ; mov w11, CHOICE_RIGHT.into()
+            ; and w11, w11, w10 // w11 = (cond == 0) ? CHOICE_RIGHT : 0
+            ; orr w11, w11, w9  // w11 = choice to write
+
+            ; ldrb w14, [x1]
+            ; orr w14, w14, w11
+            ; strb w14, [x1], 1 // post-increment
+
+            ; strb w14, [x2, 0] // store any non-zero value to `simplify`
+
+            // Accumulate our output value
+            ; and v5.b8, v6.b8, V(reg(a_reg)).b8
+            ; mvn v6.b8, v6.b8
+            ; and v6.b8, v6.b8, V(reg(b_reg)).b8
+            ; orr V(reg(out_reg)).b8, v5.b8, v6.b8
+        );
+    }
 
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         // This is using SIMD instructions to avoid branch; dunno if it's faster
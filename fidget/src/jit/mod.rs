@@ -27,14 +27,18 @@ use crate::{
     compiler::RegOp,
     context::{Context, Node},
     eval::{
-        BulkEvaluator, BulkOutput, Function, MathFunction, Tape,
-        TracingEvaluator,
+        BulkEvaluator, BulkOutput, BulkTraces, BulkTracingEvaluator,
+        BulkTracingResult, Function, MathFunction, MultiTracingEvaluator,
+        MultiTracingResult, Tape, Trace, TracingEvaluator,
     },
     jit::mmap::{Mmap, MmapWriter},
     render::{RenderHints, TileSizes},
-    types::{Grad, Interval},
+    types::{Dual, Grad, Interval},
     var::VarMap,
-    vm::{Choice, GenericVmFunction, VmData, VmTrace, VmWorkspace},
+    vm::{
+        Choice, GenericVmFunction, GenericVmTape, VmData,
+        VmFloatSliceTracingEval, VmTrace, VmWorkspace,
+    },
     Error,
 };
 
@@ -182,6 +186,24 @@ trait Assembler {
     /// Natural log
     fn build_ln(&mut self, out_reg: u8, lhs_reg: u8);
 
+    /// Base-2 log
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8);
+
+    /// Hyperbolic sine
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8);
+
+    /// Hyperbolic cosine
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8);
+
+    /// Hyperbolic tangent
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8);
+
+    /// Sign (-1, 0, or +1)
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8);
+
+    /// Cube root
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8);
+
     /// Less than
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8);
 
@@ -212,6 +234,12 @@ trait Assembler {
     /// Logical or (short-circuiting)
     fn build_or(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8);
 
+    /// Selects `a_reg` if `cond_reg` is non-zero, else `b_reg`
+    ///
+    /// In a tracing evaluator, this function must also write to the `choices`
+    /// array and may set `simplify` if one branch is always taken.
+    fn build_select(&mut self, out_reg: u8, cond_reg: u8, a_reg: u8, b_reg: u8);
+
     /// Addition
     fn build_add(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8);
 
@@ -242,6 +270,9 @@ trait Assembler {
     /// Modulo of two values (least non-negative remainder)
     fn build_mod(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8);
 
+    /// Raises `lhs_reg` to the power of `rhs_reg`
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8);
+
     // Special-case functions for immediates.  In some cases, you can be more
     // efficient if you know that an argument is an immediate (for example, both
     // values in the interval will be the same, and it will have no gradients).
@@ -734,6 +765,24 @@ fn build_asm_fn_with_storage<A: Assembler>(
             RegOp::LnReg(out, arg) => {
                 asm.build_ln(out, arg);
             }
+            RegOp::Log2Reg(out, arg) => {
+                asm.build_log2(out, arg);
+            }
+            RegOp::SinhReg(out, arg) => {
+                asm.build_sinh(out, arg);
+            }
+            RegOp::CoshReg(out, arg) => {
+                asm.build_cosh(out, arg);
+            }
+            RegOp::TanhReg(out, arg) => {
+                asm.build_tanh(out, arg);
+            }
+            RegOp::SignReg(out, arg) => {
+                asm.build_sign(out, arg);
+            }
+            RegOp::CbrtReg(out, arg) => {
+                asm.build_cbrt(out, arg);
+            }
             RegOp::CopyReg(out, arg) => {
                 asm.build_copy(out, arg);
             }
@@ -820,6 +869,17 @@ fn build_asm_fn_with_storage<A: Assembler>(
                 let reg = asm.load_imm(imm);
                 asm.build_mod(out, reg, arg);
             }
+            RegOp::PowRegReg(out, lhs, rhs) => {
+                asm.build_pow(out, lhs, rhs);
+            }
+            RegOp::PowRegImm(out, arg, imm) => {
+                let reg = asm.load_imm(imm);
+                asm.build_pow(out, arg, reg);
+            }
+            RegOp::PowImmReg(out, arg, imm) => {
+                let reg = asm.load_imm(imm);
+                asm.build_pow(out, reg, arg);
+            }
             RegOp::AndRegReg(out, lhs, rhs) => {
                 asm.build_and(out, lhs, rhs);
             }
@@ -830,6 +890,9 @@ fn build_asm_fn_with_storage<A: Assembler>(
             RegOp::OrRegReg(out, lhs, rhs) => {
                 asm.build_or(out, lhs, rhs);
             }
+            RegOp::SelectRegRegReg(out, cond, a, b) => {
+                asm.build_select(out, cond, a, b);
+            }
             RegOp::OrRegImm(out, arg, imm) => {
                 let reg = asm.load_imm(imm);
                 asm.build_or(out, arg, reg);
@@ -906,8 +969,11 @@ impl Function for JitFunction {
 
     type IntervalEval = JitIntervalEval;
     type PointEval = JitPointEval;
+    type IntervalSliceEval = JitIntervalSliceEval;
     type FloatSliceEval = JitFloatSliceEval;
     type GradSliceEval = JitGradSliceEval;
+    type FloatSliceTracingEval = JitFloatSliceTracingEval;
+    type DualSliceEval<const P: usize> = JitDualSliceEval<P>;
 
     fn point_tape(&self, storage: Mmap) -> JitTracingFn<f32> {
         self.tracing_tape::<point::PointAssembler>(storage)
@@ -917,6 +983,10 @@ impl Function for JitFunction {
         self.tracing_tape::<interval::IntervalAssembler>(storage)
     }
 
+    fn interval_slice_tape(&self, storage: Mmap) -> JitTracingFn<Interval> {
+        self.tracing_tape::<interval::IntervalAssembler>(storage)
+    }
+
     fn float_slice_tape(&self, storage: Mmap) -> JitBulkFn<f32> {
         self.bulk_tape::<float_slice::FloatSliceAssembler>(storage)
     }
@@ -925,6 +995,16 @@ impl Function for JitFunction {
         self.bulk_tape::<grad_slice::GradSliceAssembler>(storage)
     }
 
+    fn float_slice_tracing_tape(&self, _storage: Mmap) -> JitInterpretedFn {
+        JitInterpretedFn(self.0.tape())
+    }
+
+    fn dual_slice_tape<const P: usize>(&self, storage: Mmap) -> JitDualSliceFn {
+        JitDualSliceFn(
+            self.bulk_tape::<grad_slice::GradSliceAssembler>(storage),
+        )
+    }
+
     fn simplify(
         &self,
         trace: &Self::Trace,
@@ -945,6 +1025,10 @@ impl Function for JitFunction {
     fn vars(&self) -> &VarMap {
         self.0.vars()
     }
+
+    fn visit_ops(&self, f: &mut dyn FnMut(RegOp)) {
+        self.0.visit_ops(f)
+    }
 }
 
 impl RenderHints for JitFunction {
@@ -1223,8 +1307,7 @@ impl<T: From<f32> + Copy + SimdSize> JitBulkEval<T> {
     ) -> BulkOutput<T> {
         let n = vars.first().map(|v| v.deref().len()).unwrap_or(0);
 
-        const OUTPUT_COUNT: usize = 1;
-        self.out.resize_with(OUTPUT_COUNT, Vec::new);
+        self.out.resize_with(tape.output_count, Vec::new);
         for o in &mut self.out {
             o.resize(n.max(T::SIMD_SIZE), f32::NAN.into());
             o.fill(f32::NAN.into());
@@ -1338,6 +1421,187 @@ impl BulkEvaluator for JitGradSliceEval {
     }
 }
 
+/// Handle to a tape which is interpreted rather than compiled
+///
+/// The JIT doesn't support tracing bulk evaluation, which needs choices to be
+/// aggregated across lanes.  For this evaluator, the tape is not compiled to
+/// machine code; instead, it holds the [`JitFunction`]'s register-allocated VM
+/// tape, which is interpreted by [`JitFloatSliceTracingEval`].
+///
+/// Tape storage is not used, so it's dropped when the tape is built.
+#[derive(Clone)]
+pub struct JitInterpretedFn(GenericVmTape<REGISTER_LIMIT>);
+
+impl Tape for JitInterpretedFn {
+    type Storage = Mmap;
+    fn recycle(self) -> Option<Self::Storage> {
+        None
+    }
+
+    fn vars(&self) -> &VarMap {
+        self.0.vars()
+    }
+
+    fn output_count(&self) -> usize {
+        self.0.output_count()
+    }
+}
+
+/// Handle to a tape for dual number evaluation
+///
+/// Dual numbers are evaluated three partial derivatives at a time, using the
+/// lanes of a [`Grad`], so this is a tape compiled with the gradient
+/// assembler.
+#[derive(Clone)]
+pub struct JitDualSliceFn(JitBulkFn<Grad>);
+
+impl Tape for JitDualSliceFn {
+    type Storage = Mmap;
+    fn recycle(self) -> Option<Self::Storage> {
+        self.0.recycle()
+    }
+
+    fn vars(&self) -> &VarMap {
+        self.0.vars()
+    }
+
+    fn output_count(&self) -> usize {
+        self.0.output_count()
+    }
+}
+
+/// Bulk evaluator for arrays of points, yielding dual numbers
+///
+/// Each dual number is split into chunks of (up to) three partial derivatives,
+/// which are converted to [`Grad`] values and evaluated with compiled code
+/// (see [`JitDualSliceFn`]).  Chunks of every sample are evaluated in a single
+/// call, then reassembled; the value is identical in every chunk, since
+/// partial derivatives don't affect it.
+#[derive(Default)]
+pub struct JitDualSliceEval<const P: usize> {
+    /// Evaluator for compiled tapes
+    grad: JitBulkEval<Grad>,
+    /// Input arrays, converted to gradients
+    args: Vec<Vec<Grad>>,
+    /// Output arrays, converted back from gradients
+    out: Vec<Vec<Dual<P>>>,
+}
+
+impl<const P: usize> JitDualSliceEval<P> {
+    /// Number of [`Grad`] values used to evaluate each dual number
+    const CHUNKS: usize = if P == 0 { 1 } else { P.div_ceil(3) };
+}
+
+impl<const P: usize> BulkEvaluator for JitDualSliceEval<P> {
+    type Data = Dual<P>;
+    type Tape = JitDualSliceFn;
+    type TapeStorage = Mmap;
+
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<BulkOutput<'_, Dual<P>>, Error> {
+        tape.vars().check_bulk_arguments(vars)?;
+        let n = vars.first().map(|v| v.len()).unwrap_or(0);
+
+        self.args.resize_with(vars.len(), Vec::new);
+        for (a, v) in self.args.iter_mut().zip(vars) {
+            a.clear();
+            a.extend(v.iter().flat_map(|d| {
+                (0..Self::CHUNKS).map(move |c| {
+                    let p =
+                        |i: usize| d.d.get(c * 3 + i).copied().unwrap_or(0.0);
+                    Grad::new(d.v, p(0), p(1), p(2))
+                })
+            }));
+        }
+        let out = self.grad.eval(&tape.0, &self.args);
+
+        self.out.resize_with(out.len(), Vec::new);
+        for (i, o) in self.out.iter_mut().enumerate() {
+            o.clear();
+            o.extend(out[i].chunks_exact(Self::CHUNKS).map(|g| {
+                Dual::new(g[0].v, std::array::from_fn(|j| g[j / 3].d(j % 3)))
+            }));
+        }
+        Ok(BulkOutput::new(&self.out, n))
+    }
+}
+
+/// Bulk evaluator for arrays of points, yielding point values and a trace
+///
+/// This evaluator uses the VM interpreter (see [`JitInterpretedFn`]); it
+/// behaves identically to [`VmFloatSliceTracingEval`].
+#[derive(Default)]
+pub struct JitFloatSliceTracingEval(VmFloatSliceTracingEval<REGISTER_LIMIT>);
+impl BulkTracingEvaluator for JitFloatSliceTracingEval {
+    type Data = f32;
+    type Tape = JitInterpretedFn;
+    type TapeStorage = Mmap;
+    type Trace = VmTrace;
+
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<BulkTracingResult<'_, f32, VmTrace>, Error> {
+        self.0.eval(&tape.0, vars)
+    }
+}
+
+/// Bulk evaluator for arrays of intervals, capturing a trace for each one
+///
+/// Each interval is evaluated by a separate call to the same compiled function
+/// as [`JitIntervalEval`], so results and traces are identical to it.
+#[derive(Default)]
+pub struct JitIntervalSliceEval {
+    eval: JitTracingEval<Interval>,
+    args: Vec<Interval>,
+    out: Vec<Vec<Interval>>,
+    traces: Vec<VmTrace>,
+    simplify: Vec<bool>,
+}
+impl MultiTracingEvaluator for JitIntervalSliceEval {
+    type Data = Interval;
+    type Tape = JitTracingFn<Interval>;
+    type TapeStorage = Mmap;
+    type Trace = VmTrace;
+
+    fn eval<V: std::ops::Deref<Target = [Self::Data]>>(
+        &mut self,
+        tape: &Self::Tape,
+        vars: &[V],
+    ) -> Result<MultiTracingResult<'_, Interval, VmTrace>, Error> {
+        tape.vars().check_bulk_arguments(vars)?;
+        let size = vars.first().map(|v| v.len()).unwrap_or(0);
+
+        self.out.resize_with(tape.output_count(), Vec::new);
+        for o in &mut self.out {
+            o.resize(size, f32::NAN.into());
+        }
+        self.traces.resize_with(size, VmTrace::default);
+        self.simplify.resize(size, false);
+
+        for i in 0..size {
+            self.args.clear();
+            self.args.extend(vars.iter().map(|v| v[i]));
+            let (out, trace) = self.eval.eval(tape, &self.args);
+            for (o, v) in self.out.iter_mut().zip(out) {
+                o[i] = *v;
+            }
+            self.simplify[i] = trace.is_some();
+            if let Some(t) = trace {
+                self.traces[i].copy_from(t);
+            }
+        }
+        Ok((
+            BulkOutput::new(&self.out, size),
+            BulkTraces::new(&self.traces[..size], &self.simplify[..size]),
+        ))
+    }
+}
+
 /// A [`Shape`](crate::shape::Shape) which uses the JIT evaluator
 pub type JitShape = crate::shape::Shape<JitFunction>;
 
@@ -1346,7 +1610,11 @@ pub type JitShape = crate::shape::Shape<JitFunction>;
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::var::Var;
     crate::grad_slice_tests!(JitFunction);
+    crate::dual_slice_tests!(JitFunction);
+    crate::float_slice_tracing_tests!(JitFunction);
+    crate::interval_slice_tests!(JitFunction);
     crate::interval_tests!(JitFunction);
     crate::float_slice_tests!(JitFunction);
     crate::point_tests!(JitFunction);
@@ -1368,4 +1636,92 @@ mod test {
             assert_eq!(v, i);
         }
     }
+
+    #[test]
+    fn test_interval_trace_stores() {
+        // Each choice and the `simplify` flag are single bytes; check that the
+        // compiled tracing function doesn't write past them.
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let ops = [
+            ctx.min(x, y).unwrap(),
+            ctx.max(x, y).unwrap(),
+            ctx.and(x, y).unwrap(),
+            ctx.or(x, y).unwrap(),
+            ctx.if_nonzero_else(z, x, y).unwrap(),
+        ];
+        const SENTINEL: u8 = 0xAA;
+        for node in ops {
+            let f = JitFunction::new(&ctx, &[node]).unwrap();
+            let tape = f.interval_tape(Default::default());
+            assert_eq!(tape.choice_count, 1);
+            let mut vars = [Interval::from(0.0); 3];
+            for (v, i) in [
+                (Var::X, Interval::new(0.0, 1.0)),
+                (Var::Y, Interval::new(2.0, 3.0)),
+                (Var::Z, Interval::new(-1.0, 1.0)),
+            ] {
+                if let Some(j) = tape.vars().get(&v) {
+                    vars[j] = i;
+                }
+            }
+            let mut choices = [Choice::Unknown as u8, SENTINEL];
+            let mut simplify = [0u8, SENTINEL];
+            let mut out = [Interval::from(f32::NAN)];
+            unsafe {
+                (tape.fn_trace)(
+                    vars.as_ptr(),
+                    choices.as_mut_ptr(),
+                    simplify.as_mut_ptr(),
+                    out.as_mut_ptr(),
+                )
+            };
+            assert_ne!(choices[0], Choice::Unknown as u8);
+            assert_eq!(choices[1], SENTINEL, "choice store overran");
+            assert_eq!(simplify[1], SENTINEL, "simplify store overran");
+        }
+    }
+
+    #[test]
+    fn test_dual_slice_chunks() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let s = ctx.mul(x, y).unwrap();
+        let s = ctx.sin(s).unwrap();
+        let f = JitFunction::new(&ctx, &[s]).unwrap();
+        let vm = GenericVmFunction::<REGISTER_LIMIT>::new(&ctx, &[s]).unwrap();
+        let ix = f.vars().get(&Var::X).unwrap();
+        let iy = f.vars().get(&Var::Y).unwrap();
+
+        // Seven partials span three chunks, with a partially-filled last one
+        let mut xs = vec![];
+        let mut ys = vec![];
+        for i in 0..5 {
+            let (a, b) = (i as f32 * 0.3, 1.0 - i as f32 * 0.2);
+            xs.push(Dual::<7>::var(a, i % 7));
+            ys.push(Dual::<7>::new(b, std::array::from_fn(|j| j as f32)));
+        }
+        let mut args = vec![vec![]; 2];
+        args[ix] = xs;
+        args[iy] = ys;
+
+        let tape = f.dual_slice_tape::<7>(Default::default());
+        let mut eval = JitFunction::new_dual_slice_eval::<7>();
+        let out = eval.eval(&tape, &args).unwrap()[0].to_vec();
+
+        let tape = vm.dual_slice_tape::<7>(Default::default());
+        let mut eval =
+            GenericVmFunction::<REGISTER_LIMIT>::new_dual_slice_eval::<7>();
+        let expected = eval.eval(&tape, &args).unwrap()[0].to_vec();
+        assert_eq!(out.len(), expected.len());
+        for (a, b) in out.iter().zip(&expected) {
+            assert!((a.v - b.v).abs() < 1e-6, "{a:?} != {b:?}");
+            for (da, db) in a.d.iter().zip(&b.d) {
+                assert!((da - db).abs() < 1e-5, "{a:?} != {b:?}");
+            }
+        }
+    }
 }
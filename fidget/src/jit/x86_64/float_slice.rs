@@ -164,6 +164,48 @@ impl Assembler for FloatSliceAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_log2(f: f32) -> f32 {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_sinh(f: f32) -> f32 {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_cosh(f: f32) -> f32 {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_tanh(f: f32) -> f32 {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_sign(f: f32) -> f32 {
+            if f > 0.0 {
+                1.0
+            } else if f < 0.0 {
+                -1.0
+            } else {
+                f
+            }
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_cbrt(f: f32) -> f32 {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cbrt);
+    }
     fn build_copy(&mut self, out_reg: u8, lhs_reg: u8) {
         dynasm!(self.0.ops
             ; vmovups Ry(reg(out_reg)), Ry(reg(lhs_reg))
@@ -255,6 +297,12 @@ impl Assembler for FloatSliceAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "sysv64" fn float_pow(lhs: f32, rhs: f32) -> f32 {
+            lhs.powf(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_pow);
+    }
     fn build_max(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             // Build a mask of NANs; conveniently, all 1s is a NAN
@@ -334,6 +382,25 @@ impl Assembler for FloatSliceAssembler {
             ; vorpd Ry(reg(out_reg)), ymm1, ymm2
         );
     }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            // Build the (cond == 0) mask in ymm1 and the opposite in ymm2
+            ; vxorps ymm1, ymm1, ymm1
+            ; vcmpeqps ymm1, ymm1, Ry(reg(cond_reg))
+            ; vpcmpeqd ymm2, ymm2, ymm2 // All 1s
+            ; vxorpd ymm2, ymm1, ymm2 // 1 ^ b = !b, so this inverts ymm1
+
+            ; vandpd ymm1, ymm1, Ry(reg(b_reg))
+            ; vandpd ymm2, ymm2, Ry(reg(a_reg))
+            ; vorpd Ry(reg(out_reg)), ymm1, ymm2
+        );
+    }
 
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
@@ -156,6 +156,42 @@ impl Assembler for GradSliceAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn grad_log2(f: Grad) -> Grad {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn grad_sinh(f: Grad) -> Grad {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn grad_cosh(f: Grad) -> Grad {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn grad_tanh(f: Grad) -> Grad {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn grad_sign(f: Grad) -> Grad {
+            f.sign()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn grad_cbrt(f: Grad) -> Grad {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, grad_cbrt);
+    }
     fn build_copy(&mut self, out_reg: u8, lhs_reg: u8) {
         dynasm!(self.0.ops
             ; vmovups Rx(reg(out_reg)), Rx(reg(lhs_reg))
@@ -346,6 +382,12 @@ impl Assembler for GradSliceAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, grad_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "sysv64" fn grad_pow(lhs: Grad, rhs: Grad) -> Grad {
+            lhs.pow(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, grad_pow);
+    }
 
     fn build_max(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
@@ -433,6 +475,25 @@ impl Assembler for GradSliceAssembler {
             ; vorpd Rx(reg(out_reg)), xmm1, xmm2
         );
     }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            ; vpxor xmm1, xmm1, xmm1
+            ; vcmpeqss xmm1, Rx(reg(cond_reg)), xmm1
+            ; vbroadcastss xmm1, xmm1
+            ; vpcmpeqd xmm2, xmm2, xmm2
+            ; vxorpd xmm2, xmm1, xmm2 // 1 ^ b = !b, so this inverts xmm1
+
+            ; vandpd xmm1, xmm1, Rx(reg(b_reg))
+            ; vandpd xmm2, xmm2, Rx(reg(a_reg))
+            ; vorpd Rx(reg(out_reg)), xmm1, xmm2
+        );
+    }
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; vcomiss Rx(reg(lhs_reg)), Rx(reg(rhs_reg))
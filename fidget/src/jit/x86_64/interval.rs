@@ -147,6 +147,42 @@ impl Assembler for IntervalAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn interval_log2(f: Interval) -> Interval {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn interval_sinh(f: Interval) -> Interval {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn interval_cosh(f: Interval) -> Interval {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn interval_tanh(f: Interval) -> Interval {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn interval_sign(f: Interval) -> Interval {
+            f.sign()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn interval_cbrt(f: Interval) -> Interval {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, interval_cbrt);
+    }
     fn build_copy(&mut self, out_reg: u8, lhs_reg: u8) {
         dynasm!(self.0.ops
             ; vmovq Rx(reg(out_reg)), Rx(reg(lhs_reg))
@@ -400,7 +436,7 @@ impl Assembler for IntervalAssembler {
     }
     fn build_max(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
-            ; mov ax, [rsi]
+            ; mov al, [rsi]
 
             // xmm1 = lhs.upper
             ; vpshufd xmm1, Rx(reg(lhs_reg)), 0b11111101u8 as i8
@@ -416,11 +452,11 @@ impl Assembler for IntervalAssembler {
 
             // Fallthrough: ambiguous case
             ; vmaxps Rx(reg(out_reg)), Rx(reg(lhs_reg)), Rx(reg(rhs_reg))
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             ; jmp >E
 
             ; N:
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             // Load NaN into out_reg
             ; vpcmpeqw Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(out_reg))
             ; vpslld Rx(reg(out_reg)), Rx(reg(out_reg)), 23
@@ -430,21 +466,19 @@ impl Assembler for IntervalAssembler {
             // lhs.upper < rhs.lower
             ; L:
             ; vmovq Rx(reg(out_reg)), Rx(reg(lhs_reg))
-            ; or ax, CHOICE_LEFT as i16
-            ; mov r8w, 1 // TODO: why can't we write 1 to [rdx] directly?
-            ; mov [rdx], r8w
+            ; or al, CHOICE_LEFT as i8
+            ; mov BYTE [rdx], 1
             ; jmp >E
 
             // rhs.upper < lhs.lower
             ; R:
             ; vmovq Rx(reg(out_reg)), Rx(reg(rhs_reg))
-            ; or ax, CHOICE_RIGHT as i16
-            ; mov r8w, 1
-            ; mov [rdx], r8w
+            ; or al, CHOICE_RIGHT as i8
+            ; mov BYTE [rdx], 1
             // Fallthrough
 
             ; E:
-            ; mov [rsi], ax
+            ; mov [rsi], al
             ; add rsi, 1
         );
         self.0.ops.commit_local().unwrap();
@@ -462,7 +496,7 @@ impl Assembler for IntervalAssembler {
             //      *choices++ |= CHOICE_BOTH
             //      out = fmin(lhs, rhs)
 
-            ; mov ax, [rsi]
+            ; mov al, [rsi]
 
             // TODO: use cmpltss to do both comparisons?
 
@@ -480,11 +514,11 @@ impl Assembler for IntervalAssembler {
 
             // Fallthrough: ambiguous case
             ; vminps Rx(reg(out_reg)), Rx(reg(lhs_reg)), Rx(reg(rhs_reg))
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             ; jmp >E
 
             ; N:
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             // Load NAN into out_reg
             ; vpcmpeqw Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(out_reg))
             ; vpslld Rx(reg(out_reg)), Rx(reg(out_reg)), 23
@@ -494,21 +528,19 @@ impl Assembler for IntervalAssembler {
             // lhs.upper < rhs.lower
             ; L:
             ; vmovq Rx(reg(out_reg)), Rx(reg(lhs_reg))
-            ; or ax, CHOICE_LEFT as i16
-            ; mov r8w, 1 // TODO: why can't we write 1 to [rdx] directly?
-            ; mov [rdx], r8w
+            ; or al, CHOICE_LEFT as i8
+            ; mov BYTE [rdx], 1
             ; jmp >E
 
             // rhs.upper < lhs.lower
             ; R:
             ; vmovq Rx(reg(out_reg)), Rx(reg(rhs_reg))
-            ; or ax, CHOICE_RIGHT as i16
-            ; mov r8w, 1
-            ; mov [rdx], r8w
+            ; or al, CHOICE_RIGHT as i8
+            ; mov BYTE [rdx], 1
             // Fallthrough
 
             ; E:
-            ; mov [rsi], ax
+            ; mov [rsi], al
             ; add rsi, 1
         );
         self.0.ops.commit_local().unwrap();
@@ -532,6 +564,15 @@ impl Assembler for IntervalAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, interval_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "sysv64" fn interval_pow(
+            lhs: Interval,
+            rhs: Interval,
+        ) -> Interval {
+            lhs.pow(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, interval_pow);
+    }
 
     fn build_not(&mut self, out_reg: u8, arg_reg: u8) {
         dynasm!(self.0.ops
@@ -571,7 +612,7 @@ impl Assembler for IntervalAssembler {
     fn build_and(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         assert_ne!(reg(lhs_reg), IMM_REG);
         dynasm!(self.0.ops
-            ; mov ax, [rsi] // load the choice flag
+            ; mov al, [rsi] // load the choice flag
 
             // check for NANs in RHS
             ; vcomiss Rx(reg(lhs_reg)), Rx(reg(lhs_reg))
@@ -582,7 +623,7 @@ impl Assembler for IntervalAssembler {
 
             // Load NAN into out_reg (TODO is this the easiest way?)
             ; N:
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             ; vpcmpeqw Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(out_reg))
             ; vpslld Rx(reg(out_reg)), Rx(reg(out_reg)), 23
             ; vpsrld Rx(reg(out_reg)), Rx(reg(out_reg)), 1
@@ -602,9 +643,8 @@ impl Assembler for IntervalAssembler {
 
             // !lhs.contains(0.0) -> RHS
             ; vmovq Rx(reg(out_reg)), Rx(reg(rhs_reg))
-            ; or ax, CHOICE_RIGHT as i16
-            ; mov r8w, 1 // TODO: why can't we write 1 to [rdx] directly?
-            ; mov [rdx], r8w
+            ; or al, CHOICE_RIGHT as i8
+            ; mov BYTE [rdx], 1
             ; jmp >E
 
             // xmm3 = (lower == 0) && (upper == 0)
@@ -618,21 +658,20 @@ impl Assembler for IntervalAssembler {
 
             // (lhs.lower == 0) && (lhs.upper == 0) -> LHS
             ; vmovq Rx(reg(out_reg)), Rx(reg(lhs_reg))
-            ; or ax, CHOICE_LEFT as i16
-            ; mov r8w, 1 // TODO: why can't we write 1 to [rdx] directly?
-            ; mov [rdx], r8w
+            ; or al, CHOICE_LEFT as i8
+            ; mov BYTE [rdx], 1
             ; jmp >E
 
             // Normal case, we have to combine the outputs
             ; C:
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             ; vpshufd xmm2, Rx(reg(rhs_reg)), 0b11111101u8 as i8 // lhs.upper
             ; vmaxss xmm2, xmm2, xmm1 // xmm1 = max(rhs.upper, 0.0)
             ; vminss xmm1, Rx(reg(rhs_reg)), xmm1 // xmm1 = min(rhs.lower, 0.0)
             ; vunpcklps Rx(reg(out_reg)), xmm1, xmm2
 
             ; E: // exit
-            ; mov [rsi], ax
+            ; mov [rsi], al
             ; add rsi, 1
         );
         self.0.ops.commit_local().unwrap();
@@ -640,7 +679,7 @@ impl Assembler for IntervalAssembler {
     fn build_or(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         assert_ne!(reg(lhs_reg), IMM_REG);
         dynasm!(self.0.ops
-            ; mov ax, [rsi] // load the choice flag
+            ; mov al, [rsi] // load the choice flag
 
             // check for NANs in RHS
             ; vcomiss Rx(reg(lhs_reg)), Rx(reg(lhs_reg))
@@ -651,7 +690,7 @@ impl Assembler for IntervalAssembler {
 
             // Load NAN into out_reg (TODO is this the easiest way?)
             ; N:
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             ; vpcmpeqw Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(out_reg))
             ; vpslld Rx(reg(out_reg)), Rx(reg(out_reg)), 23
             ; vpsrld Rx(reg(out_reg)), Rx(reg(out_reg)), 1
@@ -670,9 +709,8 @@ impl Assembler for IntervalAssembler {
 
             // !lhs.contains(0.0) -> LHS
             ; vmovq Rx(reg(out_reg)), Rx(reg(lhs_reg))
-            ; or ax, CHOICE_LEFT as i16
-            ; mov r8w, 1 // TODO: why can't we write 1 to [rdx] directly?
-            ; mov [rdx], r8w
+            ; or al, CHOICE_LEFT as i8
+            ; mov BYTE [rdx], 1
             ; jmp >E
 
             // xmm3 = (lower == 0) && (upper == 0)
@@ -686,14 +724,13 @@ impl Assembler for IntervalAssembler {
 
             // (lhs.lower == 0) && (lhs.upper == 0) -> RHS
             ; vmovq Rx(reg(out_reg)), Rx(reg(rhs_reg))
-            ; or ax, CHOICE_RIGHT as i16
-            ; mov r8w, 1 // TODO: why can't we write 1 to [rdx] directly?
-            ; mov [rdx], r8w
+            ; or al, CHOICE_RIGHT as i8
+            ; mov BYTE [rdx], 1
             ; jmp >E
 
             // Normal case, combining the outputs
             ; C:
-            ; or ax, CHOICE_BOTH as i16
+            ; or al, CHOICE_BOTH as i8
             ; vpshufd xmm2, Rx(reg(lhs_reg)), 0b11111101u8 as i8 // lhs.upper
             ; vpshufd xmm1, Rx(reg(rhs_reg)), 0b11111101u8 as i8 // rhs.upper
             ; vmaxss xmm1, xmm1, xmm2 // xmm1 = max(lhs.upper, rhs.upper)
@@ -701,9 +738,83 @@ impl Assembler for IntervalAssembler {
             ; vunpcklps Rx(reg(out_reg)), xmm2, xmm1
 
             ; E: // exit
-            ; mov [rsi], ax
+            ; mov [rsi], al
+            ; add rsi, 1
+        );
+    }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            ; mov al, [rsi] // load the choice flag
+
+            // check for NANs in the condition; NANs in the branches only
+            // matter if we can't pick one of them
+            ; vcomiss Rx(reg(cond_reg)), Rx(reg(cond_reg))
+            ; jnp >M
+            // otherwise, fallthrough into nan handling
+
+            ; N:
+            ; or al, CHOICE_BOTH as i8
+            ; vpcmpeqw Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(out_reg))
+            ; vpslld Rx(reg(out_reg)), Rx(reg(out_reg)), 23
+            ; vpsrld Rx(reg(out_reg)), Rx(reg(out_reg)), 1
+            ; jmp >E
+
+            ; M:
+            ; vpxor xmm1, xmm1, xmm1 // xmm1 = 0.0
+
+            // xmm2 = !cond.contains(0.0)
+            ; vcmpgtss xmm3, Rx(reg(cond_reg)), xmm1 // lower > 0.0
+            ; vpshufd xmm2, Rx(reg(cond_reg)), 0b11111101u8 as i8 // upper
+            ; vcmpltss xmm2, xmm2, xmm1 // upper < 0.0
+            ; vorps xmm2, xmm2, xmm3 // (lower > 0) || (upper < 0)
+            ; vcomiss xmm1, xmm2 // compare against 0.0
+            ; jnp >A // skip this branch (jnp because xmm2 will be NAN, all 1s)
+
+            // !cond.contains(0.0) -> A
+            ; vmovq Rx(reg(out_reg)), Rx(reg(a_reg))
+            ; or al, CHOICE_LEFT as i8
+            ; mov BYTE [rdx], 1
+            ; jmp >E
+
+            // xmm3 = (lower == 0) && (upper == 0)
+            ; A:
+            ; vcmpeqss xmm3, Rx(reg(cond_reg)), xmm1
+            ; vpshufd xmm2, Rx(reg(cond_reg)), 0b11111101u8 as i8 // upper
+            ; vcmpeqss xmm2, xmm2, xmm1
+            ; vandps xmm3, xmm2, xmm3
+            ; vcomiss xmm1, xmm3
+            ; jnp >C // skip this branch
+
+            // (cond.lower == 0) && (cond.upper == 0) -> B
+            ; vmovq Rx(reg(out_reg)), Rx(reg(b_reg))
+            ; or al, CHOICE_RIGHT as i8
+            ; mov BYTE [rdx], 1
+            ; jmp >E
+
+            // Normal case, combining the outputs (if neither has a NAN)
+            ; C:
+            ; vcomiss Rx(reg(a_reg)), Rx(reg(a_reg))
+            ; jp <N
+            ; vcomiss Rx(reg(b_reg)), Rx(reg(b_reg))
+            ; jp <N
+            ; or al, CHOICE_BOTH as i8
+            ; vpshufd xmm2, Rx(reg(a_reg)), 0b11111101u8 as i8 // a.upper
+            ; vpshufd xmm1, Rx(reg(b_reg)), 0b11111101u8 as i8 // b.upper
+            ; vmaxss xmm1, xmm1, xmm2 // xmm1 = max(a.upper, b.upper)
+            ; vminss xmm2, Rx(reg(a_reg)), Rx(reg(b_reg))
+            ; vunpcklps Rx(reg(out_reg)), xmm2, xmm1
+
+            ; E: // exit
+            ; mov [rsi], al
             ; add rsi, 1
         );
+        self.0.ops.commit_local().unwrap();
     }
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         // TODO: Godbolt uses unpcklps ?
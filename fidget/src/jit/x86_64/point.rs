@@ -151,6 +151,48 @@ impl Assembler for PointAssembler {
         }
         self.call_fn_unary(out_reg, lhs_reg, float_ln);
     }
+    fn build_log2(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_log2(f: f32) -> f32 {
+            f.log2()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_log2);
+    }
+    fn build_sinh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_sinh(f: f32) -> f32 {
+            f.sinh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sinh);
+    }
+    fn build_cosh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_cosh(f: f32) -> f32 {
+            f.cosh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cosh);
+    }
+    fn build_tanh(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_tanh(f: f32) -> f32 {
+            f.tanh()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_tanh);
+    }
+    fn build_sign(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_sign(f: f32) -> f32 {
+            if f > 0.0 {
+                1.0
+            } else if f < 0.0 {
+                -1.0
+            } else {
+                f
+            }
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_sign);
+    }
+    fn build_cbrt(&mut self, out_reg: u8, lhs_reg: u8) {
+        extern "sysv64" fn float_cbrt(f: f32) -> f32 {
+            f.cbrt()
+        }
+        self.call_fn_unary(out_reg, lhs_reg, float_cbrt);
+    }
     fn build_neg(&mut self, out_reg: u8, lhs_reg: u8) {
         // Flip the sign bit in the float
         dynasm!(self.0.ops
@@ -236,6 +278,12 @@ impl Assembler for PointAssembler {
         }
         self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_atan2);
     }
+    fn build_pow(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
+        extern "sysv64" fn float_pow(lhs: f32, rhs: f32) -> f32 {
+            lhs.powf(rhs)
+        }
+        self.call_fn_binary(out_reg, lhs_reg, rhs_reg, float_pow);
+    }
     fn build_max(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; vcomiss Rx(reg(lhs_reg)), Rx(reg(rhs_reg))
@@ -371,6 +419,35 @@ impl Assembler for PointAssembler {
         );
         self.0.ops.commit_local().unwrap()
     }
+    fn build_select(
+        &mut self,
+        out_reg: u8,
+        cond_reg: u8,
+        a_reg: u8,
+        b_reg: u8,
+    ) {
+        dynasm!(self.0.ops
+            ; vxorps xmm1, xmm1, xmm1
+            ; vucomiss Rx(reg(cond_reg)), xmm1
+            ; jp >A // NAN is non-zero, so it selects the first branch
+            ; je >B
+
+            ; A:
+            ; vmovss Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(a_reg))
+            ; or [rsi], CHOICE_LEFT as i8
+            ; or [rdx], 1
+            ; jmp >O
+
+            ; B:
+            ; vmovss Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(b_reg))
+            ; or [rsi], CHOICE_RIGHT as i8
+            ; or [rdx], 1
+            // fallthrough to out
+
+            ; O:
+        );
+        self.0.ops.commit_local().unwrap()
+    }
     fn build_compare(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; vcomiss Rx(reg(lhs_reg)), Rx(reg(rhs_reg))
@@ -133,7 +133,7 @@
 //!     &[2.0, 3.0, 4.0, 5.0], // Y
 //!     &[0.0, 0.0, 0.0, 0.0], // Z
 //! )?;
-//! assert_eq!(out, &[2.0, 4.0, 6.0, 8.0]);
+//! assert_eq!(&out[0], &[2.0, 4.0, 6.0, 8.0]);
 //! # Ok::<(), fidget::Error>(())
 //! ```
 //!
@@ -222,6 +222,7 @@
 //! };
 //! let shape = VmShape::from(tree);
 //! let out = cfg.run::<_, BitRenderMode>(shape).unwrap();
+//! # let out = fidget::stats::without_stats(out);
 //! let mut iter = out.iter();
 //! for y in 0..cfg.image_size.height() {
 //!     for x in 0..cfg.image_size.width() {
@@ -271,9 +272,12 @@ pub use crate::core::*;
 
 mod error;
 pub use error::Error;
+pub mod analysis;
+pub mod codegen;
 pub mod mesh;
 pub mod render;
 pub mod solver;
+pub mod stats;
 
 #[cfg(feature = "rhai")]
 pub mod rhai;
@@ -30,6 +30,7 @@
 //!     ..Default::default()
 //! };
 //! let o = Octree::build(&shape, settings);
+//! # let o = fidget::stats::without_stats(o);
 //! let mesh = o.walk_dual(settings);
 //!
 //! // Open a file to write, e.g.
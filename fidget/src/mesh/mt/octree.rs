@@ -13,6 +13,7 @@ use crate::{
     },
     render::RenderHints,
     shape::ShapeVars,
+    stats::StatsRecorder,
 };
 use std::sync::{mpsc::TryRecvError, Arc};
 
@@ -126,7 +127,7 @@ impl<F: Function + RenderHints> OctreeWorker<F> {
         eval: Arc<EvalGroup<F>>,
         vars: &ShapeVars<f32>,
         settings: MultithreadedSettings,
-    ) -> Octree {
+    ) -> (Octree, StatsRecorder) {
         let thread_count = settings.threads.get();
         let task_queues = QueuePool::new(settings.threads);
         let done_queues = std::iter::repeat_with(std::sync::mpsc::channel)
@@ -166,19 +167,26 @@ impl<F: Function + RenderHints> OctreeWorker<F> {
         };
         if let Some(c) = c {
             workers[0].octree.record(0, c.into());
-            workers.into_iter().next().unwrap().octree.into()
+            let mut octree = workers.into_iter().next().unwrap().octree;
+            let stats = std::mem::take(&mut octree.stats);
+            (octree.into(), stats)
         } else {
             let pool = &ThreadPool::new(settings.threads);
-            let out: Vec<Octree> = std::thread::scope(|s| {
-                let mut handles = vec![];
-                for w in workers {
-                    handles.push(
-                        s.spawn(move || w.run(vars, pool, settings.depth)),
-                    );
-                }
-                handles.into_iter().map(|h| h.join().unwrap()).collect()
-            });
-            Octree::merge(&out)
+            let (out, worker_stats): (Vec<Octree>, Vec<StatsRecorder>) =
+                std::thread::scope(|s| {
+                    let mut handles = vec![];
+                    for w in workers {
+                        handles.push(
+                            s.spawn(move || w.run(vars, pool, settings.depth)),
+                        );
+                    }
+                    handles.into_iter().map(|h| h.join().unwrap()).unzip()
+                });
+            let mut stats = StatsRecorder::default();
+            for s in worker_stats {
+                stats.merge(s);
+            }
+            (Octree::merge(&out), stats)
         }
     }
 
@@ -188,7 +196,7 @@ impl<F: Function + RenderHints> OctreeWorker<F> {
         vars: &ShapeVars<f32>,
         threads: &ThreadPool,
         max_depth: u8,
-    ) -> Octree {
+    ) -> (Octree, StatsRecorder) {
         let mut ctx = threads.start(self.thread_index);
         loop {
             // First, check to see if anyone has finished a task and sent us
@@ -267,7 +275,8 @@ impl<F: Function + RenderHints> OctreeWorker<F> {
         // At this point, the `done` queue should be flushed
         assert_eq!(self.done.try_recv().err(), Some(TryRecvError::Empty));
 
-        self.octree.into()
+        let stats = std::mem::take(&mut self.octree.stats);
+        (self.octree.into(), stats)
     }
 
     fn reclaim(&mut self, task: Task<F>) {
@@ -14,6 +14,7 @@ use crate::{
     eval::{BulkEvaluator, Function, TracingEvaluator},
     render::RenderHints,
     shape::{Shape, ShapeBulkEval, ShapeTape, ShapeTracingEval, ShapeVars},
+    stats::{OpCounts, StatsRecorder, WithStats},
     types::Grad,
 };
 use std::{num::NonZeroUsize, sync::Arc, sync::OnceLock};
@@ -37,6 +38,9 @@ pub struct EvalGroup<F: Function> {
         OnceLock<ShapeTape<<F::FloatSliceEval as BulkEvaluator>::Tape>>,
     pub grad_slice:
         OnceLock<ShapeTape<<F::GradSliceEval as BulkEvaluator>::Tape>>,
+
+    /// Opcode histogram, used when recording statistics
+    pub(crate) ops: OpCounts,
 }
 
 impl<F: Function> EvalGroup<F> {
@@ -46,6 +50,7 @@ impl<F: Function> EvalGroup<F> {
             interval: OnceLock::new(),
             float_slice: OnceLock::new(),
             grad_slice: OnceLock::new(),
+            ops: OpCounts::default(),
         }
     }
     fn interval_tape(
@@ -96,18 +101,28 @@ impl Octree {
     /// Builds an octree to the given depth, with user-provided variables
     ///
     /// The shape is evaluated on the region specified by `settings.bounds`.
+    /// The return value is the same as for [`build`](Octree::build).
     pub fn build_with_vars<F: Function + RenderHints + Clone>(
         shape: &Shape<F>,
         vars: &ShapeVars<f32>,
         settings: Settings,
-    ) -> Self {
+    ) -> WithStats<Self> {
+        let (out, stats) = Self::build_transformed(shape, vars, settings);
+        stats.attach(out)
+    }
+
+    fn build_transformed<F: Function + RenderHints + Clone>(
+        shape: &Shape<F>,
+        vars: &ShapeVars<f32>,
+        settings: Settings,
+    ) -> (Self, StatsRecorder) {
         // Transform the shape given our world-to-model matrix
         let t = settings.view.world_to_model();
         if t == nalgebra::Matrix4::identity() {
             Self::build_inner(shape, vars, settings)
         } else {
             let shape = shape.clone().apply_transform(t);
-            let mut out = Self::build_inner(&shape, vars, settings);
+            let (mut out, stats) = Self::build_inner(&shape, vars, settings);
 
             // Apply the transform from [-1, +1] back to model space
             for v in &mut out.verts {
@@ -115,7 +130,7 @@ impl Octree {
                 let q = t.transform_point(&p);
                 v.pos = q.coords;
             }
-            out
+            (out, stats)
         }
     }
 
@@ -126,10 +141,12 @@ impl Octree {
     /// this function will return an error).
     ///
     /// The shape is evaluated on the region specified by `settings.bounds`.
+    /// With the `eval-stats` feature, the octree is returned with evaluation
+    /// statistics (see [`WithStats`]).
     pub fn build<F: Function + RenderHints + Clone>(
         shape: &Shape<F>,
         settings: Settings,
-    ) -> Self {
+    ) -> WithStats<Self> {
         Self::build_with_vars(shape, &ShapeVars::new(), settings)
     }
 
@@ -137,14 +154,15 @@ impl Octree {
         shape: &Shape<F>,
         vars: &ShapeVars<f32>,
         settings: Settings,
-    ) -> Self {
+    ) -> (Self, StatsRecorder) {
         let eval = Arc::new(EvalGroup::new(shape.clone()));
 
         match settings.threads {
             ThreadCount::One => {
                 let mut out = OctreeBuilder::new();
                 out.recurse(&eval, vars, CellIndex::default(), settings.depth);
-                out.into()
+                let stats = std::mem::take(&mut out.stats);
+                (out.into(), stats)
             }
 
             #[cfg(not(target_arch = "wasm32"))]
@@ -291,6 +309,9 @@ pub(crate) struct OctreeBuilder<F: Function + RenderHints> {
     pub tape_storage: Vec<F::TapeStorage>,
     pub shape_storage: Vec<F::Storage>,
     workspace: F::Workspace,
+
+    /// Statistics recorded while building
+    pub stats: StatsRecorder,
 }
 
 impl<F: Function + RenderHints> Default for OctreeBuilder<F> {
@@ -342,6 +363,7 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
             tape_storage: vec![],
             shape_storage: vec![],
             workspace: Default::default(),
+            stats: Default::default(),
         }
     }
 
@@ -373,6 +395,19 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
         vars: &ShapeVars<f32>,
         cell: CellIndex,
         max_depth: u8,
+    ) -> CellResult<F> {
+        let timer = self.stats.start();
+        let out = self.eval_cell_inner(eval, vars, cell, max_depth);
+        self.stats.tile(cell.depth, timer);
+        out
+    }
+
+    fn eval_cell_inner(
+        &mut self,
+        eval: &Arc<EvalGroup<F>>,
+        vars: &ShapeVars<f32>,
+        cell: CellIndex,
+        max_depth: u8,
     ) -> CellResult<F> {
         let (i, r) = self
             .eval_interval
@@ -384,6 +419,7 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
                 vars,
             )
             .unwrap();
+        self.stats.interval(&eval.ops, eval.shape.inner(), r);
         if i.upper() < 0.0 {
             CellResult::Done(Cell::Full)
         } else if i.lower() > 0.0 {
@@ -392,9 +428,14 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
             let sub_tape = if F::simplify_tree_during_meshing(cell.depth) {
                 let s = self.shape_storage.pop().unwrap_or_default();
                 r.map(|r| {
-                    Arc::new(EvalGroup::new(
-                        eval.shape.simplify(r, s, &mut self.workspace).unwrap(),
-                    ))
+                    let next =
+                        eval.shape.simplify(r, s, &mut self.workspace).unwrap();
+                    self.stats.simplify(
+                        cell.depth,
+                        eval.shape.size(),
+                        next.size(),
+                    );
+                    Arc::new(EvalGroup::new(next))
                 })
             } else {
                 None
@@ -518,7 +559,9 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
                 &zs,
                 vars,
             )
-            .unwrap();
+            .unwrap()
+            .borrow(0);
+        self.stats.float(&eval.ops, eval.shape.inner(), xs.len());
         debug_assert_eq!(out.len(), 8);
 
         // Build a mask of active corners, which determines cell
@@ -625,7 +668,9 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
                     zs,
                     vars,
                 )
-                .unwrap();
+                .unwrap()
+                .borrow(0);
+            self.stats.float(&eval.ops, eval.shape.inner(), xs.len());
 
             // Update start and end positions based on evaluation
             for ((start, end), search) in start
@@ -697,7 +742,9 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
                 zs,
                 vars,
             )
-            .unwrap();
+            .unwrap()
+            .borrow(0);
+        self.stats.grad(&eval.ops, eval.shape.inner(), xs.len());
 
         let mut verts: arrayvec::ArrayVec<_, 4> = arrayvec::ArrayVec::new();
         let mut i = 0;
@@ -982,6 +1029,7 @@ impl<F: Function + RenderHints> OctreeBuilder<F> {
             tape_storage: vec![],
             shape_storage: vec![],
             workspace: Default::default(),
+            stats: Default::default(),
         }
     }
 
@@ -1220,6 +1268,7 @@ mod test {
         mesh::types::{Edge, X, Y, Z},
         render::View3,
         shape::EzShape,
+        stats::without_stats,
         var::Var,
         vm::{VmFunction, VmShape},
     };
@@ -1266,7 +1315,8 @@ mod test {
         let shape = VmShape::from(cube([-f, f], [-f, 0.3], [-f, 0.6]));
         // This should be a cube with a single edge running through the root
         // node of the octree, with an edge vertex at [0, 0.3, 0.6]
-        let octree = Octree::build(&shape, depth0_single_thread());
+        let octree =
+            without_stats(Octree::build(&shape, depth0_single_thread()));
         assert_eq!(octree.verts.len(), 5);
         let v = octree.verts[0].pos;
         let expected = nalgebra::Vector3::new(0.0, 0.3, 0.6);
@@ -1316,7 +1366,8 @@ mod test {
 
         // If we only build a depth-0 octree, then it's a leaf without any
         // vertices (since all the corners are empty)
-        let octree = Octree::build(&shape, depth0_single_thread());
+        let octree =
+            without_stats(Octree::build(&shape, depth0_single_thread()));
         assert_eq!(octree.cells.len(), 8); // we always build at least 8 cells
         assert_eq!(Cell::Empty, octree.cells[0].into(),);
         assert_eq!(octree.verts.len(), 0);
@@ -1326,7 +1377,8 @@ mod test {
         assert!(empty_mesh.triangles.is_empty());
 
         // Now, at depth-1, each cell should be a Leaf with one vertex
-        let octree = Octree::build(&shape, depth1_single_thread());
+        let octree =
+            without_stats(Octree::build(&shape, depth1_single_thread()));
         assert_eq!(octree.cells.len(), 16); // we always build at least 8 cells
         assert_eq!(
             Cell::Branch {
@@ -1357,7 +1409,8 @@ mod test {
     fn test_sphere_verts() {
         let shape = VmShape::from(sphere([0.0; 3], 0.2));
 
-        let octree = Octree::build(&shape, depth1_single_thread());
+        let octree =
+            without_stats(Octree::build(&shape, depth1_single_thread()));
         let sphere_mesh = octree.walk_dual(depth1_single_thread());
 
         let mut edge_count = 0;
@@ -1401,7 +1454,7 @@ mod test {
                 threads,
                 ..Default::default()
             };
-            let octree = Octree::build(&shape, settings);
+            let octree = without_stats(Octree::build(&shape, settings));
             let sphere_mesh = octree.walk_dual(settings);
 
             if let Err(e) = check_for_vertex_dupes(&sphere_mesh) {
@@ -1417,7 +1470,8 @@ mod test {
     fn test_cube_verts() {
         let shape = VmShape::from(cube([-0.1, 0.6], [-0.2, 0.75], [-0.3, 0.4]));
 
-        let octree = Octree::build(&shape, depth1_single_thread());
+        let octree =
+            without_stats(Octree::build(&shape, depth1_single_thread()));
         let mesh = octree.walk_dual(depth1_single_thread());
         const EPSILON: f32 = 2.0 / u16::MAX as f32;
         assert!(!mesh.vertices.is_empty());
@@ -1466,7 +1520,10 @@ mod test {
                     let (x, y, z) = Tree::axes();
                     let f = x * dx + y * dy + z + offset;
                     let shape = VmShape::from(f);
-                    let octree = Octree::build(&shape, depth0_single_thread());
+                    let octree = without_stats(Octree::build(
+                        &shape,
+                        depth0_single_thread(),
+                    ));
 
                     assert_eq!(octree.cells.len(), 8);
                     let pos = octree.verts[0].pos;
@@ -1510,7 +1567,8 @@ mod test {
                 eval.eval(&tape, corner.x, corner.y, corner.z).unwrap();
             assert!(v < 0.0, "bad corner value: {v}");
 
-            let octree = Octree::build(&shape, depth0_single_thread());
+            let octree =
+                without_stats(Octree::build(&shape, depth0_single_thread()));
             assert_eq!(octree.cells.len(), 8);
             assert_eq!(octree.verts.len(), 4);
 
@@ -1547,7 +1605,7 @@ mod test {
             threads,
             ..Default::default()
         };
-        let octree = Octree::build(&shape, settings);
+        let octree = without_stats(Octree::build(&shape, settings));
 
         let mesh = octree.walk_dual(settings);
         if mask != 0 && mask != 255 {
@@ -1629,7 +1687,7 @@ mod test {
                 threads,
                 ..Default::default()
             };
-            let octree = Octree::build(&shape, settings);
+            let octree = without_stats(Octree::build(&shape, settings));
             assert_eq!(
                 octree.cells[0],
                 Cell::Empty.into(),
@@ -1652,7 +1710,7 @@ mod test {
                 threads,
                 ..Default::default()
             };
-            let octree = Octree::build(&tape, settings);
+            let octree = without_stats(Octree::build(&tape, settings));
             let mesh = octree.walk_dual(settings);
             // Note: the model has duplicate vertices!
             if let Err(e) = check_for_edge_matching(&mesh) {
@@ -1675,7 +1733,7 @@ mod test {
                 threads,
                 ..Default::default()
             };
-            let octree = Octree::build(&tape, settings);
+            let octree = without_stats(Octree::build(&tape, settings));
             let mesh = octree.walk_dual(settings);
             for v in mesh.vertices.iter() {
                 assert!(
@@ -1705,7 +1763,7 @@ mod test {
                 threads,
                 ..Default::default()
             };
-            let octree = Octree::build(&tape, settings);
+            let octree = without_stats(Octree::build(&tape, settings));
             let mesh = octree.walk_dual(settings);
             for v in mesh.vertices.iter() {
                 assert!(
@@ -1808,7 +1866,8 @@ mod test {
             ..Default::default()
         };
 
-        let octree = Octree::build(&shape, settings).walk_dual(settings);
+        let octree =
+            without_stats(Octree::build(&shape, settings)).walk_dual(settings);
         for v in octree.vertices.iter() {
             let n = v.norm();
             assert!(n > 0.7 && n < 0.8, "invalid vertex at {v:?}: {n}");
@@ -1826,7 +1885,8 @@ mod test {
             view: View3::from_center_and_scale(center, 0.5),
         };
 
-        let octree = Octree::build(&shape, settings).walk_dual(settings);
+        let octree =
+            without_stats(Octree::build(&shape, settings)).walk_dual(settings);
         for v in octree.vertices.iter() {
             let n = (v - center).norm();
             assert!(n > 0.2 && n < 0.3, "invalid vertex at {v:?}: {n}");
@@ -1853,8 +1913,10 @@ mod test {
             for r in [0.5, 0.75] {
                 let mut vars = ShapeVars::new();
                 vars.insert(v.index().unwrap(), r);
-                let octree = Octree::build_with_vars(&shape, &vars, settings)
-                    .walk_dual(settings);
+                let octree = without_stats(Octree::build_with_vars(
+                    &shape, &vars, settings,
+                ))
+                .walk_dual(settings);
                 for v in octree.vertices.iter() {
                     let n = v.norm();
                     assert!(
@@ -5,6 +5,7 @@ use crate::{
         TileSizes, View2, View3, VoxelSize,
     },
     shape::{Shape, ShapeVars},
+    stats::WithStats,
 };
 use nalgebra::{Const, Matrix3, Matrix4, OPoint, Point2, Vector2};
 use std::sync::{
@@ -126,20 +127,27 @@ impl RenderConfig for ImageRenderConfig<'_> {
 
 impl ImageRenderConfig<'_> {
     /// Render a shape in 2D using this configuration
+    ///
+    /// Returns `None` if rendering was cancelled.  With the `eval-stats`
+    /// feature, the image is returned with evaluation statistics (see
+    /// [`WithStats`]).
     pub fn run<F: Function, M: RenderMode + Sync>(
         &self,
         shape: Shape<F>,
-    ) -> Option<Image<<M as RenderMode>::Output>> {
+    ) -> Option<WithStats<Image<<M as RenderMode>::Output>>> {
         self.run_with_vars::<F, M>(shape, &ShapeVars::new())
     }
 
     /// Render a shape in 2D using this configuration and variables
+    ///
+    /// The return value is the same as for [`run`](Self::run).
     pub fn run_with_vars<F: Function, M: RenderMode + Sync>(
         &self,
         shape: Shape<F>,
         vars: &ShapeVars<f32>,
-    ) -> Option<Image<<M as RenderMode>::Output>> {
+    ) -> Option<WithStats<Image<<M as RenderMode>::Output>>> {
         crate::render::render2d::<F, M>(shape, vars, self)
+            .map(|(i, stats)| stats.attach(i))
     }
 
     /// Returns the combined screen-to-model transform matrix
@@ -212,21 +220,25 @@ impl VoxelRenderConfig<'_> {
     /// Render a shape in 3D using this configuration
     ///
     /// Returns a tuple of `(heightmap, RGB image)` or `None` if rendering was
-    /// cancelled.
+    /// cancelled.  With the `eval-stats` feature, the tuple is returned with
+    /// evaluation statistics (see [`WithStats`]).
     pub fn run<F: Function>(
         &self,
         shape: Shape<F>,
-    ) -> Option<(DepthImage, NormalImage)> {
+    ) -> Option<WithStats<(DepthImage, NormalImage)>> {
         self.run_with_vars::<F>(shape, &ShapeVars::new())
     }
 
     /// Render a shape in 3D using this configuration and variables
+    ///
+    /// The return value is the same as for [`run`](Self::run).
     pub fn run_with_vars<F: Function>(
         &self,
         shape: Shape<F>,
         vars: &ShapeVars<f32>,
-    ) -> Option<(DepthImage, NormalImage)> {
+    ) -> Option<WithStats<(DepthImage, NormalImage)>> {
         crate::render::render3d::<F>(shape, vars, self)
+            .map(|(depth, norm, stats)| stats.attach((depth, norm)))
     }
 
     /// Returns the combined screen-to-model transform matrix
@@ -3,8 +3,11 @@
 //! To render something, build a configuration object then call its `run`
 //! function, e.g. [`ImageRenderConfig::run`] and [`VoxelRenderConfig::run`].
 use crate::{
-    eval::{BulkEvaluator, Function, Trace, TracingEvaluator},
+    eval::{
+        BulkEvaluator, Function, MultiTracingEvaluator, Trace, TracingEvaluator,
+    },
     shape::{Shape, ShapeTape, ShapeVars},
+    stats::{OpCounts, StatsRecorder},
     Error,
 };
 use nalgebra::Point2;
@@ -28,7 +31,7 @@ use render2d::render as render2d;
 use render3d::render as render3d;
 
 pub use render2d::{
-    BitRenderMode, DebugRenderMode, RenderMode, SdfPixelRenderMode,
+    BitRenderMode, DebugPixel, DebugRenderMode, RenderMode, SdfPixelRenderMode,
     SdfRenderMode,
 };
 
@@ -41,9 +44,15 @@ pub struct RenderHandle<F: Function> {
     shape: Shape<F>,
 
     i_tape: Option<ShapeTape<<F::IntervalEval as TracingEvaluator>::Tape>>,
+    is_tape: Option<
+        ShapeTape<<F::IntervalSliceEval as MultiTracingEvaluator>::Tape>,
+    >,
     f_tape: Option<ShapeTape<<F::FloatSliceEval as BulkEvaluator>::Tape>>,
     g_tape: Option<ShapeTape<<F::GradSliceEval as BulkEvaluator>::Tape>>,
 
+    /// Opcode histogram, used when recording statistics
+    pub(crate) ops: OpCounts,
+
     next: Option<(F::Trace, Box<Self>)>,
 }
 
@@ -52,8 +61,10 @@ impl<F: Function> Clone for RenderHandle<F> {
         Self {
             shape: self.shape.clone(),
             i_tape: self.i_tape.clone(),
+            is_tape: self.is_tape.clone(),
             f_tape: self.f_tape.clone(),
             g_tape: self.g_tape.clone(),
+            ops: self.ops.clone(),
             next: None,
         }
     }
@@ -67,8 +78,10 @@ impl<F: Function> RenderHandle<F> {
         Self {
             shape,
             i_tape: None,
+            is_tape: None,
             f_tape: None,
             g_tape: None,
+            ops: OpCounts::default(),
             next: None,
         }
     }
@@ -83,6 +96,17 @@ impl<F: Function> RenderHandle<F> {
         })
     }
 
+    /// Returns a tape for interval slice evaluation
+    pub fn is_tape(
+        &mut self,
+        storage: &mut Vec<F::TapeStorage>,
+    ) -> &ShapeTape<<F::IntervalSliceEval as MultiTracingEvaluator>::Tape> {
+        self.is_tape.get_or_insert_with(|| {
+            self.shape
+                .interval_slice_tape(storage.pop().unwrap_or_default())
+        })
+    }
+
     /// Returns a tape for bulk float evaluation
     pub fn f_tape(
         &mut self,
@@ -153,8 +177,10 @@ impl<F: Function> RenderHandle<F> {
                     Box::new(RenderHandle {
                         shape: next,
                         i_tape: None,
+                        is_tape: None,
                         f_tape: None,
                         g_tape: None,
+                        ops: OpCounts::default(),
                         next: None,
                     }),
                 ));
@@ -179,6 +205,9 @@ impl<F: Function> RenderHandle<F> {
         if let Some(i_tape) = self.i_tape.take() {
             tape_storage.extend(i_tape.recycle());
         }
+        if let Some(is_tape) = self.is_tape.take() {
+            tape_storage.extend(is_tape.recycle());
+        }
         if let Some(g_tape) = self.g_tape.take() {
             tape_storage.extend(g_tape.recycle());
         }
@@ -279,17 +308,21 @@ pub trait RenderHints {
     }
 }
 
+/// Rendered tiles, each paired with its worker-dependent output
+type RenderedTiles<T> = Vec<(Tile<2>, T)>;
+
 /// Grand unified render function
 ///
 /// This handles tile generation and building + calling render workers in
 /// parallel (using [`rayon`] for parallelism at the tile level).
 ///
-/// It returns a set of output tiles, or `None` if rendering has been cancelled
+/// It returns a set of output tiles and accumulated statistics, or `None` if
+/// rendering has been cancelled
 pub(crate) fn render_tiles<'a, F: Function, W: RenderWorker<'a, F>>(
     shape: Shape<F>,
     vars: &ShapeVars<f32>,
     config: &'a W::Config,
-) -> Option<Vec<(Tile<2>, W::Output)>>
+) -> Option<(RenderedTiles<W::Output>, StatsRecorder)>
 where
     W::Config: Send + Sync,
 {
@@ -312,7 +345,10 @@ where
 
     let mut rh = RenderHandle::new(shape);
 
-    let _ = rh.i_tape(&mut vec![]); // populate i_tape before cloning
+    // Populate root interval tapes before cloning, so that they're shared
+    // between workers (the 2D and 3D renderers each use one of them)
+    let _ = rh.i_tape(&mut vec![]);
+    let _ = rh.is_tape(&mut vec![]);
     let init = || {
         let rh = rh.clone();
         let worker = W::new(config);
@@ -329,7 +365,7 @@ where
                         Err(())
                     } else {
                         let pixels = worker.render_tile(&mut rh, vars, tile);
-                        Ok((tile, pixels))
+                        Ok((tile, pixels, worker.take_stats()))
                     }
                 })
                 .collect::<Result<Vec<_>, ()>>()
@@ -345,7 +381,7 @@ where
                             Err(())
                         } else {
                             let pixels = w.render_tile(rh, vars, tile);
-                            Ok((tile, pixels))
+                            Ok((tile, pixels, w.take_stats()))
                         }
                     })
                     .collect::<Result<Vec<_>, ()>>()
@@ -358,7 +394,15 @@ where
         }
     };
 
-    out
+    let mut stats = StatsRecorder::default();
+    let out = out?
+        .into_iter()
+        .map(|(tile, pixels, s)| {
+            stats.merge(s);
+            (tile, pixels)
+        })
+        .collect();
+    Some((out, stats))
 }
 
 /// Helper trait for tiled rendering configuration
@@ -387,6 +431,9 @@ pub(crate) trait RenderWorker<'a, F: Function> {
         vars: &ShapeVars<f32>,
         tile: config::Tile<2>,
     ) -> Self::Output;
+
+    /// Takes statistics recorded since the previous call
+    fn take_stats(&mut self) -> StatsRecorder;
 }
 
 /// Generic image type
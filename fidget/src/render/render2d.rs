@@ -1,12 +1,13 @@
 //! 2D bitmap rendering / rasterization
 use super::RenderHandle;
 use crate::{
-    eval::Function,
+    eval::{Function, Trace},
     render::{
         config::{ImageRenderConfig, Tile},
         Image, RenderWorker, TileSizes,
     },
-    shape::{Shape, ShapeBulkEval, ShapeTracingEval, ShapeVars},
+    shape::{Shape, ShapeBulkEval, ShapeMultiTracingEval, ShapeVars},
+    stats::StatsRecorder,
     types::Interval,
 };
 use nalgebra::{Point2, Vector2};
@@ -65,19 +66,31 @@ impl RenderMode for DebugRenderMode {
     }
 }
 
+/// Pixel type for [`DebugRenderMode`], recording how each pixel was classified
 #[derive(Copy, Clone, Debug, Default)]
 pub enum DebugPixel {
+    /// Empty, proven by interval evaluation of a root tile
     EmptyTile,
+    /// Filled, proven by interval evaluation of a root tile
     FilledTile,
+    /// Empty, proven by interval evaluation of a subtile
     EmptySubtile,
+    /// Filled, proven by interval evaluation of a subtile
     FilledSubtile,
+    /// Empty, found by evaluating the pixel itself
     Empty,
+    /// Filled, found by evaluating the pixel itself
     Filled,
+    /// Not yet assigned
     #[default]
     Invalid,
 }
 
 impl DebugPixel {
+    /// Returns an RGBA color for the pixel
+    ///
+    /// # Panics
+    /// If the pixel is [`DebugPixel::Invalid`]
     #[inline]
     pub fn as_debug_color(&self) -> [u8; 4] {
         match self {
@@ -91,6 +104,10 @@ impl DebugPixel {
         }
     }
 
+    /// Checks whether the pixel is filled
+    ///
+    /// # Panics
+    /// If the pixel is [`DebugPixel::Invalid`]
     #[inline]
     pub fn is_filled(&self) -> bool {
         match self {
@@ -209,7 +226,13 @@ struct Worker<'a, F: Function, M: RenderMode> {
     scratch: Scratch,
 
     eval_float_slice: ShapeBulkEval<F::FloatSliceEval>,
-    eval_interval: ShapeTracingEval<F::IntervalEval>,
+    eval_interval: ShapeMultiTracingEval<F::IntervalSliceEval>,
+
+    /// Interval bounds of the tiles in a batch, in screen coordinates
+    bounds: [Vec<Interval>; 3],
+
+    /// Interval results and traces of the most recent batch at each depth
+    results: Vec<Vec<(Interval, Option<F::Trace>)>>,
 
     /// Spare tape storage for reuse
     tape_storage: Vec<F::TapeStorage>,
@@ -220,6 +243,9 @@ struct Worker<'a, F: Function, M: RenderMode> {
     /// Workspace for shape simplification
     workspace: F::Workspace,
 
+    /// Statistics recorded while rendering
+    stats: StatsRecorder,
+
     /// Tile being rendered
     ///
     /// This is a root tile, i.e. width and height of `config.tile_sizes[0]`
@@ -236,9 +262,12 @@ impl<'a, F: Function, M: RenderMode> RenderWorker<'a, F> for Worker<'a, F, M> {
             tile_sizes: &cfg.tile_sizes,
             eval_float_slice: Default::default(),
             eval_interval: Default::default(),
+            bounds: Default::default(),
+            results: vec![vec![]; cfg.tile_sizes.len()],
             tape_storage: vec![],
             shape_storage: vec![],
             workspace: Default::default(),
+            stats: Default::default(),
         }
     }
 
@@ -249,33 +278,116 @@ impl<'a, F: Function, M: RenderMode> RenderWorker<'a, F> for Worker<'a, F, M> {
         tile: super::config::Tile<2>,
     ) -> Self::Output {
         self.image = Image::new(self.tile_sizes[0], self.tile_sizes[0]);
-        self.render_tile_recurse(shape, vars, 0, tile);
+        self.eval_tiles(shape, vars, 0, tile, 1);
+        self.render_tiles(shape, vars, 0, tile, 1);
         std::mem::take(&mut self.image)
     }
+
+    fn take_stats(&mut self) -> StatsRecorder {
+        std::mem::take(&mut self.stats)
+    }
 }
 
 impl<F: Function, M: RenderMode> Worker<'_, F, M> {
-    fn render_tile_recurse(
+    /// Returns the tile at index `i` within an `n × n` grid of tiles
+    fn grid_tile(
+        &self,
+        depth: usize,
+        corner: Tile<2>,
+        n: usize,
+        i: usize,
+    ) -> Tile<2> {
+        let tile_size = self.tile_sizes[depth];
+        Tile::new(corner.corner + Vector2::new(i % n, i / n) * tile_size)
+    }
+
+    /// Evaluates an `n × n` grid of tiles, starting at `corner`
+    ///
+    /// Every tile is evaluated in a single call to the interval slice
+    /// evaluator; results and traces are stored in `self.results[depth]`.
+    fn eval_tiles(
         &mut self,
         shape: &mut RenderHandle<F>,
         vars: &ShapeVars<f32>,
         depth: usize,
-        tile: Tile<2>,
+        corner: Tile<2>,
+        n: usize,
     ) {
         let tile_size = self.tile_sizes[depth];
 
-        // Find the interval bounds of the region, in screen coordinates
-        let base = Point2::from(tile.corner).cast::<f32>();
-        let x = Interval::new(base.x, base.x + tile_size as f32);
-        let y = Interval::new(base.y, base.y + tile_size as f32);
-        let z = Interval::new(0.0, 0.0);
+        // Find the interval bounds of each tile, in screen coordinates
+        for b in &mut self.bounds {
+            b.clear();
+        }
+        for i in 0..n * n {
+            let tile = self.grid_tile(depth, corner, n, i);
+            let base = Point2::from(tile.corner).cast::<f32>();
+            let [x, y, z] = &mut self.bounds;
+            x.push(Interval::new(base.x, base.x + tile_size as f32));
+            y.push(Interval::new(base.y, base.y + tile_size as f32));
+            z.push(Interval::new(0.0, 0.0));
+        }
 
         // The shape applies the screen-to-model transform
-        let (i, simplify) = self
+        let [x, y, z] = &self.bounds;
+        let (out, traces) = self
             .eval_interval
-            .eval_v(shape.i_tape(&mut self.tape_storage), x, y, z, vars)
+            .eval_v(shape.is_tape(&mut self.tape_storage), x, y, z, vars)
             .unwrap();
 
+        // Copy results out of the evaluator, reusing trace allocations
+        let results = &mut self.results[depth];
+        results.resize_with(n * n, || (Interval::from(0.0), None));
+        for (r, (i, t)) in
+            results.iter_mut().zip(out[0].iter().zip(traces.iter()))
+        {
+            self.stats.interval(&shape.ops, shape.shape.inner(), t);
+            r.0 = *i;
+            match (&mut r.1, t) {
+                (Some(prev), Some(t)) => prev.copy_from(t),
+                (prev, t) => *prev = t.cloned(),
+            }
+        }
+    }
+
+    /// Renders an `n × n` grid of tiles, which was evaluated by
+    /// [`eval_tiles`](Self::eval_tiles)
+    fn render_tiles(
+        &mut self,
+        shape: &mut RenderHandle<F>,
+        vars: &ShapeVars<f32>,
+        depth: usize,
+        corner: Tile<2>,
+        n: usize,
+    ) {
+        // Take the results, since deeper tiles will use their own buffers
+        let results = std::mem::take(&mut self.results[depth]);
+        for (i, (v, trace)) in results.iter().enumerate() {
+            let tile = self.grid_tile(depth, corner, n, i);
+            self.render_tile_recurse(
+                shape,
+                vars,
+                depth,
+                tile,
+                *v,
+                trace.as_ref(),
+            );
+        }
+        self.results[depth] = results;
+    }
+
+    fn render_tile_recurse(
+        &mut self,
+        shape: &mut RenderHandle<F>,
+        vars: &ShapeVars<f32>,
+        depth: usize,
+        tile: Tile<2>,
+        i: Interval,
+        simplify: Option<&F::Trace>,
+    ) {
+        let timer = self.stats.start();
+        let tile_size = self.tile_sizes[depth];
+
         match M::interval(i, depth) {
             IntervalAction::Fill(fill) => {
                 for y in 0..tile_size {
@@ -284,16 +396,22 @@ impl<F: Function, M: RenderMode> Worker<'_, F, M> {
                         .pixel_offset(tile.add(Vector2::new(0, y)));
                     self.image[start..][..tile_size].fill(fill);
                 }
+                self.stats.tile(depth, timer);
                 return;
             }
             IntervalAction::Interpolate => {
+                let base = Point2::from(tile.corner).cast::<f32>();
+                let x = Interval::new(base.x, base.x + tile_size as f32);
+                let y = Interval::new(base.y, base.y + tile_size as f32);
                 let xs = [x.lower(), x.lower(), x.upper(), x.upper()];
                 let ys = [y.lower(), y.upper(), y.lower(), y.upper()];
                 let zs = [0.0; 4];
                 let vs = self
                     .eval_float_slice
                     .eval(shape.f_tape(&mut self.tape_storage), &xs, &ys, &zs)
-                    .unwrap();
+                    .unwrap()
+                    .borrow(0);
+                self.stats.float(&shape.ops, shape.shape.inner(), xs.len());
 
                 // Bilinear interpolation on a per-pixel basis
                 for y in 0..tile_size {
@@ -315,38 +433,35 @@ impl<F: Function, M: RenderMode> Worker<'_, F, M> {
                         i += 1;
                     }
                 }
+                self.stats.tile(depth, timer);
                 return;
             }
             IntervalAction::Recurse => (), // keep going
         }
 
-        let sub_tape = if let Some(trace) = simplify.as_ref() {
-            shape.simplify(
+        let sub_tape = if let Some(trace) = simplify {
+            let before = shape.shape.size();
+            let next = shape.simplify(
                 trace,
                 &mut self.workspace,
                 &mut self.shape_storage,
                 &mut self.tape_storage,
-            )
+            );
+            self.stats.simplify(depth, before, next.shape.size());
+            next
         } else {
             shape
         };
 
         if let Some(next_tile_size) = self.tile_sizes.get(depth + 1) {
+            // Evaluating the subtiles is counted as part of this tile
             let n = tile_size / next_tile_size;
-            for j in 0..n {
-                for i in 0..n {
-                    self.render_tile_recurse(
-                        sub_tape,
-                        vars,
-                        depth + 1,
-                        Tile::new(
-                            tile.corner + Vector2::new(i, j) * next_tile_size,
-                        ),
-                    );
-                }
-            }
+            self.eval_tiles(sub_tape, vars, depth + 1, tile, n);
+            self.stats.tile(depth, timer);
+            self.render_tiles(sub_tape, vars, depth + 1, tile, n);
         } else {
             self.render_tile_pixels(sub_tape, vars, tile_size, tile);
+            self.stats.tile(depth, timer);
         }
     }
 
@@ -375,7 +490,10 @@ impl<F: Function, M: RenderMode> Worker<'_, F, M> {
                 &self.scratch.z,
                 vars,
             )
-            .unwrap();
+            .unwrap()
+            .borrow(0);
+        self.stats
+            .float(&shape.ops, shape.shape.inner(), self.scratch.x.len());
 
         let mut index = 0;
         for j in 0..tile_size {
@@ -400,20 +518,22 @@ impl<F: Function, M: RenderMode> Worker<'_, F, M> {
 /// perform evaluation) and render mode (which tells us how to color in the
 /// resulting pixels).
 ///
-/// Returns a `Vec` of pixel data if rendering succeeds, or `None` if rendering
-/// was cancelled (using the [`ImageRenderConfig::cancel`] token)
+/// Returns a `Vec` of pixel data and evaluation statistics if rendering
+/// succeeds, or `None` if rendering was cancelled (using the
+/// [`ImageRenderConfig::cancel`] token)
 pub fn render<F: Function, M: RenderMode + Sync>(
     shape: Shape<F>,
     vars: &ShapeVars<f32>,
     config: &ImageRenderConfig,
-) -> Option<Image<M::Output>> {
+) -> Option<(Image<M::Output>, StatsRecorder)> {
     // Convert to a 4x4 matrix and apply to the shape
     let mat = config.mat();
     let mat = mat.insert_row(2, 0.0);
     let mat = mat.insert_column(2, 0.0);
     let shape = shape.apply_transform(mat);
 
-    let tiles = super::render_tiles::<F, Worker<F, M>>(shape, vars, config)?;
+    let (tiles, stats) =
+        super::render_tiles::<F, Worker<F, M>>(shape, vars, config)?;
 
     let width = config.image_size.width() as usize;
     let height = config.image_size.height() as usize;
@@ -431,7 +551,7 @@ pub fn render<F: Function, M: RenderMode + Sync>(
             }
         }
     }
-    Some(image)
+    Some((image, stats))
 }
 
 #[cfg(test)]
@@ -441,6 +561,7 @@ mod test {
         eval::{Function, MathFunction},
         render::{ImageSize, View2},
         shape::Shape,
+        stats::without_stats,
         var::Var,
         vm::{GenericVmFunction, VmFunction},
         Context,
@@ -470,6 +591,7 @@ mod test {
             };
             let out = cfg
                 .run_with_vars::<_, BitRenderMode>(shape, &self.vars)
+                .map(without_stats)
                 .expect("rendering should not be cancelled");
             let mut img_str = String::new();
             for (i, b) in out.iter().enumerate() {
@@ -7,6 +7,7 @@ use crate::{
         DepthImage, NormalImage, RenderWorker, TileSizes, VoxelSize,
     },
     shape::{Shape, ShapeBulkEval, ShapeTracingEval, ShapeVars},
+    stats::StatsRecorder,
     types::{Grad, Interval},
 };
 
@@ -63,6 +64,9 @@ struct Worker<'a, F: Function> {
     shape_storage: Vec<F::Storage>,
     workspace: F::Workspace,
 
+    /// Statistics recorded while rendering
+    stats: StatsRecorder,
+
     /// Output images for this specific tile
     depth: DepthImage,
     color: NormalImage,
@@ -89,6 +93,7 @@ impl<'a, F: Function> RenderWorker<'a, F> for Worker<'a, F> {
             tape_storage: vec![],
             shape_storage: vec![],
             workspace: Default::default(),
+            stats: Default::default(),
         }
     }
 
@@ -116,6 +121,10 @@ impl<'a, F: Function> RenderWorker<'a, F> for Worker<'a, F> {
         let color = std::mem::take(&mut self.color);
         (depth, color)
     }
+
+    fn take_stats(&mut self) -> StatsRecorder {
+        std::mem::take(&mut self.stats)
+    }
 }
 
 impl<F: Function> Worker<'_, F> {
@@ -144,6 +153,7 @@ impl<F: Function> Worker<'_, F> {
             return false;
         }
 
+        let timer = self.stats.start();
         let base = Point3::from(tile.corner).cast::<f32>();
         let x = Interval::new(base.x, base.x + tile_size as f32);
         let y = Interval::new(base.y, base.y + tile_size as f32);
@@ -153,6 +163,7 @@ impl<F: Function> Worker<'_, F> {
             .eval_interval
             .eval_v(shape.i_tape(&mut self.tape_storage), x, y, z, vars)
             .unwrap();
+        self.stats.interval(&shape.ops, shape.shape.inner(), trace);
 
         // Return early if this tile is completely empty or full, returning
         // `data_interval` to scratch memory for reuse.
@@ -163,25 +174,31 @@ impl<F: Function> Worker<'_, F> {
                     self.depth[i + x] = self.depth[i + x].max(fill_z);
                 }
             }
+            self.stats.tile(depth, timer);
             return false; // completely full, stop rendering
         } else if i.lower() > 0.0 {
+            self.stats.tile(depth, timer);
             return true; // complete empty, keep going
         }
 
         // Calculate a simplified tape based on the trace
         let sub_tape = if let Some(trace) = trace.as_ref() {
-            shape.simplify(
+            let before = shape.shape.size();
+            let next = shape.simplify(
                 trace,
                 &mut self.workspace,
                 &mut self.shape_storage,
                 &mut self.tape_storage,
-            )
+            );
+            self.stats.simplify(depth, before, next.shape.size());
+            next
         } else {
             shape
         };
 
         // Recurse!
         if let Some(next_tile_size) = self.tile_sizes.get(depth + 1) {
+            self.stats.tile(depth, timer);
             let n = tile_size / next_tile_size;
 
             for j in 0..n {
@@ -201,6 +218,7 @@ impl<F: Function> Worker<'_, F> {
             }
         } else {
             self.render_tile_pixels(sub_tape, vars, tile_size, tile);
+            self.stats.tile(depth, timer);
         };
         // TODO recycle something here?
         true // keep going
@@ -263,7 +281,9 @@ impl<F: Function> Worker<'_, F> {
                 &self.scratch.z[..index],
                 vars,
             )
-            .unwrap();
+            .unwrap()
+            .borrow(0);
+        self.stats.float(&shape.ops, shape.shape.inner(), index);
 
         // We're iterating over a few things simultaneously
         // - col refers to the xy position in the tile
@@ -322,7 +342,9 @@ impl<F: Function> Worker<'_, F> {
                     &self.scratch.zg[..grad],
                     vars,
                 )
-                .unwrap();
+                .unwrap()
+                .borrow(0);
+            self.stats.grad(&shape.ops, shape.shape.inner(), grad);
 
             for (index, o) in self.scratch.columns[0..grad].iter().enumerate() {
                 let g = out[index];
@@ -343,17 +365,18 @@ impl<F: Function> Worker<'_, F> {
 /// This function is parameterized by shape type, which determines how we
 /// perform evaluation.
 ///
-/// Returns two `Vec` of pixel data (color, normals) if rendering succeeds, or
-/// `None` if rendering was cancelled (using the [`VoxelRenderConfig::cancel`]
-/// token)
+/// Returns two `Vec` of pixel data (color, normals) and evaluation statistics
+/// if rendering succeeds, or `None` if rendering was cancelled (using the
+/// [`VoxelRenderConfig::cancel`] token)
 pub fn render<F: Function>(
     shape: Shape<F>,
     vars: &ShapeVars<f32>,
     config: &VoxelRenderConfig,
-) -> Option<(DepthImage, NormalImage)> {
+) -> Option<(DepthImage, NormalImage, StatsRecorder)> {
     let shape = shape.apply_transform(config.mat());
 
-    let tiles = super::render_tiles::<F, Worker<F>>(shape, vars, config)?;
+    let (tiles, stats) =
+        super::render_tiles::<F, Worker<F>>(shape, vars, config)?;
 
     let width = config.image_size.width() as usize;
     let height = config.image_size.height() as usize;
@@ -376,7 +399,7 @@ pub fn render<F: Function>(
             }
         }
     }
-    Some((image_depth, image_color))
+    Some((image_depth, image_color, stats))
 }
 
 #[cfg(test)]
@@ -386,6 +409,7 @@ mod test {
         context::Tree,
         eval::MathFunction,
         render::{View3, VoxelSize},
+        stats::without_stats,
         var::Var,
         vm::VmShape,
         Context,
@@ -402,7 +426,7 @@ mod test {
             image_size: VoxelSize::from(128), // very small!
             ..Default::default()
         };
-        let (depth, rgb) = cfg.run(shape).unwrap();
+        let (depth, rgb) = cfg.run(shape).map(without_stats).unwrap();
         assert_eq!(depth.len(), 128 * 128);
         assert_eq!(rgb.len(), 128 * 128);
     }
@@ -425,8 +449,10 @@ mod test {
             for r in [0.5, 0.75] {
                 let mut vars = ShapeVars::new();
                 vars.insert(v.index().unwrap(), r);
-                let (depth, _normal) =
-                    cfg.run_with_vars::<_>(shape.clone(), &vars).unwrap();
+                let (depth, _normal) = cfg
+                    .run_with_vars::<_>(shape.clone(), &vars)
+                    .map(without_stats)
+                    .unwrap();
 
                 let epsilon = 0.08;
                 for (i, p) in depth.iter().enumerate() {
@@ -107,6 +107,7 @@ impl Engine {
         register_binary_fns!("and", and, engine);
         register_binary_fns!("or", or, engine);
         register_binary_fns!("atan2", atan2, engine);
+        register_binary_fns!("pow", pow, engine);
         register_unary_fns!("abs", abs, engine);
         register_unary_fns!("sqrt", sqrt, engine);
         register_unary_fns!("square", square, engine);
@@ -118,6 +119,12 @@ impl Engine {
         register_unary_fns!("atan", atan, engine);
         register_unary_fns!("exp", exp, engine);
         register_unary_fns!("ln", ln, engine);
+        register_unary_fns!("log2", log2, engine);
+        register_unary_fns!("sinh", sinh, engine);
+        register_unary_fns!("cosh", cosh, engine);
+        register_unary_fns!("tanh", tanh, engine);
+        register_unary_fns!("sign", sign, engine);
+        register_unary_fns!("cbrt", cbrt, engine);
         register_unary_fns!("not", not, engine);
         register_unary_fns!("ceil", ceil, engine);
         register_unary_fns!("floor", floor, engine);
@@ -371,6 +378,7 @@ define_binary_fns!(modulo);
 define_binary_fns!(and);
 define_binary_fns!(or);
 define_binary_fns!(atan2);
+define_binary_fns!(pow);
 define_unary_fns!(sqrt);
 define_unary_fns!(square);
 define_unary_fns!(neg);
@@ -387,6 +395,12 @@ define_unary_fns!(abs);
 define_unary_fns!(floor);
 define_unary_fns!(ceil);
 define_unary_fns!(round);
+define_unary_fns!(log2);
+define_unary_fns!(sinh);
+define_unary_fns!(cosh);
+define_unary_fns!(tanh);
+define_unary_fns!(sign);
+define_unary_fns!(cbrt);
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -1,7 +1,7 @@
 //! Solver for systems of equations expressed as sets of [Function] objects
 use crate::{
     eval::{BulkEvaluator, Function, Tape, TracingEvaluator},
-    types::Grad,
+    types::Dual,
     var::Var,
     Error,
 };
@@ -17,36 +17,40 @@ pub enum Parameter {
 }
 
 /// Workspace for solvers
-struct Solver<'a, F: Function> {
+///
+/// `P` is the number of partial derivatives carried by each sample in the
+/// Jacobian.  Systems with more free variables than this are split across
+/// multiple samples, which are still computed in a single bulk evaluation.
+struct Solver<'a, F: Function, const P: usize> {
     /// Input parameters
     vars: &'a HashMap<Var, Parameter>,
 
     /// Tapes for bulk gradient evaluation of each constraint
-    grad_tapes: Vec<<F::GradSliceEval as BulkEvaluator>::Tape>,
+    grad_tapes: Vec<<F::DualSliceEval<P> as BulkEvaluator>::Tape>,
 
     /// Tapes for single-point evaluation of each constraint
     point_tapes: Vec<<F::PointEval as TracingEvaluator>::Tape>,
 
     /// Bulk gradient evaluator, for use in computing the Jacobian
-    grad_eval: F::GradSliceEval,
+    grad_eval: F::DualSliceEval<P>,
 
     /// Single-point evaluator, for use in checking our current error
     point_eval: F::PointEval,
 
     /// Input data for use when calling the gradient bulk evaluator
-    input_grad: Vec<Vec<Grad>>,
+    input_grad: Vec<Vec<Dual<P>>>,
 
     /// Input data for use when calling the single-point evaluator
     input_point: Vec<f32>,
 
     /// Map from (free) variables to the index of their gradient
     ///
-    /// We evaluate 3x gradients per sample, so for `grad_index = gi`, the
-    /// relevant derivative will be `out[gi / 3].d(gi % 3)`
+    /// We evaluate `P` gradients per sample, so for `grad_index = gi`, the
+    /// relevant derivative will be `out[gi / P].d[gi % P]`
     grad_index: HashMap<Var, usize>,
 }
 
-impl<'a, F: Function> Solver<'a, F> {
+impl<'a, F: Function, const P: usize> Solver<'a, F, P> {
     fn new(
         eqs: &'a [F],
         vars: &'a HashMap<Var, Parameter>,
@@ -54,7 +58,7 @@ impl<'a, F: Function> Solver<'a, F> {
         // Build our per-constraint
         let grad_tapes = eqs
             .iter()
-            .map(|f| f.grad_slice_tape(Default::default()))
+            .map(|f| f.dual_slice_tape(Default::default()))
             .collect::<Vec<_>>();
         let point_tapes = eqs
             .iter()
@@ -76,7 +80,7 @@ impl<'a, F: Function> Solver<'a, F> {
         // to simultaneously compute all of the gradients that we need
         let input_grad =
             vec![
-                vec![Grad::from(0f32); grad_index.len().div_ceil(3)];
+                vec![Dual::from(0f32); grad_index.len().div_ceil(P)];
                 vars.len()
             ];
         let input_point = vec![0f32; vars.len()];
@@ -116,17 +120,11 @@ impl<'a, F: Function> Solver<'a, F> {
                 match p {
                     Parameter::Free(..) => {
                         let gi = self.grad_index[v];
-                        for (j, v) in slice.iter_mut().enumerate() {
-                            *v = Grad::new(
-                                cur[gi],
-                                if j * 3 == gi { 1.0 } else { 0.0 },
-                                if j * 3 + 1 == gi { 1.0 } else { 0.0 },
-                                if j * 3 + 2 == gi { 1.0 } else { 0.0 },
-                            );
-                        }
+                        slice.fill(Dual::from(cur[gi]));
+                        slice[gi / P] = Dual::var(cur[gi], gi % P);
                     }
                     Parameter::Fixed(f) => {
-                        slice.fill(Grad::new(*f, 0.0, 0.0, 0.0));
+                        slice.fill(Dual::from(*f));
                     }
                 };
             }
@@ -135,7 +133,7 @@ impl<'a, F: Function> Solver<'a, F> {
 
             // Populate this row of the Jacobian
             for gi in 0..self.grad_index.len() {
-                *jacobian.get_mut((ti, gi)).unwrap() = out[0][gi / 3].d(gi % 3);
+                *jacobian.get_mut((ti, gi)).unwrap() = out[0][gi / P].d[gi % P];
             }
             result[ti] = out[0][0].v;
         }
@@ -190,11 +188,26 @@ pub fn solve<F: Function>(
     eqs: &[F],
     vars: &HashMap<Var, Parameter>,
 ) -> Result<HashMap<Var, f32>, Error> {
-    let tapes = eqs
-        .iter()
-        .map(|f| f.grad_slice_tape(Default::default()))
-        .collect::<Vec<_>>();
+    // Pick the number of partials per sample based on the number of free
+    // variables, so that small systems don't carry (and evaluate) unused
+    // partial derivatives.  The JIT evaluates partials in chunks of three, so
+    // larger systems still use compiled code.
+    let free = vars
+        .values()
+        .filter(|p| matches!(p, Parameter::Free(..)))
+        .count();
+    match free {
+        0..=3 => solve_with::<F, 3>(eqs, vars),
+        4..=8 => solve_with::<F, 8>(eqs, vars),
+        _ => solve_with::<F, 16>(eqs, vars),
+    }
+}
 
+/// Least-squares minimization with `P` partial derivatives per sample
+fn solve_with<F: Function, const P: usize>(
+    eqs: &[F],
+    vars: &HashMap<Var, Parameter>,
+) -> Result<HashMap<Var, f32>, Error> {
     // Current values for free variables
     let mut cur = HashMap::new();
     for (v, p) in vars {
@@ -203,7 +216,7 @@ pub fn solve<F: Function>(
         }
     }
 
-    let mut solver = Solver::new(eqs, vars)?;
+    let mut solver = Solver::<F, P>::new(eqs, vars)?;
 
     // Build an array of current values for each free variable
     let mut cur = vec![0f32; solver.grad_index.len()];
@@ -215,8 +228,8 @@ pub fn solve<F: Function>(
     }
 
     // Working arrays for the current Jacobian and result
-    let mut jacobian = nalgebra::DMatrix::repeat(tapes.len(), cur.len(), 0f32);
-    let mut result = nalgebra::DVector::repeat(tapes.len(), 0f32);
+    let mut jacobian = nalgebra::DMatrix::repeat(eqs.len(), cur.len(), 0f32);
+    let mut result = nalgebra::DVector::repeat(eqs.len(), 0f32);
 
     let mut damping = 1.0;
     let mut prev_err = f32::INFINITY;
@@ -358,6 +371,30 @@ mod test {
         }
     }
 
+    #[cfg(all(feature = "jit", not(target_arch = "wasm32")))]
+    #[test]
+    fn many_vars_jit() {
+        use crate::jit::JitFunction;
+        let vs = (0..10).map(|_| Var::new()).collect::<Vec<Var>>();
+        let mut eqns = vec![];
+        let mut ctx = Context::new();
+        for (i, &v) in vs.iter().enumerate() {
+            let eqn = Tree::from(v) * Tree::from(v) - Tree::from(i as f32);
+            let root = ctx.import(&eqn);
+            eqns.push(JitFunction::new(&ctx, &[root]).unwrap());
+        }
+
+        let mut values = HashMap::new();
+        for &v in &vs {
+            values.insert(v, Parameter::Free(1.0));
+        }
+        let sol = solve(&eqns, &values).unwrap();
+        assert_eq!(sol.len(), 10);
+        for (i, v) in vs.iter().enumerate() {
+            assert_relative_eq!((i as f32).sqrt(), sol[v], epsilon = 1e-3);
+        }
+    }
+
     #[test]
     fn xy_nonlinear() {
         let constraints = vec![
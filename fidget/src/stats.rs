@@ -0,0 +1,481 @@
+//! Evaluation statistics and tape profiling
+//!
+//! If the `eval-stats` feature is set, then rendering and meshing record
+//! statistics about how the shape was evaluated:
+//! `ImageRenderConfig::run`, `VoxelRenderConfig::run`, and `Octree::build`
+//! (and their `_with_vars` variants) return an [`EvalStats`] alongside their
+//! usual output (see [`WithStats`]).  Otherwise, their return types are
+//! unchanged, and recording compiles to nothing.
+//!
+//! Statistics are gathered per subdivision level: for rendering, level `i`
+//! uses tile size `tile_sizes[i]`; for meshing, it's the depth of the octree
+//! cell.
+use crate::eval::Function;
+
+#[cfg(feature = "eval-stats")]
+use crate::{compiler::RegOp, eval::Trace, vm::Choice};
+#[cfg(feature = "eval-stats")]
+use std::{collections::BTreeMap, sync::OnceLock, time::Duration};
+
+/// Output of a render or meshing function, along with its statistics
+///
+/// When the `eval-stats` feature is enabled, this is a tuple of the output and
+/// an [`EvalStats`]; otherwise, it's just the output.
+#[cfg(feature = "eval-stats")]
+pub type WithStats<T> = (T, EvalStats);
+
+/// Output of a render or meshing function, along with its statistics
+///
+/// When the `eval-stats` feature is enabled, this is a tuple of the output and
+/// an `EvalStats`; otherwise, it's just the output.
+#[cfg(not(feature = "eval-stats"))]
+pub type WithStats<T> = T;
+
+/// Discards statistics from a [`WithStats`] value
+///
+/// This is useful for code which should compile with or without the
+/// `eval-stats` feature.
+#[inline]
+pub fn without_stats<T>(v: WithStats<T>) -> T {
+    #[cfg(feature = "eval-stats")]
+    {
+        v.0
+    }
+    #[cfg(not(feature = "eval-stats"))]
+    {
+        v
+    }
+}
+
+/// Statistics recorded during rendering or meshing
+#[cfg(feature = "eval-stats")]
+#[derive(Clone, Debug, Default)]
+pub struct EvalStats {
+    /// Statistics for each subdivision level, starting from the root
+    pub levels: Vec<LevelStats>,
+
+    /// Number of times that each opcode was executed, keyed by name
+    ///
+    /// Each evaluation counts every operation in the tape once per value, so
+    /// evaluating a 10-operation tape on 64 points counts 640 operations.
+    /// This is only populated for functions which implement
+    /// [`Function::visit_ops`].
+    pub opcodes: BTreeMap<String, u64>,
+
+    /// Choices in the traces returned by interval evaluation
+    ///
+    /// This is only populated for traces which implement
+    /// [`Trace::choices`].
+    pub choices: ChoiceCounts,
+
+    /// Number of intervals evaluated
+    pub interval_evals: u64,
+
+    /// Number of points evaluated with the float slice evaluator
+    pub float_evals: u64,
+
+    /// Number of points evaluated with the gradient slice evaluator
+    pub grad_evals: u64,
+}
+
+#[cfg(feature = "eval-stats")]
+impl EvalStats {
+    /// Accumulates statistics from another object into this one
+    pub fn merge(&mut self, other: &EvalStats) {
+        if self.levels.len() < other.levels.len() {
+            self.levels
+                .resize(other.levels.len(), LevelStats::default());
+        }
+        for (a, b) in self.levels.iter_mut().zip(&other.levels) {
+            a.tiles += b.tiles;
+            a.time += b.time;
+            a.simplify_count += b.simplify_count;
+            a.tape_len_before += b.tape_len_before;
+            a.tape_len_after += b.tape_len_after;
+        }
+        for (k, v) in &other.opcodes {
+            *self.opcodes.entry(k.clone()).or_default() += v;
+        }
+        self.choices.unknown += other.choices.unknown;
+        self.choices.left += other.choices.left;
+        self.choices.right += other.choices.right;
+        self.choices.both += other.choices.both;
+        self.interval_evals += other.interval_evals;
+        self.float_evals += other.float_evals;
+        self.grad_evals += other.grad_evals;
+    }
+}
+
+/// Statistics for a single subdivision level
+#[cfg(feature = "eval-stats")]
+#[derive(Clone, Debug, Default)]
+pub struct LevelStats {
+    /// Number of tiles (or octree cells) visited at this level
+    pub tiles: u64,
+
+    /// Total time spent on tiles at this level
+    ///
+    /// This excludes time spent in subdivided tiles, which is counted at the
+    /// next level.  Time is not recorded on WebAssembly, where it's always
+    /// zero.
+    pub time: Duration,
+
+    /// Number of calls to `simplify` at this level
+    pub simplify_count: u64,
+
+    /// Sum of tape lengths before each call to `simplify`
+    pub tape_len_before: u64,
+
+    /// Sum of tape lengths after each call to `simplify`
+    ///
+    /// If the simplified tape isn't any shorter, then the renderer keeps the
+    /// original tape; this counts the length of the tape which is used.
+    pub tape_len_after: u64,
+}
+
+/// Number of each kind of [`Choice`] seen in traces
+#[cfg(feature = "eval-stats")]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChoiceCounts {
+    /// Number of [`Choice::Unknown`] values
+    pub unknown: u64,
+    /// Number of [`Choice::Left`] values
+    pub left: u64,
+    /// Number of [`Choice::Right`] values
+    pub right: u64,
+    /// Number of [`Choice::Both`] values
+    pub both: u64,
+}
+
+/// Lazily-built histogram of the opcodes in a function
+///
+/// This is stored next to the function's tapes (e.g. in a `RenderHandle`), so
+/// that the function is only walked once, rather than on every evaluation.
+/// When the `eval-stats` feature is disabled, this is a zero-sized type.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OpCounts {
+    #[cfg(feature = "eval-stats")]
+    counts: OnceLock<Vec<(RegOp, u64)>>,
+}
+
+#[cfg(feature = "eval-stats")]
+impl OpCounts {
+    /// Returns the number of each kind of opcode in `f`
+    ///
+    /// `f` must be the same function every time this is called.
+    fn get<F: Function>(&self, f: &F) -> &[(RegOp, u64)] {
+        self.counts.get_or_init(|| {
+            let mut counts: std::collections::HashMap<_, (RegOp, u64)> =
+                Default::default();
+            f.visit_ops(&mut |op| {
+                counts
+                    .entry(std::mem::discriminant(&op))
+                    .or_insert((op, 0))
+                    .1 += 1;
+            });
+            counts.into_values().collect()
+        })
+    }
+}
+
+/// Start time of a tile, returned by [`StatsRecorder::start`]
+#[derive(Copy, Clone)]
+pub(crate) struct TileTimer {
+    #[cfg(all(feature = "eval-stats", not(target_arch = "wasm32")))]
+    start: std::time::Instant,
+}
+
+/// Internal statistics recorder
+///
+/// When the `eval-stats` feature is disabled, this is a zero-sized type and
+/// every function is a no-op.
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder {
+    #[cfg(feature = "eval-stats")]
+    stats: EvalStats,
+
+    /// Opcode counts, keyed by discriminant and converted to names at the end
+    #[cfg(feature = "eval-stats")]
+    ops: std::collections::HashMap<std::mem::Discriminant<RegOp>, (RegOp, u64)>,
+}
+
+#[cfg_attr(
+    any(not(feature = "eval-stats"), target_arch = "wasm32"),
+    allow(unused_variables)
+)]
+impl StatsRecorder {
+    /// Returns a level's statistics, resizing the array if necessary
+    #[cfg(feature = "eval-stats")]
+    fn level(&mut self, depth: usize) -> &mut LevelStats {
+        if self.stats.levels.len() <= depth {
+            self.stats.levels.resize(depth + 1, LevelStats::default());
+        }
+        &mut self.stats.levels[depth]
+    }
+
+    /// Counts every operation in the function `n` times
+    #[cfg(feature = "eval-stats")]
+    fn ops<F: Function>(&mut self, ops: &OpCounts, f: &F, n: usize) {
+        for (op, count) in ops.get(f) {
+            self.ops
+                .entry(std::mem::discriminant(op))
+                .or_insert((*op, 0))
+                .1 += count * n as u64;
+        }
+    }
+
+    /// Starts timing a tile
+    #[inline]
+    pub fn start(&self) -> TileTimer {
+        TileTimer {
+            #[cfg(all(feature = "eval-stats", not(target_arch = "wasm32")))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Records a tile at the given level, which was started at `timer`
+    #[inline]
+    pub fn tile(&mut self, depth: usize, timer: TileTimer) {
+        #[cfg(feature = "eval-stats")]
+        {
+            let level = self.level(depth);
+            level.tiles += 1;
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                level.time += timer.start.elapsed();
+            }
+        }
+    }
+
+    /// Records a call to `simplify` at the given level
+    #[inline]
+    pub fn simplify(&mut self, depth: usize, before: usize, after: usize) {
+        #[cfg(feature = "eval-stats")]
+        {
+            let level = self.level(depth);
+            level.simplify_count += 1;
+            level.tape_len_before += before as u64;
+            level.tape_len_after += after as u64;
+        }
+    }
+
+    /// Records a single interval evaluation, and the resulting trace
+    ///
+    /// `ops` caches the opcode histogram of `f`.
+    #[inline]
+    pub fn interval<F: Function>(
+        &mut self,
+        ops: &OpCounts,
+        f: &F,
+        trace: Option<&F::Trace>,
+    ) {
+        #[cfg(feature = "eval-stats")]
+        {
+            self.stats.interval_evals += 1;
+            self.ops(ops, f, 1);
+            let c = &mut self.stats.choices;
+            for choice in trace.map(Trace::choices).unwrap_or_default() {
+                match choice {
+                    Choice::Unknown => c.unknown += 1,
+                    Choice::Left => c.left += 1,
+                    Choice::Right => c.right += 1,
+                    Choice::Both => c.both += 1,
+                }
+            }
+        }
+    }
+
+    /// Records a float slice evaluation of `n` points
+    #[inline]
+    pub fn float<F: Function>(&mut self, ops: &OpCounts, f: &F, n: usize) {
+        #[cfg(feature = "eval-stats")]
+        {
+            self.stats.float_evals += n as u64;
+            self.ops(ops, f, n);
+        }
+    }
+
+    /// Records a gradient slice evaluation of `n` points
+    #[inline]
+    pub fn grad<F: Function>(&mut self, ops: &OpCounts, f: &F, n: usize) {
+        #[cfg(feature = "eval-stats")]
+        {
+            self.stats.grad_evals += n as u64;
+            self.ops(ops, f, n);
+        }
+    }
+
+    /// Accumulates statistics from another recorder into this one
+    #[inline]
+    pub fn merge(&mut self, other: StatsRecorder) {
+        #[cfg(feature = "eval-stats")]
+        {
+            self.stats.merge(&other.stats);
+            for (k, (op, n)) in other.ops {
+                self.ops.entry(k).or_insert((op, 0)).1 += n;
+            }
+        }
+    }
+
+    /// Attaches the recorded statistics (if enabled) to an output value
+    #[inline]
+    pub fn attach<T>(self, value: T) -> WithStats<T> {
+        #[cfg(feature = "eval-stats")]
+        {
+            (value, self.finish())
+        }
+        #[cfg(not(feature = "eval-stats"))]
+        {
+            value
+        }
+    }
+
+    /// Converts the recorded data into an [`EvalStats`]
+    #[cfg(feature = "eval-stats")]
+    fn finish(self) -> EvalStats {
+        let mut stats = self.stats;
+        for (op, n) in self.ops.into_values() {
+            // Strip the arguments from the debug representation, e.g.
+            // `AddRegImm(0, 1, 2.0)` becomes `AddRegImm`
+            let name = format!("{op:?}");
+            let name = name.split('(').next().unwrap().to_owned();
+            *stats.opcodes.entry(name).or_default() += n;
+        }
+        stats
+    }
+}
+
+#[cfg(all(test, feature = "eval-stats"))]
+mod test {
+    use super::*;
+    use crate::{
+        context::Tree,
+        mesh::{Octree, Settings},
+        render::{BitRenderMode, ImageRenderConfig, VoxelRenderConfig},
+        shape::ShapeVars,
+        vm::VmShape,
+    };
+
+    /// Union of two spheres, so that simplification has something to do
+    fn spheres() -> VmShape {
+        let (x, y, z) = Tree::axes();
+        let a =
+            ((x.clone() - 0.5).square() + y.square() + z.square()).sqrt() - 0.4;
+        let b = ((x + 0.5).square() + y.square() + z.square()).sqrt() - 0.4;
+        VmShape::from(a.min(b))
+    }
+
+    fn check(stats: &EvalStats) {
+        assert!(stats.interval_evals > 0);
+        assert!(stats.float_evals > 0);
+        assert!(stats.levels.len() > 1);
+        assert_eq!(
+            stats.levels.iter().map(|l| l.tiles).sum::<u64>(),
+            stats.interval_evals
+        );
+
+        // Tiles away from the middle pick one sphere or the other
+        assert!(stats.choices.left > 0);
+        assert!(stats.choices.right > 0);
+        let simplify_count = stats.levels.iter().map(|l| l.simplify_count);
+        assert!(simplify_count.sum::<u64>() > 0);
+        for level in &stats.levels {
+            assert!(level.tape_len_after <= level.tape_len_before);
+        }
+        assert!(stats
+            .levels
+            .iter()
+            .any(|l| l.tape_len_after < l.tape_len_before));
+
+        // Evaluations on the root tape run the `min`, but evaluations on
+        // simplified tapes skip it.
+        let evals = stats.interval_evals + stats.float_evals + stats.grad_evals;
+        assert!(stats.opcodes["MinRegReg"] >= stats.levels[0].tiles);
+        assert!(stats.opcodes["MinRegReg"] < evals);
+        assert!(stats.opcodes["SqrtReg"] > stats.opcodes["MinRegReg"]);
+    }
+
+    #[test]
+    fn test_image_stats() {
+        let cfg = ImageRenderConfig {
+            image_size: 256.into(),
+            ..Default::default()
+        };
+        let shape = spheres();
+        let (image, stats) = cfg
+            .run_with_vars::<_, BitRenderMode>(shape.clone(), &ShapeVars::new())
+            .unwrap();
+        let (expected, _) = cfg.run::<_, BitRenderMode>(shape).unwrap();
+        assert!(image.iter().eq(expected.iter()));
+        check(&stats);
+        assert_eq!(stats.grad_evals, 0);
+        assert_eq!(stats.levels.len(), cfg.tile_sizes.len());
+    }
+
+    #[test]
+    fn test_voxel_stats() {
+        let cfg = VoxelRenderConfig {
+            image_size: 128.into(),
+            ..Default::default()
+        };
+        let ((_depth, _norm), stats) = cfg.run(spheres()).unwrap();
+        check(&stats);
+        assert!(stats.grad_evals > 0);
+    }
+
+    #[test]
+    fn test_octree_stats() {
+        let settings = Settings {
+            depth: 5,
+            ..Default::default()
+        };
+        let (_octree, stats) = Octree::build(&spheres(), settings);
+        check(&stats);
+        assert!(stats.grad_evals > 0);
+        assert_eq!(stats.levels.len(), 6);
+    }
+
+    #[test]
+    fn test_op_counts_cached() {
+        let shape = spheres();
+        let ops = OpCounts::default();
+        let mut a = StatsRecorder::default();
+        a.float(&ops, shape.inner(), 3);
+        let counts = ops.get(shape.inner()).as_ptr();
+        a.float(&ops, shape.inner(), 2);
+        assert_eq!(ops.get(shape.inner()).as_ptr(), counts);
+
+        let mut len = 0;
+        shape.inner().visit_ops(&mut |_| len += 1);
+        let stats = a.attach(()).1;
+        assert_eq!(stats.opcodes.values().sum::<u64>(), len * 5);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = EvalStats {
+            levels: vec![LevelStats {
+                tiles: 2,
+                ..Default::default()
+            }],
+            interval_evals: 3,
+            ..Default::default()
+        };
+        a.opcodes.insert("AddRegReg".to_owned(), 1);
+        let mut b = EvalStats {
+            levels: vec![LevelStats::default(), LevelStats::default()],
+            float_evals: 5,
+            ..Default::default()
+        };
+        b.levels[0].tiles = 1;
+        b.opcodes.insert("AddRegReg".to_owned(), 2);
+        b.choices.left = 4;
+        a.merge(&b);
+        assert_eq!(a.levels.len(), 2);
+        assert_eq!(a.levels[0].tiles, 3);
+        assert_eq!(a.interval_evals, 3);
+        assert_eq!(a.float_evals, 5);
+        assert_eq!(a.opcodes["AddRegReg"], 3);
+        assert_eq!(a.choices.left, 4);
+    }
+}